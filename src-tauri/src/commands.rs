@@ -1,8 +1,8 @@
 use tauri::{AppHandle, Manager, Emitter};
-use crate::types::{AppSettings, DatabaseState};
+use crate::types::{DEFAULT_PROFILE, PROFILE_CHANGED_EVENT, ProfileList, ActivityState, AppSettings, AutoStartNames, PasteAttempt, PasteMetricsState, BlurHideState, CaptureControlState, CaptureGroupState, CAPTURE_FORMATS, CLIPBOARD_ITEM_TYPES, ClipboardItem, CommandErrorPayload, COMMAND_ERROR_EVENT, DatabaseState, HistoryChangedPayload, HistoryItem, HISTORY_CHANGED_EVENT, ItemAddedPayload, LastPastedState, LIST_PREVIEW_MAX_CHARS, LIST_PREVIEW_MIN_CHARS, ITEM_ADDED_EVENT, MANUAL_CAPTURE_MODE_EVENT, SETTINGS_VERSION, UI_STATE_FILE};
 use crate::logging;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::io::{Write, Read};
 use dirs_next::config_dir;
 use base64::{engine::general_purpose, Engine as _};
@@ -24,11 +24,858 @@ fn settings_file_path() -> Result<PathBuf, String> {
     Ok(dir.join(SETTINGS_FILE))
 }
 
+// 设置文件旁的附属文件（.tmp 临时文件、.bak 备份）
+fn settings_sidecar_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+// 原子地写入设置文件：先写同目录下的临时文件再重命名覆盖，写入中途崩溃不会损坏原文件；
+// 覆盖前把上一份能正常解析的设置复制为 .bak
+fn write_settings_file(path: &Path, json: &str) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("创建设置目录失败: {}", e))?;
+    }
+    // 只备份能正常解析的旧文件，避免损坏的文件覆盖上一份好的备份
+    if let Ok(previous) = fs::read_to_string(path) {
+        if serde_json::from_str::<AppSettings>(&previous).is_ok() {
+            if let Err(e) = fs::write(settings_sidecar_path(path, ".bak"), previous) {
+                tracing::warn!("⚠️ 备份设置文件失败: {}", e);
+            }
+        }
+    }
+
+    let temp_path = settings_sidecar_path(path, ".tmp");
+    let result = (|| -> std::io::Result<()> {
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(json.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&temp_path, path)
+    })();
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temp_path);
+        return Err(format!("保存设置文件失败: {}", e));
+    }
+    Ok(())
+}
+
+// 把旧版本的设置结构升级到 SETTINGS_VERSION，返回是否做了升级（需要写回文件）。
+// 比当前版本新的文件（降级运行）保持原样
+fn migrate_settings(value: &mut serde_json::Value) -> bool {
+    let Some(settings) = value.as_object_mut() else { return false };
+    let version = settings.get("version").and_then(|v| v.as_u64()).unwrap_or(0);
+    if version >= SETTINGS_VERSION as u64 {
+        return false;
+    }
+    if version < 1 {
+        // 版本 1：新增按类型保留天数和应用黑名单
+        settings.entry("retention_by_type").or_insert_with(|| serde_json::json!({}));
+        settings.entry("blacklisted_apps").or_insert_with(|| serde_json::json!([]));
+    }
+    settings.insert("version".to_string(), SETTINGS_VERSION.into());
+    true
+}
+
+// 解析设置文件内容并升级到当前版本，返回设置和是否做了升级
+fn parse_settings(content: &str) -> Result<(AppSettings, bool), serde_json::Error> {
+    let mut value: serde_json::Value = serde_json::from_str(content)?;
+    let migrated = migrate_settings(&mut value);
+    Ok((serde_json::from_value(value)?, migrated))
+}
+
+// 读取设置文件；文件无法解析（例如旧版本直接写入时中途断电）时回退到 .bak 备份。
+// 返回的设置已升级到当前版本，第二个值表示是否需要写回文件
+fn read_settings_file(path: &Path) -> Result<(AppSettings, bool), String> {
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let error = match parse_settings(&content) {
+        Ok(result) => return Ok(result),
+        Err(e) => e,
+    };
+    let backup_path = settings_sidecar_path(path, ".bak");
+    let backup = fs::read_to_string(&backup_path)
+        .ok()
+        .and_then(|content| parse_settings(&content).ok());
+    match backup {
+        Some(result) => {
+            tracing::warn!("⚠️ 设置文件解析失败（{}），已从备份恢复: {}", error, backup_path.display());
+            Ok(result)
+        }
+        None => Err(error.to_string()),
+    }
+}
+
+// 数据库被锁定时的最大重试次数与初始退避时间（每次重试翻倍）
+const DB_LOCK_MAX_RETRIES: u32 = 4;
+const DB_LOCK_INITIAL_BACKOFF_MS: u64 = 50;
+
+/// 是否为可重试的 SQLite 锁冲突错误（SQLITE_BUSY / SQLITE_LOCKED）
+pub(crate) fn is_database_locked(error: &sqlx::Error) -> bool {
+    match error {
+        sqlx::Error::Database(db_error) => {
+            // 扩展错误码的低 8 位为主错误码：5 = SQLITE_BUSY，6 = SQLITE_LOCKED
+            let primary_code = db_error
+                .code()
+                .and_then(|code| code.parse::<i32>().ok())
+                .map(|code| code & 0xff);
+            matches!(primary_code, Some(5) | Some(6))
+                || db_error.message().contains("database is locked")
+        }
+        _ => false,
+    }
+}
+
+/// 执行数据库写操作，遇到锁冲突时按指数退避重试，其它错误立即返回
+pub(crate) async fn with_db_retry<T, F, Fut>(operation: &str, mut f: F) -> Result<T, sqlx::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T, sqlx::Error>>,
+{
+    let mut attempt = 0;
+    loop {
+        match f().await {
+            Err(e) if is_database_locked(&e) && attempt < DB_LOCK_MAX_RETRIES => {
+                let backoff = DB_LOCK_INITIAL_BACKOFF_MS << attempt;
+                attempt += 1;
+                tracing::warn!("⚠️ {}时数据库被锁定，{}ms 后第 {} 次重试: {}", operation, backoff, attempt, e);
+                tokio::time::sleep(std::time::Duration::from_millis(backoff)).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+// 命令失败时发送 command-error 事件，前端统一弹出错误提示；原样返回结果
+pub(crate) fn report_command_error<T>(app: &AppHandle, command: &str, result: Result<T, String>) -> Result<T, String> {
+    if let Err(message) = &result {
+        tracing::warn!("⚠️ 命令 {} 失败: {}", command, message);
+        let _ = app.emit(COMMAND_ERROR_EVENT, CommandErrorPayload {
+            command: command.to_string(),
+            message: message.clone(),
+        });
+    }
+    result
+}
+
 #[tauri::command]
 pub fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
+/// 当前构建/平台支持的可选功能，前端据此隐藏无法使用的操作
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct Capabilities {
+    pub platform: String,
+    pub auto_paste: bool,
+    pub paste_to_target_app: bool,
+    pub image_capture: bool,
+    pub file_clipboard: bool,
+    pub source_app_detection: bool,
+    pub source_app_icons: bool,
+    pub lan_queue: bool,
+    pub overlay_window: bool,
+    pub ocr: bool,
+    pub encryption: bool,
+}
+
+// Linux 下 rdev 的按键模拟依赖 X11，Wayland 会话中无法自动粘贴
+#[cfg(target_os = "linux")]
+fn is_wayland_session() -> bool {
+    env::var("XDG_SESSION_TYPE").map(|v| v.eq_ignore_ascii_case("wayland")).unwrap_or(false)
+        || env::var("WAYLAND_DISPLAY").is_ok()
+}
+
+#[tauri::command]
+pub async fn get_capabilities() -> Capabilities {
+    #[cfg(target_os = "linux")]
+    let (auto_paste, source_app_detection) = {
+        // 获取来源应用依赖 xdotool；启动外部进程会阻塞，放到阻塞线程中执行
+        let has_xdotool = tokio::task::spawn_blocking(|| {
+            std::process::Command::new("xdotool")
+                .arg("--version")
+                .output()
+                .map(|output| output.status.success())
+                .unwrap_or(false)
+        }).await.unwrap_or(false);
+        (!is_wayland_session(), has_xdotool)
+    };
+    #[cfg(not(target_os = "linux"))]
+    let (auto_paste, source_app_detection) = (true, true);
+
+    Capabilities {
+        platform: env::consts::OS.to_string(),
+        auto_paste,
+        paste_to_target_app: cfg!(target_os = "macos"),
+        image_capture: true,
+        file_clipboard: true,
+        source_app_detection,
+        source_app_icons: cfg!(any(target_os = "windows", target_os = "macos")),
+        lan_queue: true,
+        overlay_window: cfg!(target_os = "macos"),
+        ocr: false,
+        encryption: false,
+    }
+}
+
+// 删除条目对应的图片文件，返回成功删除的数量
+fn remove_image_files(image_paths: &[String]) -> usize {
+    let mut removed = 0;
+    for image_path in image_paths {
+        if let Err(e) = std::fs::remove_file(resolve_image_path(image_path)) {
+            tracing::info!("删除图片文件失败 {}: {}", image_path, e);
+        } else {
+            tracing::info!("已删除图片文件: {}", image_path);
+            removed += 1;
+        }
+    }
+    removed
+}
+
+/// 删除通过 LAN 队列收到、消息 id 为 `lan_message_id` 且由 `origin` 发出的非收藏条目及其图片，返回被删除的条目 id
+pub(crate) async fn delete_lan_items(app: &AppHandle, lan_message_id: &str, origin: &str) -> Result<Vec<i64>, String> {
+    let db_state = app.try_state::<Mutex<DatabaseState>>()
+        .ok_or_else(|| "无法获取数据库状态".to_string())?;
+    let db_guard = db_state.lock().await;
+    delete_lan_items_in_pool(&db_guard.pool, lan_message_id, origin).await
+}
+
+async fn delete_lan_items_in_pool(pool: &sqlx::SqlitePool, lan_message_id: &str, origin: &str) -> Result<Vec<i64>, String> {
+    // metadata 可能不是合法 JSON（旧数据），先用 json_valid 过滤避免 json_extract 报错；
+    // 只删除原发送者发出的条目，其他成员不能撤回别人共享的内容
+    let rows = sqlx::query(
+        "SELECT id, image_path FROM clipboard_history
+         WHERE is_favorite = 0
+           AND CASE WHEN json_valid(metadata) THEN json_extract(metadata, '$.lan_message_id') END = ?
+           AND CASE WHEN json_valid(metadata) THEN json_extract(metadata, '$.lan_origin') END = ?"
+    )
+        .bind(lan_message_id)
+        .bind(origin)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("查询 LAN 条目失败: {}", e))?;
+
+    let mut ids = Vec::with_capacity(rows.len());
+    let mut image_paths = Vec::new();
+    for row in &rows {
+        ids.push(row.get::<i64, _>("id"));
+        if let Some(path) = row.get::<Option<String>, _>("image_path") {
+            image_paths.push(path);
+        }
+    }
+
+    for id in &ids {
+        with_db_retry("删除 LAN 条目", || sqlx::query("DELETE FROM clipboard_history WHERE id = ?")
+            .bind(id)
+            .execute(pool))
+            .await
+            .map_err(|e| format!("删除 LAN 条目失败: {}", e))?;
+    }
+    remove_image_files(&image_paths);
+
+    Ok(ids)
+}
+
+// 同步历史快照默认/最多包含的条目数
+const LAN_SYNC_DEFAULT_LIMIT: usize = 50;
+const LAN_SYNC_MAX_LIMIT: usize = 500;
+
+// 把历史条目转换为 LAN 条目：文本直接发送，图片读取文件后 base64 编码，其他类型（本地文件路径）不同步。
+// 内容超过 max_payload 字节的条目不同步，图片按文件大小预先判断，不读取放不下的文件。
+// ID 由内容哈希生成，多次同步同一条目时接收方可以去重
+fn history_item_to_lan(item: &ClipboardItem, max_payload: usize) -> Option<crate::lan_queue::LanClipboardItem> {
+    let payload = match item.item_type.as_str() {
+        "text" if item.content.len() <= max_payload => item.content.clone(),
+        "image" => {
+            let path = resolve_image_path(item.image_path.as_deref().unwrap_or(&item.content));
+            let file_len = std::fs::metadata(&path)
+                .map_err(|e| tracing::warn!("⚠️ 读取同步图片失败 {}: {}", path.display(), e))
+                .ok()?
+                .len();
+            // base64 编码后每 3 字节变为 4 字节
+            let encoded_len = usize::try_from(file_len).unwrap_or(usize::MAX).div_ceil(3).saturating_mul(4);
+            if encoded_len > max_payload {
+                tracing::debug!("同步图片过大，跳过: {} ({} 字节)", path.display(), file_len);
+                return None;
+            }
+            let bytes = std::fs::read(&path)
+                .map_err(|e| tracing::warn!("⚠️ 读取同步图片失败 {}: {}", path.display(), e))
+                .ok()?;
+            general_purpose::STANDARD.encode(bytes)
+        }
+        _ => return None,
+    };
+    let hash = item.data_hash.clone().filter(|hash| !hash.is_empty()).unwrap_or_else(|| compute_content_hash(&payload));
+    Some(crate::lan_queue::LanClipboardItem {
+        id: format!("history-{}", hash),
+        kind: item.item_type.clone(),
+        payload,
+        timestamp: item.timestamp.clone(),
+        origin: String::new(),
+        sender_name: None,
+    })
+}
+
+// 条目序列化后能放入剩余预算时计入已用大小并返回 true
+fn fits_snapshot_budget(item: &crate::lan_queue::LanClipboardItem, used: &mut usize, budget: usize) -> bool {
+    // 额外 1 字节为数组中的逗号
+    let size = serde_json::to_vec(item).map(|bytes| bytes.len() + 1).unwrap_or(usize::MAX);
+    if size > budget.saturating_sub(*used) {
+        return false;
+    }
+    *used += size;
+    true
+}
+
+/// 获取最近的历史条目（按时间倒序）作为 LAN 条目，供主机在成员加入时发送历史快照；
+/// 总大小不超过单帧的快照预算，放不下的条目会被跳过
+pub(crate) async fn recent_items_for_sync(app: &AppHandle, limit: usize) -> Result<Vec<crate::lan_queue::LanClipboardItem>, String> {
+    let limit = limit.clamp(1, LAN_SYNC_MAX_LIMIT);
+    let db_state = app.try_state::<Mutex<DatabaseState>>()
+        .ok_or_else(|| "无法获取数据库状态".to_string())?;
+    let items: Vec<ClipboardItem> = {
+        let db_guard = db_state.lock().await;
+        let query = format!(
+            "SELECT {} FROM clipboard_history WHERE type IN ('text', 'image') ORDER BY timestamp DESC LIMIT ?",
+            CLIPBOARD_ITEM_COLUMNS
+        );
+        sqlx::query(&query)
+            .bind(limit as i64)
+            .fetch_all(&db_guard.pool)
+            .await
+            .map_err(|e| format!("查询同步条目失败: {}", e))?
+            .iter()
+            .map(row_to_clipboard_item)
+            .collect()
+    };
+
+    // 读取图片和 base64 编码较慢，放到阻塞线程中执行
+    let (snapshot, skipped, used) = tokio::task::spawn_blocking(move || {
+        let budget = crate::lan_queue::SNAPSHOT_MAX_BYTES;
+        let mut used = 0;
+        let mut skipped = 0;
+        let mut snapshot = Vec::new();
+        for item in &items {
+            match history_item_to_lan(item, budget.saturating_sub(used)) {
+                Some(lan_item) if fits_snapshot_budget(&lan_item, &mut used, budget) => snapshot.push(lan_item),
+                _ => skipped += 1,
+            }
+        }
+        (snapshot, skipped, used)
+    })
+    .await
+    .map_err(|e| format!("准备同步快照失败: {}", e))?;
+    tracing::info!("✅ 准备同步快照: {} 条，跳过 {} 条，约 {} 字节", snapshot.len(), skipped, used);
+    Ok(snapshot)
+}
+
+/// 获取最近的历史条目作为 LAN 条目（默认 50 条，最多 500 条）
+#[tauri::command]
+pub async fn get_recent_for_sync(app: AppHandle, limit: Option<usize>) -> Result<Vec<crate::lan_queue::LanClipboardItem>, String> {
+    recent_items_for_sync(&app, limit.unwrap_or(LAN_SYNC_DEFAULT_LIMIT)).await
+}
+
+/// 将 RFC3339 时间转换为数据库中存储的 UTC 格式
+fn normalize_timestamp_arg(timestamp: &str) -> Result<String, String> {
+    Ok(chrono::DateTime::parse_from_rfc3339(timestamp)
+        .map_err(|e| format!("时间格式无效（需要 RFC3339）: {}", e))?
+        .with_timezone(&chrono::Utc)
+        // 与前端 toISOString() 写入的时间戳格式一致，保证字符串比较正确
+        .to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+}
+
+/// 统计指定时间之后新增的条目数（用于"未读"角标）
+#[tauri::command]
+pub async fn count_since(app: AppHandle, timestamp: String) -> Result<i64, String> {
+    let since = normalize_timestamp_arg(&timestamp)?;
+
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM clipboard_history WHERE timestamp > ?")
+            .bind(&since)
+            .fetch_one(&db_guard.pool)
+            .await
+            .map_err(|e| {
+                let error_msg = format!("统计新增条目失败: {}", e);
+                tracing::error!("❌ {}", error_msg);
+                error_msg
+            })
+    } else {
+        Err("无法获取数据库状态".to_string())
+    }
+}
+
+/// 设置单个条目的到期时间（RFC3339），传 None 取消；到期后由定时清理删除，不受全局保留策略影响
+#[tauri::command]
+pub async fn set_item_expiry(app: AppHandle, id: i64, timestamp: Option<String>) -> Result<Option<String>, String> {
+    let expires_at = timestamp.as_deref().map(normalize_timestamp_arg).transpose()?;
+    tracing::info!("设置条目到期时间: ID={}, expires_at={:?}", id, expires_at);
+
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        let result = with_db_retry("设置条目到期时间", || sqlx::query("UPDATE clipboard_history SET expires_at = ? WHERE id = ?")
+            .bind(&expires_at)
+            .bind(id)
+            .execute(&db_guard.pool))
+            .await
+            .map_err(|e| {
+                let error_msg = format!("设置到期时间失败: {}", e);
+                tracing::error!("❌ {}", error_msg);
+                error_msg
+            })?;
+        if result.rows_affected() == 0 {
+            return Err(format!("条目不存在: ID={}", id));
+        }
+    } else {
+        return Err("无法获取数据库状态".to_string());
+    }
+
+    if let Err(e) = app.emit(HISTORY_CHANGED_EVENT, HistoryChangedPayload { ids: vec![id] }) {
+        tracing::warn!("⚠️ 发送 history-changed 事件失败: {}", e);
+    }
+    Ok(expires_at)
+}
+
+// 删除 expires_at 早于 now 的条目，返回被删除的条目 ID 和图片路径
+async fn delete_items_past_expiry(pool: &sqlx::SqlitePool, now: &str, protect_favorites: bool) -> Result<(Vec<i64>, Vec<String>), sqlx::Error> {
+    let filter = if protect_favorites {
+        "expires_at IS NOT NULL AND expires_at <= ? AND is_favorite = 0"
+    } else {
+        "expires_at IS NOT NULL AND expires_at <= ?"
+    };
+    let mut tx = pool.begin().await?;
+    let rows: Vec<(i64, Option<String>)> = sqlx::query_as(&format!("SELECT id, image_path FROM clipboard_history WHERE {}", filter))
+        .bind(now)
+        .fetch_all(&mut *tx)
+        .await?;
+    if rows.is_empty() {
+        return Ok((Vec::new(), Vec::new()));
+    }
+    sqlx::query(&format!("DELETE FROM clipboard_history WHERE {}", filter))
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    let ids = rows.iter().map(|(id, _)| *id).collect();
+    let image_paths = rows.into_iter().filter_map(|(_, path)| path).collect();
+    Ok((ids, image_paths))
+}
+
+// 删除已到期的条目及其图片，并通知前端移除这些条目
+async fn purge_expired_items_with_pool(app: &AppHandle, pool: &sqlx::SqlitePool, protect_favorites: bool) -> Result<u64, String> {
+    let now = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+    let (ids, image_paths) = delete_items_past_expiry(pool, &now, protect_favorites)
+        .await
+        .map_err(|e| format!("删除到期条目失败: {}", e))?;
+    if ids.is_empty() {
+        return Ok(0);
+    }
+
+    let removed_images = remove_image_files(&image_paths);
+    tracing::info!("✅ 已删除 {} 条到期条目，{} 个图片文件", ids.len(), removed_images);
+    let deleted = ids.len() as u64;
+    if let Err(e) = app.emit(HISTORY_CHANGED_EVENT, HistoryChangedPayload { ids }) {
+        tracing::warn!("⚠️ 发送 history-changed 事件失败: {}", e);
+    }
+    Ok(deleted)
+}
+
+pub(crate) async fn purge_expired_items(app: &AppHandle, protect_favorites: bool) -> Result<u64, String> {
+    let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() else {
+        return Ok(0);
+    };
+    let db_guard = db_state.lock().await;
+    purge_expired_items_with_pool(app, &db_guard.pool, protect_favorites).await
+}
+
+/// 最近新增条目的速度，以及按当前速度达到条数上限的预计天数
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct GrowthStats {
+    pub last_hour: i64,
+    pub last_day: i64,
+    pub last_week: i64,
+    // 按最近一周计算的日均新增条目数
+    pub daily_rate: f64,
+    // 参与按数量清理的条目数（非收藏、非置顶、未分组）
+    pub current_items: i64,
+    pub max_history_items: usize,
+    // 预计多少天后达到 max_history_items；按保留天数清理能维持在上限以下时为 None
+    pub days_until_cap: Option<f64>,
+}
+
+// 按日均新增数估算达到条数上限的天数。稳定状态下保留的条目约为 日均新增 × 保留天数，
+// 不超过上限时永远不会触发按数量清理
+fn project_days_until_cap(current_items: i64, max_items: usize, daily_rate: f64, retention_days: u64) -> Option<f64> {
+    if daily_rate <= 0.0 || daily_rate * retention_days as f64 <= max_items as f64 {
+        return None;
+    }
+    let remaining = max_items as f64 - current_items as f64;
+    Some((remaining / daily_rate).max(0.0))
+}
+
+/// 统计最近一小时/一天/一周新增的条目数，并预计达到条数上限的天数
+#[tauri::command]
+pub async fn get_growth_stats(app: AppHandle) -> Result<GrowthStats, String> {
+    let settings = load_settings(app.clone()).await.unwrap_or_default();
+    let now = chrono::Utc::now();
+    let cutoff = |duration: chrono::Duration| (now - duration).to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        let pool = &db_guard.pool;
+        let (last_week, last_day, last_hour) = sqlx::query_as::<_, (i64, i64, i64)>(
+            "SELECT COUNT(*),
+                    COUNT(CASE WHEN timestamp > ? THEN 1 END),
+                    COUNT(CASE WHEN timestamp > ? THEN 1 END)
+             FROM clipboard_history WHERE timestamp > ?"
+        )
+            .bind(cutoff(chrono::Duration::days(1)))
+            .bind(cutoff(chrono::Duration::hours(1)))
+            .bind(cutoff(chrono::Duration::weeks(1)))
+            .fetch_one(pool)
+            .await
+            .map_err(|e| format!("统计新增条目失败: {}", e))?;
+        let current_items = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM clipboard_history WHERE is_favorite = 0 AND is_pinned = 0 AND group_id IS NULL"
+        )
+            .fetch_one(pool)
+            .await
+            .map_err(|e| format!("统计条目数失败: {}", e))?;
+
+        let daily_rate = last_week as f64 / 7.0;
+        Ok(GrowthStats {
+            last_hour,
+            last_day,
+            last_week,
+            daily_rate,
+            current_items,
+            max_history_items: settings.max_history_items,
+            days_until_cap: project_days_until_cap(current_items, settings.max_history_items, daily_rate, settings.max_history_time),
+        })
+    } else {
+        Err("无法获取数据库状态".to_string())
+    }
+}
+
+// 统计中列出的来源应用数量
+const STATISTICS_TOP_APPS: i64 = 10;
+
+/// 来源应用及其条目数
+#[derive(serde::Serialize, Debug, Clone, PartialEq)]
+pub struct AppItemCount {
+    pub name: String,
+    pub item_count: i64,
+}
+
+/// 剪贴板使用统计
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct ClipboardStatistics {
+    pub total_items: i64,
+    // 各类型（text、image、file 等）的条目数
+    pub type_counts: std::collections::BTreeMap<String, i64>,
+    pub favorite_items: i64,
+    pub grouped_items: i64,
+    // 条目最多的来源应用（最多 10 个，忽略空名称）
+    pub top_source_apps: Vec<AppItemCount>,
+    pub oldest_timestamp: Option<String>,
+    pub newest_timestamp: Option<String>,
+}
+
+async fn collect_statistics(pool: &sqlx::SqlitePool) -> Result<ClipboardStatistics, sqlx::Error> {
+    let (total_items, favorite_items, grouped_items, oldest_timestamp, newest_timestamp) =
+        sqlx::query_as::<_, (i64, i64, i64, Option<String>, Option<String>)>(
+            "SELECT COUNT(*),
+                    COUNT(CASE WHEN is_favorite = 1 THEN 1 END),
+                    COUNT(group_id),
+                    MIN(timestamp),
+                    MAX(timestamp)
+             FROM clipboard_history"
+        )
+            .fetch_one(pool)
+            .await?;
+    let type_counts = sqlx::query_as::<_, (String, i64)>(
+        "SELECT type, COUNT(*) FROM clipboard_history GROUP BY type"
+    )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .collect();
+    let top_source_apps = sqlx::query_as::<_, (String, i64)>(
+        "SELECT source_app_name, COUNT(*) AS item_count FROM clipboard_history
+         WHERE source_app_name IS NOT NULL AND TRIM(source_app_name) != ''
+         GROUP BY source_app_name
+         ORDER BY item_count DESC, source_app_name
+         LIMIT ?"
+    )
+        .bind(STATISTICS_TOP_APPS)
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|(name, item_count)| AppItemCount { name, item_count })
+        .collect();
+
+    Ok(ClipboardStatistics {
+        total_items,
+        type_counts,
+        favorite_items,
+        grouped_items,
+        top_source_apps,
+        oldest_timestamp,
+        newest_timestamp,
+    })
+}
+
+/// 获取剪贴板使用统计：总条目数、各类型数量、收藏和分组数量、常用来源应用以及最早/最新时间
+#[tauri::command]
+pub async fn get_statistics(app: AppHandle) -> Result<ClipboardStatistics, String> {
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        collect_statistics(&db_guard.pool)
+            .await
+            .map_err(|e| {
+                let error_msg = format!("统计剪贴板数据失败: {}", e);
+                tracing::error!("❌ {}", error_msg);
+                error_msg
+            })
+    } else {
+        Err("无法获取数据库状态".to_string())
+    }
+}
+
+/// 删除指定时间之前的所有非收藏、非置顶条目及其图片，返回删除的条目数
+#[tauri::command]
+pub async fn delete_before(app: AppHandle, timestamp: String) -> Result<u64, String> {
+    tracing::info!("删除指定时间之前的条目: {}", timestamp);
+    let cutoff = normalize_timestamp_arg(&timestamp)?;
+
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        let pool = &db_guard.pool;
+
+        let image_paths: Vec<String> = sqlx::query_scalar(
+            "SELECT image_path FROM clipboard_history
+             WHERE timestamp < ? AND is_favorite = 0 AND is_pinned = 0 AND image_path IS NOT NULL"
+        )
+            .bind(&cutoff)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| {
+                let error_msg = format!("查询待删除图片失败: {}", e);
+                tracing::error!("❌ 按时间删除失败: {}", error_msg);
+                error_msg
+            })?;
+
+        let result = with_db_retry("按时间删除条目", || sqlx::query(
+            "DELETE FROM clipboard_history WHERE timestamp < ? AND is_favorite = 0 AND is_pinned = 0"
+        )
+            .bind(&cutoff)
+            .execute(pool))
+            .await
+            .map_err(|e| {
+                let error_msg = format!("删除条目失败: {}", e);
+                tracing::error!("❌ 按时间删除失败: {}", error_msg);
+                error_msg
+            })?;
+
+        let removed_images = remove_image_files(&image_paths);
+        let deleted = result.rows_affected();
+        tracing::info!("✅ 已删除 {} 之前的 {} 条记录，{} 个图片文件", cutoff, deleted, removed_images);
+        Ok(deleted)
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 按时间删除失败: {}", error_msg);
+        Err(error_msg)
+    }
+}
+
+/// 清理非收藏、非置顶、未分组条目的结果
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct ClearTransientResult {
+    pub deleted: u64,
+    // 文本内容与图片文件合计释放的字节数
+    pub bytes_freed: u64,
+}
+
+// 在一个事务中删除所有非收藏、非置顶、未分组的条目，返回删除的条目数、图片路径和文本内容的字节数
+async fn delete_transient_items(pool: &sqlx::SqlitePool) -> Result<(u64, Vec<String>, u64), sqlx::Error> {
+    const FILTER: &str = "is_favorite = 0 AND is_pinned = 0 AND group_id IS NULL";
+    let mut tx = pool.begin().await?;
+    let (content_bytes,): (i64,) = sqlx::query_as(&format!(
+        "SELECT COALESCE(SUM(length(CAST(content AS BLOB))), 0) FROM clipboard_history WHERE {} AND image_path IS NULL",
+        FILTER
+    ))
+        .fetch_one(&mut *tx)
+        .await?;
+    let image_paths: Vec<String> = sqlx::query_scalar(&format!(
+        "SELECT image_path FROM clipboard_history WHERE {} AND image_path IS NOT NULL",
+        FILTER
+    ))
+        .fetch_all(&mut *tx)
+        .await?;
+    let result = sqlx::query(&format!("DELETE FROM clipboard_history WHERE {}", FILTER))
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok((result.rows_affected(), image_paths, content_bytes.max(0) as u64))
+}
+
+/// 立即删除所有非收藏、非置顶、未分组的条目及其图片（不受保留时间和数量限制影响）
+#[tauri::command]
+pub async fn clear_transient_history(app: AppHandle) -> Result<ClearTransientResult, String> {
+    tracing::info!("清理非收藏、非置顶、未分组的条目");
+
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        let (deleted, image_paths, content_bytes) = delete_transient_items(&db_guard.pool)
+            .await
+            .map_err(|e| {
+                let error_msg = format!("删除条目失败: {}", e);
+                tracing::error!("❌ 清理条目失败: {}", error_msg);
+                error_msg
+            })?;
+
+        // 图片文件在删除前统计大小，只计入实际删除成功的文件
+        let mut image_bytes = 0u64;
+        for image_path in &image_paths {
+            let path = resolve_image_path(image_path);
+            let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            if remove_image_files(std::slice::from_ref(image_path)) > 0 {
+                image_bytes += size;
+            }
+        }
+
+        let bytes_freed = content_bytes + image_bytes;
+        tracing::info!("✅ 已清理 {} 条记录，{} 个图片文件，释放 {} 字节", deleted, image_paths.len(), bytes_freed);
+        Ok(ClearTransientResult { deleted, bytes_freed })
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 清理条目失败: {}", error_msg);
+        Err(error_msg)
+    }
+}
+
+// 每次清理时最多为多少条旧图片条目补算 content_hash
+const CONTENT_HASH_BACKFILL_BATCH: i64 = 200;
+
+// 为缺少 content_hash 的图片条目读取文件计算哈希；文件不存在时写入空字符串，避免每次重复读取
+async fn backfill_image_content_hashes(pool: &sqlx::SqlitePool, limit: i64) -> Result<u64, sqlx::Error> {
+    let rows: Vec<(i64, Option<String>, String)> = sqlx::query_as(
+        "SELECT id, image_path, content FROM clipboard_history WHERE type = 'image' AND content_hash IS NULL LIMIT ?"
+    )
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+    let mut updated = 0;
+    for (id, image_path, content) in rows {
+        let path = resolve_image_path(image_path.as_deref().unwrap_or(&content));
+        let hash = fs::read(&path).map(|bytes| compute_bytes_hash(&bytes)).unwrap_or_default();
+        sqlx::query("UPDATE clipboard_history SET content_hash = ? WHERE id = ?")
+            .bind(&hash)
+            .bind(id)
+            .execute(pool)
+            .await?;
+        updated += 1;
+    }
+    Ok(updated)
+}
+
+// 删除与其他条目图片文件相同（content_hash 相同）的多余图片条目：优先保留收藏、置顶、分组、有备注或标签的条目，
+// 其次保留最新的一条；有备注或标签的条目本身不会被删除。返回被删除的条目 ID，图片文件由之后的孤立文件清理删除
+async fn delete_duplicate_images(pool: &sqlx::SqlitePool) -> Result<Vec<i64>, sqlx::Error> {
+    const FILTER: &str = "type = 'image' AND content_hash IS NOT NULL AND content_hash != ''
+        AND is_favorite = 0 AND is_pinned = 0 AND group_id IS NULL
+        AND TRIM(COALESCE(note, '')) = ''
+        AND NOT EXISTS (SELECT 1 FROM tags WHERE tags.item_id = clipboard_history.id)
+        AND EXISTS (
+            SELECT 1 FROM clipboard_history AS other
+            WHERE other.content_hash = clipboard_history.content_hash AND other.id != clipboard_history.id
+              AND (other.is_favorite = 1 OR other.is_pinned = 1 OR other.group_id IS NOT NULL
+                   OR TRIM(COALESCE(other.note, '')) != ''
+                   OR EXISTS (SELECT 1 FROM tags WHERE tags.item_id = other.id)
+                   OR other.timestamp > clipboard_history.timestamp
+                   OR (other.timestamp = clipboard_history.timestamp AND other.id > clipboard_history.id))
+        )";
+    let mut tx = pool.begin().await?;
+    let ids: Vec<i64> = sqlx::query_scalar(&format!("SELECT id FROM clipboard_history WHERE {}", FILTER))
+        .fetch_all(&mut *tx)
+        .await?;
+    if ids.is_empty() {
+        return Ok(ids);
+    }
+    sqlx::query(&format!("DELETE FROM clipboard_history WHERE {}", FILTER))
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(ids)
+}
+
+// 按时间清理的筛选条件：早于 cutoff 的未收藏、未置顶、未分组的条目。item_type 为 Some 时只匹配该类型，
+// 否则匹配除 skip_types 以外的所有类型。返回 SQL 条件和依次绑定的参数
+fn older_than_filter<'a>(cutoff: &'a str, item_type: Option<&'a str>, skip_types: &[&'a str]) -> (String, Vec<&'a str>) {
+    let mut filter = String::from("timestamp < ? AND is_favorite = 0 AND is_pinned = 0 AND group_id IS NULL");
+    let mut params = vec![cutoff];
+    if let Some(item_type) = item_type {
+        filter.push_str(" AND type = ?");
+        params.push(item_type);
+    } else if !skip_types.is_empty() {
+        filter.push_str(&format!(" AND type NOT IN ({})", vec!["?"; skip_types.len()].join(", ")));
+        params.extend_from_slice(skip_types);
+    }
+    (filter, params)
+}
+
+// 按保留设置计算时间清理的截止时间 (类型, 保留天数, 截止时间)：设置了按类型保留天数的类型各一项（按类型名排序），
+// 最后一项类型为 None，按全局的 max_history_time 处理其余类型
+fn retention_cutoffs(settings: &AppSettings) -> Vec<(Option<&str>, u64, String)> {
+    let cutoff_for_days = |days: u64| (chrono::Utc::now() - chrono::Duration::days(days as i64)).to_rfc3339();
+    let mut cutoffs: Vec<(Option<&str>, u64, String)> = settings.retention_by_type
+        .iter()
+        .map(|(item_type, days)| (Some(item_type.as_str()), *days, cutoff_for_days(*days)))
+        .collect();
+    cutoffs.sort();
+    cutoffs.push((None, settings.max_history_time, cutoff_for_days(settings.max_history_time)));
+    cutoffs
+}
+
+// 按保留设置已过期（会被时间清理删除）的条目的 SQL 条件和依次绑定的参数，各截止时间来自 retention_cutoffs
+fn expired_condition<'a>(cutoffs: &'a [(Option<&'a str>, u64, String)]) -> (String, Vec<&'a str>) {
+    let typed: Vec<&str> = cutoffs.iter().filter_map(|(item_type, _, _)| *item_type).collect();
+    let mut filters = Vec::with_capacity(cutoffs.len());
+    let mut params = Vec::new();
+    for (item_type, _, cutoff) in cutoffs {
+        let skip_types: &[&str] = if item_type.is_some() { &[] } else { &typed };
+        let (filter, filter_params) = older_than_filter(cutoff, *item_type, skip_types);
+        filters.push(format!("({})", filter));
+        params.extend(filter_params);
+    }
+    (filters.join(" OR "), params)
+}
+
+// 删除早于 cutoff 的未收藏、未置顶、未分组的条目（条件见 older_than_filter），返回删除的记录数和这些记录的图片路径
+async fn delete_items_older_than(
+    db: &sqlx::SqlitePool,
+    cutoff: &str,
+    item_type: Option<&str>,
+    skip_types: &[&str],
+) -> Result<(u64, Vec<String>), sqlx::Error> {
+    let (filter, params) = older_than_filter(cutoff, item_type, skip_types);
+
+    let images_sql = format!("SELECT image_path FROM clipboard_history WHERE {} AND image_path IS NOT NULL", filter);
+    let mut images_query = sqlx::query_scalar::<_, String>(&images_sql);
+    for param in &params {
+        images_query = images_query.bind(*param);
+    }
+    let image_paths = images_query.fetch_all(db).await?;
+
+    let delete_sql = format!("DELETE FROM clipboard_history WHERE {}", filter);
+    let mut delete_query = sqlx::query(&delete_sql);
+    for param in &params {
+        delete_query = delete_query.bind(*param);
+    }
+    let deleted = delete_query.execute(db).await?.rows_affected();
+    Ok((deleted, image_paths))
+}
+
 // 清理过期的剪贴板历史数据
 async fn cleanup_expired_data(app: &AppHandle, settings: &AppSettings) -> Result<(), String> {
     tracing::info!("开始清理过期数据，设置：max_items={}, max_time={}", settings.max_history_items, settings.max_history_time);
@@ -65,58 +912,65 @@ async fn cleanup_expired_data(app: &AppHandle, settings: &AppSettings) -> Result
         }
     }
     
+    // 0. 收藏数量超出上限时取消最旧的收藏，使其参与后续的按时间/数量清理
+    if let Err(e) = enforce_favorite_cap(db, settings.max_favorites, &settings.favorite_overflow_policy).await {
+        tracing::warn!("⚠️ {}", e);
+    }
+
+    // 0.5 删除已到单条到期时间的条目（不受收藏/置顶/分组保护，除非设置了保护收藏）
+    if let Err(e) = purge_expired_items_with_pool(app, db, settings.expiry_protects_favorites).await {
+        tracing::warn!("⚠️ {}", e);
+    }
+
+    // 0.6 合并重复的图片：先为旧条目补算 content_hash，再删除图片文件相同的多余条目
+    match backfill_image_content_hashes(db, CONTENT_HASH_BACKFILL_BATCH).await {
+        Ok(updated) if updated > 0 => tracing::info!("已为 {} 条图片记录计算 content_hash", updated),
+        Ok(_) => {}
+        Err(e) => tracing::warn!("⚠️ 计算图片 content_hash 失败: {}", e),
+    }
+    match delete_duplicate_images(db).await {
+        Ok(ids) if !ids.is_empty() => {
+            tracing::info!("已删除 {} 条重复的图片记录", ids.len());
+            if let Err(e) = app.emit(HISTORY_CHANGED_EVENT, HistoryChangedPayload { ids }) {
+                tracing::warn!("⚠️ 发送 history-changed 事件失败: {}", e);
+            }
+        }
+        Ok(_) => {}
+        Err(e) => tracing::warn!("⚠️ 删除重复图片记录失败: {}", e),
+    }
+
     // 1. 按时间清理：删除超过指定天数的记录（但保留收藏的）
     // 使用 ISO 格式的时间戳，与前端保持一致
-    let days_ago = chrono::Utc::now() - chrono::Duration::days(settings.max_history_time as i64);
-    let timestamp_cutoff = days_ago.to_rfc3339(); // 使用 ISO 8601 格式
-    
-    tracing::info!("时间清理：删除 {} 之前的记录", timestamp_cutoff);
-    
-    // 首先获取需要删除的图片文件路径
-    let time_images_query = "
-        SELECT image_path FROM clipboard_history
-        WHERE timestamp < ? AND is_favorite = 0 AND is_pinned = 0 AND group_id IS NULL AND image_path IS NOT NULL
-    ";
-    
-    let time_expired_images = match sqlx::query(time_images_query)
-        .bind(&timestamp_cutoff)
-        .fetch_all(db)
-        .await {
-        Ok(rows) => {
-            let mut paths = Vec::new();
-            for row in rows {
-                if let Ok(path) = row.try_get::<String, &str>("image_path") {
-                    paths.push(path);
-                }
+    let cutoffs = retention_cutoffs(settings);
+    let typed: Vec<&str> = cutoffs.iter().filter_map(|(item_type, _, _)| *item_type).collect();
+
+    // 1.1 设置了按类型保留天数的类型单独清理，其余类型使用全局的 max_history_time
+    for (item_type, days, timestamp_cutoff) in &cutoffs {
+        let Some(item_type) = item_type else { continue };
+        tracing::info!("按类型清理：{} 类型保留 {} 天，删除 {} 之前的记录", item_type, days, timestamp_cutoff);
+        match delete_items_older_than(db, timestamp_cutoff, Some(item_type), &[]).await {
+            Ok((deleted, image_paths)) => {
+                let removed_files = remove_image_files(&image_paths);
+                tracing::info!("{} 类型按时间清理完成，删除了 {} 条记录，删除了 {} 个图片文件", item_type, deleted, removed_files);
+            }
+            Err(e) => {
+                tracing::error!("{} 类型按时间清理失败: {}", item_type, e);
+                return Err(format!("按时间清理数据失败: {}", e));
             }
-            paths
-        }
-        Err(e) => {
-            tracing::info!("查询过期图片路径失败: {}", e);
-            Vec::new()
-        }
-    };
-    
-    // 删除过期的图片文件
-    for image_path in &time_expired_images {
-        if let Err(e) = std::fs::remove_file(image_path) {
-            tracing::info!("删除图片文件失败 {}: {}", image_path, e);
-        } else {
-            tracing::info!("已删除图片文件: {}", image_path);
         }
     }
-    
-    let time_cleanup_query = "
-        DELETE FROM clipboard_history
-        WHERE timestamp < ? AND is_favorite = 0 AND is_pinned = 0 AND group_id IS NULL
-    ";
-    
-    match sqlx::query(time_cleanup_query)
-        .bind(&timestamp_cutoff)
-        .execute(db)
-        .await {
-        Ok(result) => {
-            tracing::info!("按时间清理完成，删除了 {} 条记录，删除了 {} 个图片文件", result.rows_affected(), time_expired_images.len());
+
+    let timestamp_cutoff = cutoffs.last().map(|(_, _, cutoff)| cutoff.as_str()).unwrap_or_default();
+    if typed.is_empty() {
+        tracing::info!("时间清理：删除 {} 之前的记录", timestamp_cutoff);
+    } else {
+        tracing::info!("时间清理：删除 {} 之前的记录（不含单独设置保留天数的类型: {:?}）", timestamp_cutoff, typed);
+    }
+
+    match delete_items_older_than(db, timestamp_cutoff, None, &typed).await {
+        Ok((deleted, image_paths)) => {
+            let removed_files = remove_image_files(&image_paths);
+            tracing::info!("按时间清理完成，删除了 {} 条记录，删除了 {} 个图片文件", deleted, removed_files);
         }
         Err(e) => {
             tracing::error!("按时间清理失败: {}", e);
@@ -181,13 +1035,7 @@ async fn cleanup_expired_data(app: &AppHandle, settings: &AppSettings) -> Result
         };
         
         // 删除图片文件
-        for image_path in &count_expired_images {
-            if let Err(e) = std::fs::remove_file(image_path) {
-                tracing::info!("删除图片文件失败 {}: {}", image_path, e);
-            } else {
-                tracing::info!("已删除图片文件: {}", image_path);
-            }
-        }
+        remove_image_files(&count_expired_images);
         
         // 删除最旧的非收藏且非分组记录
         let count_cleanup_query = "
@@ -247,10 +1095,12 @@ async fn cleanup_expired_data(app: &AppHandle, settings: &AppSettings) -> Result
                             let file_path = entry.path();
                             if file_path.is_file() {
                                 let file_path_str = file_path.to_string_lossy().to_string();
+                                let file_name = entry.file_name().to_string_lossy().to_string();
                                 
-                                // 检查数据库中是否存在此文件路径的记录
-                                let check_query = "SELECT COUNT(*) as count FROM clipboard_history WHERE image_path = ?";
+                                // 检查数据库中是否存在此文件路径的记录（相对路径或旧的绝对路径）
+                                let check_query = "SELECT COUNT(*) as count FROM clipboard_history WHERE image_path = ? OR image_path = ?";
                                 match sqlx::query(check_query)
+                                    .bind(&file_name)
                                     .bind(&file_path_str)
                                     .fetch_one(db)
                                     .await {
@@ -292,13 +1142,185 @@ async fn cleanup_expired_data(app: &AppHandle, settings: &AppSettings) -> Result
     Ok(())
 }
 
+/// 收藏数量超过 max_favorites（0 表示不限制）时按 policy 处理最旧的收藏，返回被取消收藏的条目数
+async fn enforce_favorite_cap(db: &sqlx::SqlitePool, max_favorites: usize, policy: &str) -> Result<u64, String> {
+    if max_favorites == 0 || policy != "unfavorite" {
+        return Ok(0);
+    }
+
+    let result = with_db_retry("收藏数量限制", || {
+        sqlx::query(
+            "UPDATE clipboard_history SET is_favorite = 0
+             WHERE id IN (
+                 SELECT id FROM clipboard_history
+                 WHERE is_favorite = 1
+                 ORDER BY timestamp DESC
+                 LIMIT -1 OFFSET ?
+             )",
+        )
+        .bind(max_favorites as i64)
+        .execute(db)
+    })
+    .await
+    .map_err(|e| format!("取消超出上限的收藏失败: {}", e))?;
+
+    if result.rows_affected() > 0 {
+        tracing::info!("收藏数量超出上限 {}，已取消 {} 条最旧的收藏", max_favorites, result.rows_affected());
+    }
+    Ok(result.rows_affected())
+}
+
+/// 记录一次用户活动（捕获、粘贴、打开窗口），空闲压缩以此判断是否空闲
+pub(crate) fn mark_activity(app: &AppHandle) {
+    if let Some(state) = app.try_state::<ActivityState>() {
+        state.mark();
+    }
+}
+
+/// 空闲时压缩数据库：可选地先清理过期数据，再执行 WAL 检查点并截断 -wal 文件
+pub(crate) async fn compact_when_idle(app: &AppHandle, settings: &AppSettings) -> Result<(), String> {
+    if settings.idle_compaction_cleanup {
+        cleanup_expired_data(app, settings).await?;
+    }
+
+    let db_state = app.try_state::<Mutex<DatabaseState>>().ok_or("无法获取数据库状态")?;
+    let db_guard = db_state.lock().await;
+    checkpoint_wal(&db_guard.pool).await
+}
+
+/// 已空闲 idle 时是否应执行空闲压缩；idle_minutes 为 0 表示关闭
+pub(crate) fn idle_compaction_due(idle: std::time::Duration, idle_minutes: u64) -> bool {
+    idle_minutes > 0 && idle >= std::time::Duration::from_secs(idle_minutes * 60)
+}
+
+// 执行 WAL 检查点，把 -wal 文件中的内容合并到数据库文件并截断 -wal 文件
+async fn checkpoint_wal(pool: &sqlx::SqlitePool) -> Result<(), String> {
+    let (busy, log_frames, checkpointed) = sqlx::query_as::<_, (i64, i64, i64)>("PRAGMA wal_checkpoint(TRUNCATE)")
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("执行 WAL 检查点失败: {}", e))?;
+    if busy != 0 {
+        tracing::warn!("⚠️ 空闲压缩时数据库正忙，WAL 检查点未完全完成（{}/{} 帧）", checkpointed, log_frames);
+    } else {
+        tracing::info!("✅ 空闲压缩完成：WAL 检查点合并了 {} 帧", checkpointed);
+    }
+    Ok(())
+}
+
+/// 压缩数据库的结果：前后的文件大小（字节，包含 -wal 文件）和耗时
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct CompactResult {
+    pub size_before: u64,
+    pub size_after: u64,
+    pub duration_ms: u64,
+}
+
+// 数据库文件及其 -wal 文件的总大小
+fn database_file_size(db_path: &Path) -> u64 {
+    [db_path.to_path_buf(), sqlite_sidecar_path(db_path, "-wal")]
+        .iter()
+        .filter_map(|path| fs::metadata(path).ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+// 重建数据库文件以回收删除数据后留下的空闲页，再截断 WAL 模式下 VACUUM 写入的 -wal 文件
+async fn vacuum_pool(pool: &sqlx::SqlitePool) -> Result<(), sqlx::Error> {
+    sqlx::query("VACUUM").execute(pool).await?;
+    sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)").execute(pool).await?;
+    Ok(())
+}
+
+/// 压缩数据库（VACUUM），回收大量删除后的磁盘空间，返回压缩前后的文件大小
+#[tauri::command]
+pub async fn compact_database(app: AppHandle) -> Result<CompactResult, String> {
+    tracing::info!("开始压缩数据库");
+    let db_path = active_profile_db_path(&app)?;
+    let db_state = app.try_state::<Mutex<DatabaseState>>().ok_or("无法获取数据库状态")?;
+    // 持有数据库锁，压缩期间其他命令等待而不是因数据库被锁定而失败
+    let db_guard = db_state.lock().await;
+
+    let size_before = database_file_size(&db_path);
+    let start = std::time::Instant::now();
+    // VACUUM 不能在事务中执行，sqlx 在独立的工作线程上运行 SQLite 语句，不会阻塞异步运行时
+    vacuum_pool(&db_guard.pool).await.map_err(|e| {
+        let error_msg = format!("压缩数据库失败: {}", e);
+        tracing::error!("❌ {}", error_msg);
+        error_msg
+    })?;
+    let duration = start.elapsed();
+    let size_after = database_file_size(&db_path);
+
+    tracing::info!(
+        "✅ 数据库压缩完成: {} -> {} 字节，耗时 {:?}",
+        size_before, size_after, duration
+    );
+    Ok(CompactResult {
+        size_before,
+        size_after,
+        duration_ms: duration.as_millis() as u64,
+    })
+}
+
+// 保存设置时写入的结构版本：前端不关心结构版本，保存的设置至少是当前版本的结构；
+// 设置文件由更新的版本写入时保留其版本号，避免降级运行后再升级时重复执行迁移
+fn settings_version_for_save(existing: u32) -> u32 {
+    existing.max(SETTINGS_VERSION)
+}
+
 #[tauri::command]
-pub async fn save_settings(app: tauri::AppHandle, settings: AppSettings) -> Result<(), String> {
-    tracing::info!("保存设置: {:?}", settings);
+pub async fn save_settings(app: tauri::AppHandle, mut settings: AppSettings) -> Result<(), String> {
+    if let Err(e) = settings.validate() {
+        tracing::warn!("⚠️ 设置无效，未保存: {}", e);
+        return Err(e);
+    }
+    // 在应用新设置之前读取旧设置（load_settings 会按读到的设置更新运行时状态）
+    let previous = load_settings(app.clone()).await.unwrap_or_default();
+    let previous_auto_start_names = previous.auto_start_names.resolved();
+    settings.version = settings_version_for_save(previous.version);
+    settings.capture_formats = normalize_capture_formats(&settings.capture_formats);
+    // 当前配置只能通过 set_active_profile 切换，避免设置中保存的配置与已打开的数据库不一致
+    if app.try_state::<Mutex<DatabaseState>>().is_some() {
+        settings.active_profile = active_profile_name();
+        // 数据库位置同理，只能通过 move_database 修改
+        settings.db_path = db_path_override().map(|path| path.to_string_lossy().to_string());
+    }
+    logging::set_content_preview_len(settings.log_content_preview_len);
+    apply_hide_on_blur(&app, settings.hide_on_blur);
+    apply_manual_capture_mode(&app, &settings);
+    crate::icon_cache::set_icon_cache_size(settings.icon_cache_size);
+    crate::icon_cache::set_icon_cache_ttl(settings.icon_cache_ttl_hours);
+    let mut logged_settings = settings.clone();
+    if !logged_settings.lan_queue_password.is_empty() {
+        logged_settings.lan_queue_password = "***".to_string();
+    }
+    tracing::info!("保存设置: {:?}", logged_settings);
     let path = settings_file_path()?;
     let json = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
-    fs::write(path, json).map_err(|e| e.to_string())?;
+    write_settings_file(&path, &json)?;
+    cache_settings(&settings);
+
+    // 自启动名称变更后移除旧名称下的自启动项，新名称的自启动项由随后的 set_auto_start 创建
+    if previous_auto_start_names != settings.auto_start_names.resolved() {
+        tracing::info!("自启动名称已变更，移除旧的自启动项: {:?}", previous_auto_start_names);
+        if let Err(e) = apply_auto_start(false, &previous_auto_start_names) {
+            tracing::warn!("⚠️ 移除旧的自启动项失败: {}", e);
+        }
+    }
     
+    // 预览长度变化后在后台重建触发器和已有预览，历史较多时不阻塞保存设置，也不长时间占用数据库锁
+    if settings.list_preview_chars != previous.list_preview_chars {
+        if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+            let pool = db_state.lock().await.pool.clone();
+            let preview_chars = settings.list_preview_chars;
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = sync_content_previews(&pool, preview_chars).await {
+                    tracing::warn!("⚠️ {}", e);
+                }
+            });
+        }
+    }
+
     tracing::info!("设置已保存，开始执行清理");
     // 保存设置后自动清理过期数据
     match cleanup_expired_data(&app, &settings).await {
@@ -309,20 +1331,213 @@ pub async fn save_settings(app: tauri::AppHandle, settings: AppSettings) -> Resu
     Ok(())
 }
 
+// 最近一次读取或保存的设置，供捕获剪贴板时使用，避免每次捕获都读取设置文件
+static CACHED_SETTINGS: std::sync::RwLock<Option<AppSettings>> = std::sync::RwLock::new(None);
+
+fn cache_settings(settings: &AppSettings) {
+    if let Ok(mut guard) = CACHED_SETTINGS.write() {
+        *guard = Some(settings.clone());
+    }
+}
+
+// 获取缓存的设置，尚未缓存时从设置文件读取
+async fn cached_settings(app: &AppHandle) -> AppSettings {
+    let cached = CACHED_SETTINGS.read().ok().and_then(|guard| guard.clone());
+    match cached {
+        Some(settings) => settings,
+        None => load_settings(app.clone()).await.unwrap_or_default(),
+    }
+}
+
 #[tauri::command]
-pub async fn load_settings(_app: tauri::AppHandle) -> Result<AppSettings, String> {
+pub async fn load_settings(app: tauri::AppHandle) -> Result<AppSettings, String> {
     let path = settings_file_path()?;
-    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
-    let settings: AppSettings = serde_json::from_str(&content).map_err(|e| e.to_string())?;
+    let (mut settings, migrated) = read_settings_file(&path)?;
+    if migrated {
+        tracing::info!("设置文件已升级到版本 {}", SETTINGS_VERSION);
+        match serde_json::to_string_pretty(&settings) {
+            Ok(json) => {
+                if let Err(e) = write_settings_file(&path, &json) {
+                    tracing::warn!("⚠️ 写回升级后的设置失败: {}", e);
+                }
+            }
+            Err(e) => tracing::warn!("⚠️ 序列化升级后的设置失败: {}", e),
+        }
+    }
+    settings.capture_formats = normalize_capture_formats(&settings.capture_formats);
+    logging::set_content_preview_len(settings.log_content_preview_len);
+    apply_hide_on_blur(&app, settings.hide_on_blur);
+    cache_settings(&settings);
     Ok(settings)
 }
 
+// 同步失去焦点自动隐藏的设置到运行时状态
+fn apply_hide_on_blur(app: &AppHandle, enabled: bool) {
+    if let Some(state) = app.try_state::<BlurHideState>() {
+        state.enabled.store(enabled, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+/// 暂停/恢复失去焦点自动隐藏（前端在钉住窗口或打开原生对话框期间调用）
+#[tauri::command]
+pub fn set_blur_hide_suppressed(app: AppHandle, suppressed: bool) {
+    if let Some(state) = app.try_state::<BlurHideState>() {
+        state.set_suppressed(suppressed);
+        tracing::debug!("失去焦点自动隐藏已{}", if suppressed { "暂停" } else { "恢复" });
+    }
+}
+
+// 同步手动捕获模式到运行时状态，并按需注册/注销手动捕获快捷键（与主快捷键相同时不注册）
+fn apply_manual_capture_mode(app: &AppHandle, settings: &AppSettings) {
+    let Some(state) = app.try_state::<CaptureControlState>() else { return };
+    let was_manual = state.manual.swap(settings.manual_capture_mode, std::sync::atomic::Ordering::Relaxed);
+    if was_manual != settings.manual_capture_mode {
+        tracing::info!("手动捕获模式已{}", if settings.manual_capture_mode { "开启" } else { "关闭" });
+        if let Err(e) = app.emit(MANUAL_CAPTURE_MODE_EVENT, settings.manual_capture_mode) {
+            tracing::warn!("⚠️ 发送手动捕获模式事件失败: {}", e);
+        }
+    }
+
+    let desired = if settings.manual_capture_mode {
+        let main_hotkey = normalize_shortcut_for_macos(&settings.hotkey).unwrap_or_default();
+        match normalize_shortcut_for_macos(&settings.manual_capture_hotkey) {
+            Ok(hotkey) if hotkey.eq_ignore_ascii_case(&main_hotkey) => {
+                tracing::warn!("⚠️ 手动捕获快捷键与主快捷键相同，未注册: {}", hotkey);
+                None
+            }
+            Ok(hotkey) => hotkey
+                .parse::<Shortcut>()
+                .map_err(|e| tracing::warn!("⚠️ 手动捕获快捷键格式无效 {}: {}", hotkey, e))
+                .ok(),
+            Err(e) => {
+                tracing::warn!("⚠️ 手动捕获快捷键格式无效: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let Ok(mut current) = state.manual_shortcut.lock() else { return };
+    let shortcuts = app.global_shortcut();
+    if *current == desired && desired.is_none_or(|shortcut| shortcuts.is_registered(shortcut)) {
+        return;
+    }
+    if let Some(previous) = current.take() {
+        if shortcuts.is_registered(previous) {
+            let _ = shortcuts.unregister(previous);
+        }
+    }
+    if let Some(shortcut) = desired {
+        match shortcuts.register(shortcut) {
+            Ok(()) => {
+                tracing::info!("✅ 手动捕获快捷键已注册: {}", settings.manual_capture_hotkey);
+                *current = Some(shortcut);
+            }
+            Err(e) => tracing::warn!("⚠️ 注册手动捕获快捷键失败 {}: {}", settings.manual_capture_hotkey, e),
+        }
+    }
+}
+
+/// 判断按下的全局快捷键是否为手动捕获快捷键
+pub(crate) fn is_manual_capture_shortcut(app: &AppHandle, shortcut: &Shortcut) -> bool {
+    app.try_state::<CaptureControlState>()
+        .and_then(|state| state.manual_shortcut.lock().ok().map(|current| *current == Some(*shortcut)))
+        .unwrap_or(false)
+}
+
+/// 开启/关闭手动捕获模式：开启后不再自动记录剪贴板，按下手动捕获快捷键时才记录
+#[tauri::command]
+pub async fn set_manual_capture_mode(app: AppHandle, enabled: bool) -> Result<bool, String> {
+    let mut settings = load_settings(app.clone()).await.unwrap_or_default();
+    settings.manual_capture_mode = enabled;
+    save_settings(app, settings).await?;
+    Ok(enabled)
+}
+
+// UI 视图状态（当前分组、类型筛选、搜索等）的读写锁，避免多个窗口同时写入时互相覆盖
+static UI_STATE_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+fn ui_state_file_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_data_dir().map_err(|e| format!("无法获取应用数据目录: {}", e))?;
+    Ok(dir.join(UI_STATE_FILE))
+}
+
+// 读取 UI 状态文件；文件不存在或内容损坏时视为空
+fn read_ui_state(path: &std::path::Path) -> serde_json::Map<String, serde_json::Value> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return serde_json::Map::new();
+    };
+    match serde_json::from_str(&content) {
+        Ok(serde_json::Value::Object(map)) => map,
+        _ => {
+            tracing::warn!("⚠️ UI 状态文件格式无效，已忽略: {}", path.display());
+            serde_json::Map::new()
+        }
+    }
+}
+
+// 写入单个键，value 为 null 时删除该键
+fn write_ui_state_entry(path: &std::path::Path, key: &str, value: serde_json::Value) -> Result<(), String> {
+    let mut state = read_ui_state(path);
+    if value.is_null() {
+        state.remove(key);
+    } else {
+        state.insert(key.to_string(), value);
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("创建数据目录失败: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(&state).map_err(|e| e.to_string())?;
+    fs::write(path, json).map_err(|e| format!("写入 UI 状态失败: {}", e))
+}
+
+/// 读取 UI 视图状态：指定 key 时返回该键的值（不存在时为 null），否则返回全部状态
+#[tauri::command]
+pub fn get_ui_state(app: AppHandle, key: Option<String>) -> Result<serde_json::Value, String> {
+    let path = ui_state_file_path(&app)?;
+    let _guard = UI_STATE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut state = read_ui_state(&path);
+    Ok(match key {
+        Some(key) => state.remove(&key).unwrap_or(serde_json::Value::Null),
+        None => serde_json::Value::Object(state),
+    })
+}
+
+/// 保存 UI 视图状态中的一个键（与 AppSettings 分开存储，不限制结构）；value 为 null 时删除该键
+#[tauri::command]
+pub fn set_ui_state(app: AppHandle, key: String, value: serde_json::Value) -> Result<(), String> {
+    if key.trim().is_empty() {
+        return Err("UI 状态的键不能为空".to_string());
+    }
+    let path = ui_state_file_path(&app)?;
+    let _guard = UI_STATE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    write_ui_state_entry(&path, &key, value).map_err(|e| {
+        tracing::error!("❌ 保存 UI 状态失败: key={}, {}", key, e);
+        e
+    })?;
+    tracing::debug!("UI 状态已保存: key={}", key);
+    Ok(())
+}
+
+/// 规范化捕获格式列表：去除未知格式和重复项，保留用户设定的优先级顺序
+fn normalize_capture_formats(formats: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for format in formats {
+        let format = format.trim().to_lowercase();
+        if CAPTURE_FORMATS.contains(&format.as_str()) && !normalized.contains(&format) {
+            normalized.push(format);
+        }
+    }
+    normalized
+}
+
 #[tauri::command]
 pub async fn register_shortcut(app: AppHandle, shortcut: String) -> Result<(), String> {
     tracing::info!("尝试注册快捷键: {}", shortcut);
-    
-    // 先尝试注销已有的快捷键
-    let _ = app.global_shortcut().unregister_all();
+    if shortcut.trim().is_empty() {
+        return Err("快捷键不能为空".to_string());
+    }
     
     // macOS 特殊处理：标准化快捷键格式
     let normalized_shortcut = normalize_shortcut_for_macos(&shortcut)?;
@@ -335,6 +1550,9 @@ pub async fn register_shortcut(app: AppHandle, shortcut: String) -> Result<(), S
         error_msg
     })?;
     
+    // 快捷键有效后再注销已有的快捷键，无效输入不会让原来的快捷键失效
+    let _ = app.global_shortcut().unregister_all();
+    
     // 注册快捷键
     app.global_shortcut().register(shortcut_parsed).map_err(|e| {
         let error_str = e.to_string();
@@ -355,9 +1573,106 @@ pub async fn register_shortcut(app: AppHandle, shortcut: String) -> Result<(), S
     })?;
     
     tracing::info!("快捷键注册成功: {}", normalized_shortcut);
+
+    // 上面注销了所有快捷键，重新注册手动捕获快捷键
+    if let Ok(settings) = load_settings(app.clone()).await {
+        apply_manual_capture_mode(&app, &settings);
+    }
     Ok(())
 }
 
+/// 快捷键可用性探测结果
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct HotkeyProbeResult {
+    pub shortcut: String,
+    pub available: bool,
+    pub error: Option<String>,
+}
+
+/// 探测快捷键是否可以被系统注册（是否已被其他应用占用）
+/// 临时注册后立即注销，并确保应用自身的快捷键保持注册状态
+#[tauri::command]
+pub async fn probe_hotkey(app: AppHandle, shortcut: String) -> Result<HotkeyProbeResult, String> {
+    let normalized_shortcut = normalize_shortcut_for_macos(&shortcut)?;
+    let shortcut_parsed = normalized_shortcut.parse::<Shortcut>()
+        .map_err(|e| format!("Invalid hotkey format: {}", e))?;
+
+    // 已经是本应用注册的快捷键，视为可用
+    if app.global_shortcut().is_registered(shortcut_parsed) {
+        return Ok(HotkeyProbeResult {
+            shortcut: normalized_shortcut,
+            available: true,
+            error: None,
+        });
+    }
+
+    let result = match app.global_shortcut().register(shortcut_parsed) {
+        Ok(()) => {
+            let _ = app.global_shortcut().unregister(shortcut_parsed);
+            HotkeyProbeResult {
+                shortcut: normalized_shortcut,
+                available: true,
+                error: None,
+            }
+        }
+        Err(e) => HotkeyProbeResult {
+            shortcut: normalized_shortcut,
+            available: false,
+            error: Some(e.to_string()),
+        },
+    };
+
+    // 无论探测结果如何，确保应用自身的快捷键仍然有效
+    let settings = load_settings(app.clone()).await.unwrap_or_default();
+    let own_shortcut = normalize_shortcut_for_macos(&settings.hotkey)
+        .ok()
+        .and_then(|hotkey| hotkey.parse::<Shortcut>().ok());
+    if let Some(own_shortcut) = own_shortcut {
+        if !app.global_shortcut().is_registered(own_shortcut) {
+            if let Err(e) = app.global_shortcut().register(own_shortcut) {
+                tracing::warn!("⚠️ 探测后恢复快捷键失败: {}", e);
+            }
+        }
+    }
+
+    tracing::info!("快捷键探测: {} 可用={}", result.shortcut, result.available);
+    Ok(result)
+}
+
+/// 单个快捷键的注册结果
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct ShortcutRegistrationResult {
+    pub shortcut: String,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+/// 按当前设置重新注册全局快捷键（与启动时的注册逻辑一致），用于快捷键失效后的恢复
+#[tauri::command]
+pub async fn reapply_shortcuts(app: AppHandle) -> Result<Vec<ShortcutRegistrationResult>, String> {
+    // 没有保存的设置时与启动流程一样使用默认快捷键
+    let settings = load_settings(app.clone()).await.unwrap_or_default();
+    tracing::info!("重新注册快捷键: {}", settings.hotkey);
+
+    let result = match register_shortcut(app, settings.hotkey.clone()).await {
+        Ok(()) => ShortcutRegistrationResult {
+            shortcut: settings.hotkey,
+            success: true,
+            error: None,
+        },
+        Err(e) => {
+            tracing::warn!("❌ 重新注册快捷键失败: {}", e);
+            ShortcutRegistrationResult {
+                shortcut: settings.hotkey,
+                success: false,
+                error: Some(e),
+            }
+        }
+    };
+
+    Ok(vec![result])
+}
+
 // macOS 快捷键格式标准化函数
 fn normalize_shortcut_for_macos(shortcut: &str) -> Result<String, String> {
     #[cfg(target_os = "macos")]
@@ -516,15 +1831,22 @@ fn set_windows_auto_start(enable: bool, app_name: &str, exe_path: &PathBuf) -> R
     }
 }
 
+// 设置中配置的自启动名称（空白项使用默认值）
+async fn configured_auto_start_names(app: &AppHandle) -> AutoStartNames {
+    load_settings(app.clone()).await.unwrap_or_default().auto_start_names.resolved()
+}
+
 #[tauri::command]
 pub async fn set_auto_start(app: AppHandle, enable: bool) -> Result<(), String> {
-    let app_name = "Clipboard Manager"; // 显示名称
-    let bundle_id = "com.clipboardmanager.app"; // Bundle ID
-    
+    let names = configured_auto_start_names(&app).await;
+    apply_auto_start(enable, &names)
+}
+
+fn apply_auto_start(enable: bool, names: &AutoStartNames) -> Result<(), String> {
     #[cfg(target_os = "windows")]
     {
         let exe_path = get_app_exe_path()?;
-        set_windows_auto_start(enable, "ClipboardManager", &exe_path).map_err(|e| {
+        set_windows_auto_start(enable, &names.registry_name, &exe_path).map_err(|e| {
             format!("Failed to update auto-start settings: {}", e)
         })?;
     }
@@ -532,7 +1854,7 @@ pub async fn set_auto_start(app: AppHandle, enable: bool) -> Result<(), String>
     #[cfg(target_os = "macos")]
     {
         let exe_path = get_app_exe_path()?;
-        set_macos_auto_start(enable, app_name, bundle_id, &exe_path).map_err(|e| {
+        set_macos_auto_start(enable, &names.display_name, &names.bundle_id, &exe_path).map_err(|e| {
             format!("设置 macOS 自启动失败: {}", e)
         })?;
     }
@@ -540,7 +1862,7 @@ pub async fn set_auto_start(app: AppHandle, enable: bool) -> Result<(), String>
     #[cfg(target_os = "linux")]
     {
         let exe_path = get_app_exe_path()?;
-        set_linux_auto_start(enable, app_name, &exe_path).map_err(|e| {
+        set_linux_auto_start(enable, &names.display_name, &exe_path).map_err(|e| {
             format!("设置 Linux 自启动失败: {}", e)
         })?;
     }
@@ -548,21 +1870,53 @@ pub async fn set_auto_start(app: AppHandle, enable: bool) -> Result<(), String>
     Ok(())
 }
 
+/// 设置主窗口是否置顶（与自启动一样，设置的保存由 save_settings 负责）
+#[tauri::command]
+pub async fn set_always_on_top(app: AppHandle, enabled: bool) -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    {
+        // macOS 上主窗口是 NSPanel，init_macos_panel 将其层级设为 Floating 以便在全屏应用上弹出，本身已位于普通窗口之上。
+        // 关闭置顶时不能降到 Normal，否则无法再覆盖全屏应用；这里只恢复 Floating 层级，
+        // 避免 NSWindow 的置顶设置覆盖 panel 的配置
+        use tauri_nspanel::{ManagerExt, PanelLevel};
+        let app_handle = app.clone();
+        app.run_on_main_thread(move || {
+            if let Ok(panel) = app_handle.get_webview_panel("main") {
+                panel.set_level(PanelLevel::Floating.value());
+            }
+        })
+            .map_err(|e| format!("设置窗口置顶失败: {}", e))?;
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        let window = app.get_webview_window("main")
+            .ok_or_else(|| "找不到主窗口".to_string())?;
+        window.set_always_on_top(enabled)
+            .map_err(|e| format!("设置窗口置顶失败: {}", e))?;
+    }
+
+    tracing::info!("窗口置顶已{}", if enabled { "开启" } else { "关闭" });
+    Ok(())
+}
+
 #[tauri::command]
-pub async fn get_auto_start_status(_app: AppHandle) -> Result<bool, String> {
+pub async fn get_auto_start_status(app: AppHandle) -> Result<bool, String> {
+    let names = configured_auto_start_names(&app).await;
+
     #[cfg(target_os = "windows")]
     {
-        get_windows_auto_start_status("ClipboardManager")
+        get_windows_auto_start_status(&names.registry_name)
     }
     
     #[cfg(target_os = "macos")]
     {
-        get_macos_auto_start_status("Clipboard Manager", "com.clipboardmanager.app")
+        get_macos_auto_start_status(&names.display_name, &names.bundle_id)
     }
     
     #[cfg(target_os = "linux")]
     {
-        get_linux_auto_start_status("Clipboard Manager")
+        get_linux_auto_start_status(&names.display_name)
     }
 }
 
@@ -715,6 +2069,12 @@ fn get_macos_auto_start_status(app_name: &str, bundle_id: &str) -> Result<bool,
     Ok(false)
 }
 
+// 转义 AppleScript 字符串字面量中的反斜杠和双引号
+#[cfg(any(target_os = "macos", test))]
+fn applescript_string(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
 // 使用 AppleScript 添加到登录项（优化版本）
 #[cfg(target_os = "macos")]
 fn add_to_login_items_applescript(app_name: &str, exe_path: &PathBuf) -> Result<(), String> {
@@ -744,7 +2104,7 @@ tell application "System Events"
         return "ERROR: " & errMsg
     end try
 end tell
-    "#, app_bundle_path, app_bundle_path);
+    "#, applescript_string(&app_bundle_path), applescript_string(&app_bundle_path));
     
     let output = Command::new("osascript")
         .arg("-e")
@@ -829,7 +2189,7 @@ tell application "System Events"
         return "ERROR: " & errMsg
     end try
 end tell
-    "#, app_name, app_name);
+    "#, applescript_string(app_name), applescript_string(app_name));
     
     let output = Command::new("osascript")
         .arg("-e")
@@ -862,7 +2222,7 @@ tell application "System Events"
     end repeat
     return "NOT_FOUND"
 end tell
-    "#, app_name);
+    "#, applescript_string(app_name));
     
     let output = Command::new("osascript")
         .arg("-e")
@@ -1008,18 +2368,7 @@ fn set_linux_auto_start(enable: bool, app_name: &str, exe_path: &PathBuf) -> Res
             .map_err(|e| format!("创建 autostart 目录失败: {}", e))?;
         
         // 创建 .desktop 文件
-        let desktop_content = format!(r#"[Desktop Entry]
-Type=Application
-Version=1.0
-Name={}
-Comment=Clipboard Manager for productivity
-Exec={}
-Icon=clipboard
-Terminal=false
-StartupNotify=false
-Hidden=false
-X-GNOME-Autostart-enabled=true
-"#, app_name, exe_path.to_string_lossy());
+        let desktop_content = linux_autostart_desktop_entry(app_name, exe_path);
         
         fs::write(&desktop_path, desktop_content)
             .map_err(|e| format!("写入 .desktop 文件失败: {}", e))?;
@@ -1038,6 +2387,68 @@ X-GNOME-Autostart-enabled=true
     Ok(())
 }
 
+// .desktop 文件中字符串值的转义（Desktop Entry 规范）
+#[cfg(target_os = "linux")]
+fn desktop_entry_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+        .replace('\r', "\\r")
+}
+
+// Exec 键中的单个参数：含保留字符时用双引号包裹并转义 " ` $ \，% 写成 %%，最后再按字符串值规则转义
+#[cfg(target_os = "linux")]
+fn desktop_exec_arg(arg: &str) -> String {
+    const RESERVED: &[char] = &[' ', '\t', '\n', '"', '\'', '\\', '>', '<', '~', '|', '&', ';', '$', '*', '?', '#', '(', ')', '`'];
+    let arg = arg.replace('%', "%%");
+    let arg = if arg.contains(RESERVED) {
+        let mut quoted = String::from("\"");
+        for c in arg.chars() {
+            if matches!(c, '"' | '`' | '$' | '\\') {
+                quoted.push('\\');
+            }
+            quoted.push(c);
+        }
+        quoted.push('"');
+        quoted
+    } else {
+        arg
+    };
+    desktop_entry_escape(&arg)
+}
+
+// 生成自启动 .desktop 文件内容（可通过 desktop-file-validate 校验）；
+// StartupWMClass 使用可执行文件名，与 GTK 默认的 WM_CLASS 一致，便于任务栏将窗口关联到应用
+#[cfg(target_os = "linux")]
+fn linux_autostart_desktop_entry(app_name: &str, exe_path: &std::path::Path) -> String {
+    let exe_path_str = exe_path.to_string_lossy();
+    let wm_class = exe_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| app_name.to_string());
+    format!(r#"[Desktop Entry]
+Type=Application
+Version=1.0
+Name={}
+Comment=Clipboard Manager for productivity
+Exec={}
+TryExec={}
+Icon=clipboard
+Terminal=false
+Categories=Utility;
+StartupNotify=false
+StartupWMClass={}
+Hidden=false
+X-GNOME-Autostart-enabled=true
+"#,
+        desktop_entry_escape(app_name),
+        desktop_exec_arg(&exe_path_str),
+        desktop_entry_escape(&exe_path_str),
+        desktop_entry_escape(&wm_class),
+    )
+}
+
 #[cfg(target_os = "linux")]
 fn get_linux_auto_start_status(app_name: &str) -> Result<bool, String> {
     use std::path::Path;
@@ -1061,36 +2472,316 @@ fn get_linux_auto_start_status(app_name: &str) -> Result<bool, String> {
 #[tauri::command]
 pub async fn cleanup_history(app: AppHandle) -> Result<(), String> {
     // 加载当前设置
-    let settings = load_settings(app.clone()).await.unwrap_or_else(|_| AppSettings {
-        max_history_items: 100,
-        max_history_time: 30,
-        hotkey: "Ctrl+Shift+V".to_string(),
-        auto_start: false,
-        lan_queue_role: "off".to_string(),
-        lan_queue_host: String::new(),
-        lan_queue_port: 21991,
-        lan_queue_password: String::new(),
-        lan_queue_name: "LAN Queue".to_string(),
-        lan_queue_member_name: String::new(),
-        theme: "light".to_string(),
-    });
+    let settings = load_settings(app.clone()).await.unwrap_or_default();
     
-    cleanup_expired_data(&app, &settings).await
+    let result = cleanup_expired_data(&app, &settings).await;
+    report_command_error(&app, "cleanup_history", result)
 }
 
-// 改进的自动粘贴功能 - 先激活目标应用，再执行粘贴
-#[tauri::command]
-pub async fn auto_paste(app: AppHandle) -> Result<(), String> {
-    tracing::info!("开始执行智能自动粘贴...");
-    
-    #[cfg(target_os = "macos")]
-    {
-        macos_simple_paste(app)
+/// 图片清理预估结果（按清理原因分别统计）
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct ImageCleanupEstimate {
+    pub time_files: u64,
+    pub time_bytes: u64,
+    pub count_files: u64,
+    pub count_bytes: u64,
+    pub total_files: u64,
+    pub total_bytes: u64,
+}
+
+// 统计图片文件数量和总大小（不存在的文件不计入）
+fn sum_image_sizes(paths: &[String]) -> (u64, u64) {
+    let mut files = 0;
+    let mut bytes = 0;
+    for path in paths {
+        if let Ok(metadata) = std::fs::metadata(resolve_image_path(path)) {
+            files += 1;
+            bytes += metadata.len();
+        }
     }
-    
-    #[cfg(target_os = "windows")]
-    {
-        // 在新线程中执行粘贴操作
+    (files, bytes)
+}
+
+async fn estimate_image_cleanup_in_pool(pool: &sqlx::SqlitePool, settings: &AppSettings) -> Result<ImageCleanupEstimate, String> {
+    // 1. 按时间清理会删除的图片：与 cleanup_expired_data 使用相同的按类型截止时间和筛选条件
+    let cutoffs = retention_cutoffs(settings);
+    let (expired, expired_params) = expired_condition(&cutoffs);
+
+    let time_sql = format!("SELECT image_path FROM clipboard_history WHERE ({}) AND image_path IS NOT NULL", expired);
+    let mut time_query = sqlx::query_scalar::<_, String>(&time_sql);
+    for param in &expired_params {
+        time_query = time_query.bind(*param);
+    }
+    let time_images = time_query
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("查询过期图片路径失败: {}", e))?;
+
+    // 2. 按数量清理会删除的图片：实际清理在按时间清理之后执行，所以只统计未过期的记录
+    let kept_filter = format!("is_favorite = 0 AND is_pinned = 0 AND group_id IS NULL AND NOT ({})", expired);
+    let count_sql = format!("SELECT COUNT(*) FROM clipboard_history WHERE {}", kept_filter);
+    let mut count_query = sqlx::query_scalar::<_, i64>(&count_sql);
+    for param in &expired_params {
+        count_query = count_query.bind(*param);
+    }
+    let remaining_count = count_query
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("查询记录数量失败: {}", e))?;
+
+    let excess_count = remaining_count - settings.max_history_items as i64;
+    let count_images: Vec<String> = if excess_count > 0 {
+        let count_images_sql = format!(
+            "SELECT image_path FROM clipboard_history
+             WHERE image_path IS NOT NULL
+             AND id IN (SELECT id FROM clipboard_history WHERE {} ORDER BY timestamp ASC LIMIT ?)",
+            kept_filter
+        );
+        let mut count_images_query = sqlx::query_scalar::<_, String>(&count_images_sql);
+        for param in &expired_params {
+            count_images_query = count_images_query.bind(*param);
+        }
+        count_images_query
+            .bind(excess_count)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| format!("查询需删除图片路径失败: {}", e))?
+    } else {
+        Vec::new()
+    };
+
+    let (time_files, time_bytes) = sum_image_sizes(&time_images);
+    let (count_files, count_bytes) = sum_image_sizes(&count_images);
+    Ok(ImageCleanupEstimate {
+        time_files,
+        time_bytes,
+        count_files,
+        count_bytes,
+        total_files: time_files + count_files,
+        total_bytes: time_bytes + count_bytes,
+    })
+}
+
+/// 预估按保留设置清理时会删除的图片文件数量和大小，不实际删除
+/// 未传入设置时使用当前保存的设置
+#[tauri::command]
+pub async fn estimate_image_cleanup(app: AppHandle, settings: Option<AppSettings>) -> Result<ImageCleanupEstimate, String> {
+    let settings = match settings {
+        Some(settings) => settings,
+        None => load_settings(app.clone()).await.unwrap_or_default(),
+    };
+    tracing::info!("预估图片清理，设置：max_items={}, max_time={}", settings.max_history_items, settings.max_history_time);
+
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        let estimate = estimate_image_cleanup_in_pool(&db_guard.pool, &settings).await?;
+
+        tracing::info!("✅ 图片清理预估: {:?}", estimate);
+        Ok(estimate)
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 预估图片清理失败: {}", error_msg);
+        Err(error_msg)
+    }
+}
+
+/// 按来源应用统计的图片磁盘占用
+#[derive(serde::Serialize, Debug, Clone, PartialEq)]
+pub struct AppImageUsage {
+    pub source_app_name: Option<String>,
+    pub items: u64,
+    pub files: u64,
+    pub bytes: u64,
+}
+
+// 按来源应用汇总图片占用：同一应用下重复引用的文件只计一次，不存在的文件不计入大小，结果按占用从大到小排序
+fn summarize_image_usage(
+    rows: Vec<(Option<String>, String)>,
+    file_size: impl Fn(&str) -> Option<u64>,
+) -> Vec<AppImageUsage> {
+    let mut usage: std::collections::HashMap<Option<String>, (AppImageUsage, std::collections::HashSet<String>)> = std::collections::HashMap::new();
+    for (source_app_name, image_path) in rows {
+        let source_app_name = source_app_name.filter(|name| !name.trim().is_empty());
+        let (entry, seen) = usage.entry(source_app_name.clone()).or_insert_with(|| {
+            (AppImageUsage { source_app_name, items: 0, files: 0, bytes: 0 }, std::collections::HashSet::new())
+        });
+        entry.items += 1;
+        if seen.insert(image_path.clone()) {
+            if let Some(size) = file_size(&image_path) {
+                entry.files += 1;
+                entry.bytes += size;
+            }
+        }
+    }
+
+    let mut usage: Vec<AppImageUsage> = usage.into_values().map(|(entry, _)| entry).collect();
+    usage.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.source_app_name.cmp(&b.source_app_name)));
+    usage
+}
+
+/// 统计各来源应用的图片占用的磁盘空间（来源未知的条目归为 source_app_name = null）
+#[tauri::command]
+pub async fn get_image_usage_by_app(app: AppHandle) -> Result<Vec<AppImageUsage>, String> {
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let rows: Vec<(Option<String>, String)> = {
+            let db_guard = db_state.lock().await;
+            sqlx::query_as("
+                SELECT source_app_name, image_path FROM clipboard_history
+                WHERE type = 'image' AND image_path IS NOT NULL
+            ")
+                .fetch_all(&db_guard.pool)
+                .await
+                .map_err(|e| format!("查询图片记录失败: {}", e))?
+        };
+
+        // 读取文件大小不需要持有数据库锁
+        let usage = tokio::task::spawn_blocking(move || {
+            summarize_image_usage(rows, |path| std::fs::metadata(resolve_image_path(path)).ok().map(|metadata| metadata.len()))
+        })
+            .await
+            .map_err(|e| format!("统计图片占用失败: {}", e))?;
+
+        tracing::info!("✅ 图片占用统计完成: {} 个来源应用", usage.len());
+        Ok(usage)
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 统计图片占用失败: {}", error_msg);
+        Err(error_msg)
+    }
+}
+
+// 按设置规范化文本换行符（"lf" / "crlf"，其他值保持原样）
+fn normalize_line_endings(text: &str, style: &str) -> String {
+    match style {
+        "lf" => text.replace("\r\n", "\n").replace('\r', "\n"),
+        "crlf" => text
+            .replace("\r\n", "\n")
+            .replace('\r', "\n")
+            .replace('\n', "\r\n"),
+        _ => text.to_string(),
+    }
+}
+
+// 按设置去除文本首尾空白（"both" / "leading" / "trailing"，其他值保持原样），
+// 去除后为空时保留原文本
+fn trim_captured_text(text: &str, mode: &str) -> String {
+    let trimmed = match mode {
+        "both" => text.trim(),
+        "leading" => text.trim_start(),
+        "trailing" => text.trim_end(),
+        _ => text,
+    };
+    if trimmed.is_empty() { text.to_string() } else { trimmed.to_string() }
+}
+
+// 按设置预处理待保存的文本：先规范化换行符，再去除首尾空白
+fn prepare_text_for_storage(text: &str, settings: &AppSettings) -> String {
+    let normalized = normalize_line_endings(text, &settings.normalize_line_endings);
+    trim_captured_text(&normalized, &settings.trim_on_capture)
+}
+
+// 计算条目内容哈希（data_hash 列使用的规则，前端通过 content_hash 命令、局域网去重也使用同一函数）：
+// - 图片内容若带有 "data:...;base64," 前缀则先去掉，只对原始 base64 字符串计算
+// - 文本不做额外处理（换行符规范化和首尾空白处理已在 prepare_captured_text 中完成）
+// - 对 UTF-8 字节计算 SHA-256，输出小写十六进制
+pub(crate) fn compute_content_hash(content: &str) -> String {
+    use sha2::{Digest, Sha256};
+
+    let payload = match (content.starts_with("data:"), content.find("base64,")) {
+        (true, Some(index)) => &content[index + "base64,".len()..],
+        _ => content,
+    };
+    let mut hasher = Sha256::new();
+    hasher.update(payload.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+// 计算图片文件字节的 SHA-256（content_hash 列），与剪贴板中的编码方式无关，用于识别重复的图片文件
+pub(crate) fn compute_bytes_hash(bytes: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+
+    hex::encode(Sha256::digest(bytes))
+}
+
+// 记录在 PRAGMA user_version 中的 data_hash 规则版本
+const DATA_HASH_RULE_VERSION: i64 = 1;
+
+// 一次性迁移：统一 data_hash 规则之前写入的图片哈希无法按新规则重算（原始剪贴板数据没有保存），
+// 清空后图片去重改用 content_hash（文件字节哈希），缺少 content_hash 的条目读取图片文件补算。
+// 返回清空哈希的条目数
+pub(crate) async fn migrate_image_data_hashes(pool: &sqlx::SqlitePool) -> Result<u64, String> {
+    let version: i64 = sqlx::query_scalar("PRAGMA user_version")
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("读取数据库版本失败: {}", e))?;
+    if version >= DATA_HASH_RULE_VERSION {
+        return Ok(0);
+    }
+
+    let rows = sqlx::query("SELECT id, content, image_path FROM clipboard_history WHERE type = 'image' AND data_hash IS NOT NULL")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("查询旧图片哈希失败: {}", e))?;
+    let mut tx = pool.begin().await.map_err(|e| format!("开始事务失败: {}", e))?;
+    for row in &rows {
+        let id: i64 = row.get("id");
+        let image_path: Option<String> = row.get("image_path");
+        let path = resolve_image_path(image_path.as_deref().unwrap_or(&row.get::<String, _>("content")));
+        let content_hash = std::fs::read(&path).ok().map(|bytes| compute_bytes_hash(&bytes));
+        sqlx::query("UPDATE clipboard_history SET data_hash = NULL, content_hash = COALESCE(content_hash, ?) WHERE id = ?")
+            .bind(content_hash)
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("更新旧图片哈希失败: {}", e))?;
+    }
+    sqlx::query(&format!("PRAGMA user_version = {}", DATA_HASH_RULE_VERSION))
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("更新数据库版本失败: {}", e))?;
+    tx.commit().await.map_err(|e| format!("提交事务失败: {}", e))?;
+
+    if !rows.is_empty() {
+        tracing::info!("✅ 已清空 {} 条旧图片记录的 data_hash，改用文件哈希去重", rows.len());
+    }
+    Ok(rows.len() as u64)
+}
+
+/// 计算内容哈希，供前端去重和局域网发送使用同一套规则
+#[tauri::command]
+pub fn content_hash(content: String) -> String {
+    compute_content_hash(&content)
+}
+
+/// 按当前设置预处理捕获到的文本（在去重和哈希之前调用）
+#[tauri::command]
+pub async fn prepare_captured_text(app: AppHandle, text: String) -> Result<String, String> {
+    mark_activity(&app);
+    let settings = cached_settings(&app).await;
+    Ok(prepare_text_for_storage(&text, &settings))
+}
+
+// 改进的自动粘贴功能 - 先激活目标应用，再执行粘贴
+#[tauri::command]
+pub async fn auto_paste(app: AppHandle, item_id: Option<i64>) -> Result<(), String> {
+    let started = std::time::Instant::now();
+    let result = run_auto_paste(app.clone(), item_id).await;
+    record_paste_attempt(&app, "auto_paste", None, started, &result);
+    report_command_error(&app, "auto_paste", result)
+}
+
+async fn run_auto_paste(app: AppHandle, item_id: Option<i64>) -> Result<(), String> {
+    tracing::info!("开始执行智能自动粘贴...");
+    record_last_pasted(&app, item_id);
+    
+    #[cfg(target_os = "macos")]
+    {
+        macos_simple_paste(app)
+    }
+    
+    #[cfg(target_os = "windows")]
+    {
+        // 在新线程中执行粘贴操作
         let result = tokio::task::spawn_blocking(|| {
             windows_auto_paste()
         }).await;
@@ -1137,8 +2828,17 @@ pub async fn auto_paste(app: AppHandle) -> Result<(), String> {
 
 // 新增：智能粘贴功能 - 先激活指定应用，再粘贴
 #[tauri::command]
-pub async fn smart_paste_to_app(app: AppHandle, app_name: String, bundle_id: Option<String>) -> Result<(), String> {
+pub async fn smart_paste_to_app(app: AppHandle, app_name: String, bundle_id: Option<String>, item_id: Option<i64>) -> Result<(), String> {
+    let started = std::time::Instant::now();
+    let target_app = Some(app_name.clone());
+    let result = run_smart_paste_to_app(app.clone(), app_name, bundle_id, item_id).await;
+    record_paste_attempt(&app, "smart_paste_to_app", target_app, started, &result);
+    report_command_error(&app, "smart_paste_to_app", result)
+}
+
+async fn run_smart_paste_to_app(app: AppHandle, app_name: String, bundle_id: Option<String>, item_id: Option<i64>) -> Result<(), String> {
     tracing::info!("开始执行智能粘贴到应用: {} (bundle: {:?})", app_name, bundle_id);
+    record_last_pasted(&app, item_id);
     
     #[cfg(target_os = "macos")]
     {
@@ -1198,6 +2898,284 @@ pub async fn smart_paste_to_app(app: AppHandle, app_name: String, bundle_id: Opt
     }
 }
 
+// 记录最近一次粘贴的条目 ID
+fn record_last_pasted(app: &AppHandle, item_id: Option<i64>) {
+    if let (Some(id), Some(state)) = (item_id, app.try_state::<LastPastedState>()) {
+        if let Ok(mut last) = state.item_id.lock() {
+            *last = Some(id);
+        }
+    }
+}
+
+// 粘贴统计最多保留的记录数
+const PASTE_METRICS_MAX_ATTEMPTS: usize = 1000;
+
+// 记录一次粘贴尝试的耗时与结果，用于 get_paste_metrics 统计
+fn record_paste_attempt(app: &AppHandle, command: &str, target_app: Option<String>, started: std::time::Instant, result: &Result<(), String>) {
+    let attempt = PasteAttempt {
+        command: command.to_string(),
+        target_app,
+        duration_ms: started.elapsed().as_millis() as u64,
+        error: result.as_ref().err().cloned(),
+    };
+    tracing::debug!("粘贴耗时: {} {}ms, 成功: {}", attempt.command, attempt.duration_ms, attempt.error.is_none());
+    mark_activity(app);
+    if let Some(state) = app.try_state::<PasteMetricsState>() {
+        if let Ok(mut attempts) = state.attempts.lock() {
+            if attempts.len() >= PASTE_METRICS_MAX_ATTEMPTS {
+                attempts.pop_front();
+            }
+            attempts.push_back(attempt);
+        }
+    }
+}
+
+/// 按目标应用汇总的粘贴统计
+#[derive(serde::Serialize, Debug, Clone, PartialEq)]
+pub struct AppPasteMetrics {
+    pub app_name: String,
+    pub count: usize,
+    pub failed: usize,
+    pub avg_ms: u64,
+}
+
+/// 粘贴失败原因及次数
+#[derive(serde::Serialize, Debug, Clone, PartialEq)]
+pub struct PasteFailureReason {
+    pub reason: String,
+    pub count: usize,
+}
+
+/// 本次运行期间的粘贴耗时与成功率
+#[derive(serde::Serialize, Debug, Clone, PartialEq)]
+pub struct PasteMetrics {
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub success_rate: f64,
+    pub avg_ms: u64,
+    pub p50_ms: u64,
+    pub p90_ms: u64,
+    pub p99_ms: u64,
+    pub max_ms: u64,
+    pub by_app: Vec<AppPasteMetrics>,
+    pub failure_reasons: Vec<PasteFailureReason>,
+}
+
+// 最近秩法计算百分位数，sorted 需已升序排列
+fn percentile(sorted: &[u64], percent: usize) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (percent * sorted.len()).div_ceil(100).max(1);
+    sorted[rank.min(sorted.len()) - 1]
+}
+
+fn summarize_paste_attempts<'a>(attempts: impl IntoIterator<Item = &'a PasteAttempt>) -> PasteMetrics {
+    let attempts: Vec<&PasteAttempt> = attempts.into_iter().collect();
+    let mut durations: Vec<u64> = attempts.iter().map(|attempt| attempt.duration_ms).collect();
+    durations.sort_unstable();
+    let total = attempts.len();
+    let failed = attempts.iter().filter(|attempt| attempt.error.is_some()).count();
+    let avg = |sum: u64, count: usize| if count == 0 { 0 } else { sum / count as u64 };
+
+    let mut by_app: Vec<AppPasteMetrics> = Vec::new();
+    let mut app_totals: Vec<u64> = Vec::new();
+    let mut failure_reasons: Vec<PasteFailureReason> = Vec::new();
+    for attempt in &attempts {
+        let app_name = attempt.target_app.clone().unwrap_or_else(|| "Unknown".to_string());
+        match by_app.iter().position(|metrics| metrics.app_name == app_name) {
+            Some(index) => {
+                by_app[index].count += 1;
+                app_totals[index] += attempt.duration_ms;
+                by_app[index].failed += attempt.error.is_some() as usize;
+            }
+            None => {
+                by_app.push(AppPasteMetrics { app_name, count: 1, failed: attempt.error.is_some() as usize, avg_ms: 0 });
+                app_totals.push(attempt.duration_ms);
+            }
+        }
+        if let Some(reason) = &attempt.error {
+            match failure_reasons.iter_mut().find(|failure| &failure.reason == reason) {
+                Some(failure) => failure.count += 1,
+                None => failure_reasons.push(PasteFailureReason { reason: reason.clone(), count: 1 }),
+            }
+        }
+    }
+    for (metrics, app_total) in by_app.iter_mut().zip(app_totals) {
+        metrics.avg_ms = avg(app_total, metrics.count);
+    }
+    by_app.sort_by(|a, b| b.count.cmp(&a.count));
+    failure_reasons.sort_by(|a, b| b.count.cmp(&a.count));
+
+    PasteMetrics {
+        total,
+        succeeded: total - failed,
+        failed,
+        success_rate: if total == 0 { 0.0 } else { (total - failed) as f64 / total as f64 },
+        avg_ms: avg(durations.iter().sum(), total),
+        p50_ms: percentile(&durations, 50),
+        p90_ms: percentile(&durations, 90),
+        p99_ms: percentile(&durations, 99),
+        max_ms: durations.last().copied().unwrap_or(0),
+        by_app,
+        failure_reasons,
+    }
+}
+
+/// 获取本次运行期间的粘贴统计（次数、成功率、耗时分布、按应用统计和失败原因），用于排查粘贴不稳定问题
+#[tauri::command]
+pub async fn get_paste_metrics(app: AppHandle) -> Result<PasteMetrics, String> {
+    let state = app.try_state::<PasteMetricsState>().ok_or("无法获取粘贴统计状态")?;
+    let attempts = state.attempts.lock().map_err(|e| format!("读取粘贴统计失败: {}", e))?;
+    Ok(summarize_paste_attempts(attempts.iter()))
+}
+
+/// 获取最近一次粘贴的条目 ID
+#[tauri::command]
+pub async fn get_last_pasted(app: AppHandle) -> Result<Option<i64>, String> {
+    let state = app.try_state::<LastPastedState>().ok_or("无法获取粘贴状态")?;
+    let last = state.item_id.lock().map_err(|e| format!("读取粘贴状态失败: {}", e))?;
+    Ok(*last)
+}
+
+/// 重新粘贴最近一次粘贴的条目：把条目内容重新写入剪贴板后执行自动粘贴
+#[tauri::command]
+pub async fn repeat_last_paste(app: AppHandle) -> Result<(), String> {
+    let started = std::time::Instant::now();
+    let result = run_repeat_last_paste(app.clone()).await;
+    record_paste_attempt(&app, "repeat_last_paste", None, started, &result);
+    report_command_error(&app, "repeat_last_paste", result)
+}
+
+async fn run_repeat_last_paste(app: AppHandle) -> Result<(), String> {
+    let item_id = get_last_pasted(app.clone()).await?.ok_or("还没有粘贴过任何条目")?;
+    tracing::info!("重复上次粘贴: ID={}", item_id);
+
+    let row = if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        sqlx::query_as::<_, (String, String, Option<String>, Option<String>)>(
+            "SELECT content, type, image_path, metadata FROM clipboard_history WHERE id = ?"
+        )
+            .bind(item_id)
+            .fetch_optional(&db_guard.pool)
+            .await
+            .map_err(|e| format!("查询条目失败: {}", e))?
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 重复粘贴失败: {}", error_msg);
+        return Err(error_msg);
+    };
+
+    let (content, item_type, image_path, metadata) = row.ok_or_else(|| format!("未找到ID为{}的条目", item_id))?;
+    match item_type.as_str() {
+        "image" => {
+            copy_image_to_clipboard(image_path.unwrap_or(content)).await?;
+        }
+        "file" => {
+            // 文件条目的路径列表保存在 metadata.files 中，缺失时退回 content
+            let file_paths = metadata
+                .and_then(|metadata| serde_json::from_str::<serde_json::Value>(&metadata).ok())
+                .and_then(|metadata| {
+                    metadata["files"].as_array().map(|files| {
+                        files.iter()
+                            .filter_map(|file| file["path"].as_str().map(|path| path.to_string()))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .filter(|paths| !paths.is_empty())
+                .unwrap_or_else(|| vec![content]);
+            copy_files_to_clipboard(file_paths).await?;
+        }
+        _ => {
+            let mut clipboard = arboard::Clipboard::new()
+                .map_err(|e| format!("初始化剪贴板失败: {}", e))?;
+            clipboard.set_text(content)
+                .map_err(|e| format!("写入剪贴板失败: {}", e))?;
+        }
+    }
+
+    run_auto_paste(app, Some(item_id)).await
+}
+
+/// 以图片数据粘贴图片条目：先把图片解码后以位图写入剪贴板（Windows 上不使用 copy_image_to_clipboard 的文件形式），
+/// 再激活目标应用并粘贴，不依赖剪贴板中原有的内容
+#[tauri::command]
+pub async fn paste_image_item(app: AppHandle, id: i64, app_name: Option<String>, bundle_id: Option<String>) -> Result<(), String> {
+    let started = std::time::Instant::now();
+    let target_app = app_name.clone().filter(|app_name| !app_name.trim().is_empty());
+    let result = run_paste_image_item(app.clone(), id, app_name, bundle_id).await;
+    record_paste_attempt(&app, "paste_image_item", target_app, started, &result);
+    report_command_error(&app, "paste_image_item", result)
+}
+
+async fn run_paste_image_item(app: AppHandle, id: i64, app_name: Option<String>, bundle_id: Option<String>) -> Result<(), String> {
+    tracing::info!("以图片数据粘贴条目: ID={}, 目标应用: {:?}", id, app_name);
+    let row = if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        sqlx::query_as::<_, (String, String, Option<String>)>(
+            "SELECT content, type, image_path FROM clipboard_history WHERE id = ?"
+        )
+            .bind(id)
+            .fetch_optional(&db_guard.pool)
+            .await
+            .map_err(|e| format!("查询条目失败: {}", e))?
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 粘贴图片失败: {}", error_msg);
+        return Err(error_msg);
+    };
+
+    let (content, item_type, image_path) = row.ok_or_else(|| format!("未找到ID为{}的条目", id))?;
+    if item_type != "image" {
+        return Err(format!("ID为{}的条目不是图片条目", id));
+    }
+    let image_path = image_path.unwrap_or(content);
+    let path = resolve_image_path(&image_path);
+    if !path.exists() {
+        return Err(format!("图片文件不存在: {}", image_path));
+    }
+
+    tokio::task::spawn_blocking(move || write_image_data_to_clipboard(&path))
+        .await
+        .map_err(|e| format!("写入图片任务失败: {}", e))??;
+    tracing::debug!("图片数据已写入剪贴板，开始粘贴");
+
+    match app_name {
+        Some(app_name) if !app_name.trim().is_empty() => run_smart_paste_to_app(app, app_name, bundle_id, Some(id)).await,
+        _ => run_auto_paste(app, Some(id)).await,
+    }
+}
+
+// 重新显示并聚焦主窗口（沿用 show_window 的二次 set_focus 方式确保焦点回到 webview）
+async fn refocus_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        let _ = window.set_focus();
+    }
+}
+
+// 粘贴后保持窗口打开：执行粘贴后重新聚焦选择窗口，便于连续粘贴多个条目
+#[tauri::command]
+pub async fn paste_keep_open(app: AppHandle, app_name: Option<String>, bundle_id: Option<String>, item_id: Option<i64>) -> Result<(), String> {
+    tracing::info!("执行粘贴并保持窗口打开: {:?}", app_name);
+
+    let started = std::time::Instant::now();
+    let target_app = app_name.clone().filter(|app_name| !app_name.trim().is_empty());
+    let result = match app_name {
+        Some(app_name) if !app_name.trim().is_empty() => run_smart_paste_to_app(app.clone(), app_name, bundle_id, item_id).await,
+        _ => run_auto_paste(app.clone(), item_id).await,
+    };
+    record_paste_attempt(&app, "paste_keep_open", target_app, started, &result);
+
+    // 等待目标应用处理完粘贴按键后再切回选择窗口
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    refocus_main_window(&app).await;
+
+    report_command_error(&app, "paste_keep_open", result)
+}
 
 // macOS 使用新的智能粘贴逻辑（基于 EcoPaste 实现）
 #[cfg(target_os = "macos")]
@@ -1298,35 +3276,659 @@ fn linux_auto_paste() -> Result<(), String> {
 
 
 
-// 获取应用程序的可执行文件路径
-fn get_app_exe_path() -> Result<PathBuf, String> {
-    env::current_exe().map_err(|e| format!("无法获取应用程序路径: {}", e))
+// 历史配置相关
+
+// 当前配置名（空字符串表示默认配置），供同步的路径解析函数使用
+static ACTIVE_PROFILE: std::sync::RwLock<String> = std::sync::RwLock::new(String::new());
+
+// 配置名最大长度（字符数）
+const PROFILE_NAME_MAX_CHARS: usize = 32;
+
+fn active_profile_name() -> String {
+    match ACTIVE_PROFILE.read() {
+        Ok(name) if !name.is_empty() => name.clone(),
+        _ => DEFAULT_PROFILE.to_string(),
+    }
 }
 
-// 获取应用程序安装目录下的图片目录
-fn get_app_images_dir() -> Result<PathBuf, String> {
-    let exe_path = get_app_exe_path()?;
-    
-    // 获取可执行文件所在的目录
-    let exe_dir = exe_path.parent()
-        .ok_or("无法获取程序目录")?;
-    
-    // 在程序目录下创建 images 文件夹
-    let images_dir = exe_dir.join("images");
-    
-    // 确保目录存在
-    if !images_dir.exists() {
-        std::fs::create_dir_all(&images_dir)
-            .map_err(|e| format!("无法创建图片目录: {}", e))?;
+pub(crate) fn set_active_profile_name(name: &str) {
+    let name = validate_profile_name(name).unwrap_or_else(|e| {
+        tracing::warn!("⚠️ {}，使用默认配置", e);
+        DEFAULT_PROFILE.to_string()
+    });
+    if let Ok(mut guard) = ACTIVE_PROFILE.write() {
+        *guard = name;
     }
-    
-    Ok(images_dir)
 }
 
+// 配置名会作为文件名的一部分，只允许字母、数字、'-' 和 '_'
+fn validate_profile_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("配置名不能为空".to_string());
+    }
+    if name.chars().count() > PROFILE_NAME_MAX_CHARS {
+        return Err(format!("配置名不能超过 {} 个字符", PROFILE_NAME_MAX_CHARS));
+    }
+    if !name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("配置名无效: {}（只能包含字母、数字、'-' 和 '_'）", name));
+    }
+    Ok(name.to_string())
+}
+
+fn profile_db_file_name(name: &str) -> String {
+    if name == DEFAULT_PROFILE {
+        "clipboard.db".to_string()
+    } else {
+        format!("clipboard-{}.db", name)
+    }
+}
+
+fn profile_images_dir_name(name: &str) -> String {
+    if name == DEFAULT_PROFILE {
+        "images".to_string()
+    } else {
+        format!("images-{}", name)
+    }
+}
+
+// 配置的图片目录：默认数据库位置时在程序目录下，自定义数据库位置时在数据库所在目录，随数据库一起移动
+fn profile_images_dir(exe_dir: &Path, db_path: Option<&Path>, name: &str) -> PathBuf {
+    db_path.and_then(Path::parent).unwrap_or(exe_dir).join(profile_images_dir_name(name))
+}
+
+// 从应用数据目录中的文件名识别已有的配置，默认配置始终排在第一位
+fn profile_names_from_files<I: IntoIterator<Item = String>>(file_names: I) -> Vec<String> {
+    let mut profiles: Vec<String> = file_names
+        .into_iter()
+        .filter_map(|file_name| {
+            let name = file_name.strip_prefix("clipboard-")?.strip_suffix(".db")?;
+            validate_profile_name(name).ok().filter(|name| name != DEFAULT_PROFILE)
+        })
+        .collect();
+    profiles.sort();
+    profiles.dedup();
+    profiles.insert(0, DEFAULT_PROFILE.to_string());
+    profiles
+}
+
+// 自定义的数据库位置（设置中的 db_path），供同步的路径解析函数使用；None 表示使用应用数据目录
+static DB_PATH_OVERRIDE: std::sync::RwLock<Option<PathBuf>> = std::sync::RwLock::new(None);
+
+fn db_path_override() -> Option<PathBuf> {
+    DB_PATH_OVERRIDE.read().ok().and_then(|path| path.clone())
+}
+
+pub(crate) fn set_db_path_override(path: Option<PathBuf>) {
+    if let Ok(mut guard) = DB_PATH_OVERRIDE.write() {
+        *guard = path;
+    }
+}
+
+/// 设置中的自定义数据库位置，未设置或为空时返回 None
+pub(crate) fn configured_db_path(settings: &AppSettings) -> Option<PathBuf> {
+    settings.db_path
+        .as_deref()
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+// 配置的数据库文件路径：自定义位置时默认配置使用该文件，其他配置的数据库放在同一目录
+fn profile_db_path(app_data_dir: &Path, db_path: Option<&Path>, name: &str) -> PathBuf {
+    match db_path {
+        Some(path) if name == DEFAULT_PROFILE => path.to_path_buf(),
+        Some(path) => path.parent().unwrap_or(app_data_dir).join(profile_db_file_name(name)),
+        None => app_data_dir.join(profile_db_file_name(name)),
+    }
+}
+
+// 校验新的数据库位置：必须是绝对路径的 .db 文件，且文件名不能与其他配置的数据库文件名冲突
+fn validate_db_path(path: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(path.trim());
+    if !path.is_absolute() {
+        return Err(format!("数据库位置必须是绝对路径: {}", path.display()));
+    }
+    if path.extension().and_then(|ext| ext.to_str()) != Some("db") {
+        return Err("数据库文件的扩展名必须是 .db".to_string());
+    }
+    let file_name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    if file_name.starts_with("clipboard-") {
+        return Err(format!("文件名 {} 与历史配置的数据库文件名冲突", file_name));
+    }
+    Ok(path)
+}
+
+pub(crate) fn active_profile_db_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let app_data_dir = app.path().app_data_dir().map_err(|e| format!("无法获取应用数据目录: {}", e))?;
+    Ok(profile_db_path(&app_data_dir, db_path_override().as_deref(), &active_profile_name()))
+}
+
+fn list_profiles(app: &AppHandle, active: &str) -> Result<ProfileList, String> {
+    let app_data_dir = app.path().app_data_dir().map_err(|e| format!("无法获取应用数据目录: {}", e))?;
+    let db_path = db_path_override();
+    let db_dir = db_path
+        .as_deref()
+        .and_then(Path::parent)
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| app_data_dir.clone());
+    let file_names = fs::read_dir(&db_dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let mut profiles = profile_names_from_files(file_names);
+    if !profiles.iter().any(|name| name == active) {
+        profiles.push(active.to_string());
+    }
+    // 前端按 db_file 连接数据库：默认位置使用文件名，自定义位置使用绝对路径
+    let db_file = match db_path.as_deref() {
+        Some(path) => profile_db_path(&app_data_dir, Some(path), active).to_string_lossy().to_string(),
+        None => profile_db_file_name(active),
+    };
+    Ok(ProfileList {
+        active: active.to_string(),
+        profiles,
+        db_file,
+    })
+}
+
+/// 获取所有历史配置及当前配置
 #[tauri::command]
-pub async fn reset_database(app: AppHandle) -> Result<(), String> {
-    tracing::info!("开始重置数据库...");
-    
+pub async fn get_profiles(app: AppHandle) -> Result<ProfileList, String> {
+    let settings = load_settings(app.clone()).await.unwrap_or_default();
+    let active = validate_profile_name(&settings.active_profile).unwrap_or_else(|_| DEFAULT_PROFILE.to_string());
+    list_profiles(&app, &active)
+}
+
+/// 切换到指定的历史配置（不存在时创建），关闭当前数据库并打开该配置的数据库
+#[tauri::command]
+pub async fn set_active_profile(app: AppHandle, name: String) -> Result<ProfileList, String> {
+    let name = validate_profile_name(&name)?;
+    let previous = active_profile_name();
+    if name == previous {
+        return list_profiles(&app, &name);
+    }
+    tracing::info!("切换历史配置: {} -> {}", previous, name);
+
+    // 切换数据库之前先读取并校验设置，避免切换后才发现设置无法保存
+    let mut settings = load_settings(app.clone()).await?;
+    settings.active_profile = name.clone();
+    settings.validate()?;
+
+    let db_state = app.try_state::<Mutex<DatabaseState>>().ok_or("无法获取数据库状态")?;
+    let app_data_dir = app.path().app_data_dir().map_err(|e| format!("无法获取应用数据目录: {}", e))?;
+    let old_pool = {
+        // 持有数据库锁期间切换，避免其他命令在新旧配置之间读写
+        let mut db_guard = db_state.lock().await;
+        set_active_profile_name(&name);
+        let new_pool = match crate::open_database(&app, &profile_db_path(&app_data_dir, db_path_override().as_deref(), &name)).await {
+            Ok(pool) => pool,
+            Err(e) => {
+                set_active_profile_name(&previous);
+                let error_msg = format!("打开配置 {} 的数据库失败: {}", name, e);
+                tracing::error!("❌ {}", error_msg);
+                return Err(error_msg);
+            }
+        };
+        std::mem::replace(&mut db_guard.pool, new_pool)
+    };
+    old_pool.close().await;
+
+    // 数据库已经切换，保存失败时仍需完成后续清理和通知，否则前端会继续显示旧配置的数据
+    if let Err(e) = save_settings(app.clone(), settings).await {
+        tracing::warn!("⚠️ 保存当前配置失败，重启后将回到配置 {}: {}", previous, e);
+    }
+
+    // 捕获分组和最近粘贴的条目属于旧配置的数据库
+    if let Some(state) = app.try_state::<CaptureGroupState>() {
+        let had_group = state.group_id.lock().map(|mut guard| guard.take().is_some()).unwrap_or(false);
+        if had_group {
+            emit_capture_group_changed(&app, &CaptureGroupInfo { group_id: None, group_name: None });
+        }
+    }
+    if let Some(state) = app.try_state::<LastPastedState>() {
+        if let Ok(mut guard) = state.item_id.lock() {
+            *guard = None;
+        }
+    }
+
+    let profiles = list_profiles(&app, &name)?;
+    if let Err(e) = app.emit(PROFILE_CHANGED_EVENT, &profiles) {
+        tracing::warn!("⚠️ 发送配置切换事件失败: {}", e);
+    }
+    tracing::info!("✅ 已切换到配置: {}", name);
+    Ok(profiles)
+}
+
+// SQLite 数据库的附属文件路径（-wal、-shm）
+fn sqlite_sidecar_path(db_path: &Path, suffix: &str) -> PathBuf {
+    let mut path = db_path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+// 复制数据库文件及其 -wal 文件（-shm 是共享内存索引，打开数据库时会重建），失败时删除已复制的文件
+fn copy_database_files(files: &[(PathBuf, PathBuf)]) -> Result<(), String> {
+    let mut copied = Vec::new();
+    let result = files.iter().try_for_each(|(from, to)| {
+        for (from, to) in [(from.clone(), to.clone()), (sqlite_sidecar_path(from, "-wal"), sqlite_sidecar_path(to, "-wal"))] {
+            if !from.exists() {
+                continue;
+            }
+            fs::copy(&from, &to).map_err(|e| format!("复制数据库文件 {} 失败: {}", from.display(), e))?;
+            copied.push(to);
+        }
+        Ok(())
+    });
+    if result.is_err() {
+        for path in &copied {
+            let _ = fs::remove_file(path);
+        }
+    }
+    result
+}
+
+// 移动数据库时要处理的文件：各配置的数据库文件和图片目录，均为（原位置, 新位置）
+#[derive(Debug)]
+struct DatabaseMove {
+    db_files: Vec<(PathBuf, PathBuf)>,
+    image_dirs: Vec<(PathBuf, PathBuf)>,
+}
+
+// 计算从 old_db_path 移动到 new_db_path（None 为默认位置）要复制的文件，目标位置已有数据库文件时报错
+fn plan_database_move(
+    app_data_dir: &Path,
+    exe_dir: &Path,
+    old_db_path: Option<&Path>,
+    new_db_path: Option<&Path>,
+    profiles: &[String],
+) -> Result<DatabaseMove, String> {
+    let db_files: Vec<(PathBuf, PathBuf)> = profiles
+        .iter()
+        .map(|name| (profile_db_path(app_data_dir, old_db_path, name), profile_db_path(app_data_dir, new_db_path, name)))
+        .filter(|(from, _)| from.exists())
+        .collect();
+    if db_files.iter().any(|(from, to)| from == to) {
+        return Err("新位置与当前数据库位置相同".to_string());
+    }
+    if let Some((_, to)) = db_files.iter().find(|(_, to)| to.exists()) {
+        return Err(format!("目标位置已存在数据库文件: {}", to.display()));
+    }
+    let image_dirs = profiles
+        .iter()
+        .map(|name| (profile_images_dir(exe_dir, old_db_path, name), profile_images_dir(exe_dir, new_db_path, name)))
+        .filter(|(from, to)| from.is_dir() && from != to)
+        .collect();
+    Ok(DatabaseMove { db_files, image_dirs })
+}
+
+// 复制图片目录中的文件（目标中已有的同名文件保留），返回新复制的文件；失败时删除已复制的文件
+fn copy_image_dirs(dirs: &[(PathBuf, PathBuf)]) -> Result<Vec<PathBuf>, String> {
+    let mut copied = Vec::new();
+    let result = dirs.iter().try_for_each(|(from, to)| {
+        fs::create_dir_all(to).map_err(|e| format!("无法创建图片目录 {}: {}", to.display(), e))?;
+        let entries = fs::read_dir(from).map_err(|e| format!("读取图片目录 {} 失败: {}", from.display(), e))?;
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let source = entry.path();
+            let target = to.join(entry.file_name());
+            if !source.is_file() || target.exists() {
+                continue;
+            }
+            fs::copy(&source, &target).map_err(|e| format!("复制图片 {} 失败: {}", source.display(), e))?;
+            copied.push(target);
+        }
+        Ok(())
+    });
+    match result {
+        Ok(()) => Ok(copied),
+        Err(e) => {
+            for path in &copied {
+                let _ = fs::remove_file(path);
+            }
+            Err(e)
+        }
+    }
+}
+
+// 新位置的数据库已切换并校验后删除原位置的数据库文件和已复制到新位置的图片，之后可以再移回原位置
+fn remove_moved_sources(plan: &DatabaseMove) {
+    for (from, _) in &plan.db_files {
+        for path in [from.clone(), sqlite_sidecar_path(from, "-wal"), sqlite_sidecar_path(from, "-shm")] {
+            if path.exists() {
+                if let Err(e) = fs::remove_file(&path) {
+                    tracing::warn!("⚠️ 删除原数据库文件 {} 失败: {}", path.display(), e);
+                }
+            }
+        }
+    }
+    for (from, to) in &plan.image_dirs {
+        let Ok(entries) = fs::read_dir(from) else { continue };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            if entry.path().is_file() && to.join(entry.file_name()).exists() {
+                if let Err(e) = fs::remove_file(entry.path()) {
+                    tracing::warn!("⚠️ 删除原图片 {} 失败: {}", entry.path().display(), e);
+                }
+            }
+        }
+        // 只删除已清空的目录
+        let _ = fs::remove_dir(from);
+    }
+}
+
+/// 把数据库移动到新位置（new_path 为默认配置的数据库文件，其他配置的数据库一并复制到同一目录；
+/// 为空时移回默认的应用数据目录），图片目录随数据库一起移动。切换到新位置并校验后删除原位置的文件
+#[tauri::command]
+pub async fn move_database(app: AppHandle, new_path: Option<String>) -> Result<ProfileList, String> {
+    let new_db_path = match new_path.as_deref().map(str::trim).filter(|path| !path.is_empty()) {
+        Some(path) => Some(validate_db_path(path)?),
+        None => None,
+    };
+    tracing::info!("移动数据库到: {}", new_db_path.as_deref().map(|path| path.display().to_string()).unwrap_or_else(|| "默认位置".to_string()));
+    let app_data_dir = app.path().app_data_dir().map_err(|e| format!("无法获取应用数据目录: {}", e))?;
+    let exe_path = get_app_exe_path()?;
+    let exe_dir = exe_path.parent().ok_or("无法获取程序目录")?;
+    let old_db_path = db_path_override();
+    let active = active_profile_name();
+
+    let profiles = list_profiles(&app, &active)?.profiles;
+    let plan = plan_database_move(&app_data_dir, exe_dir, old_db_path.as_deref(), new_db_path.as_deref(), &profiles)?;
+    let target_dir = new_db_path.as_deref().and_then(Path::parent).unwrap_or(&app_data_dir);
+    fs::create_dir_all(target_dir).map_err(|e| format!("无法创建数据库目录: {}", e))?;
+
+    // 移动期间暂停剪贴板记录，避免前端在复制数据库时继续写入旧文件
+    let was_paused = set_capture_paused(&app, true);
+    let result = relocate_database(&app, &plan, &app_data_dir, old_db_path, new_db_path.as_deref(), &active).await;
+    if !was_paused {
+        set_capture_paused(&app, false);
+    }
+    result?;
+    remove_moved_sources(&plan);
+
+    // 通知前端重新连接新位置的数据库
+    let profiles = list_profiles(&app, &active)?;
+    if let Err(e) = app.emit(PROFILE_CHANGED_EVENT, &profiles) {
+        tracing::warn!("⚠️ 发送配置切换事件失败: {}", e);
+    }
+    tracing::info!("✅ 数据库已移动到 {}", profiles.db_file);
+    Ok(profiles)
+}
+
+// 持有数据库锁复制数据库文件和图片并切换到新位置；新位置在切换前写入设置文件，
+// 失败时删除已复制的文件并恢复原位置
+async fn relocate_database(
+    app: &AppHandle,
+    plan: &DatabaseMove,
+    app_data_dir: &Path,
+    old_db_path: Option<PathBuf>,
+    new_db_path: Option<&Path>,
+    active: &str,
+) -> Result<(), String> {
+    let db_state = app.try_state::<Mutex<DatabaseState>>().ok_or("无法获取数据库状态")?;
+    let old_pool = {
+        // 持有数据库锁期间复制和切换，避免复制过程中有新的写入
+        let mut db_guard = db_state.lock().await;
+        // 旧数据中指向原图片目录的绝对路径先转换为相对路径，移动后才能在新图片目录中找到
+        migrate_image_paths_to_relative(&db_guard.pool).await;
+        if let Err(e) = sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)").execute(&db_guard.pool).await {
+            tracing::warn!("⚠️ 移动数据库前执行 WAL 检查点失败: {}", e);
+        }
+        copy_database_files(&plan.db_files)?;
+        let copied_images = match copy_image_dirs(&plan.image_dirs) {
+            Ok(copied) => copied,
+            Err(e) => {
+                remove_database_copies(&plan.db_files);
+                tracing::error!("❌ {}", e);
+                return Err(e);
+            }
+        };
+        let remove_copies = || {
+            remove_database_copies(&plan.db_files);
+            for path in &copied_images {
+                let _ = fs::remove_file(path);
+            }
+        };
+
+        // 先保存新位置再切换，避免切换后保存失败导致重启时又打开旧位置的数据库
+        if let Err(e) = persist_db_path(new_db_path) {
+            remove_copies();
+            let error_msg = format!("保存数据库位置失败: {}", e);
+            tracing::error!("❌ {}", error_msg);
+            return Err(error_msg);
+        }
+
+        set_db_path_override(new_db_path.map(Path::to_path_buf));
+        let opened = match crate::open_database(app, &profile_db_path(app_data_dir, new_db_path, active)).await {
+            Ok(pool) => verify_database(&pool).await.map(|_| pool),
+            Err(e) => Err(e),
+        };
+        match opened {
+            Ok(pool) => std::mem::replace(&mut db_guard.pool, pool),
+            Err(e) => {
+                if let Err(e) = persist_db_path(old_db_path.as_deref()) {
+                    tracing::warn!("⚠️ 恢复设置中的数据库位置失败: {}", e);
+                }
+                set_db_path_override(old_db_path);
+                remove_copies();
+                let error_msg = format!("打开新位置的数据库失败: {}", e);
+                tracing::error!("❌ {}", error_msg);
+                return Err(error_msg);
+            }
+        }
+    };
+    old_pool.close().await;
+    Ok(())
+}
+
+fn remove_database_copies(files: &[(PathBuf, PathBuf)]) {
+    for (_, to) in files {
+        let _ = fs::remove_file(to);
+        let _ = fs::remove_file(sqlite_sidecar_path(to, "-wal"));
+    }
+}
+
+// 删除原位置的文件前确认复制后的数据库完整
+async fn verify_database(pool: &sqlx::SqlitePool) -> Result<(), String> {
+    let result: String = sqlx::query_scalar("PRAGMA quick_check")
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("校验数据库失败: {}", e))?;
+    if result != "ok" {
+        return Err(format!("数据库校验未通过: {}", result));
+    }
+    Ok(())
+}
+
+// 直接修改设置文件中的数据库位置（调用方持有数据库锁，不能经过会再次获取锁的 save_settings）
+fn persist_db_path(db_path: Option<&Path>) -> Result<(), String> {
+    let path = settings_file_path()?;
+    let (mut settings, _) = read_settings_file(&path)?;
+    settings.db_path = db_path.map(|path| path.to_string_lossy().to_string());
+    let json = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
+    write_settings_file(&path, &json)
+}
+
+// 暂停/恢复剪贴板记录并通知前端同步停止/恢复监听（与托盘菜单的"停止监听"相同），返回之前是否已暂停
+fn set_capture_paused(app: &AppHandle, paused: bool) -> bool {
+    let Some(state) = app.try_state::<CaptureControlState>() else { return paused };
+    let was_paused = state.paused.swap(paused, std::sync::atomic::Ordering::Relaxed);
+    if was_paused != paused {
+        if let Err(e) = app.emit("toggle-monitoring", paused) {
+            tracing::warn!("⚠️ 发送监听切换事件失败: {}", e);
+        }
+    }
+    was_paused
+}
+
+// 获取应用程序的可执行文件路径
+fn get_app_exe_path() -> Result<PathBuf, String> {
+    env::current_exe().map_err(|e| format!("无法获取应用程序路径: {}", e))
+}
+
+// 获取应用程序安装目录下的图片目录
+fn get_app_images_dir() -> Result<PathBuf, String> {
+    let exe_path = get_app_exe_path()?;
+    
+    // 获取可执行文件所在的目录
+    let exe_dir = exe_path.parent()
+        .ok_or("无法获取程序目录")?;
+    
+    // 当前配置的图片文件夹（默认配置为 images），自定义数据库位置时放在数据库所在目录
+    let images_dir = profile_images_dir(exe_dir, db_path_override().as_deref(), &active_profile_name());
+    
+    // 确保目录存在
+    if !images_dir.exists() {
+        std::fs::create_dir_all(&images_dir)
+            .map_err(|e| format!("无法创建图片目录: {}", e))?;
+    }
+    
+    Ok(images_dir)
+}
+
+// 将数据库中的图片路径解析为绝对路径
+// 新数据存储相对于图片目录的路径，旧数据可能仍是绝对路径
+pub(crate) fn resolve_image_path(image_path: &str) -> PathBuf {
+    let path = PathBuf::from(image_path);
+    if path.is_absolute() {
+        return path;
+    }
+    match get_app_images_dir() {
+        Ok(images_dir) => images_dir.join(path),
+        Err(_) => path,
+    }
+}
+
+// 数据库迁移：将位于图片目录下的绝对路径转换为相对路径，使数据库可随程序目录移动
+pub(crate) async fn migrate_image_paths_to_relative(pool: &sqlx::SqlitePool) {
+    let images_dir = match get_app_images_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            tracing::warn!("⚠️ 图片路径迁移跳过: {}", e);
+            return;
+        }
+    };
+
+    let rows = match sqlx::query_as::<_, (i64, String)>("SELECT id, image_path FROM clipboard_history WHERE image_path IS NOT NULL")
+        .fetch_all(pool)
+        .await {
+        Ok(rows) => rows,
+        Err(e) => {
+            tracing::warn!("⚠️ 图片路径迁移查询失败: {}", e);
+            return;
+        }
+    };
+
+    let mut migrated = 0;
+    for (id, image_path) in rows {
+        let path = PathBuf::from(&image_path);
+        if !path.is_absolute() {
+            continue;
+        }
+        let relative = match path.strip_prefix(&images_dir) {
+            Ok(relative) => relative.to_string_lossy().to_string(),
+            Err(_) => continue,
+        };
+        // 图片条目的 content 也存储了路径，一并更新
+        match sqlx::query("UPDATE clipboard_history SET image_path = ?, content = CASE WHEN type = 'image' AND content = image_path THEN ? ELSE content END WHERE id = ?")
+            .bind(&relative)
+            .bind(&relative)
+            .bind(id)
+            .execute(pool)
+            .await {
+            Ok(_) => migrated += 1,
+            Err(e) => tracing::warn!("⚠️ 图片路径迁移失败: ID={}, {}", id, e),
+        }
+    }
+
+    if migrated > 0 {
+        tracing::info!("已将 {} 条图片路径迁移为相对路径", migrated);
+    }
+}
+
+/// 获取图片在磁盘上的绝对路径（用于"作为路径粘贴"等需要真实路径的场景）
+#[tauri::command]
+pub async fn get_image_file_path(image_path: String) -> Result<String, String> {
+    Ok(resolve_image_path(&image_path).to_string_lossy().to_string())
+}
+
+/// 图片路径修复结果
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct RebaseImagePathsResult {
+    pub fixed: u64,
+    pub missing: u64,
+}
+
+/// 程序目录移动后，将指向旧目录的图片路径改写到当前图片目录
+/// old_base 为空时处理所有不在当前图片目录下的路径
+#[tauri::command]
+pub async fn rebase_image_paths(app: AppHandle, old_base: Option<String>) -> Result<RebaseImagePathsResult, String> {
+    tracing::info!("开始修复图片路径: old_base={:?}", old_base);
+    let images_dir = get_app_images_dir()?;
+
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        let pool = &db_guard.pool;
+
+        let rows = sqlx::query_as::<_, (i64, String)>("SELECT id, image_path FROM clipboard_history WHERE image_path IS NOT NULL")
+            .fetch_all(pool)
+            .await
+            .map_err(|e| format!("查询图片路径失败: {}", e))?;
+
+        let old_base = old_base.map(PathBuf::from);
+        let mut result = RebaseImagePathsResult { fixed: 0, missing: 0 };
+
+        for (id, image_path) in rows {
+            let path = PathBuf::from(&image_path);
+            // 相对路径始终基于当前图片目录，无需修复
+            if !path.is_absolute() || path.starts_with(&images_dir) {
+                continue;
+            }
+            if let Some(old_base) = &old_base {
+                if !path.starts_with(old_base) {
+                    continue;
+                }
+            }
+
+            // 图片文件名保持不变，改为存储相对于当前图片目录的路径
+            let file_name = match path.file_name() {
+                Some(file_name) => file_name.to_string_lossy().to_string(),
+                None => {
+                    result.missing += 1;
+                    continue;
+                }
+            };
+            if !images_dir.join(&file_name).exists() {
+                tracing::warn!("⚠️ 图片文件在新目录中不存在: ID={}, {}", id, file_name);
+                result.missing += 1;
+                continue;
+            }
+
+            sqlx::query("UPDATE clipboard_history SET image_path = ?, content = CASE WHEN type = 'image' AND content = image_path THEN ? ELSE content END WHERE id = ?")
+                .bind(&file_name)
+                .bind(&file_name)
+                .bind(id)
+                .execute(pool)
+                .await
+                .map_err(|e| format!("更新图片路径失败: {}", e))?;
+            result.fixed += 1;
+        }
+
+        tracing::info!("✅ 图片路径修复完成: 修复 {} 条，仍缺失 {} 条", result.fixed, result.missing);
+        Ok(result)
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 修复图片路径失败: {}", error_msg);
+        Err(error_msg)
+    }
+}
+
+#[tauri::command]
+pub async fn reset_database(app: AppHandle) -> Result<(), String> {
+    tracing::info!("开始重置数据库...");
+    
     // 尝试获取数据库状态
     if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
         let db_guard = db_state.lock().await;
@@ -1353,7 +3955,7 @@ pub async fn reset_database(app: AppHandle) -> Result<(), String> {
         
         // 删除所有图片文件
         for image_path in &all_images {
-            if let Err(e) = std::fs::remove_file(image_path) {
+            if let Err(e) = std::fs::remove_file(resolve_image_path(image_path)) {
                 tracing::info!("删除图片文件失败 {}: {}", image_path, e);
             } else {
                 tracing::info!("已删除图片文件: {}", image_path);
@@ -1391,9 +3993,83 @@ pub async fn reset_database(app: AppHandle) -> Result<(), String> {
     }
 }
 
+// 数据库表中的一列（PRAGMA table_info 的结果）
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct ColumnInfo {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub column_type: String,
+    pub not_null: bool,
+    pub default_value: Option<String>,
+    pub primary_key: bool,
+}
+
+// 数据库表结构及与代码预期相比缺失的列
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct TableSchema {
+    pub columns: Vec<ColumnInfo>,
+    pub missing_columns: Vec<String>,
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct DatabaseSchema {
+    pub clipboard_history: TableSchema,
+    pub groups: TableSchema,
+}
+
+// describe_schema 检查的列：clipboard_history 包含迁移添加的全部列（含写入时生成、查询条目时不读取的列）
+const HISTORY_SCHEMA_COLUMNS: &str = "id, content, type, timestamp, is_favorite, is_pinned, image_path, source_app_name, source_app_icon, thumbnail_data, metadata, note, group_id, data_hash, expires_at, preview, content_length, content_hash";
+const GROUP_COLUMNS: &str = "id, name, color, created_at, item_count";
+
+async fn describe_table(pool: &sqlx::SqlitePool, table: &str, expected_columns: &str) -> Result<TableSchema, String> {
+    let rows = sqlx::query(&format!("PRAGMA table_info({})", table))
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("查询 {} 表结构失败: {}", table, e))?;
+    let columns: Vec<ColumnInfo> = rows.iter().map(|row| ColumnInfo {
+        name: row.get("name"),
+        column_type: row.get("type"),
+        not_null: row.get::<i64, _>("notnull") != 0,
+        default_value: row.get("dflt_value"),
+        primary_key: row.get::<i64, _>("pk") != 0,
+    }).collect();
+    let missing_columns = expected_columns
+        .split(", ")
+        .filter(|expected| !columns.iter().any(|column| column.name == *expected))
+        .map(|expected| expected.to_string())
+        .collect();
+    Ok(TableSchema { columns, missing_columns })
+}
+
+/// 返回 clipboard_history 和 groups 表的列信息，用于诊断迁移缺失的列
+#[tauri::command]
+pub async fn describe_schema(app: AppHandle) -> Result<DatabaseSchema, String> {
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        let pool = &db_guard.pool;
+        let schema = DatabaseSchema {
+            clipboard_history: describe_table(pool, "clipboard_history", HISTORY_SCHEMA_COLUMNS).await?,
+            groups: describe_table(pool, "groups", GROUP_COLUMNS).await?,
+        };
+        let missing: Vec<&String> = schema.clipboard_history.missing_columns.iter()
+            .chain(schema.groups.missing_columns.iter())
+            .collect();
+        if missing.is_empty() {
+            tracing::info!("✅ 数据库结构检查通过");
+        } else {
+            tracing::warn!("⚠️ 数据库缺少列: {:?}", missing);
+        }
+        Ok(schema)
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 获取数据库结构失败: {}", error_msg);
+        Err(error_msg)
+    }
+}
+
 #[tauri::command]
 pub async fn load_image_file(image_path: String) -> Result<String, String> {
-    let path = PathBuf::from(&image_path);
+    let path = resolve_image_path(&image_path);
     
     // 检查文件是否存在
     if !path.exists() {
@@ -1404,54 +4080,232 @@ pub async fn load_image_file(image_path: String) -> Result<String, String> {
     let image_data = std::fs::read(&path)
         .map_err(|e| format!("无法读取图片文件: {}", e))?;
     
-    // 转换为 base64
-    let b64 = general_purpose::STANDARD.encode(&image_data);
-    let data_url = format!("data:image/png;base64,{}", b64);
-    
-    Ok(data_url)
+    Ok(image_bytes_to_data_url(&image_data))
 }
 
-#[tauri::command]
-pub async fn save_clipboard_image(base64_data: String) -> Result<String, String> {
-    // 1. 解析base64数据
-    // 处理可能的前缀 "data:image/png;base64,"
-    let base64_start = base64_data.find("base64,").map(|i| i + 7).unwrap_or(0);
-    let base64_str = &base64_data[base64_start..];
-
-    // 2. 解码base64
-    let image_bytes = general_purpose::STANDARD
-        .decode(base64_str)
-        .map_err(|e| format!("base64解码失败: {}", e))?;
+// 列表缩略图的最大边长和模糊半径
+const THUMBNAIL_MAX_SIZE: u32 = 256;
+const THUMBNAIL_BLUR_SIGMA: f32 = 8.0;
 
-    // 3. 获取图片信息（宽度、高度、大小）
-    let (width, height, format) = match image::load_from_memory(&image_bytes) {
-        Ok(img) => {
-            let width = img.width();
-            let height = img.height();
-            let format = match img {
-                image::DynamicImage::ImageLuma8(_) => "Luma8",
-                image::DynamicImage::ImageLumaA8(_) => "LumaA8",
-                image::DynamicImage::ImageRgb8(_) => "RGB8",
-                image::DynamicImage::ImageRgba8(_) => "RGBA8",
-                image::DynamicImage::ImageLuma16(_) => "Luma16",
-                image::DynamicImage::ImageLumaA16(_) => "LumaA16",
-                image::DynamicImage::ImageRgb16(_) => "RGB16",
-                image::DynamicImage::ImageRgba16(_) => "RGBA16",
-                image::DynamicImage::ImageRgb32F(_) => "RGB32F",
-                image::DynamicImage::ImageRgba32F(_) => "RGBA32F",
-                _ => "Unknown"
-            };
-            (width, height, format)
-        }
-        Err(_) => (0, 0, "Unknown"),
+// 生成列表缩略图：按比例缩小到 THUMBNAIL_MAX_SIZE 以内，需要时在编码前模糊处理
+fn generate_thumbnail(image_data: &[u8], blur: bool) -> Result<Vec<u8>, String> {
+    let img = image::load_from_memory(image_data)
+        .map_err(|e| format!("解码图片失败: {}", e))?;
+    let mut thumbnail = if img.width() > THUMBNAIL_MAX_SIZE || img.height() > THUMBNAIL_MAX_SIZE {
+        img.thumbnail(THUMBNAIL_MAX_SIZE, THUMBNAIL_MAX_SIZE)
+    } else {
+        img
     };
+    if blur {
+        thumbnail = thumbnail.blur(THUMBNAIL_BLUR_SIGMA);
+    }
+    let mut png_bytes = Vec::new();
+    thumbnail
+        .write_to(&mut std::io::Cursor::new(&mut png_bytes), ImageFormat::Png)
+        .map_err(|e| format!("编码缩略图失败: {}", e))?;
+    Ok(png_bytes)
+}
 
-    // 4. 获取图片目录
-    let images_dir = get_app_images_dir()?;
+/// 加载列表用的图片缩略图，开启「模糊缩略图」时返回模糊后的图片；
+/// 选中条目或复制时仍通过 load_image_file 读取原图
+#[tauri::command]
+pub async fn load_image_thumbnail(app: AppHandle, image_path: String) -> Result<String, String> {
+    let blur = load_settings(app).await.map(|s| s.blur_thumbnails).unwrap_or(false);
+    let path = resolve_image_path(&image_path);
+    if !path.exists() {
+        return Err("图片文件不存在".to_string());
+    }
+
+    let thumbnail = tokio::task::spawn_blocking(move || {
+        let image_data = std::fs::read(&path)
+            .map_err(|e| format!("无法读取图片文件: {}", e))?;
+        generate_thumbnail(&image_data, blur)
+    })
+    .await
+    .map_err(|e| format!("生成缩略图任务失败: {}", e))??;
+    Ok(image_bytes_to_data_url(&thumbnail))
+}
+
+// 将剪贴板图片转换为可存储的 PNG：PNG 和无法识别的数据原样保留，
+// 其他可解码格式（如浏览器复制的 WebP）重新编码为 PNG。
+// AVIF 解码需要系统 dav1d 库，未启用，直接报错而不是存下无法显示的数据
+fn convert_image_to_storable(image_bytes: Vec<u8>) -> Result<Vec<u8>, String> {
+    let format = match image::guess_format(&image_bytes) {
+        Ok(ImageFormat::Png) | Err(_) => return Ok(image_bytes),
+        Ok(format) => format,
+    };
+    if format == ImageFormat::Avif {
+        return Err("AVIF 不受支持".to_string());
+    }
+    if !format.reading_enabled() {
+        return Err(format!("不支持的图片格式: {:?}", format));
+    }
+
+    let img = image::load_from_memory_with_format(&image_bytes, format)
+        .map_err(|e| format!("解码 {:?} 图片失败: {}", format, e))?;
+    let mut png_bytes = Vec::new();
+    img.write_to(&mut std::io::Cursor::new(&mut png_bytes), ImageFormat::Png)
+        .map_err(|e| format!("转换为 PNG 失败: {}", e))?;
+    tracing::debug!("已将 {:?} 图片转换为 PNG: {} -> {} 字节", format, image_bytes.len(), png_bytes.len());
+    Ok(png_bytes)
+}
+
+// 按设置的存储格式重新编码图片（保持原始尺寸），返回编码后的字节和对应的格式。
+// 重新编码失败或结果不比原图小时保留原来的 PNG
+fn encode_image_for_storage(png_bytes: Vec<u8>, storage_format: &str, quality: u8) -> (Vec<u8>, ImageFormat) {
+    use image::{ColorType, ImageEncoder};
+
+    let target = match storage_format {
+        "png_optimized" => ImageFormat::Png,
+        "webp_lossless" => ImageFormat::WebP,
+        "jpeg" => ImageFormat::Jpeg,
+        _ => return (png_bytes, ImageFormat::Png),
+    };
+    let img = match image::load_from_memory_with_format(&png_bytes, ImageFormat::Png) {
+        Ok(img) => img,
+        Err(_) => return (png_bytes, ImageFormat::Png),
+    };
+    let (width, height) = (img.width(), img.height());
+
+    let mut encoded = Vec::new();
+    let result = match target {
+        ImageFormat::WebP => image::codecs::webp::WebPEncoder::new_lossless(&mut encoded)
+            .write_image(img.to_rgba8().as_raw(), width, height, ColorType::Rgba8),
+        ImageFormat::Jpeg => {
+            // JPEG 不支持透明通道，先合成到白色背景上
+            let rgba = img.to_rgba8();
+            let mut rgb = Vec::with_capacity((width * height * 3) as usize);
+            for pixel in rgba.pixels() {
+                let [r, g, b, a] = pixel.0;
+                for channel in [r, g, b] {
+                    rgb.push(((channel as u32 * a as u32 + 255 * (255 - a as u32)) / 255) as u8);
+                }
+            }
+            image::codecs::jpeg::JpegEncoder::new_with_quality(&mut encoded, quality.clamp(1, 100))
+                .write_image(&rgb, width, height, ColorType::Rgb8)
+        }
+        _ => image::codecs::png::PngEncoder::new_with_quality(
+            &mut encoded,
+            image::codecs::png::CompressionType::Best,
+            image::codecs::png::FilterType::Adaptive,
+        )
+            .write_image(img.as_bytes(), width, height, img.color()),
+    };
+
+    match result {
+        Ok(()) if encoded.len() < png_bytes.len() => {
+            tracing::debug!("图片已按 {:?} 存储: {} -> {} 字节", target, png_bytes.len(), encoded.len());
+            (encoded, target)
+        }
+        Ok(()) => (png_bytes, ImageFormat::Png),
+        Err(e) => {
+            tracing::warn!("⚠️ 图片编码为 {:?} 失败，按 PNG 保存: {}", target, e);
+            (png_bytes, ImageFormat::Png)
+        }
+    }
+}
+
+// 将图片字节转换为 data URL，MIME 类型根据实际格式推断（无法识别时按 PNG 处理）
+fn image_bytes_to_data_url(image_data: &[u8]) -> String {
+    let mime_type = image::guess_format(image_data)
+        .map(|format| format.to_mime_type())
+        .unwrap_or("image/png");
+    let b64 = general_purpose::STANDARD.encode(image_data);
+    format!("data:{};base64,{}", mime_type, b64)
+}
+
+/// 按条目 ID 获取图片的 data URL（在后端解析图片路径）
+#[tauri::command]
+pub async fn get_item_image(app: AppHandle, id: i64) -> Result<String, String> {
+    let row = if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        sqlx::query_as::<_, (String, String, Option<String>)>(
+            "SELECT content, type, image_path FROM clipboard_history WHERE id = ?"
+        )
+            .bind(id)
+            .fetch_optional(&db_guard.pool)
+            .await
+            .map_err(|e| format!("查询条目失败: {}", e))?
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 获取条目图片失败: {}", error_msg);
+        return Err(error_msg);
+    };
+
+    let (content, item_type, image_path) = row.ok_or_else(|| format!("未找到ID为{}的条目", id))?;
+    if item_type != "image" {
+        return Err(format!("ID为{}的条目不是图片", id));
+    }
+
+    // 图片条目的 content 同样保存了路径，image_path 缺失时使用它
+    let path = resolve_image_path(&image_path.unwrap_or(content));
+    if !path.exists() {
+        let error_msg = format!("图片文件不存在: {}", path.display());
+        tracing::warn!("❌ 获取条目图片失败: {}", error_msg);
+        return Err(error_msg);
+    }
+
+    let image_data = std::fs::read(&path)
+        .map_err(|e| format!("无法读取图片文件: {}", e))?;
+    Ok(image_bytes_to_data_url(&image_data))
+}
+
+#[tauri::command]
+pub async fn save_clipboard_image(app: AppHandle, base64_data: String) -> Result<String, String> {
+    mark_activity(&app);
+    // 0. 设置中关闭了图片记录时不保存任何文件
+    let settings = cached_settings(&app).await;
+    if !settings.capture_images {
+        tracing::debug!("图片记录已关闭，跳过保存图片");
+        return Err("图片记录已关闭".to_string());
+    }
+
+    // 1. 解析base64数据
+    // 处理可能的前缀 "data:image/png;base64,"
+    let base64_start = base64_data.find("base64,").map(|i| i + 7).unwrap_or(0);
+    let base64_str = &base64_data[base64_start..];
+
+    // 2. 解码base64，并将 WebP 等格式转换为 PNG 存储
+    let image_bytes = general_purpose::STANDARD
+        .decode(base64_str)
+        .map_err(|e| format!("base64解码失败: {}", e))?;
+    let image_bytes = convert_image_to_storable(image_bytes)?;
+    let (image_bytes, storage_format) = encode_image_for_storage(
+        image_bytes,
+        &settings.image_storage_format,
+        settings.image_storage_quality,
+    );
+
+    // 3. 获取图片信息（宽度、高度、大小）
+    let (width, height, format) = match image::load_from_memory(&image_bytes) {
+        Ok(img) => {
+            let width = img.width();
+            let height = img.height();
+            let format = match img {
+                image::DynamicImage::ImageLuma8(_) => "Luma8",
+                image::DynamicImage::ImageLumaA8(_) => "LumaA8",
+                image::DynamicImage::ImageRgb8(_) => "RGB8",
+                image::DynamicImage::ImageRgba8(_) => "RGBA8",
+                image::DynamicImage::ImageLuma16(_) => "Luma16",
+                image::DynamicImage::ImageLumaA16(_) => "LumaA16",
+                image::DynamicImage::ImageRgb16(_) => "RGB16",
+                image::DynamicImage::ImageRgba16(_) => "RGBA16",
+                image::DynamicImage::ImageRgb32F(_) => "RGB32F",
+                image::DynamicImage::ImageRgba32F(_) => "RGBA32F",
+                _ => "Unknown"
+            };
+            (width, height, format)
+        }
+        Err(_) => (0, 0, "Unknown"),
+    };
+
+    // 4. 获取图片目录
+    let images_dir = get_app_images_dir()?;
 
     // 5. 生成文件名 (使用时间戳)
     let timestamp = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
-    let filename = format!("img_{}.png", timestamp);
+    let extension = storage_format.extensions_str().first().copied().unwrap_or("png");
+    let filename = format!("img_{}.{}", timestamp, extension);
     let file_path = images_dir.join(&filename);
     println!("保存图片到: {:?}", file_path);
 
@@ -1464,13 +4318,16 @@ pub async fn save_clipboard_image(base64_data: String) -> Result<String, String>
         "width": width,
         "height": height,
         "size": image_bytes.len(),
-        "format": format
+        "format": format,
+        "mime_type": storage_format.to_mime_type()
     });
 
-    // 8. 构建返回结果
+    // 8. 构建返回结果（存储相对于图片目录的路径，程序目录移动后依然有效），
+    // content_hash 为写入文件的字节哈希，插入记录时写入 content_hash 列
     let result = serde_json::json!({
-        "path": file_path.to_string_lossy().to_string(),
-        "metadata": metadata
+        "path": filename,
+        "metadata": metadata,
+        "content_hash": compute_bytes_hash(&image_bytes)
     });
 
     // 9. 返回包含路径和元数据的JSON对象
@@ -1479,7 +4336,7 @@ pub async fn save_clipboard_image(base64_data: String) -> Result<String, String>
 
 #[tauri::command]
 pub async fn get_image_metadata(image_path: String) -> Result<serde_json::Value, String> {
-    let path = PathBuf::from(&image_path);
+    let path = resolve_image_path(&image_path);
 
     // 检查文件是否存在
     if !path.exists() {
@@ -1529,7 +4386,7 @@ pub async fn copy_image_to_clipboard(image_path: String) -> Result<(), String> {
     let start = std::time::Instant::now();
     tracing::info!("复制图片到剪贴板: {}", image_path);
     
-    let path = PathBuf::from(&image_path);
+    let path = resolve_image_path(&image_path);
     if !path.exists() {
         return Err(format!("图片文件不存在: {}", image_path));
     }
@@ -1549,7 +4406,7 @@ pub async fn copy_image_to_clipboard(image_path: String) -> Result<(), String> {
             .map_err(|e| format!("清空剪贴板失败: {}", e))?;
 
         // 设置文件列表 (CF_HDROP)
-        let paths = vec![image_path.clone()];
+        let paths = vec![path.to_string_lossy().to_string()];
 
         // 使用 formats::FileList
         formats::FileList.write_clipboard(&paths)
@@ -1562,41 +4419,45 @@ pub async fn copy_image_to_clipboard(image_path: String) -> Result<(), String> {
     #[cfg(not(target_os = "windows"))]
     {
         // 其他平台继续使用 arboard 处理图像数据
-        
-        // 读取图片文件
-        let read_start = std::time::Instant::now();
-        let image_bytes = std::fs::read(&path)
-            .map_err(|e| format!("读取图片文件失败: {}", e))?;
-        tracing::debug!("读取文件耗时: {:?}", read_start.elapsed());
-            
-        // 解码图片
-        let decode_start = std::time::Instant::now();
-        let img = image::load_from_memory(&image_bytes)
-            .map_err(|e| format!("解码图片失败: {}", e))?;
-        tracing::debug!("解码图片耗时: {:?}", decode_start.elapsed());
-        
-        let rgba8 = img.to_rgba8();
-        let (width, height) = rgba8.dimensions();
-        let image_data = arboard::ImageData {
-            width: width as usize,
-            height: height as usize,
-            bytes: std::borrow::Cow::Borrowed(&rgba8),
-        };
-        
-        // 使用 arboard 写入剪贴板
-        let write_start = std::time::Instant::now();
-        let mut clipboard = arboard::Clipboard::new()
-            .map_err(|e| format!("初始化剪贴板失败: {}", e))?;
-            
-        clipboard.set_image(image_data)
-            .map_err(|e| format!("写入剪贴板失败: {}", e))?;
-        tracing::debug!("写入剪贴板耗时: {:?}", write_start.elapsed());
-            
+        write_image_data_to_clipboard(&path)?;
         tracing::info!("✅ 图片成功写入剪贴板 (Rust Arboard), 总耗时: {:?}", start.elapsed());
         Ok(())
     }
 }
 
+// 解码图片文件并以位图数据写入剪贴板（各平台均通过 arboard 写入原生图片格式）
+fn write_image_data_to_clipboard(path: &std::path::Path) -> Result<(), String> {
+    // 读取图片文件
+    let read_start = std::time::Instant::now();
+    let image_bytes = std::fs::read(path)
+        .map_err(|e| format!("读取图片文件失败: {}", e))?;
+    tracing::debug!("读取文件耗时: {:?}", read_start.elapsed());
+
+    // 解码图片
+    let decode_start = std::time::Instant::now();
+    let img = image::load_from_memory(&image_bytes)
+        .map_err(|e| format!("解码图片失败: {}", e))?;
+    tracing::debug!("解码图片耗时: {:?}", decode_start.elapsed());
+
+    let rgba8 = img.to_rgba8();
+    let (width, height) = rgba8.dimensions();
+    let image_data = arboard::ImageData {
+        width: width as usize,
+        height: height as usize,
+        bytes: std::borrow::Cow::Borrowed(&rgba8),
+    };
+
+    // 使用 arboard 写入剪贴板
+    let write_start = std::time::Instant::now();
+    let mut clipboard = arboard::Clipboard::new()
+        .map_err(|e| format!("初始化剪贴板失败: {}", e))?;
+
+    clipboard.set_image(image_data)
+        .map_err(|e| format!("写入剪贴板失败: {}", e))?;
+    tracing::debug!("写入剪贴板耗时: {:?}", write_start.elapsed());
+    Ok(())
+}
+
 // ===== 日志相关命令 =====
 
 /// 前端写入日志到文件
@@ -1777,6 +4638,105 @@ pub async fn open_log_folder() -> Result<(), String> {
     Ok(())
 }
 
+// 按当前的日志内容预览设置脱敏一行日志：单引号包裹的内容（条目内容、备注等）重新截断或隐藏，
+// 用户主目录替换为 "~"
+fn redact_log_line(line: &str, max_len: usize, home_dir: Option<&str>) -> String {
+    let mut redacted = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find('\'') {
+        let Some(len) = rest[start + 1..].find('\'') else { break };
+        let quoted = &rest[start + 1..start + 1 + len];
+        redacted.push_str(&rest[..start]);
+        redacted.push('\'');
+        // 写入日志时已经生成的预览不再重复截断
+        let is_preview = quoted.starts_with("<已隐藏 ") || (max_len > 0 && quoted.contains("…(共 ") && quoted.ends_with(" 字符)"));
+        if is_preview {
+            redacted.push_str(quoted);
+        } else {
+            redacted.push_str(&logging::preview_with_len(quoted, max_len));
+        }
+        redacted.push('\'');
+        rest = &rest[start + len + 2..];
+    }
+    redacted.push_str(rest);
+
+    match home_dir.filter(|home| home.len() > 1) {
+        Some(home) => redacted.replace(home, "~"),
+        None => redacted,
+    }
+}
+
+// 诊断信息中不应包含的敏感设置
+fn diagnostics_settings(mut settings: AppSettings) -> AppSettings {
+    if !settings.lan_queue_password.is_empty() {
+        settings.lan_queue_password = "<已隐藏>".to_string();
+    }
+    settings
+}
+
+/// 把日志文件（app.log*）打包为 zip，供反馈问题时附加。日志按内容预览设置脱敏，
+/// include_diagnostics 为 true（默认）时额外写入版本、系统和设置信息。返回生成的文件路径
+#[tauri::command]
+pub async fn export_logs_bundle(
+    app: AppHandle,
+    export_path: String,
+    include_diagnostics: Option<bool>,
+) -> Result<String, String> {
+    tracing::info!("开始导出日志包到: {}", export_path);
+
+    let log_files = logging::get_log_files().map_err(|e| format!("获取日志文件列表失败: {}", e))?;
+    let max_len = logging::content_preview_len();
+    let home_dir = std::env::var("HOME").or_else(|_| std::env::var("USERPROFILE")).ok();
+
+    let export_path = PathBuf::from(&export_path);
+    let file = fs::File::create(&export_path)
+        .map_err(|e| format!("无法创建日志包文件: {}", e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+
+    for path in &log_files {
+        let Some(name) = path.file_name().map(|name| name.to_string_lossy().to_string()) else { continue };
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                tracing::warn!("⚠️ 读取日志文件失败 {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        let redacted: String = String::from_utf8_lossy(&bytes)
+            .lines()
+            .map(|line| redact_log_line(line, max_len, home_dir.as_deref()) + "\n")
+            .collect();
+        zip.start_file(format!("logs/{}", name), options)
+            .map_err(|e| format!("写入日志到zip失败: {}", e))?;
+        zip.write_all(redacted.as_bytes())
+            .map_err(|e| format!("写入日志数据失败: {}", e))?;
+    }
+
+    if include_diagnostics.unwrap_or(true) {
+        let settings = load_settings(app.clone()).await.unwrap_or_default();
+        let diagnostics = serde_json::json!({
+            "app_version": app.package_info().version.to_string(),
+            "os": std::env::consts::OS,
+            "arch": std::env::consts::ARCH,
+            "generated_at": chrono::Local::now().to_rfc3339(),
+            "log_files": log_files.len(),
+            "settings": diagnostics_settings(settings),
+        });
+        let json = serde_json::to_string_pretty(&diagnostics)
+            .map_err(|e| format!("序列化诊断信息失败: {}", e))?;
+        zip.start_file("diagnostics.json", options)
+            .map_err(|e| format!("写入诊断信息到zip失败: {}", e))?;
+        zip.write_all(json.as_bytes())
+            .map_err(|e| format!("写入诊断信息失败: {}", e))?;
+    }
+
+    zip.finish().map_err(|e| format!("完成日志包失败: {}", e))?;
+    tracing::info!("✅ 日志包导出完成: {} 个日志文件", log_files.len());
+    Ok(export_path.to_string_lossy().to_string())
+}
+
 /// 删除所有日志文件
 #[tauri::command]
 pub async fn delete_all_logs() -> Result<(), String> {
@@ -1864,17 +4824,17 @@ pub async fn delete_all_logs() -> Result<(), String> {
 
 #[tauri::command]
 pub async fn update_item_note(app: AppHandle, item_id: i64, note: String) -> Result<(), String> {
-    tracing::info!("更新条目备注: ID={}, note='{}'", item_id, note);
+    tracing::info!("更新条目备注: ID={}, note='{}'", item_id, logging::preview(&note));
     
     if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
         let db_guard = db_state.lock().await;
         let pool = &db_guard.pool;
         
         // 更新数据库中的备注
-        let result = sqlx::query("UPDATE clipboard_history SET note = ? WHERE id = ?")
+        let result = with_db_retry("更新备注", || sqlx::query("UPDATE clipboard_history SET note = ? WHERE id = ?")
             .bind(&note)
             .bind(item_id)
-            .execute(pool)
+            .execute(pool))
             .await;
             
             match result {
@@ -1901,1065 +4861,5043 @@ pub async fn update_item_note(app: AppHandle, item_id: i64, note: String) -> Res
     }
 }
 
+/// 设置条目的收藏状态（收藏条目不会被自动清理删除）
 #[tauri::command]
-pub async fn get_item_note(app: AppHandle, item_id: i64) -> Result<Option<String>, String> {
-    tracing::debug!("获取条目备注: ID={}", item_id);
-    
+pub async fn set_item_favorite(app: AppHandle, item_id: i64, is_favorite: bool) -> Result<(), String> {
+    tracing::info!("更新条目收藏状态: ID={}, is_favorite={}", item_id, is_favorite);
+
     if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
         let db_guard = db_state.lock().await;
         let pool = &db_guard.pool;
-        
-        // 从数据库获取备注
-        let result = sqlx::query_as::<_, (Option<String>,)>("SELECT note FROM clipboard_history WHERE id = ?")
+
+        let result = with_db_retry("更新收藏状态", || sqlx::query("UPDATE clipboard_history SET is_favorite = ? WHERE id = ?")
+            .bind(is_favorite)
             .bind(item_id)
-            .fetch_optional(pool)
+            .execute(pool))
             .await;
-            
-            match result {
-                Ok(Some((note,))) => {
-                    tracing::debug!("✅ 获取备注成功: ID={}, note={:?}", item_id, note);
-                    Ok(note)
-                }
-                Ok(None) => {
+
+        match result {
+            Ok(query_result) => {
+                if query_result.rows_affected() > 0 {
+                    tracing::info!("✅ 收藏状态更新成功: ID={}", item_id);
+                    Ok(())
+                } else {
                     let error_msg = format!("未找到ID为{}的条目", item_id);
-                    tracing::warn!("❌ 获取备注失败: {}", error_msg);
-                    Err(error_msg)
-                }
-                Err(e) => {
-                    let error_msg = format!("数据库查询失败: {}", e);
-                    tracing::error!("❌ 获取备注失败: {}", error_msg);
+                    tracing::warn!("❌ 收藏状态更新失败: {}", error_msg);
                     Err(error_msg)
                 }
             }
+            Err(e) => {
+                let error_msg = format!("数据库更新失败: {}", e);
+                tracing::error!("❌ 收藏状态更新失败: {}", error_msg);
+                Err(error_msg)
+            }
+        }
     } else {
         let error_msg = "无法获取数据库状态".to_string();
-        tracing::error!("❌ 获取备注失败: {}", error_msg);
+        tracing::error!("❌ 收藏状态更新失败: {}", error_msg);
         Err(error_msg)
     }
 }
 
-// 分组管理相关命令
+// 标签管理 API：一个条目可以有多个标签，保存在 tags 表中
+
+// 创建标签表及索引；删除条目时由触发器一并删除其标签，覆盖所有删除条目的路径
+pub(crate) async fn ensure_tags_table(pool: &sqlx::SqlitePool) -> Result<(), String> {
+    let statements = [
+        "CREATE TABLE IF NOT EXISTS tags (
+            item_id INTEGER NOT NULL,
+            tag TEXT NOT NULL
+        )",
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_tags_item_tag ON tags(item_id, tag)",
+        "CREATE INDEX IF NOT EXISTS idx_tags_tag ON tags(tag)",
+        "CREATE TRIGGER IF NOT EXISTS trg_tags_delete
+         AFTER DELETE ON clipboard_history
+         BEGIN
+             DELETE FROM tags WHERE item_id = OLD.id;
+         END",
+    ];
+    for sql in statements {
+        sqlx::query(sql)
+            .execute(pool)
+            .await
+            .map_err(|e| format!("无法创建标签表: {}", e))?;
+    }
+    Ok(())
+}
 
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
-pub struct Group {
-    pub id: i64,
-    pub name: String,
-    pub color: String,
-    pub created_at: String,
-    pub item_count: i64,
+// 去除标签首尾空白，空标签无效
+fn normalize_tag(tag: &str) -> Result<String, String> {
+    let tag = tag.trim();
+    if tag.is_empty() {
+        return Err("标签不能为空".to_string());
+    }
+    Ok(tag.to_string())
+}
+
+// 为条目添加标签，条目不存在时返回 false，标签已存在时不重复添加
+async fn add_item_tag(pool: &sqlx::SqlitePool, item_id: i64, tag: &str) -> Result<bool, sqlx::Error> {
+    let result = with_db_retry("添加标签", || sqlx::query(
+        "INSERT OR IGNORE INTO tags (item_id, tag) SELECT id, ? FROM clipboard_history WHERE id = ?"
+    )
+        .bind(tag)
+        .bind(item_id)
+        .execute(pool))
+        .await?;
+    if result.rows_affected() > 0 {
+        return Ok(true);
+    }
+    let exists: Option<i64> = sqlx::query_scalar("SELECT id FROM clipboard_history WHERE id = ?")
+        .bind(item_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(exists.is_some())
+}
+
+async fn fetch_item_tags(pool: &sqlx::SqlitePool, item_id: i64) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar("SELECT tag FROM tags WHERE item_id = ? ORDER BY tag")
+        .bind(item_id)
+        .fetch_all(pool)
+        .await
+}
+
+async fn fetch_items_by_tag(pool: &sqlx::SqlitePool, tag: &str) -> Result<Vec<HistoryItem>, sqlx::Error> {
+    let query = format!(
+        "SELECT {} FROM clipboard_history WHERE id IN (SELECT item_id FROM tags WHERE tag = ?) ORDER BY timestamp DESC",
+        HISTORY_ITEM_COLUMNS
+    );
+    let rows = sqlx::query(&query).bind(tag).fetch_all(pool).await?;
+    Ok(rows.iter().map(row_to_history_item).collect())
 }
 
+/// 为条目添加标签（去除首尾空白），重复添加同一标签不会报错
 #[tauri::command]
-pub async fn create_group(app: AppHandle, name: String, color: String) -> Result<Group, String> {
-    tracing::info!("创建分组: name='{}', color='{}'", name, color);
+pub async fn add_tag(app: AppHandle, item_id: i64, tag: String) -> Result<(), String> {
+    let tag = normalize_tag(&tag)?;
+    tracing::info!("添加标签: ID={}, tag='{}'", item_id, logging::preview(&tag));
     if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
         let db_guard = db_state.lock().await;
-        let pool = &db_guard.pool;
-        
-        let created_at = chrono::Utc::now().to_rfc3339();
-        
-        // 插入新分组
-        let result = sqlx::query("INSERT INTO groups (name, color, created_at) VALUES (?, ?, ?)")
-            .bind(&name)
-            .bind(&color)
-            .bind(&created_at)
-            .execute(pool)
-            .await;
-            
-        match result {
-            Ok(_) => {
-                // 获取新创建的分组ID
-                let id_result = sqlx::query_as::<_, (i64,)>("SELECT last_insert_rowid()")
-                    .fetch_one(pool)
-                    .await;
-                    
-                match id_result {
-                    Ok((id,)) => {
-                        tracing::info!("✅ 分组创建成功: ID={}", id);
-                        Ok(Group {
-                            id,
-                            name,
-                            color,
-                            created_at,
-                            item_count: 0,
-                        })
-                    }
-                    Err(e) => {
-                        let error_msg = format!("获取新分组ID失败: {}", e);
-                        tracing::error!("❌ 创建分组失败: {}", error_msg);
-                        Err(error_msg)
-                    }
-                }
+        match add_item_tag(&db_guard.pool, item_id, &tag).await {
+            Ok(true) => {
+                tracing::info!("✅ 标签添加成功: ID={}", item_id);
+                Ok(())
+            }
+            Ok(false) => {
+                let error_msg = format!("未找到ID为{}的条目", item_id);
+                tracing::warn!("❌ 添加标签失败: {}", error_msg);
+                Err(error_msg)
             }
             Err(e) => {
-                let error_msg = format!("插入分组失败: {}", e);
-                tracing::error!("❌ 创建分组失败: {}", error_msg);
+                let error_msg = format!("添加标签失败: {}", e);
+                tracing::error!("❌ {}", error_msg);
                 Err(error_msg)
             }
         }
     } else {
         let error_msg = "无法获取数据库状态".to_string();
-        tracing::error!("❌ 创建分组失败: {}", error_msg);
+        tracing::error!("❌ 添加标签失败: {}", error_msg);
         Err(error_msg)
     }
 }
 
+/// 移除条目的标签，标签不存在时不做任何操作
 #[tauri::command]
-pub async fn get_groups(app: AppHandle) -> Result<Vec<Group>, String> {
-    tracing::debug!("获取所有分组");
+pub async fn remove_tag(app: AppHandle, item_id: i64, tag: String) -> Result<(), String> {
+    let tag = normalize_tag(&tag)?;
+    tracing::info!("移除标签: ID={}, tag='{}'", item_id, logging::preview(&tag));
     if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
         let db_guard = db_state.lock().await;
         let pool = &db_guard.pool;
-        
-        let result = sqlx::query_as::<_, (i64, String, String, String, i64)>(
-            "SELECT id, name, color, created_at, 
-                    (SELECT COUNT(*) FROM clipboard_history WHERE group_id = groups.id) as item_count 
-             FROM groups ORDER BY created_at DESC"
-        )
-        .fetch_all(pool)
-        .await;
-        
-        match result {
-            Ok(rows) => {
-                let groups: Vec<Group> = rows.into_iter().map(|(id, name, color, created_at, item_count)| {
-                    Group { id, name, color, created_at, item_count }
-                }).collect();
-                tracing::debug!("✅ 获取分组成功: {} 个分组", groups.len());
-                Ok(groups)
+        match with_db_retry("移除标签", || sqlx::query("DELETE FROM tags WHERE item_id = ? AND tag = ?")
+            .bind(item_id)
+            .bind(&tag)
+            .execute(pool))
+            .await
+        {
+            Ok(_) => {
+                tracing::info!("✅ 标签移除成功: ID={}", item_id);
+                Ok(())
             }
             Err(e) => {
-                let error_msg = format!("查询分组失败: {}", e);
-                tracing::error!("❌ 获取分组失败: {}", error_msg);
+                let error_msg = format!("移除标签失败: {}", e);
+                tracing::error!("❌ {}", error_msg);
                 Err(error_msg)
             }
         }
     } else {
         let error_msg = "无法获取数据库状态".to_string();
-        tracing::error!("❌ 获取分组失败: {}", error_msg);
+        tracing::error!("❌ 移除标签失败: {}", error_msg);
         Err(error_msg)
     }
 }
 
+/// 获取条目的所有标签，按名称排序
 #[tauri::command]
-pub async fn update_group(app: AppHandle, id: i64, name: String, color: String) -> Result<(), String> {
-    tracing::info!("更新分组: ID={}, name='{}', color='{}'", id, name, color);
+pub async fn get_tags(app: AppHandle, item_id: i64) -> Result<Vec<String>, String> {
     if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
         let db_guard = db_state.lock().await;
-        let pool = &db_guard.pool;
-        
-        let result = sqlx::query("UPDATE groups SET name = ?, color = ? WHERE id = ?")
-            .bind(&name)
-            .bind(&color)
-            .bind(id)
-            .execute(pool)
-            .await;
-            
-        match result {
-            Ok(_) => {
-                tracing::info!("✅ 分组更新成功: ID={}", id);
-                Ok(())
-            }
-            Err(e) => {
-                let error_msg = format!("更新分组失败: {}", e);
-                tracing::error!("❌ 更新分组失败: {}", error_msg);
-                Err(error_msg)
-            }
-        }
+        fetch_item_tags(&db_guard.pool, item_id).await.map_err(|e| {
+            let error_msg = format!("查询标签失败: {}", e);
+            tracing::error!("❌ {}", error_msg);
+            error_msg
+        })
     } else {
         let error_msg = "无法获取数据库状态".to_string();
-        tracing::error!("❌ 更新分组失败: {}", error_msg);
+        tracing::error!("❌ 查询标签失败: {}", error_msg);
         Err(error_msg)
     }
 }
 
+/// 获取带有指定标签的所有条目，按时间倒序
 #[tauri::command]
-pub async fn delete_group(app: AppHandle, id: i64) -> Result<(), String> {
-    tracing::info!("删除分组: ID={}", id);
+pub async fn get_items_by_tag(app: AppHandle, tag: String) -> Result<Vec<HistoryItem>, String> {
+    let tag = normalize_tag(&tag)?;
+    tracing::debug!("按标签查询条目: tag='{}'", logging::preview(&tag));
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        fetch_items_by_tag(&db_guard.pool, &tag).await.map_err(|e| {
+            let error_msg = format!("按标签查询条目失败: {}", e);
+            tracing::error!("❌ {}", error_msg);
+            error_msg
+        })
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 按标签查询条目失败: {}", error_msg);
+        Err(error_msg)
+    }
+}
+
+#[tauri::command]
+pub async fn get_item_note(app: AppHandle, item_id: i64) -> Result<Option<String>, String> {
+    tracing::debug!("获取条目备注: ID={}", item_id);
+    
     if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
         let db_guard = db_state.lock().await;
         let pool = &db_guard.pool;
         
-        // 先将该分组下的所有条目的group_id设为NULL
-        let update_result = sqlx::query("UPDATE clipboard_history SET group_id = NULL WHERE group_id = ?")
-            .bind(id)
-            .execute(pool)
-            .await;
-            
-        if let Err(e) = update_result {
-            let error_msg = format!("清除分组关联失败: {}", e);
-            tracing::error!("❌ 删除分组失败: {}", error_msg);
-            return Err(error_msg);
-        }
-        
-        // 删除分组
-        let result = sqlx::query("DELETE FROM groups WHERE id = ?")
-            .bind(id)
-            .execute(pool)
+        // 从数据库获取备注
+        let result = sqlx::query_as::<_, (Option<String>,)>("SELECT note FROM clipboard_history WHERE id = ?")
+            .bind(item_id)
+            .fetch_optional(pool)
             .await;
             
-        match result {
-            Ok(_) => {
-                tracing::info!("✅ 分组删除成功: ID={}", id);
-                Ok(())
+            match result {
+                Ok(Some((note,))) => {
+                    tracing::debug!("✅ 获取备注成功: ID={}, note={:?}", item_id, note.as_deref().map(logging::preview));
+                    Ok(note)
+                }
+                Ok(None) => {
+                    let error_msg = format!("未找到ID为{}的条目", item_id);
+                    tracing::warn!("❌ 获取备注失败: {}", error_msg);
+                    Err(error_msg)
+                }
+                Err(e) => {
+                    let error_msg = format!("数据库查询失败: {}", e);
+                    tracing::error!("❌ 获取备注失败: {}", error_msg);
+                    Err(error_msg)
+                }
             }
-            Err(e) => {
-                let error_msg = format!("删除分组失败: {}", e);
-                tracing::error!("❌ 删除分组失败: {}", error_msg);
-                Err(error_msg)
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 获取备注失败: {}", error_msg);
+        Err(error_msg)
+    }
+}
+
+// 剪贴板条目查询相关命令
+
+// 查询完整条目时使用的列
+const CLIPBOARD_ITEM_COLUMNS: &str = "id, content, type, timestamp, is_favorite, is_pinned, image_path, source_app_name, source_app_icon, thumbnail_data, metadata, note, group_id, data_hash, expires_at";
+
+fn row_to_clipboard_item(row: &sqlx::sqlite::SqliteRow) -> ClipboardItem {
+    ClipboardItem {
+        id: row.try_get("id").unwrap_or_default(),
+        content: row.try_get("content").unwrap_or_default(),
+        item_type: row.try_get("type").unwrap_or_default(),
+        timestamp: row.try_get("timestamp").unwrap_or_default(),
+        is_favorite: row.try_get::<i64, _>("is_favorite").unwrap_or(0) != 0,
+        is_pinned: row.try_get::<i64, _>("is_pinned").unwrap_or(0) != 0,
+        image_path: row.try_get("image_path").ok().flatten(),
+        source_app_name: row.try_get("source_app_name").ok().flatten(),
+        source_app_icon: row.try_get("source_app_icon").ok().flatten(),
+        thumbnail_data: row.try_get("thumbnail_data").ok().flatten(),
+        metadata: row.try_get("metadata").ok().flatten(),
+        note: row.try_get("note").ok().flatten(),
+        group_id: row.try_get("group_id").ok().flatten(),
+        data_hash: row.try_get("data_hash").ok().flatten(),
+        expires_at: row.try_get("expires_at").ok().flatten(),
+    }
+}
+
+// item-added 事件中预览文本的最大字符数
+const ITEM_PREVIEW_MAX_CHARS: usize = 200;
+
+fn item_preview(item: &ClipboardItem) -> String {
+    let mut preview: String = item.content.chars().take(ITEM_PREVIEW_MAX_CHARS).collect();
+    if item.content.chars().count() > ITEM_PREVIEW_MAX_CHARS {
+        preview.push('…');
+    }
+    preview
+}
+
+/// 条目写入数据库后通知前端（item-added 事件，携带完整条目）
+async fn emit_items_added(app: &AppHandle, pool: &sqlx::SqlitePool, ids: &[i64]) {
+    if ids.is_empty() {
+        return;
+    }
+    mark_activity(app);
+    let query = format!(
+        "SELECT {} FROM clipboard_history WHERE id = ?",
+        CLIPBOARD_ITEM_COLUMNS
+    );
+    for id in ids {
+        match sqlx::query(&query).bind(id).fetch_optional(pool).await {
+            Ok(Some(row)) => {
+                let item = row_to_clipboard_item(&row);
+                let payload = ItemAddedPayload { preview: item_preview(&item), item };
+                if let Err(e) = app.emit(ITEM_ADDED_EVENT, &payload) {
+                    tracing::warn!("⚠️ 发送新增条目事件失败: ID={}, {}", id, e);
+                }
             }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("⚠️ 查询新增条目失败: ID={}, {}", id, e),
         }
+    }
+}
+
+/// 前端直接写入数据库后调用，为新条目发送 item-added 事件，使所有订阅者都能收到前端和后端写入的条目
+#[tauri::command]
+pub async fn notify_item_added(app: AppHandle, id: i64) -> Result<(), String> {
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        emit_items_added(&app, &db_guard.pool, &[id]).await;
+        Ok(())
+    } else {
+        Err("无法获取数据库状态".to_string())
+    }
+}
+
+/// 历史记录中出现过的来源应用
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct SourceAppSummary {
+    pub name: String,
+    pub item_count: i64,
+    pub last_used: String,
+    // 该应用最近一条带图标记录中的图标
+    pub icon: Option<String>,
+}
+
+/// 列出历史中所有来源应用（忽略空名称）及其条目数，按条目数降序
+#[tauri::command]
+pub async fn get_source_apps(app: AppHandle) -> Result<Vec<SourceAppSummary>, String> {
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        let rows = sqlx::query_as::<_, (String, i64, String, Option<String>)>(
+            "SELECT source_app_name, COUNT(*) AS item_count, MAX(timestamp) AS last_used,
+                    (SELECT source_app_icon FROM clipboard_history AS latest
+                     WHERE latest.source_app_name = history.source_app_name AND latest.source_app_icon IS NOT NULL
+                     ORDER BY latest.id DESC LIMIT 1) AS icon
+             FROM clipboard_history AS history
+             WHERE source_app_name IS NOT NULL AND TRIM(source_app_name) != ''
+             GROUP BY source_app_name
+             ORDER BY item_count DESC, source_app_name"
+        )
+            .fetch_all(&db_guard.pool)
+            .await
+            .map_err(|e| {
+                let error_msg = format!("查询来源应用失败: {}", e);
+                tracing::error!("❌ 获取来源应用失败: {}", error_msg);
+                error_msg
+            })?;
+
+        let apps: Vec<SourceAppSummary> = rows.into_iter()
+            .map(|(name, item_count, last_used, icon)| SourceAppSummary { name, item_count, last_used, icon })
+            .collect();
+        tracing::debug!("✅ 获取来源应用成功: {} 个", apps.len());
+        Ok(apps)
     } else {
         let error_msg = "无法获取数据库状态".to_string();
-        tracing::error!("❌ 删除分组失败: {}", error_msg);
+        tracing::error!("❌ 获取来源应用失败: {}", error_msg);
         Err(error_msg)
     }
 }
 
+// 单页历史记录的最大条数
+const HISTORY_PAGE_MAX_LIMIT: i64 = 500;
+
+// 列表条目（HistoryItem）使用的列，文本只读取预览
+const HISTORY_ITEM_COLUMNS: &str = "id, COALESCE(preview, content) AS content,
+    COALESCE(content_length > length(preview), 0) AS content_truncated,
+    type, timestamp, is_favorite, image_path, source_app_name, note";
+
+fn row_to_history_item(row: &sqlx::sqlite::SqliteRow) -> HistoryItem {
+    HistoryItem {
+        id: row.try_get("id").unwrap_or_default(),
+        content: row.try_get("content").unwrap_or_default(),
+        content_truncated: row.try_get::<i64, _>("content_truncated").unwrap_or(0) != 0,
+        item_type: row.try_get("type").unwrap_or_default(),
+        timestamp: row.try_get("timestamp").unwrap_or_default(),
+        is_favorite: row.try_get::<i64, _>("is_favorite").unwrap_or(0) != 0,
+        image_path: row.try_get("image_path").ok().flatten(),
+        source_app_name: row.try_get("source_app_name").ok().flatten(),
+        note: row.try_get("note").ok().flatten(),
+    }
+}
+
+// 按时间倒序读取一页历史记录；before_timestamp 为上一页最后一条的时间，type_filter 为条目类型
+// retention 为 Some 时按其保留设置隐藏已过期、尚未被清理的条目（enforce_max_age_on_query）
+async fn fetch_history_page(
+    pool: &sqlx::SqlitePool,
+    limit: i64,
+    before: Option<(&str, i64)>,
+    type_filter: Option<&str>,
+    retention: Option<&AppSettings>,
+) -> Result<Vec<HistoryItem>, sqlx::Error> {
+    let cutoffs = retention.map(retention_cutoffs).unwrap_or_default();
+    let (expired, expired_params) = expired_condition(&cutoffs);
+    let mut conditions = Vec::new();
+    // 以 (timestamp, id) 作为游标，时间戳相同的条目按 id 继续分页，不会在页边界重复或遗漏
+    if before.is_some() {
+        conditions.push("(timestamp < ? OR (timestamp = ? AND id < ?))".to_string());
+    }
+    if type_filter.is_some() {
+        conditions.push("type = ?".to_string());
+    }
+    if !cutoffs.is_empty() {
+        conditions.push(format!("NOT ({})", expired));
+    }
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+    // 有类型过滤时可使用 idx_clipboard_type_timestamp 复合索引，否则使用 idx_clipboard_timestamp
+    let query = format!(
+        "SELECT {} FROM clipboard_history {} ORDER BY timestamp DESC, id DESC LIMIT ?",
+        HISTORY_ITEM_COLUMNS, where_clause
+    );
+
+    let mut q = sqlx::query(&query);
+    if let Some((timestamp, id)) = before {
+        q = q.bind(timestamp).bind(timestamp).bind(id);
+    }
+    if let Some(item_type) = type_filter {
+        q = q.bind(item_type);
+    }
+    for param in &expired_params {
+        q = q.bind(*param);
+    }
+    let rows = q.bind(limit.clamp(1, HISTORY_PAGE_MAX_LIMIT)).fetch_all(pool).await?;
+    Ok(rows.iter().map(row_to_history_item).collect())
+}
+
+/// 分页获取历史记录（按时间倒序），传入上一页最后一条的 timestamp 和 id 作为 before_timestamp、before_id 加载更早的条目；
+/// 不传 before_id 时只返回时间戳更早的条目
 #[tauri::command]
-pub async fn delete_item(app: AppHandle, id: i64) -> Result<(), String> {
-    tracing::info!("删除条目: ID={}", id);
+pub async fn get_history(
+    app: AppHandle,
+    limit: i64,
+    before_timestamp: Option<String>,
+    before_id: Option<i64>,
+    type_filter: Option<String>,
+) -> Result<Vec<HistoryItem>, String> {
+    tracing::debug!("分页获取历史记录: limit={}, before={:?}/{:?}, type={:?}", limit, before_timestamp, before_id, type_filter);
+    let before_timestamp = before_timestamp.as_deref().map(normalize_timestamp_arg).transpose()?;
+    let before = before_timestamp.as_deref().map(|timestamp| (timestamp, before_id.unwrap_or(i64::MIN)));
+
+    let settings = cached_settings(&app).await;
+    let retention = settings.enforce_max_age_on_query.then_some(&settings);
+
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        fetch_history_page(&db_guard.pool, limit, before, type_filter.as_deref(), retention)
+            .await
+            .map_err(|e| {
+                let error_msg = format!("查询历史记录失败: {}", e);
+                tracing::error!("❌ 分页获取历史记录失败: {}", error_msg);
+                error_msg
+            })
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 分页获取历史记录失败: {}", error_msg);
+        Err(error_msg)
+    }
+}
+
+/// 获取单个完整条目，ID 不存在时返回 None
+#[tauri::command]
+pub async fn get_item(app: AppHandle, id: i64) -> Result<Option<ClipboardItem>, String> {
+    tracing::debug!("获取条目: ID={}", id);
     if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
         let db_guard = db_state.lock().await;
         let pool = &db_guard.pool;
-        
-        // 1. 获取条目信息，检查是否有图片文件
-        let result = sqlx::query_as::<_, (Option<String>,)>("SELECT image_path FROM clipboard_history WHERE id = ?")
+
+        let query = format!("SELECT {} FROM clipboard_history WHERE id = ?", CLIPBOARD_ITEM_COLUMNS);
+        let row = sqlx::query(&query)
             .bind(id)
             .fetch_optional(pool)
-            .await;
-            
-        if let Ok(Some((Some(image_path),))) = result {
-            // 如果有图片文件，尝试删除
-            let path = PathBuf::from(&image_path);
-            if path.exists() {
-                if let Err(e) = std::fs::remove_file(&path) {
-                    tracing::warn!("删除图片文件失败: {} ({})", image_path, e);
-                } else {
-                    tracing::info!("已删除图片文件: {}", image_path);
-                }
-            }
-        }
-        
-        // 2. 从数据库删除记录
-        let delete_result = sqlx::query("DELETE FROM clipboard_history WHERE id = ?")
+            .await
+            .map_err(|e| {
+                let error_msg = format!("查询条目失败: {}", e);
+                tracing::error!("❌ 获取条目失败: {}", error_msg);
+                error_msg
+            })?;
+
+        Ok(row.as_ref().map(row_to_clipboard_item))
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 获取条目失败: {}", error_msg);
+        Err(error_msg)
+    }
+}
+
+/// 按条目的来源应用重新提取图标并更新该条目，用于补全捕获时未能获取图标的历史条目
+#[tauri::command]
+pub async fn refresh_item_icon(app: AppHandle, id: i64) -> Result<String, String> {
+    tracing::info!("刷新条目图标: ID={}", id);
+    let source_app_name = if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        let row = sqlx::query_as::<_, (Option<String>,)>("SELECT source_app_name FROM clipboard_history WHERE id = ?")
             .bind(id)
-            .execute(pool)
-            .await;
-            
-        match delete_result {
-            Ok(_) => {
-                tracing::info!("✅ 条目删除成功: ID={}", id);
-                Ok(())
+            .fetch_optional(&db_guard.pool)
+            .await
+            .map_err(|e| format!("查询条目失败: {}", e))?;
+        match row {
+            Some((Some(name),)) if !name.trim().is_empty() && name != "Unknown" => name,
+            Some(_) => {
+                let error_msg = format!("ID为{}的条目没有来源应用", id);
+                tracing::warn!("❌ 刷新条目图标失败: {}", error_msg);
+                return Err(error_msg);
             }
-            Err(e) => {
-                let error_msg = format!("数据库删除失败: {}", e);
-                tracing::error!("❌ 删除条目失败: {}", error_msg);
-                Err(error_msg)
+            None => {
+                let error_msg = format!("未找到ID为{}的条目", id);
+                tracing::warn!("❌ 刷新条目图标失败: {}", error_msg);
+                return Err(error_msg);
             }
         }
     } else {
         let error_msg = "无法获取数据库状态".to_string();
-        tracing::error!("❌ 删除条目失败: {}", error_msg);
+        tracing::error!("❌ 刷新条目图标失败: {}", error_msg);
+        return Err(error_msg);
+    };
+
+    // 图标提取可能较慢，不持有数据库锁
+    let Some(icon) = crate::window_info::get_app_icon_by_name(&source_app_name).await else {
+        let error_msg = format!("未能获取应用 '{}' 的图标", source_app_name);
+        tracing::warn!("❌ 刷新条目图标失败: {}", error_msg);
+        return Err(error_msg);
+    };
+
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        let pool = &db_guard.pool;
+        with_db_retry("刷新条目图标", || {
+            sqlx::query("UPDATE clipboard_history SET source_app_icon = ? WHERE id = ?")
+                .bind(&icon)
+                .bind(id)
+                .execute(pool)
+        })
+        .await
+        .map_err(|e| {
+            let error_msg = format!("更新条目图标失败: {}", e);
+            tracing::error!("❌ 刷新条目图标失败: {}", error_msg);
+            error_msg
+        })?;
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 刷新条目图标失败: {}", error_msg);
+        return Err(error_msg);
+    }
+
+    if let Err(e) = app.emit(HISTORY_CHANGED_EVENT, HistoryChangedPayload { ids: vec![id] }) {
+        tracing::warn!("⚠️ 发送 history-changed 事件失败: {}", e);
+    }
+    tracing::info!("✅ 条目图标已刷新: ID={}, 应用={}", id, source_app_name);
+    Ok(icon)
+}
+
+/// 按内容精确匹配查找条目（利用 idx_clipboard_content 索引），用于插入前的去重检查
+#[tauri::command]
+pub async fn find_by_content(app: AppHandle, content: String, item_type: Option<String>) -> Result<Option<ClipboardItem>, String> {
+    tracing::debug!("按内容查找条目: type={:?}, content='{}'", item_type, logging::preview(&content));
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        let pool = &db_guard.pool;
+
+        let query = format!(
+            "SELECT {} FROM clipboard_history WHERE content = ? AND (? IS NULL OR type = ?) ORDER BY timestamp DESC LIMIT 1",
+            CLIPBOARD_ITEM_COLUMNS
+        );
+        let row = sqlx::query(&query)
+            .bind(&content)
+            .bind(&item_type)
+            .bind(&item_type)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| {
+                let error_msg = format!("查询条目失败: {}", e);
+                tracing::error!("❌ 按内容查找条目失败: {}", error_msg);
+                error_msg
+            })?;
+
+        Ok(row.as_ref().map(row_to_clipboard_item))
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 按内容查找条目失败: {}", error_msg);
         Err(error_msg)
     }
 }
 
+// 单条 IN 查询中绑定的最大 ID 数（SQLite 对绑定参数数量有限制）
+const IDS_PER_QUERY: usize = 500;
+
+/// 按 ID 批量获取条目，按请求的顺序返回，不存在的 ID 会被忽略
 #[tauri::command]
-pub async fn add_item_to_group(app: AppHandle, item_id: i64, group_id: Option<i64>) -> Result<(), String> {
-    tracing::info!("设置条目分组: item_id={}, group_id={:?}", item_id, group_id);
+pub async fn get_items_by_ids(app: AppHandle, ids: Vec<i64>) -> Result<Vec<ClipboardItem>, String> {
+    tracing::debug!("按 ID 批量获取条目: {} 个", ids.len());
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
     if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
         let db_guard = db_state.lock().await;
         let pool = &db_guard.pool;
-        
-        let result = sqlx::query("UPDATE clipboard_history SET group_id = ? WHERE id = ?")
-            .bind(group_id)
-            .bind(item_id)
-            .execute(pool)
-            .await;
-            
-        match result {
-            Ok(_) => {
-                tracing::info!("✅ 条目分组设置成功: item_id={}", item_id);
-                Ok(())
+
+        let mut found: std::collections::HashMap<i64, ClipboardItem> = std::collections::HashMap::new();
+        for chunk in ids.chunks(IDS_PER_QUERY) {
+            let placeholders = vec!["?"; chunk.len()].join(", ");
+            let query = format!(
+                "SELECT {} FROM clipboard_history WHERE id IN ({})",
+                CLIPBOARD_ITEM_COLUMNS, placeholders
+            );
+            let mut q = sqlx::query(&query);
+            for id in chunk {
+                q = q.bind(id);
             }
-            Err(e) => {
-                let error_msg = format!("设置条目分组失败: {}", e);
-                tracing::error!("❌ 设置条目分组失败: {}", error_msg);
-                Err(error_msg)
+            let rows = q.fetch_all(pool).await.map_err(|e| {
+                let error_msg = format!("批量获取条目失败: {}", e);
+                tracing::error!("❌ {}", error_msg);
+                error_msg
+            })?;
+            for row in &rows {
+                let item = row_to_clipboard_item(row);
+                found.insert(item.id, item);
             }
         }
+
+        let items: Vec<ClipboardItem> = ids.iter().filter_map(|id| found.get(id).cloned()).collect();
+        tracing::debug!("✅ 批量获取条目完成: 请求 {} 个，找到 {} 个", ids.len(), items.len());
+        Ok(items)
     } else {
         let error_msg = "无法获取数据库状态".to_string();
-        tracing::error!("❌ 设置条目分组失败: {}", error_msg);
+        tracing::error!("❌ 批量获取条目失败: {}", error_msg);
         Err(error_msg)
     }
 }
 
-// ===== 文件剪贴板相关命令 =====
+// 单次批量插入的最大条目数
+const BATCH_INSERT_MAX_ITEMS: usize = 500;
+// 文本条目的最大长度（与前端捕获时的限制一致）
+const MAX_TEXT_CONTENT_LEN: usize = 100_000;
+
+/// 待插入的条目
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct NewClipboardItem {
+    pub content: String,
+    #[serde(rename = "type")]
+    pub item_type: String,
+    #[serde(default)]
+    pub timestamp: Option<String>,
+    #[serde(default)]
+    pub is_favorite: bool,
+    #[serde(default)]
+    pub is_pinned: bool,
+    #[serde(default)]
+    pub image_path: Option<String>,
+    #[serde(default)]
+    pub source_app_name: Option<String>,
+    #[serde(default)]
+    pub source_app_icon: Option<String>,
+    #[serde(default)]
+    pub thumbnail_data: Option<String>,
+    #[serde(default)]
+    pub metadata: Option<String>,
+    #[serde(default)]
+    pub note: Option<String>,
+    #[serde(default)]
+    pub group_id: Option<i64>,
+    #[serde(default)]
+    pub data_hash: Option<String>,
+    // 图片文件字节的哈希，未提供时按 image_path 读取文件计算
+    #[serde(default)]
+    pub content_hash: Option<String>,
+}
 
-/// 文件元信息结构
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
-pub struct FileMetadata {
-    pub path: String,
-    pub name: String,
-    pub extension: String,
-    pub size: u64,
-    pub exists: bool,
-    pub is_directory: bool,
-    pub modified_time: String, // ISO 8601 string
+// 校验待插入条目的类型和大小
+fn validate_new_item(item: &NewClipboardItem) -> Result<(), String> {
+    if !CLIPBOARD_ITEM_TYPES.contains(&item.item_type.as_str()) {
+        return Err(format!("不支持的条目类型: {}", item.item_type));
+    }
+    if item.content.is_empty() {
+        return Err("条目内容为空".to_string());
+    }
+    if item.content.len() > MAX_TEXT_CONTENT_LEN {
+        return Err(format!("条目内容过长: {} 字节", item.content.len()));
+    }
+    Ok(())
 }
 
-/// 复制文件到剪贴板 (Windows CF_HDROP)
-#[tauri::command]
-pub async fn copy_files_to_clipboard(file_paths: Vec<String>) -> Result<(), String> {
-    let start = std::time::Instant::now();
-    tracing::info!("复制文件到剪贴板: {:?}", file_paths);
-    
-    if file_paths.is_empty() {
-        return Err("文件路径列表为空".to_string());
+// 应用捕获设置（文本规范化），为图片补全 content_hash，返回条目的去重哈希
+fn prepare_new_item(item: &mut NewClipboardItem, settings: &AppSettings) -> String {
+    if item.item_type == "text" {
+        item.content = prepare_text_for_storage(&item.content, settings);
     }
-    
-    // 验证所有文件是否存在
-    for path_str in &file_paths {
-        let path = PathBuf::from(path_str);
-        if !path.exists() {
-            return Err(format!("文件不存在: {}", path_str));
-        }
+    item.content_hash = item.content_hash.take().filter(|hash| !hash.is_empty());
+    if item.item_type == "image" && item.content_hash.is_none() {
+        item.content_hash = item.image_path
+            .as_deref()
+            .and_then(|path| fs::read(resolve_image_path(path)).ok())
+            .map(|bytes| compute_bytes_hash(&bytes));
     }
+    item.data_hash
+        .take()
+        .filter(|hash| !hash.is_empty())
+        .unwrap_or_else(|| compute_content_hash(&item.content))
+}
 
-    #[cfg(target_os = "windows")]
-    {
-        use clipboard_win::{formats, Clipboard, Setter};
+// 查找内容相同的已有条目（旧的文本条目没有 data_hash，同时按内容和类型比较，可使用 idx_clipboard_content；
+// 图片还按文件字节的 content_hash 比较）
+async fn find_duplicate_item(
+    conn: &mut sqlx::SqliteConnection,
+    item: &NewClipboardItem,
+    data_hash: &str,
+) -> Result<Option<i64>, String> {
+    sqlx::query_scalar(
+        "SELECT id FROM clipboard_history WHERE data_hash = ? OR (content = ? AND type = ?) OR content_hash = ? LIMIT 1"
+    )
+        .bind(data_hash)
+        .bind(&item.content)
+        .bind(&item.item_type)
+        .bind(&item.content_hash)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| format!("去重查询失败: {}", e))
+}
 
-        let _clip = Clipboard::new_attempts(10)
-            .map_err(|e| format!("无法打开剪贴板: {}", e))?;
-
-        // 清空剪贴板，确保之前的内容被清除
-        clipboard_win::raw::empty()
-            .map_err(|e| format!("清空剪贴板失败: {}", e))?;
+// 插入单个条目（应用捕获设置，按哈希去重），重复时返回 None
+async fn insert_clipboard_item(
+    conn: &mut sqlx::SqliteConnection,
+    mut item: NewClipboardItem,
+    settings: &AppSettings,
+) -> Result<Option<i64>, String> {
+    let data_hash = prepare_new_item(&mut item, settings);
+    if find_duplicate_item(conn, &item, &data_hash).await?.is_some() {
+        return Ok(None);
+    }
+    insert_prepared_item(conn, item, &data_hash).await.map(Some)
+}
 
-        // 设置文件列表 (CF_HDROP)
-        formats::FileList.write_clipboard(&file_paths)
-            .map_err(|e| format!("设置剪贴板文件失败: {}", e))?;
+/// 插入或提升条目的结果：inserted 为 false 表示已有相同内容的条目，已将其移到最前
+#[derive(serde::Serialize, Debug, Clone, PartialEq)]
+pub struct UpsertResult {
+    pub id: i64,
+    pub inserted: bool,
+}
 
-        tracing::info!("✅ 文件已写入剪贴板 (Windows CF_HDROP), 文件数: {}, 耗时: {:?}",
-            file_paths.len(), start.elapsed());
+// 提升已有条目后，新条目刚写入的图片文件已不再需要：没有任何条目引用它时删除
+async fn remove_unreferenced_image(conn: &mut sqlx::SqliteConnection, image_path: &str) -> Result<(), String> {
+    let referenced: Vec<(Option<String>, String)> = sqlx::query_as(
+        "SELECT image_path, content FROM clipboard_history WHERE type = 'image'"
+    )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| format!("查询图片引用失败: {}", e))?;
+    let target = resolve_image_path(image_path);
+    if referenced.iter().any(|(path, content)| resolve_image_path(path.as_deref().unwrap_or(content)) == target) {
         return Ok(());
     }
+    remove_image_files(&[image_path.to_string()]);
+    Ok(())
+}
 
-    #[cfg(not(target_os = "windows"))]
-    {
-        // 其他平台暂不支持文件剪贴板
-        Err("文件剪贴板功能目前仅支持 Windows 平台".to_string())
+// 插入单个条目；已有相同内容的条目时把它的时间更新为现在（移到最前）而不是新增一行，
+// 并删除新条目已写入但不再被引用的图片文件
+async fn upsert_clipboard_item(
+    conn: &mut sqlx::SqliteConnection,
+    mut item: NewClipboardItem,
+    settings: &AppSettings,
+) -> Result<UpsertResult, String> {
+    let data_hash = prepare_new_item(&mut item, settings);
+    if let Some(id) = find_duplicate_item(conn, &item, &data_hash).await? {
+        sqlx::query("UPDATE clipboard_history SET timestamp = ? WHERE id = ?")
+            .bind(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+            .bind(id)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| format!("更新条目时间失败: {}", e))?;
+        if let Some(image_path) = item.image_path.as_deref().filter(|_| item.item_type == "image") {
+            remove_unreferenced_image(conn, image_path).await?;
+        }
+        return Ok(UpsertResult { id, inserted: false });
     }
+    let id = insert_prepared_item(conn, item, &data_hash).await?;
+    Ok(UpsertResult { id, inserted: true })
 }
 
-/// 获取文件元信息
+// 写入已规范化并完成去重检查的条目，返回新 ID
+async fn insert_prepared_item(
+    conn: &mut sqlx::SqliteConnection,
+    mut item: NewClipboardItem,
+    data_hash: &str,
+) -> Result<i64, String> {
+    let timestamp = item.timestamp
+        .take()
+        .unwrap_or_else(|| chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true));
+    let result = sqlx::query(
+        "INSERT INTO clipboard_history (content, type, timestamp, is_favorite, is_pinned, image_path, source_app_name, source_app_icon, thumbnail_data, metadata, note, group_id, data_hash, content_hash)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+        .bind(&item.content)
+        .bind(&item.item_type)
+        .bind(&timestamp)
+        .bind(item.is_favorite as i64)
+        .bind(item.is_pinned as i64)
+        .bind(&item.image_path)
+        .bind(&item.source_app_name)
+        .bind(&item.source_app_icon)
+        .bind(&item.thumbnail_data)
+        .bind(&item.metadata)
+        .bind(&item.note)
+        .bind(item.group_id)
+        .bind(data_hash)
+        .bind(&item.content_hash)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| format!("插入记录失败: {}", e))?;
+
+    Ok(result.last_insert_rowid())
+}
+
+/// 写入一个条目；已有相同内容和类型的条目时把它移到最前，返回条目 ID 以及是否为新增
 #[tauri::command]
-pub async fn get_file_metadata(file_path: String) -> Result<FileMetadata, String> {
-    let path = PathBuf::from(&file_path);
-    
-    let name = path.file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("unknown")
-        .to_string();
-        
-    let extension = path.extension()
-        .and_then(|e| e.to_str())
-        .unwrap_or("")
-        .to_string();
-    
-    let exists = path.exists();
-    let is_directory = path.is_dir();
-    
-    let size = if exists && !is_directory {
-        std::fs::metadata(&path)
-            .map(|m| m.len())
-            .unwrap_or(0)
-    } else {
-        0
-    };
-    
-    let modified_time = if exists {
-        std::fs::metadata(&path)
-            .and_then(|m| m.modified())
-            .ok()
-            .map(|t| chrono::DateTime::<chrono::Local>::from(t).format("%Y-%m-%d %H:%M").to_string())
-            .unwrap_or_else(|| "".to_string())
+pub async fn upsert_history_item(app: AppHandle, item: NewClipboardItem) -> Result<UpsertResult, String> {
+    tracing::debug!("写入或提升条目: type={}", item.item_type);
+    validate_new_item(&item)?;
+    let settings = cached_settings(&app).await;
+
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        let pool = &db_guard.pool;
+
+        let mut tx = pool.begin().await.map_err(|e| format!("开启事务失败: {}", e))?;
+        let result = upsert_clipboard_item(&mut tx, item, &settings).await?;
+        tx.commit().await.map_err(|e| format!("提交事务失败: {}", e))?;
+
+        if result.inserted {
+            tracing::info!("✅ 已新增条目: ID={}", result.id);
+            emit_items_added(&app, pool, &[result.id]).await;
+        } else {
+            tracing::info!("内容已存在，已移到最前: ID={}", result.id);
+            mark_activity(&app);
+            if let Err(e) = app.emit(HISTORY_CHANGED_EVENT, HistoryChangedPayload { ids: vec![result.id] }) {
+                tracing::warn!("⚠️ 发送 history-changed 事件失败: {}", e);
+            }
+        }
+        Ok(result)
     } else {
-        "".to_string()
-    };
-    
-    Ok(FileMetadata {
-        path: file_path,
-        name,
-        extension,
-        size,
-        exists,
-        is_directory,
-        modified_time,
-    })
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 写入条目失败: {}", error_msg);
+        Err(error_msg)
+    }
 }
 
-/// 批量获取文件元信息
+/// 在单个事务中批量插入条目，按哈希去重；返回每个条目的新 ID（重复的为 null）
 #[tauri::command]
-pub async fn get_files_metadata(file_paths: Vec<String>) -> Result<Vec<FileMetadata>, String> {
-    let mut results = Vec::with_capacity(file_paths.len());
-    
-    for file_path in file_paths {
-        let metadata = get_file_metadata(file_path).await?;
-        results.push(metadata);
+pub async fn batch_insert(app: AppHandle, items: Vec<NewClipboardItem>) -> Result<Vec<Option<i64>>, String> {
+    tracing::info!("批量插入条目: {} 条", items.len());
+    if items.len() > BATCH_INSERT_MAX_ITEMS {
+        return Err(format!("单次最多插入 {} 条，实际 {} 条", BATCH_INSERT_MAX_ITEMS, items.len()));
+    }
+    for (index, item) in items.iter().enumerate() {
+        validate_new_item(item).map_err(|e| format!("第 {} 条无效: {}", index + 1, e))?;
+    }
+
+    let settings = load_settings(app.clone()).await.unwrap_or_default();
+
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        let pool = &db_guard.pool;
+
+        let mut tx = pool.begin().await.map_err(|e| format!("开启事务失败: {}", e))?;
+        let mut ids = Vec::with_capacity(items.len());
+        for item in items {
+            ids.push(insert_clipboard_item(&mut tx, item, &settings).await?);
+        }
+        tx.commit().await.map_err(|e| format!("提交事务失败: {}", e))?;
+
+        let inserted_ids: Vec<i64> = ids.iter().flatten().copied().collect();
+        emit_items_added(&app, pool, &inserted_ids).await;
+
+        let inserted = inserted_ids.len();
+        tracing::info!("✅ 批量插入完成: 新增 {} 条，跳过 {} 条重复", inserted, ids.len() - inserted);
+        Ok(ids)
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 批量插入失败: {}", error_msg);
+        Err(error_msg)
     }
-    
-    Ok(results)
 }
 
-/// 检查文件是否存在
-#[tauri::command]
-pub async fn check_files_exist(file_paths: Vec<String>) -> Result<Vec<bool>, String> {
-    let results: Vec<bool> = file_paths.iter()
-        .map(|p| PathBuf::from(p).exists())
+// 从系统剪贴板读取到的内容
+enum ClipboardSnapshot {
+    Text(String),
+    Image(Vec<u8>), // PNG 编码
+}
+
+// 来源应用的名称或 bundle id 是否与黑名单中的某一项相同（不区分大小写，忽略首尾空白）
+fn app_matches_blacklist(blacklist: &[String], app_name: &str, bundle_id: Option<&str>) -> bool {
+    let candidates: Vec<String> = std::iter::once(app_name)
+        .chain(bundle_id)
+        .map(|value| value.trim().to_lowercase())
+        .filter(|value| !value.is_empty())
         .collect();
-    
-    Ok(results)
+    blacklist
+        .iter()
+        .map(|entry| entry.trim().to_lowercase())
+        .any(|entry| !entry.is_empty() && candidates.contains(&entry))
 }
 
-/// 获取文件图标 (Windows Shell API)
+/// 调整来源应用图标缓存的容量（至少为 1），超出时移除最久未使用的图标，只影响本次运行
 #[tauri::command]
-pub async fn get_file_icon(file_path: String) -> Result<String, String> {
-    let path = PathBuf::from(&file_path);
-    
-    // 如果文件不存在，尝试根据扩展名获取图标
-    let extension = path.extension()
-        .and_then(|e| e.to_str())
-        .unwrap_or("");
-    
-    #[cfg(target_os = "windows")]
-    {
-        use std::ptr::null_mut;
-        use winapi::um::shellapi::{SHGetFileInfoW, SHFILEINFOW, SHGFI_ICON, SHGFI_SMALLICON, SHGFI_USEFILEATTRIBUTES};
-        use winapi::um::winuser::{DestroyIcon, GetIconInfo, ICONINFO};
-        use winapi::um::wingdi::{GetDIBits, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, DeleteObject, GetObjectW, BITMAP};
-        use winapi::shared::windef::HBITMAP;
-        use winapi::um::winuser::GetDC;
-        use winapi::um::wingdi::CreateCompatibleDC;
-        use winapi::um::wingdi::SelectObject;
-        use winapi::um::wingdi::DeleteDC;
-        use winapi::um::winuser::ReleaseDC;
-        use winapi::shared::minwindef::DWORD;
-        
-        // 将路径转换为宽字符
-        let wide_path: Vec<u16> = file_path.encode_utf16().chain(std::iter::once(0)).collect();
-        
-        let mut shfi: SHFILEINFOW = unsafe { std::mem::zeroed() };
-        
-        // 如果文件存在，直接获取图标；否则使用扩展名
-        let flags = if path.exists() {
-            SHGFI_ICON | SHGFI_SMALLICON
-        } else {
-            SHGFI_ICON | SHGFI_SMALLICON | SHGFI_USEFILEATTRIBUTES
-        };
-        
-        let result = unsafe {
-            SHGetFileInfoW(
-                wide_path.as_ptr(),
-                0x80, // FILE_ATTRIBUTE_NORMAL
-                &mut shfi,
-                std::mem::size_of::<SHFILEINFOW>() as u32,
-                flags,
-            )
-        };
-        
-        if result == 0 || shfi.hIcon.is_null() {
-            // 返回默认图标占位符
-            return Ok(get_default_file_icon(extension));
+pub async fn resize_icon_cache(size: usize) -> Result<(), String> {
+    crate::icon_cache::set_icon_cache_size(size);
+    Ok(())
+}
+
+/// 检查来源应用是否在黑名单中，黑名单应用复制的内容不应记录到历史
+#[tauri::command]
+pub async fn is_app_blacklisted(app: AppHandle, app_name: String, bundle_id: Option<String>) -> bool {
+    let settings = cached_settings(&app).await;
+    app_matches_blacklist(&settings.blacklisted_apps, &app_name, bundle_id.as_deref())
+}
+
+// 读取当前剪贴板：优先文本，其次图片（文件列表不支持手动捕获）
+fn read_clipboard_snapshot() -> Result<Option<ClipboardSnapshot>, String> {
+    let mut clipboard = arboard::Clipboard::new()
+        .map_err(|e| format!("初始化剪贴板失败: {}", e))?;
+    if let Ok(text) = clipboard.get_text() {
+        if !text.is_empty() {
+            return Ok(Some(ClipboardSnapshot::Text(text)));
         }
-        
-        // 获取图标信息
-        let mut icon_info: ICONINFO = unsafe { std::mem::zeroed() };
-        let got_info = unsafe { GetIconInfo(shfi.hIcon, &mut icon_info) };
-        
-        if got_info == 0 {
-            unsafe { DestroyIcon(shfi.hIcon) };
-            return Ok(get_default_file_icon(extension));
+    }
+    let Ok(image) = clipboard.get_image() else { return Ok(None) };
+    let rgba = image::RgbaImage::from_raw(image.width as u32, image.height as u32, image.bytes.into_owned())
+        .ok_or("剪贴板图片数据无效")?;
+    let mut png = std::io::Cursor::new(Vec::new());
+    image::DynamicImage::ImageRgba8(rgba)
+        .write_to(&mut png, ImageFormat::Png)
+        .map_err(|e| format!("编码剪贴板图片失败: {}", e))?;
+    Ok(Some(ClipboardSnapshot::Image(png.into_inner())))
+}
+
+/// 手动捕获当前剪贴板内容（手动捕获快捷键触发），返回新条目 ID；
+/// 内容已存在时把已有条目移到最前并返回其 ID，剪贴板为空时返回 null
+#[tauri::command]
+pub async fn capture_current_clipboard(app: AppHandle) -> Result<Option<i64>, String> {
+    let result = run_capture_current_clipboard(&app).await;
+    report_command_error(&app, "capture_current_clipboard", result)
+}
+
+async fn run_capture_current_clipboard(app: &AppHandle) -> Result<Option<i64>, String> {
+    if app.try_state::<CaptureControlState>().is_some_and(|state| state.paused.load(std::sync::atomic::Ordering::Relaxed)) {
+        return Err("剪贴板记录已暂停，请先在托盘菜单中恢复监听".to_string());
+    }
+    mark_activity(app);
+
+    let snapshot = tokio::task::spawn_blocking(read_clipboard_snapshot)
+        .await
+        .map_err(|e| format!("读取剪贴板失败: {}", e))??;
+    let Some(snapshot) = snapshot else {
+        tracing::info!("手动捕获：剪贴板为空或格式不支持");
+        return Ok(None);
+    };
+
+    let settings = cached_settings(&app).await;
+    let source_app = crate::window_info::get_active_window_info_for_clipboard().await.ok();
+    if let Some(info) = &source_app {
+        if app_matches_blacklist(&settings.blacklisted_apps, &info.name, info.bundle_id.as_deref()) {
+            tracing::info!("手动捕获：来源应用 {} 在黑名单中，跳过记录", info.name);
+            return Ok(None);
         }
-        
-        // 获取位图信息
-        let hbm_color = icon_info.hbmColor;
-        if hbm_color.is_null() {
-            unsafe {
-                if !icon_info.hbmMask.is_null() { DeleteObject(icon_info.hbmMask as _); }
-                DestroyIcon(shfi.hIcon);
+    }
+    let group_id = app
+        .try_state::<CaptureGroupState>()
+        .and_then(|state| state.group_id.lock().ok().and_then(|guard| *guard));
+
+    let mut item = NewClipboardItem {
+        content: String::new(),
+        item_type: "text".to_string(),
+        timestamp: None,
+        is_favorite: false,
+        is_pinned: false,
+        image_path: None,
+        source_app_name: source_app.as_ref().map(|info| info.name.clone()),
+        source_app_icon: source_app.and_then(|info| info.icon),
+        thumbnail_data: None,
+        metadata: None,
+        note: None,
+        group_id,
+        data_hash: None,
+        content_hash: None,
+    };
+    match snapshot {
+        ClipboardSnapshot::Text(text) => {
+            if text.len() > MAX_TEXT_CONTENT_LEN {
+                return Err(format!("文本内容过长: {} 字节", text.len()));
             }
-            return Ok(get_default_file_icon(extension));
+            item.content = prepare_text_for_storage(&text, &settings);
         }
-        
-        // 获取位图尺寸
-        let mut bm: BITMAP = unsafe { std::mem::zeroed() };
-        unsafe {
-            GetObjectW(
-                hbm_color as _,
-                std::mem::size_of::<BITMAP>() as i32,
-                &mut bm as *mut _ as _,
-            );
+        ClipboardSnapshot::Image(png) => {
+            let base64_data = general_purpose::STANDARD.encode(&png);
+            item.data_hash = Some(compute_content_hash(&base64_data));
+            let saved: serde_json::Value = serde_json::from_str(&save_clipboard_image(app.clone(), base64_data).await?)
+                .map_err(|e| format!("解析图片保存结果失败: {}", e))?;
+            let path = saved["path"].as_str().ok_or("图片保存结果缺少路径")?.to_string();
+            item.item_type = "image".to_string();
+            item.content = path.clone();
+            item.image_path = Some(path);
+            item.metadata = Some(saved["metadata"].to_string());
+            item.content_hash = saved["content_hash"].as_str().map(|hash| hash.to_string());
         }
-        
-        let width = bm.bmWidth as usize;
-        let height = bm.bmHeight as usize;
-        
-        if width == 0 || height == 0 {
-            unsafe {
-                if !icon_info.hbmMask.is_null() { DeleteObject(icon_info.hbmMask as _); }
-                DeleteObject(hbm_color as _);
-                DestroyIcon(shfi.hIcon);
+    }
+    let item_type = item.item_type.clone();
+
+    let db_state = app.try_state::<Mutex<DatabaseState>>().ok_or("无法获取数据库状态")?;
+    let db_guard = db_state.lock().await;
+    let pool = &db_guard.pool;
+    let mut tx = pool.begin().await.map_err(|e| format!("开启事务失败: {}", e))?;
+    let result = upsert_clipboard_item(&mut tx, item, &settings).await?;
+    tx.commit().await.map_err(|e| format!("提交事务失败: {}", e))?;
+    if result.inserted {
+        tracing::info!("✅ 手动捕获完成: ID={}, type={}", result.id, item_type);
+        emit_items_added(app, pool, &[result.id]).await;
+        return Ok(Some(result.id));
+    }
+
+    // 内容已存在：已移到最前，刚保存的重复图片文件已由 upsert_clipboard_item 删除
+    let existing = result.id;
+    if let Err(e) = app.emit(HISTORY_CHANGED_EVENT, HistoryChangedPayload { ids: vec![existing] }) {
+        tracing::warn!("⚠️ 发送 history-changed 事件失败: {}", e);
+    }
+    tracing::info!("手动捕获：内容已存在，已移到最前: ID={}", existing);
+    Ok(Some(existing))
+}
+
+// 外部导入文件的最大大小
+const EXTERNAL_IMPORT_MAX_BYTES: u64 = 50 * 1024 * 1024;
+
+/// 从其他剪贴板工具导入的结果：哪些条目和设置被导入、哪些被跳过及原因
+#[derive(serde::Serialize, Debug, Clone, Default)]
+pub struct ExternalImportReport {
+    pub imported_items: usize,
+    pub duplicate_items: usize,
+    pub skipped_items: Vec<String>,
+    pub imported_settings: Vec<String>,
+    pub ignored_settings: Vec<String>,
+}
+
+// 解析后的外部导出数据
+#[derive(Default)]
+struct ExternalExport {
+    items: Vec<NewClipboardItem>,
+    skipped_items: Vec<String>,
+    imported_settings: Vec<String>,
+    ignored_settings: Vec<String>,
+}
+
+// 依次尝试多个常见字段名
+fn first_field<'a>(object: &'a serde_json::Map<String, serde_json::Value>, keys: &[&str]) -> Option<&'a serde_json::Value> {
+    keys.iter().find_map(|key| object.get(*key))
+}
+
+// 把外部时间戳统一为 UTC 毫秒精度的 RFC3339 格式，无法解析时返回 None（使用导入时间）
+fn normalize_external_timestamp(value: &serde_json::Value) -> Option<String> {
+    let timestamp = match value {
+        serde_json::Value::String(text) => chrono::DateTime::parse_from_rfc3339(text).ok()?.with_timezone(&chrono::Utc),
+        // 数字按 Unix 时间戳处理，超过 1e12 的视为毫秒
+        serde_json::Value::Number(number) => {
+            let number = number.as_i64()?;
+            let millis = if number.abs() >= 1_000_000_000_000 { number } else { number * 1000 };
+            chrono::DateTime::from_timestamp_millis(millis)?
+        }
+        _ => return None,
+    };
+    Some(timestamp.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+}
+
+fn parse_external_item(value: &serde_json::Value) -> Result<NewClipboardItem, String> {
+    let text_item = |content: String| NewClipboardItem {
+        content,
+        item_type: "text".to_string(),
+        timestamp: None,
+        is_favorite: false,
+        is_pinned: false,
+        image_path: None,
+        source_app_name: None,
+        source_app_icon: None,
+        thumbnail_data: None,
+        metadata: None,
+        note: None,
+        group_id: None,
+        data_hash: None,
+        content_hash: None,
+    };
+
+    let item = match value {
+        serde_json::Value::String(content) => text_item(content.clone()),
+        serde_json::Value::Object(object) => {
+            if let Some(item_type) = first_field(object, &["type", "kind"]).and_then(|value| value.as_str()) {
+                if item_type != "text" {
+                    return Err(format!("仅支持文本条目，实际为 {}", item_type));
+                }
             }
-            return Ok(get_default_file_icon(extension));
+            let content = first_field(object, &["content", "text", "value", "snippet"])
+                .and_then(|value| value.as_str())
+                .ok_or("缺少文本内容")?;
+            let flag = |keys: &[&str]| first_field(object, keys).and_then(|value| value.as_bool()).unwrap_or(false);
+            let mut item = text_item(content.to_string());
+            item.timestamp = first_field(object, &["timestamp", "created_at", "createdAt", "date"]).and_then(normalize_external_timestamp);
+            item.is_favorite = flag(&["is_favorite", "favorite", "starred"]);
+            item.is_pinned = flag(&["is_pinned", "pinned"]);
+            item.note = first_field(object, &["note", "title", "name"])
+                .and_then(|value| value.as_str())
+                .filter(|note| !note.trim().is_empty())
+                .map(|note| note.to_string());
+            item.source_app_name = first_field(object, &["source_app_name", "app", "source"])
+                .and_then(|value| value.as_str())
+                .map(|name| name.to_string());
+            item
         }
-        
-        // 准备位图信息头
-        let mut bmi: BITMAPINFO = unsafe { std::mem::zeroed() };
-        bmi.bmiHeader.biSize = std::mem::size_of::<BITMAPINFOHEADER>() as u32;
-        bmi.bmiHeader.biWidth = width as i32;
-        bmi.bmiHeader.biHeight = -(height as i32); // 负数表示自上而下
-        bmi.bmiHeader.biPlanes = 1;
-        bmi.bmiHeader.biBitCount = 32;
-        bmi.bmiHeader.biCompression = BI_RGB;
-        
-        // 分配像素缓冲区
-        let mut pixels: Vec<u8> = vec![0u8; width * height * 4];
-        
-        // 获取设备上下文
-        let hdc_screen = unsafe { GetDC(null_mut()) };
-        let hdc_mem = unsafe { CreateCompatibleDC(hdc_screen) };
-        let old_bmp = unsafe { SelectObject(hdc_mem, hbm_color as _) };
-        
-        // 读取位图数据
-        unsafe {
-            GetDIBits(
-                hdc_mem,
-                hbm_color,
-                0,
-                height as u32,
-                pixels.as_mut_ptr() as _,
-                &mut bmi,
-                0, // DIB_RGB_COLORS
-            );
+        _ => return Err("格式无法识别".to_string()),
+    };
+    validate_new_item(&item)?;
+    Ok(item)
+}
+
+// 把外部设置映射到本应用的设置上，无法识别或取值无效的设置记录到 ignored_settings
+fn apply_external_settings(object: &serde_json::Map<String, serde_json::Value>, settings: &mut AppSettings, export: &mut ExternalExport) {
+    for (key, value) in object {
+        let applied = match key.as_str() {
+            "max_history_items" | "history_limit" | "max_items" | "maxItems" => value
+                .as_u64()
+                .filter(|count| *count > 0)
+                .map(|count| settings.max_history_items = count as usize)
+                .map(|_| "max_history_items"),
+            "max_history_time" | "history_days" | "max_days" | "retention_days" => value
+                .as_u64()
+                .filter(|days| *days > 0)
+                .map(|days| settings.max_history_time = days)
+                .map(|_| "max_history_time"),
+            "hotkey" | "shortcut" => value
+                .as_str()
+                .filter(|hotkey| !hotkey.trim().is_empty())
+                .map(|hotkey| settings.hotkey = hotkey.trim().to_string())
+                .map(|_| "hotkey"),
+            "theme" => value
+                .as_str()
+                .filter(|theme| ["light", "dark", "system"].contains(theme))
+                .map(|theme| settings.theme = theme.to_string())
+                .map(|_| "theme"),
+            "capture_images" | "save_images" => value
+                .as_bool()
+                .map(|enabled| settings.capture_images = enabled)
+                .map(|_| "capture_images"),
+            _ => None,
+        };
+        match applied {
+            Some(field) => export.imported_settings.push(format!("{} -> {}", key, field)),
+            None => export.ignored_settings.push(key.clone()),
         }
-        
-        // 清理资源
-        unsafe {
-            SelectObject(hdc_mem, old_bmp);
-            DeleteDC(hdc_mem);
-            ReleaseDC(null_mut(), hdc_screen);
-            if !icon_info.hbmMask.is_null() { DeleteObject(icon_info.hbmMask as _); }
-            DeleteObject(hbm_color as _);
-            DestroyIcon(shfi.hIcon);
+    }
+}
+
+/// 解析其他剪贴板工具的 JSON 导出：支持纯文本片段列表（["a", "b"]）、条目对象列表，
+/// 以及包含 items/history/snippets/clips 和 settings/preferences 的对象
+fn parse_external_export(value: &serde_json::Value, settings: &mut AppSettings) -> Result<ExternalExport, String> {
+    let mut export = ExternalExport::default();
+    let items = match value {
+        serde_json::Value::Array(items) => Some(items),
+        serde_json::Value::Object(object) => {
+            if let Some(external_settings) = first_field(object, &["settings", "preferences"]).and_then(|value| value.as_object()) {
+                apply_external_settings(external_settings, settings, &mut export);
+            }
+            first_field(object, &["items", "history", "snippets", "clips"]).and_then(|value| value.as_array())
         }
-        
-        // BGRA -> RGBA 转换
-        for chunk in pixels.chunks_exact_mut(4) {
-            chunk.swap(0, 2); // B <-> R
+        _ => return Err("无法识别的导入格式：需要 JSON 数组或对象".to_string()),
+    };
+
+    for (index, item) in items.into_iter().flatten().enumerate() {
+        match parse_external_item(item) {
+            Ok(item) => export.items.push(item),
+            Err(e) => export.skipped_items.push(format!("第 {} 条: {}", index + 1, e)),
         }
+    }
+    if export.items.is_empty() && export.skipped_items.is_empty() && export.imported_settings.is_empty() && export.ignored_settings.is_empty() {
+        return Err("文件中没有可导入的条目或设置".to_string());
+    }
+    Ok(export)
+}
+
+// 设置导入文件的大小上限
+const SETTINGS_IMPORT_MAX_BYTES: u64 = 1024 * 1024;
+
+/// 导入设置的结果：合并后的设置和被忽略的未知字段
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct SettingsImportResult {
+    pub settings: AppSettings,
+    pub ignored_keys: Vec<String>,
+}
+
+// 校验并合并导入的设置：缺失字段使用默认值，未知字段忽略；
+// 导入文件中没有 LAN 密码时保留当前密码，当前配置不随导入切换
+fn merge_imported_settings(content: &str, current: &AppSettings) -> Result<SettingsImportResult, String> {
+    let value: serde_json::Value = serde_json::from_str(content).map_err(|e| format!("设置文件不是有效的 JSON: {}", e))?;
+    let imported = value.as_object().ok_or("设置文件格式无效：顶层必须是 JSON 对象")?;
+
+    let defaults = serde_json::to_value(AppSettings::default()).map_err(|e| e.to_string())?;
+    let mut merged = defaults.as_object().cloned().unwrap_or_default();
+    let mut ignored_keys = Vec::new();
+    for (key, value) in imported {
+        if merged.contains_key(key) {
+            merged.insert(key.clone(), value.clone());
+        } else {
+            ignored_keys.push(key.clone());
+        }
+    }
+    if ignored_keys.len() == imported.len() {
+        return Err("设置文件中没有可识别的设置项".to_string());
+    }
+
+    let mut settings: AppSettings = serde_json::from_value(serde_json::Value::Object(merged))
+        .map_err(|e| format!("设置文件内容无效: {}", e))?;
+    if settings.lan_queue_password.is_empty() {
+        settings.lan_queue_password = current.lan_queue_password.clone();
+    }
+    settings.active_profile = current.active_profile.clone();
+    settings.db_path = current.db_path.clone();
+    settings.validate()?;
+    Ok(SettingsImportResult { settings, ignored_keys })
+}
+
+/// 将当前设置导出为 JSON 文件；include_secrets 为 false（默认）时不导出 LAN 密码
+#[tauri::command]
+pub async fn export_settings(app: AppHandle, path: String, include_secrets: Option<bool>) -> Result<(), String> {
+    tracing::info!("导出设置到: {}", path);
+    let mut settings = load_settings(app.clone()).await.unwrap_or_default();
+    if !include_secrets.unwrap_or(false) {
+        settings.lan_queue_password.clear();
+    }
+    let json = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| format!("写入设置文件失败: {}", e))?;
+    tracing::info!("✅ 设置已导出");
+    Ok(())
+}
+
+/// 从 JSON 文件导入设置并立即应用，返回合并后的设置
+#[tauri::command]
+pub async fn import_settings(app: AppHandle, path: String) -> Result<SettingsImportResult, String> {
+    tracing::info!("从文件导入设置: {}", path);
+    let size = fs::metadata(&path).map_err(|e| format!("无法读取设置文件: {}", e))?.len();
+    if size > SETTINGS_IMPORT_MAX_BYTES {
+        return Err(format!("设置文件过大: {} 字节（上限 {} 字节）", size, SETTINGS_IMPORT_MAX_BYTES));
+    }
+    let content = fs::read_to_string(&path).map_err(|e| format!("无法读取设置文件: {}", e))?;
+
+    let current = load_settings(app.clone()).await.unwrap_or_default();
+    let result = merge_imported_settings(&content, &current)?;
+    if !result.ignored_keys.is_empty() {
+        tracing::warn!("⚠️ 导入设置时忽略了未知字段: {:?}", result.ignored_keys);
+    }
+
+    save_settings(app.clone(), result.settings.clone()).await?;
+    if result.settings.hotkey != current.hotkey {
+        if let Err(e) = register_shortcut(app.clone(), result.settings.hotkey.clone()).await {
+            tracing::warn!("⚠️ 注册导入的快捷键失败: {}", e);
+        }
+    }
+    if result.settings.auto_start != current.auto_start {
+        if let Err(e) = set_auto_start(app.clone(), result.settings.auto_start).await {
+            tracing::warn!("⚠️ 应用导入的自启动设置失败: {}", e);
+        }
+    }
+    tracing::info!("✅ 设置已导入");
+    Ok(result)
+}
+
+/// 从其他剪贴板工具的 JSON 导出中导入设置和文本条目（条目按哈希去重），返回导入报告
+#[tauri::command]
+pub async fn import_external_settings(app: AppHandle, path: String) -> Result<ExternalImportReport, String> {
+    tracing::info!("从外部文件导入: {}", path);
+    let size = fs::metadata(&path).map_err(|e| format!("无法读取导入文件: {}", e))?.len();
+    if size > EXTERNAL_IMPORT_MAX_BYTES {
+        return Err(format!("导入文件过大: {} 字节（上限 {} 字节）", size, EXTERNAL_IMPORT_MAX_BYTES));
+    }
+    let content = fs::read_to_string(&path).map_err(|e| format!("无法读取导入文件: {}", e))?;
+    let value: serde_json::Value = serde_json::from_str(&content).map_err(|e| format!("导入文件不是有效的 JSON: {}", e))?;
+
+    let mut settings = load_settings(app.clone()).await.unwrap_or_default();
+    let previous_hotkey = settings.hotkey.clone();
+    let export = parse_external_export(&value, &mut settings)?;
+    settings.validate()?;
+    let mut report = ExternalImportReport {
+        skipped_items: export.skipped_items,
+        imported_settings: export.imported_settings,
+        ignored_settings: export.ignored_settings,
+        ..Default::default()
+    };
+
+    if !report.imported_settings.is_empty() {
+        save_settings(app.clone(), settings.clone()).await?;
+        if settings.hotkey != previous_hotkey {
+            if let Err(e) = register_shortcut(app.clone(), settings.hotkey.clone()).await {
+                tracing::warn!("⚠️ 注册导入的快捷键失败: {}", e);
+            }
+        }
+    }
+
+    if !export.items.is_empty() {
+        if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+            let db_guard = db_state.lock().await;
+            let pool = &db_guard.pool;
+
+            let mut tx = pool.begin().await.map_err(|e| format!("开启事务失败: {}", e))?;
+            let mut inserted_ids = Vec::new();
+            for item in export.items {
+                match insert_clipboard_item(&mut tx, item, &settings).await? {
+                    Some(id) => inserted_ids.push(id),
+                    None => report.duplicate_items += 1,
+                }
+            }
+            tx.commit().await.map_err(|e| format!("提交事务失败: {}", e))?;
+            report.imported_items = inserted_ids.len();
+            emit_items_added(&app, pool, &inserted_ids).await;
+        } else {
+            let error_msg = "无法获取数据库状态".to_string();
+            tracing::error!("❌ 外部导入失败: {}", error_msg);
+            return Err(error_msg);
+        }
+    }
+
+    tracing::info!(
+        "✅ 外部导入完成: 导入 {} 条，重复 {} 条，跳过 {} 条，导入设置 {} 项，忽略设置 {} 项",
+        report.imported_items, report.duplicate_items, report.skipped_items.len(),
+        report.imported_settings.len(), report.ignored_settings.len()
+    );
+    Ok(report)
+}
+
+// 分组管理相关命令
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct Group {
+    pub id: i64,
+    pub name: String,
+    pub color: String,
+    pub created_at: String,
+    pub item_count: i64,
+}
+
+#[tauri::command]
+pub async fn create_group(app: AppHandle, name: String, color: String) -> Result<Group, String> {
+    tracing::info!("创建分组: name='{}', color='{}'", name, color);
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        let pool = &db_guard.pool;
         
-        // 编码为 PNG
-        let png_data = encode_rgba_to_png(&pixels, width as u32, height as u32)?;
-        
-        // 转换为 base64 data URL
-        let b64 = base64::engine::general_purpose::STANDARD.encode(&png_data);
-        let data_url = format!("data:image/png;base64,{}", b64);
+        let created_at = chrono::Utc::now().to_rfc3339();
         
-        return Ok(data_url);
+        // 插入新分组
+        let result = with_db_retry("创建分组", || sqlx::query("INSERT INTO groups (name, color, created_at) VALUES (?, ?, ?)")
+            .bind(&name)
+            .bind(&color)
+            .bind(&created_at)
+            .execute(pool))
+            .await;
+            
+        match result {
+            Ok(_) => {
+                // 获取新创建的分组ID
+                let id_result = sqlx::query_as::<_, (i64,)>("SELECT last_insert_rowid()")
+                    .fetch_one(pool)
+                    .await;
+                    
+                match id_result {
+                    Ok((id,)) => {
+                        tracing::info!("✅ 分组创建成功: ID={}", id);
+                        Ok(Group {
+                            id,
+                            name,
+                            color,
+                            created_at,
+                            item_count: 0,
+                        })
+                    }
+                    Err(e) => {
+                        let error_msg = format!("获取新分组ID失败: {}", e);
+                        tracing::error!("❌ 创建分组失败: {}", error_msg);
+                        Err(error_msg)
+                    }
+                }
+            }
+            Err(e) => {
+                let error_msg = format!("插入分组失败: {}", e);
+                tracing::error!("❌ 创建分组失败: {}", error_msg);
+                Err(error_msg)
+            }
+        }
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 创建分组失败: {}", error_msg);
+        Err(error_msg)
     }
+}
 
-    #[cfg(not(target_os = "windows"))]
-    {
-        // 非 Windows 平台返回默认图标
-        Ok(get_default_file_icon(extension))
+// 以 clipboard_history 中的实际关联为准重新计算分组条目数，只更新与实际不一致的分组
+const RECALCULATE_GROUP_COUNTS_SQL: &str =
+    "UPDATE groups SET item_count = (SELECT COUNT(*) FROM clipboard_history WHERE group_id = groups.id)
+     WHERE item_count != (SELECT COUNT(*) FROM clipboard_history WHERE group_id = groups.id)";
+
+// 数据库迁移：用触发器维护 groups.item_count。
+// 前端和后端都会直接写 clipboard_history，触发器可以覆盖所有写入路径；首次创建时重新计算一次
+pub(crate) async fn ensure_group_count_triggers(pool: &sqlx::SqlitePool) -> Result<(), String> {
+    let existing: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'trigger' AND name LIKE 'trg_group_count_%'"
+    )
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("查询分组计数触发器失败: {}", e))?;
+    if existing == 3 {
+        return Ok(());
+    }
+
+    let triggers = [
+        "CREATE TRIGGER IF NOT EXISTS trg_group_count_insert
+         AFTER INSERT ON clipboard_history WHEN NEW.group_id IS NOT NULL
+         BEGIN
+             UPDATE groups SET item_count = item_count + 1 WHERE id = NEW.group_id;
+         END",
+        "CREATE TRIGGER IF NOT EXISTS trg_group_count_delete
+         AFTER DELETE ON clipboard_history WHEN OLD.group_id IS NOT NULL
+         BEGIN
+             UPDATE groups SET item_count = item_count - 1 WHERE id = OLD.group_id;
+         END",
+        "CREATE TRIGGER IF NOT EXISTS trg_group_count_update
+         AFTER UPDATE OF group_id ON clipboard_history WHEN OLD.group_id IS NOT NEW.group_id
+         BEGIN
+             UPDATE groups SET item_count = item_count - 1 WHERE id = OLD.group_id;
+             UPDATE groups SET item_count = item_count + 1 WHERE id = NEW.group_id;
+         END",
+    ];
+    for sql in triggers {
+        sqlx::query(sql)
+            .execute(pool)
+            .await
+            .map_err(|e| format!("创建分组计数触发器失败: {}", e))?;
     }
+
+    let fixed = sqlx::query(RECALCULATE_GROUP_COUNTS_SQL)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("初始化分组条目数失败: {}", e))?
+        .rows_affected();
+    tracing::info!("✅ 已创建分组计数触发器，初始化 {} 个分组的条目数", fixed);
+    Ok(())
 }
 
-/// 根据扩展名返回默认图标（SVG data URL）
-fn get_default_file_icon(extension: &str) -> String {
-    // 使用简单的文件图标 SVG - 所有类型使用相同的基础图标，颜色不同
-    let (color, label) = match extension.to_lowercase().as_str() {
-        "pdf" => ("E53935", "PDF"),
-        "doc" | "docx" => ("1976D2", "DOC"),
-        "xls" | "xlsx" => ("388E3C", "XLS"),
-        "ppt" | "pptx" => ("D84315", "PPT"),
-        "zip" | "rar" | "7z" | "tar" | "gz" => ("FFA000", "ZIP"),
-        "mp3" | "wav" | "flac" | "aac" | "ogg" => ("7B1FA2", "♪"),
-        "mp4" | "avi" | "mkv" | "mov" | "wmv" => ("C62828", "▶"),
-        "exe" | "msi" => ("455A64", "EXE"),
-        "txt" | "md" | "log" => ("616161", "TXT"),
-        "js" | "ts" | "jsx" | "tsx" => ("F7DF1E", "JS"),
-        "py" => ("3776AB", "PY"),
-        "rs" => ("DEA584", "RS"),
-        "html" | "htm" => ("E34F26", "HTML"),
-        "css" | "scss" | "sass" => ("1572B6", "CSS"),
-        "json" => ("000000", "{ }"),
-        "xml" => ("F16529", "XML"),
-        "jpg" | "jpeg" | "png" | "gif" | "bmp" | "webp" => ("4CAF50", "IMG"),
-        _ => ("757575", "FILE"),
-    };
-    
-    // 构建简单的 SVG 文件图标
-    let svg = format!(
-        r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24"><path fill="#{}" d="M14 2H6c-1.1 0-2 .9-2 2v16c0 1.1.9 2 2 2h12c1.1 0 2-.9 2-2V8l-6-6zm4 18H6V4h7v5h5v11z"/><text x="12" y="16" text-anchor="middle" font-size="4" fill="#{}">{}</text></svg>"##,
-        color, color, label
+// 数据库迁移：创建全文索引 clipboard_fts（外部内容表，trigram 分词以支持中文子串匹配），
+// 用触发器与 clipboard_history 同步，首次创建时为已有条目建立索引。
+// SQLite 未编译 FTS5 时返回 false，搜索回退到 LIKE 查询
+pub(crate) async fn ensure_fts_index(pool: &sqlx::SqlitePool) -> bool {
+    let existing: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'trigger' AND name LIKE 'trg_fts_%'"
+    )
+        .fetch_one(pool)
+        .await
+        .unwrap_or(0);
+    if existing == 3 && fts_index_available(pool).await {
+        return true;
+    }
+
+    let statements = [
+        "CREATE VIRTUAL TABLE IF NOT EXISTS clipboard_fts USING fts5(
+             content, content='clipboard_history', content_rowid='id', tokenize='trigram'
+         )",
+        "CREATE TRIGGER IF NOT EXISTS trg_fts_insert AFTER INSERT ON clipboard_history
+         BEGIN
+             INSERT INTO clipboard_fts(rowid, content) VALUES (NEW.id, NEW.content);
+         END",
+        "CREATE TRIGGER IF NOT EXISTS trg_fts_delete AFTER DELETE ON clipboard_history
+         BEGIN
+             INSERT INTO clipboard_fts(clipboard_fts, rowid, content) VALUES ('delete', OLD.id, OLD.content);
+         END",
+        "CREATE TRIGGER IF NOT EXISTS trg_fts_update AFTER UPDATE OF content ON clipboard_history
+         BEGIN
+             INSERT INTO clipboard_fts(clipboard_fts, rowid, content) VALUES ('delete', OLD.id, OLD.content);
+             INSERT INTO clipboard_fts(rowid, content) VALUES (NEW.id, NEW.content);
+         END",
+        "INSERT INTO clipboard_fts(clipboard_fts) VALUES ('rebuild')",
+    ];
+    let result = async {
+        let mut tx = pool.begin().await?;
+        for sql in statements {
+            sqlx::query(sql).execute(&mut *tx).await?;
+        }
+        tx.commit().await
+    }
+    .await;
+
+    match result {
+        Ok(()) => {
+            tracing::info!("✅ 已创建全文索引 clipboard_fts");
+            true
+        }
+        Err(e) => {
+            tracing::warn!("⚠️ 创建全文索引失败，搜索将使用 LIKE 查询: {}", e);
+            false
+        }
+    }
+}
+
+async fn fts_index_available(pool: &sqlx::SqlitePool) -> bool {
+    sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'clipboard_fts'")
+        .fetch_one(pool)
+        .await
+        .is_ok_and(|count| count > 0)
+}
+
+// trigram 分词下少于 3 个字符的词无法通过全文索引匹配
+const FTS_MIN_TERM_CHARS: usize = 3;
+
+// 把用户输入转换为 FTS5 查询：每个词作为短语加引号（避免特殊语法），多个词之间为 AND
+fn fts_match_query(terms: &[&str]) -> String {
+    terms
+        .iter()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn escape_like(term: &str) -> String {
+    term.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+// 搜索历史记录：可用全文索引时按 bm25 相关度排序，否则（或有过短的词时）用 LIKE 按时间倒序匹配
+// retention 为 Some 时按其保留设置隐藏已过期、尚未被清理的条目（与 fetch_history_page 相同）
+async fn search_history_in_pool(
+    pool: &sqlx::SqlitePool,
+    query: &str,
+    limit: i64,
+    retention: Option<&AppSettings>,
+) -> Result<Vec<HistoryItem>, sqlx::Error> {
+    let terms: Vec<&str> = query.split_whitespace().collect();
+    if terms.is_empty() {
+        return Ok(Vec::new());
+    }
+    let limit = limit.clamp(1, HISTORY_PAGE_MAX_LIMIT);
+    let cutoffs = retention.map(retention_cutoffs).unwrap_or_default();
+    let (expired, expired_params) = expired_condition(&cutoffs);
+    let max_age_filter = if cutoffs.is_empty() { String::new() } else { format!(" AND NOT ({})", expired) };
+
+    let use_fts = terms.iter().all(|term| term.chars().count() >= FTS_MIN_TERM_CHARS);
+    if use_fts && fts_index_available(pool).await {
+        // 过期条目在排序和 LIMIT 之前过滤，避免结果数量不足
+        let sql = format!(
+            "SELECT {} FROM clipboard_history
+             JOIN (SELECT rowid, bm25(clipboard_fts) AS rank FROM clipboard_fts WHERE clipboard_fts MATCH ?) AS matches
+               ON clipboard_history.id = matches.rowid
+             WHERE 1 = 1{}
+             ORDER BY matches.rank LIMIT ?",
+            HISTORY_ITEM_COLUMNS, max_age_filter
+        );
+        let mut q = sqlx::query(&sql).bind(fts_match_query(&terms));
+        for param in &expired_params {
+            q = q.bind(*param);
+        }
+        let rows = q.bind(limit).fetch_all(pool).await?;
+        return Ok(rows.iter().map(row_to_history_item).collect());
+    }
+
+    if use_fts {
+        tracing::warn!("⚠️ 全文索引不可用，使用 LIKE 查询搜索");
+    }
+    let conditions = vec!["content LIKE ? ESCAPE '\\'"; terms.len()].join(" AND ");
+    let sql = format!(
+        "SELECT {} FROM clipboard_history WHERE {}{} ORDER BY timestamp DESC LIMIT ?",
+        HISTORY_ITEM_COLUMNS, conditions, max_age_filter
     );
-    
-    let encoded = base64::engine::general_purpose::STANDARD.encode(svg.as_bytes());
-    format!("data:image/svg+xml;base64,{}", encoded)
+    let mut q = sqlx::query(&sql);
+    for term in &terms {
+        q = q.bind(format!("%{}%", escape_like(term)));
+    }
+    for param in &expired_params {
+        q = q.bind(*param);
+    }
+    let rows = q.bind(limit).fetch_all(pool).await?;
+    Ok(rows.iter().map(row_to_history_item).collect())
 }
 
-/// 将 RGBA 像素数据编码为 PNG
-fn encode_rgba_to_png(pixels: &[u8], width: u32, height: u32) -> Result<Vec<u8>, String> {
-    use image::{ImageBuffer, RgbaImage, ImageEncoder, ColorType};
-    
-    let img: RgbaImage = ImageBuffer::from_raw(width, height, pixels.to_vec())
-        .ok_or_else(|| "无法创建图片缓冲区".to_string())?;
-    
-    let mut png_data = Vec::new();
-    let encoder = image::codecs::png::PngEncoder::new(&mut png_data);
-    
-    encoder.write_image(
-        img.as_raw(),
-        width,
-        height,
-        ColorType::Rgba8,
-    ).map_err(|e| format!("PNG 编码失败: {}", e))?;
-    
-    Ok(png_data)
+/// 全文搜索历史记录，按相关度排序返回最多 limit 条
+#[tauri::command]
+pub async fn search_history(app: AppHandle, query: String, limit: i64) -> Result<Vec<HistoryItem>, String> {
+    tracing::debug!("搜索历史记录: '{}', limit={}", logging::preview(&query), limit);
+
+    let settings = cached_settings(&app).await;
+    let retention = settings.enforce_max_age_on_query.then_some(&settings);
+
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        search_history_in_pool(&db_guard.pool, &query, limit, retention)
+            .await
+            .map_err(|e| {
+                let error_msg = format!("搜索历史记录失败: {}", e);
+                tracing::error!("❌ {}", error_msg);
+                error_msg
+            })
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 搜索历史记录失败: {}", error_msg);
+        Err(error_msg)
+    }
+}
+
+// 生成 preview / content_length 的赋值语句，prefix 为触发器中的 "NEW." 或空
+fn preview_assignments(preview_chars: usize, prefix: &str) -> String {
+    format!(
+        "preview = CASE WHEN {p}type = 'text' THEN substr({p}content, 1, {n}) END, content_length = length({p}content)",
+        p = prefix,
+        n = preview_chars.clamp(LIST_PREVIEW_MIN_CHARS, LIST_PREVIEW_MAX_CHARS)
+    )
+}
+
+// 数据库迁移：用触发器在写入时生成文本条目的列表预览（preview）和内容长度（content_length），
+// 覆盖前端和后端的所有写入路径。触发器已存在但预览长度不同时重建触发器，返回是否需要重新生成已有预览
+async fn ensure_preview_triggers(pool: &sqlx::SqlitePool, preview_chars: usize) -> Result<bool, String> {
+    let update = format!("UPDATE clipboard_history SET {} WHERE id = NEW.id;", preview_assignments(preview_chars, "NEW."));
+    let existing: Option<String> = sqlx::query_scalar(
+        "SELECT sql FROM sqlite_master WHERE type = 'trigger' AND name = 'trg_preview_insert'"
+    )
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("查询预览触发器失败: {}", e))?;
+    if existing.as_deref().is_some_and(|sql| sql.contains(&update)) {
+        return Ok(false);
+    }
+
+    let statements = [
+        "DROP TRIGGER IF EXISTS trg_preview_insert".to_string(),
+        "DROP TRIGGER IF EXISTS trg_preview_update".to_string(),
+        format!("CREATE TRIGGER trg_preview_insert AFTER INSERT ON clipboard_history BEGIN {} END", update),
+        format!("CREATE TRIGGER trg_preview_update AFTER UPDATE OF content, type ON clipboard_history BEGIN {} END", update),
+    ];
+    let mut tx = pool.begin().await.map_err(|e| format!("创建预览触发器失败: {}", e))?;
+    for sql in &statements {
+        sqlx::query(sql)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("创建预览触发器失败: {}", e))?;
+    }
+    tx.commit().await.map_err(|e| format!("创建预览触发器失败: {}", e))?;
+    Ok(existing.is_some())
+}
+
+// 为缺少预览的条目生成预览；rebuild 为 true 时重新生成所有条目的预览
+async fn backfill_previews_in_pool(pool: &sqlx::SqlitePool, preview_chars: usize, rebuild: bool) -> Result<u64, sqlx::Error> {
+    let filter = if rebuild { "" } else { " WHERE content_length IS NULL" };
+    let sql = format!("UPDATE clipboard_history SET {}{}", preview_assignments(preview_chars, ""), filter);
+    Ok(sqlx::query(&sql).execute(pool).await?.rows_affected())
+}
+
+// 确保预览触发器与设置一致，并补全（预览长度变化时重建）已有条目的预览
+pub(crate) async fn sync_content_previews(pool: &sqlx::SqlitePool, preview_chars: usize) -> Result<u64, String> {
+    let rebuild = ensure_preview_triggers(pool, preview_chars).await?;
+    let updated = with_db_retry("生成列表预览", || backfill_previews_in_pool(pool, preview_chars, rebuild))
+        .await
+        .map_err(|e| format!("生成列表预览失败: {}", e))?;
+    if updated > 0 {
+        tracing::info!("✅ 已为 {} 条记录生成列表预览（{} 字符）", updated, preview_chars);
+    }
+    Ok(updated)
+}
+
+/// 为已有条目生成列表预览，rebuild 为 true 时重新生成全部，返回更新的条目数
+#[tauri::command]
+pub async fn backfill_content_previews(app: AppHandle, rebuild: Option<bool>) -> Result<u64, String> {
+    let settings = load_settings(app.clone()).await.unwrap_or_default();
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        let pool = &db_guard.pool;
+        if rebuild.unwrap_or(false) {
+            ensure_preview_triggers(pool, settings.list_preview_chars).await?;
+            let updated = with_db_retry("重建列表预览", || backfill_previews_in_pool(pool, settings.list_preview_chars, true))
+                .await
+                .map_err(|e| format!("重建列表预览失败: {}", e))?;
+            tracing::info!("✅ 已重建 {} 条记录的列表预览", updated);
+            Ok(updated)
+        } else {
+            sync_content_previews(pool, settings.list_preview_chars).await
+        }
+    } else {
+        Err("无法获取数据库状态".to_string())
+    }
+}
+
+/// 按实际关联的条目重新计算所有分组的 item_count，返回被修正的分组数
+#[tauri::command]
+pub async fn recalculate_group_counts(app: AppHandle) -> Result<u64, String> {
+    tracing::info!("重新计算分组条目数");
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        let result = with_db_retry("重新计算分组条目数", || sqlx::query(RECALCULATE_GROUP_COUNTS_SQL)
+            .execute(&db_guard.pool))
+            .await
+            .map_err(|e| {
+                let error_msg = format!("更新分组条目数失败: {}", e);
+                tracing::error!("❌ 重新计算分组条目数失败: {}", error_msg);
+                error_msg
+            })?;
+        let fixed = result.rows_affected();
+        tracing::info!("✅ 分组条目数重新计算完成，修正 {} 个分组", fixed);
+        Ok(fixed)
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 重新计算分组条目数失败: {}", error_msg);
+        Err(error_msg)
+    }
+}
+
+// 在一个事务中清除指向不存在分组的 group_id，返回被修复的条目 ID
+async fn repair_group_references_in_transaction(pool: &sqlx::SqlitePool) -> Result<Vec<i64>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let ids: Vec<i64> = sqlx::query_scalar(
+        "SELECT id FROM clipboard_history
+         WHERE group_id IS NOT NULL AND group_id NOT IN (SELECT id FROM groups)"
+    )
+        .fetch_all(&mut *tx)
+        .await?;
+    if !ids.is_empty() {
+        sqlx::query(
+            "UPDATE clipboard_history SET group_id = NULL
+             WHERE group_id IS NOT NULL AND group_id NOT IN (SELECT id FROM groups)"
+        )
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(ids)
+}
+
+/// 检查并修复指向不存在分组的条目（将其 group_id 置为 NULL），返回修复的条目数
+#[tauri::command]
+pub async fn repair_group_references(app: AppHandle) -> Result<u64, String> {
+    tracing::info!("检查失效的分组引用");
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        let ids = with_db_retry("修复分组引用", || repair_group_references_in_transaction(&db_guard.pool))
+            .await
+            .map_err(|e| {
+                let error_msg = format!("修复分组引用失败: {}", e);
+                tracing::error!("❌ {}", error_msg);
+                error_msg
+            })?;
+        drop(db_guard);
+
+        if !ids.is_empty() {
+            if let Err(e) = app.emit(HISTORY_CHANGED_EVENT, HistoryChangedPayload { ids: ids.clone() }) {
+                tracing::warn!("⚠️ 发送 history-changed 事件失败: {}", e);
+            }
+        }
+        tracing::info!("✅ 分组引用检查完成，修复 {} 个条目", ids.len());
+        Ok(ids.len() as u64)
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 修复分组引用失败: {}", error_msg);
+        Err(error_msg)
+    }
+}
+
+#[tauri::command]
+pub async fn get_groups(app: AppHandle) -> Result<Vec<Group>, String> {
+    tracing::debug!("获取所有分组");
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        let pool = &db_guard.pool;
+        
+        let result = sqlx::query_as::<_, (i64, String, String, String, i64)>(
+            // item_count 由触发器维护，出现偏差时可调用 recalculate_group_counts 修复
+            "SELECT id, name, color, created_at, item_count FROM groups ORDER BY created_at DESC"
+        )
+        .fetch_all(pool)
+        .await;
+        
+        match result {
+            Ok(rows) => {
+                let groups: Vec<Group> = rows.into_iter().map(|(id, name, color, created_at, item_count)| {
+                    Group { id, name, color, created_at, item_count }
+                }).collect();
+                tracing::debug!("✅ 获取分组成功: {} 个分组", groups.len());
+                Ok(groups)
+            }
+            Err(e) => {
+                let error_msg = format!("查询分组失败: {}", e);
+                tracing::error!("❌ 获取分组失败: {}", error_msg);
+                Err(error_msg)
+            }
+        }
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 获取分组失败: {}", error_msg);
+        Err(error_msg)
+    }
+}
+
+#[tauri::command]
+pub async fn update_group(app: AppHandle, id: i64, name: String, color: String) -> Result<(), String> {
+    tracing::info!("更新分组: ID={}, name='{}', color='{}'", id, name, color);
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        let pool = &db_guard.pool;
+        
+        let result = with_db_retry("更新分组", || sqlx::query("UPDATE groups SET name = ?, color = ? WHERE id = ?")
+            .bind(&name)
+            .bind(&color)
+            .bind(id)
+            .execute(pool))
+            .await;
+            
+        match result {
+            Ok(_) => {
+                tracing::info!("✅ 分组更新成功: ID={}", id);
+                Ok(())
+            }
+            Err(e) => {
+                let error_msg = format!("更新分组失败: {}", e);
+                tracing::error!("❌ 更新分组失败: {}", error_msg);
+                Err(error_msg)
+            }
+        }
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 更新分组失败: {}", error_msg);
+        Err(error_msg)
+    }
+}
+
+#[tauri::command]
+pub async fn delete_group(app: AppHandle, id: i64) -> Result<(), String> {
+    tracing::info!("删除分组: ID={}", id);
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        let pool = &db_guard.pool;
+        
+        // 先将该分组下的所有条目的group_id设为NULL
+        let update_result = with_db_retry("清除分组关联", || sqlx::query("UPDATE clipboard_history SET group_id = NULL WHERE group_id = ?")
+            .bind(id)
+            .execute(pool))
+            .await;
+            
+        if let Err(e) = update_result {
+            let error_msg = format!("清除分组关联失败: {}", e);
+            tracing::error!("❌ 删除分组失败: {}", error_msg);
+            return Err(error_msg);
+        }
+        
+        // 删除分组
+        let result = with_db_retry("删除分组", || sqlx::query("DELETE FROM groups WHERE id = ?")
+            .bind(id)
+            .execute(pool))
+            .await;
+            
+        match result {
+            Ok(_) => {
+                tracing::info!("✅ 分组删除成功: ID={}", id);
+                clear_capture_group_if(&app, id);
+                Ok(())
+            }
+            Err(e) => {
+                let error_msg = format!("删除分组失败: {}", e);
+                tracing::error!("❌ 删除分组失败: {}", error_msg);
+                Err(error_msg)
+            }
+        }
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 删除分组失败: {}", error_msg);
+        Err(error_msg)
+    }
+}
+
+// 在一个事务中把源分组的条目移到目标分组并删除源分组，返回目标分组的条目数
+async fn merge_groups_in_transaction(pool: &sqlx::SqlitePool, source_id: i64, target_id: i64) -> Result<i64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE clipboard_history SET group_id = ? WHERE group_id = ?")
+        .bind(target_id)
+        .bind(source_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM groups WHERE id = ?")
+        .bind(source_id)
+        .execute(&mut *tx)
+        .await?;
+    let item_count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM clipboard_history WHERE group_id = ?")
+        .bind(target_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(item_count)
+}
+
+/// 将源分组合并到目标分组：条目全部归入目标分组后删除源分组，返回目标分组的条目数
+#[tauri::command]
+pub async fn merge_groups(app: AppHandle, source_id: i64, target_id: i64) -> Result<i64, String> {
+    tracing::info!("合并分组: {} -> {}", source_id, target_id);
+    if source_id == target_id {
+        return Err("不能将分组合并到自身".to_string());
+    }
+
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        let pool = &db_guard.pool;
+
+        for id in [source_id, target_id] {
+            let exists = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM groups WHERE id = ?")
+                .bind(id)
+                .fetch_one(pool)
+                .await
+                .map_err(|e| format!("查询分组失败: {}", e))?;
+            if exists == 0 {
+                let error_msg = format!("分组不存在: ID={}", id);
+                tracing::warn!("❌ 合并分组失败: {}", error_msg);
+                return Err(error_msg);
+            }
+        }
+
+        let item_count = with_db_retry("合并分组", || merge_groups_in_transaction(pool, source_id, target_id))
+            .await
+            .map_err(|e| {
+                let error_msg = format!("合并分组失败: {}", e);
+                tracing::error!("❌ {}", error_msg);
+                error_msg
+            })?;
+
+        tracing::info!("✅ 分组合并成功: {} -> {}，目标分组现有 {} 个条目", source_id, target_id, item_count);
+        clear_capture_group_if(&app, source_id);
+        Ok(item_count)
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 合并分组失败: {}", error_msg);
+        Err(error_msg)
+    }
+}
+
+/// 当前捕获分组信息，通过 capture-group-changed 事件通知前端
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct CaptureGroupInfo {
+    pub group_id: Option<i64>,
+    pub group_name: Option<String>,
+}
+
+fn emit_capture_group_changed(app: &AppHandle, info: &CaptureGroupInfo) {
+    if let Err(e) = app.emit("capture-group-changed", info) {
+        tracing::warn!("⚠️ 发送捕获分组变更事件失败: {}", e);
+    }
+}
+
+// 被删除的分组正是当前捕获分组时，清除捕获分组
+fn clear_capture_group_if(app: &AppHandle, group_id: i64) {
+    if let Some(state) = app.try_state::<CaptureGroupState>() {
+        let cleared = match state.group_id.lock() {
+            Ok(mut guard) if *guard == Some(group_id) => {
+                *guard = None;
+                true
+            }
+            _ => false,
+        };
+        if cleared {
+            tracing::info!("捕获分组已被删除，停止自动归组: ID={}", group_id);
+            emit_capture_group_changed(app, &CaptureGroupInfo { group_id: None, group_name: None });
+        }
+    }
+}
+
+/// 设置当前捕获分组，传入 None 时停止自动归组
+#[tauri::command]
+pub async fn set_capture_group(app: AppHandle, group_id: Option<i64>) -> Result<CaptureGroupInfo, String> {
+    tracing::info!("设置捕获分组: {:?}", group_id);
+
+    let group_name = match group_id {
+        Some(id) => {
+            if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+                let db_guard = db_state.lock().await;
+                let name = sqlx::query_scalar::<_, String>("SELECT name FROM groups WHERE id = ?")
+                    .bind(id)
+                    .fetch_optional(&db_guard.pool)
+                    .await
+                    .map_err(|e| {
+                        let error_msg = format!("查询分组失败: {}", e);
+                        tracing::error!("❌ 设置捕获分组失败: {}", error_msg);
+                        error_msg
+                    })?;
+                match name {
+                    Some(name) => Some(name),
+                    None => {
+                        let error_msg = format!("分组不存在: ID={}", id);
+                        tracing::error!("❌ 设置捕获分组失败: {}", error_msg);
+                        return Err(error_msg);
+                    }
+                }
+            } else {
+                let error_msg = "无法获取数据库状态".to_string();
+                tracing::error!("❌ 设置捕获分组失败: {}", error_msg);
+                return Err(error_msg);
+            }
+        }
+        None => None,
+    };
+
+    let state = app.try_state::<CaptureGroupState>().ok_or("无法获取捕获分组状态")?;
+    *state.group_id.lock().map_err(|e| format!("捕获分组状态锁定失败: {}", e))? = group_id;
+
+    let info = CaptureGroupInfo { group_id, group_name };
+    emit_capture_group_changed(&app, &info);
+    tracing::info!("✅ 捕获分组已更新: {:?}", info);
+    Ok(info)
+}
+
+/// 获取当前捕获分组
+#[tauri::command]
+pub async fn get_capture_group(app: AppHandle) -> Result<CaptureGroupInfo, String> {
+    let group_id = app
+        .try_state::<CaptureGroupState>()
+        .and_then(|state| state.group_id.lock().ok().and_then(|guard| *guard));
+
+    let group_name = match group_id {
+        Some(id) => {
+            if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+                let db_guard = db_state.lock().await;
+                sqlx::query_scalar::<_, String>("SELECT name FROM groups WHERE id = ?")
+                    .bind(id)
+                    .fetch_optional(&db_guard.pool)
+                    .await
+                    .map_err(|e| format!("查询分组失败: {}", e))?
+            } else {
+                None
+            }
+        }
+        None => None,
+    };
+
+    Ok(CaptureGroupInfo { group_id, group_name })
+}
+
+// 删除单个条目，删除成功后再删除其图片文件；返回条目是否存在
+async fn delete_single_item(pool: &sqlx::SqlitePool, id: i64) -> Result<bool, String> {
+    let image_path = sqlx::query_scalar::<_, Option<String>>("SELECT image_path FROM clipboard_history WHERE id = ?")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("查询条目失败: {}", e))?
+        .flatten();
+
+    let result = with_db_retry("删除条目", || sqlx::query("DELETE FROM clipboard_history WHERE id = ?")
+        .bind(id)
+        .execute(pool))
+        .await
+        .map_err(|e| format!("数据库删除失败: {}", e))?;
+    if result.rows_affected() == 0 {
+        return Ok(false);
+    }
+    // 条目的标签由 trg_tags_delete 触发器在同一语句中删除
+
+    if let Some(image_path) = image_path {
+        if remove_image_files(std::slice::from_ref(&image_path)) > 0 {
+            tracing::info!("已删除图片文件: {}", image_path);
+        }
+    }
+    Ok(true)
+}
+
+#[tauri::command]
+pub async fn delete_item(app: AppHandle, id: i64) -> Result<(), String> {
+    tracing::info!("删除条目: ID={}", id);
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        match delete_single_item(&db_guard.pool, id).await {
+            Ok(_) => {
+                tracing::info!("✅ 条目删除成功: ID={}", id);
+                Ok(())
+            }
+            Err(error_msg) => {
+                tracing::error!("❌ 删除条目失败: {}", error_msg);
+                Err(error_msg)
+            }
+        }
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 删除条目失败: {}", error_msg);
+        Err(error_msg)
+    }
+}
+
+/// 删除单个条目及其图片文件，ID 不存在时返回错误
+#[tauri::command]
+pub async fn delete_history_item(app: AppHandle, item_id: i64) -> Result<(), String> {
+    tracing::info!("删除历史条目: ID={}", item_id);
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        match delete_single_item(&db_guard.pool, item_id).await {
+            Ok(true) => {
+                tracing::info!("✅ 条目删除成功: ID={}", item_id);
+                Ok(())
+            }
+            Ok(false) => {
+                let error_msg = format!("未找到ID为{}的条目", item_id);
+                tracing::error!("❌ 删除条目失败: {}", error_msg);
+                Err(error_msg)
+            }
+            Err(error_msg) => {
+                tracing::error!("❌ 删除条目失败: {}", error_msg);
+                Err(error_msg)
+            }
+        }
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 删除条目失败: {}", error_msg);
+        Err(error_msg)
+    }
+}
+
+#[tauri::command]
+pub async fn add_item_to_group(app: AppHandle, item_id: i64, group_id: Option<i64>) -> Result<(), String> {
+    tracing::info!("设置条目分组: item_id={}, group_id={:?}", item_id, group_id);
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        let pool = &db_guard.pool;
+        
+        let result = with_db_retry("设置条目分组", || sqlx::query("UPDATE clipboard_history SET group_id = ? WHERE id = ?")
+            .bind(group_id)
+            .bind(item_id)
+            .execute(pool))
+            .await;
+            
+        match result {
+            Ok(_) => {
+                tracing::info!("✅ 条目分组设置成功: item_id={}", item_id);
+                Ok(())
+            }
+            Err(e) => {
+                let error_msg = format!("设置条目分组失败: {}", e);
+                tracing::error!("❌ 设置条目分组失败: {}", error_msg);
+                Err(error_msg)
+            }
+        }
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 设置条目分组失败: {}", error_msg);
+        Err(error_msg)
+    }
+}
+
+/// 将单个条目导出到文件（图片写入原始字节，文本写入内容），返回写入的字节数
+#[tauri::command]
+pub async fn export_item(app: AppHandle, id: i64, dest_path: String) -> Result<u64, String> {
+    tracing::info!("导出条目: ID={}, dest={}", id, dest_path);
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        let pool = &db_guard.pool;
+
+        let row = sqlx::query_as::<_, (String, String, Option<String>)>(
+            "SELECT content, type, image_path FROM clipboard_history WHERE id = ?"
+        )
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| format!("查询条目失败: {}", e))?;
+
+        let (content, item_type, image_path) = match row {
+            Some(row) => row,
+            None => {
+                let error_msg = format!("未找到ID为{}的条目", id);
+                tracing::warn!("❌ 导出条目失败: {}", error_msg);
+                return Err(error_msg);
+            }
+        };
+
+        // 图片条目读取原始文件字节，并根据实际格式推断扩展名
+        let (bytes, extension) = if item_type == "image" {
+            let image_path = image_path.ok_or("图片条目缺少文件路径")?;
+            let bytes = std::fs::read(resolve_image_path(&image_path))
+                .map_err(|e| format!("无法读取图片文件: {}", e))?;
+            let extension = image::guess_format(&bytes)
+                .ok()
+                .and_then(|format| format.extensions_str().first().copied())
+                .unwrap_or("png");
+            (bytes, extension)
+        } else {
+            (content.into_bytes(), "txt")
+        };
+
+        // 目标路径没有扩展名时补上推断出的扩展名
+        let mut dest = PathBuf::from(&dest_path);
+        if dest.extension().is_none() {
+            dest.set_extension(extension);
+        }
+
+        if let Some(parent) = dest.parent() {
+            if !parent.as_os_str().is_empty() && !parent.exists() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| format!("无法创建导出目录: {}", e))?;
+            }
+        }
+
+        std::fs::write(&dest, &bytes).map_err(|e| {
+            let error_msg = format!("写入导出文件失败: {}", e);
+            tracing::error!("❌ 导出条目失败: {}", error_msg);
+            error_msg
+        })?;
+
+        tracing::info!("✅ 条目导出成功: ID={}, path={:?}, {} 字节", id, dest, bytes.len());
+        Ok(bytes.len() as u64)
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 导出条目失败: {}", error_msg);
+        Err(error_msg)
+    }
+}
+
+// 判断文本是否为单个 http(s) 链接
+fn is_url(text: &str) -> bool {
+    let text = text.trim();
+    (text.starts_with("http://") || text.starts_with("https://"))
+        && text.len() > "https://".len()
+        && !text.chars().any(char::is_whitespace)
+}
+
+// 从 HTML 中提取 <title> 内容。只做 ASCII 小写转换，保证在小写副本中找到的字节偏移在原文中同样有效
+fn extract_html_title(html: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let tag_start = lower.find("<title")?;
+    let content_start = tag_start + lower[tag_start..].find('>')? + 1;
+    let content_end = content_start + lower[content_start..].find("</title")?;
+    let title = html.get(content_start..content_end)?
+        .replace("&amp;", "&")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'");
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+    if title.is_empty() {
+        None
+    } else {
+        Some(title)
+    }
+}
+
+// 获取网页标题时最多读取的响应字节数，<title> 通常位于页面开头
+const PAGE_TITLE_MAX_BYTES: usize = 64 * 1024;
+
+// 获取网页标题（短超时，失败返回 None）
+async fn fetch_page_title(url: &str) -> Option<String> {
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(3))
+        .build()
+        .ok()?;
+    let mut response = match client.get(url).send().await {
+        Ok(response) => response,
+        Err(e) => {
+            tracing::warn!("⚠️ 获取网页标题失败: {}", e);
+            return None;
+        }
+    };
+    if !response.status().is_success() {
+        tracing::warn!("⚠️ 获取网页标题失败: HTTP {}", response.status());
+        return None;
+    }
+    let mut body = Vec::new();
+    while body.len() < PAGE_TITLE_MAX_BYTES {
+        match response.chunk().await {
+            Ok(Some(chunk)) => body.extend_from_slice(&chunk),
+            Ok(None) => break,
+            Err(e) => {
+                tracing::warn!("⚠️ 读取网页内容失败: {}", e);
+                break;
+            }
+        }
+    }
+    body.truncate(PAGE_TITLE_MAX_BYTES);
+    extract_html_title(&String::from_utf8_lossy(&body))
+}
+
+/// 将链接条目复制为 Markdown 链接 `[title](url)`，返回生成的文本
+#[tauri::command]
+pub async fn copy_as_markdown_link(app: AppHandle, id: i64) -> Result<String, String> {
+    tracing::info!("复制为 Markdown 链接: ID={}", id);
+    let content = if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        let pool = &db_guard.pool;
+
+        let row = sqlx::query_as::<_, (String,)>("SELECT content FROM clipboard_history WHERE id = ? AND type = 'text'")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| format!("查询条目失败: {}", e))?;
+
+        match row {
+            Some((content,)) => content,
+            None => {
+                let error_msg = format!("未找到ID为{}的文本条目", id);
+                tracing::warn!("❌ 复制 Markdown 链接失败: {}", error_msg);
+                return Err(error_msg);
+            }
+        }
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 复制 Markdown 链接失败: {}", error_msg);
+        return Err(error_msg);
+    };
+
+    let url = content.trim();
+    if !is_url(url) {
+        let error_msg = "该条目不是链接".to_string();
+        tracing::warn!("❌ 复制 Markdown 链接失败: {}", error_msg);
+        return Err(error_msg);
+    }
+
+    // 获取标题需要联网，仅在设置开启时进行；失败时回退为链接本身
+    let settings = load_settings(app.clone()).await.unwrap_or_default();
+    let title = if settings.fetch_link_titles {
+        fetch_page_title(url).await
+    } else {
+        None
+    };
+    let title = title
+        .unwrap_or_else(|| url.to_string())
+        .replace('[', "\\[")
+        .replace(']', "\\]");
+    let markdown = format!("[{}]({})", title, url);
+
+    let mut clipboard = arboard::Clipboard::new()
+        .map_err(|e| format!("初始化剪贴板失败: {}", e))?;
+    clipboard.set_text(markdown.clone())
+        .map_err(|e| format!("写入剪贴板失败: {}", e))?;
+
+    tracing::info!("✅ 已复制 Markdown 链接: {}", logging::preview(&markdown));
+    Ok(markdown)
+}
+
+// 参与比较的单个条目内容上限，避免对超大文本做逐行 diff
+const DIFF_MAX_CONTENT_LEN: usize = MAX_TEXT_CONTENT_LEN;
+
+/// diff 中的一行："unchanged"、"added" 或 "removed"
+#[derive(serde::Serialize, Debug, Clone, PartialEq)]
+pub struct DiffLine {
+    pub kind: String,
+    pub content: String,
+    // 在旧/新文本中的行号（从 1 开始），新增行没有旧行号，删除行没有新行号
+    pub old_line: Option<usize>,
+    pub new_line: Option<usize>,
+}
+
+/// 两个文本条目之间的逐行 diff
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct ItemDiff {
+    pub lines: Vec<DiffLine>,
+    pub added: usize,
+    pub removed: usize,
+    pub unchanged: usize,
+}
+
+fn diff_text(old: &str, new: &str) -> ItemDiff {
+    let diff = similar::TextDiff::from_lines(old, new);
+    let mut result = ItemDiff { lines: Vec::new(), added: 0, removed: 0, unchanged: 0 };
+    for change in diff.iter_all_changes() {
+        let kind = match change.tag() {
+            similar::ChangeTag::Equal => {
+                result.unchanged += 1;
+                "unchanged"
+            }
+            similar::ChangeTag::Insert => {
+                result.added += 1;
+                "added"
+            }
+            similar::ChangeTag::Delete => {
+                result.removed += 1;
+                "removed"
+            }
+        };
+        result.lines.push(DiffLine {
+            kind: kind.to_string(),
+            content: change.value().trim_end_matches(['\r', '\n']).to_string(),
+            old_line: change.old_index().map(|index| index + 1),
+            new_line: change.new_index().map(|index| index + 1),
+        });
+    }
+    result
+}
+
+/// 比较两个文本条目，返回从 id_a 到 id_b 的逐行 diff
+#[tauri::command]
+pub async fn diff_items(app: AppHandle, id_a: i64, id_b: i64) -> Result<ItemDiff, String> {
+    tracing::info!("比较条目: ID={} -> ID={}", id_a, id_b);
+    let (content_a, content_b) = if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        let pool = &db_guard.pool;
+
+        let mut contents = Vec::with_capacity(2);
+        for id in [id_a, id_b] {
+            let row = sqlx::query_as::<_, (String, String)>("SELECT type, content FROM clipboard_history WHERE id = ?")
+                .bind(id)
+                .fetch_optional(pool)
+                .await
+                .map_err(|e| format!("查询条目失败: {}", e))?;
+
+            let error_msg = match row {
+                Some((item_type, _)) if item_type != "text" => format!("ID为{}的条目不是文本条目，无法比较", id),
+                Some((_, content)) if content.len() > DIFF_MAX_CONTENT_LEN => {
+                    format!("ID为{}的条目内容过大（{} 字节，上限 {} 字节），无法比较", id, content.len(), DIFF_MAX_CONTENT_LEN)
+                }
+                Some((_, content)) => {
+                    contents.push(content);
+                    continue;
+                }
+                None => format!("未找到ID为{}的条目", id),
+            };
+            tracing::warn!("❌ 比较条目失败: {}", error_msg);
+            return Err(error_msg);
+        }
+        let content_b = contents.pop().unwrap_or_default();
+        let content_a = contents.pop().unwrap_or_default();
+        (content_a, content_b)
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 比较条目失败: {}", error_msg);
+        return Err(error_msg);
+    };
+
+    let diff = diff_text(&content_a, &content_b);
+    tracing::info!("✅ 比较完成: +{} -{} ={}", diff.added, diff.removed, diff.unchanged);
+    Ok(diff)
+}
+
+/// 查找替换的结果：dry_run 时只返回匹配的条目而不修改
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct FindReplaceResult {
+    pub ids: Vec<i64>,
+    pub changed: usize,
+    pub dry_run: bool,
+}
+
+enum ContentMatcher {
+    Plain(String),
+    Regex(regex::Regex),
+}
+
+impl ContentMatcher {
+    fn new(find: &str, use_regex: bool) -> Result<Self, String> {
+        if find.is_empty() {
+            return Err("查找内容不能为空".to_string());
+        }
+        if use_regex {
+            regex::Regex::new(find)
+                .map(ContentMatcher::Regex)
+                .map_err(|e| format!("正则表达式无效: {}", e))
+        } else {
+            Ok(ContentMatcher::Plain(find.to_string()))
+        }
+    }
+
+    // 返回替换后的内容，没有匹配时返回 None；正则模式下替换文本支持 $1 / ${name} 引用分组
+    fn replace(&self, content: &str, replacement: &str) -> Option<String> {
+        let replaced = match self {
+            ContentMatcher::Plain(find) if content.contains(find.as_str()) => content.replace(find.as_str(), replacement),
+            ContentMatcher::Regex(regex) if regex.is_match(content) => regex.replace_all(content, replacement).into_owned(),
+            _ => return None,
+        };
+        (replaced != content).then_some(replaced)
+    }
+}
+
+async fn apply_replacements_in_transaction(pool: &sqlx::SqlitePool, changes: &[(i64, String)]) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    for (id, content) in changes {
+        // 同步更新 data_hash，否则替换后的内容再次复制时无法被去重识别
+        sqlx::query("UPDATE clipboard_history SET content = ?, data_hash = ? WHERE id = ?")
+            .bind(content)
+            .bind(compute_content_hash(content))
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await
+}
+
+/// 在已保存的文本条目中查找并替换内容（文件和图片条目不参与），scope 为 "all"、"group"（需指定 group_id）或 "favorites"；
+/// 所有修改在同一事务中完成，返回被修改的条目
+#[tauri::command]
+pub async fn find_and_replace(
+    app: AppHandle,
+    find: String,
+    replacement: String,
+    use_regex: Option<bool>,
+    scope: String,
+    group_id: Option<i64>,
+    dry_run: Option<bool>,
+) -> Result<FindReplaceResult, String> {
+    let dry_run = dry_run.unwrap_or(false);
+    tracing::info!("查找替换: scope={}, group_id={:?}, regex={:?}, dry_run={}, find='{}'", scope, group_id, use_regex, dry_run, logging::preview(&find));
+    let matcher = ContentMatcher::new(&find, use_regex.unwrap_or(false))?;
+    let scope_filter = match (scope.as_str(), group_id) {
+        ("all", _) => "1 = 1",
+        ("favorites", _) => "is_favorite = 1",
+        ("group", Some(_)) => "group_id = ?",
+        ("group", None) => return Err("按分组替换时必须指定分组".to_string()),
+        _ => return Err(format!("不支持的替换范围: {}", scope)),
+    };
+
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        let pool = &db_guard.pool;
+
+        let query = format!("SELECT id, content FROM clipboard_history WHERE type = 'text' AND {}", scope_filter);
+        let mut select = sqlx::query_as::<_, (i64, String)>(&query);
+        if scope == "group" {
+            select = select.bind(group_id);
+        }
+        let rows = select.fetch_all(pool).await.map_err(|e| {
+            let error_msg = format!("查询条目失败: {}", e);
+            tracing::error!("❌ 查找替换失败: {}", error_msg);
+            error_msg
+        })?;
+
+        let changes: Vec<(i64, String)> = rows
+            .into_iter()
+            .filter_map(|(id, content)| matcher.replace(&content, &replacement).map(|replaced| (id, replaced)))
+            .collect();
+        let ids: Vec<i64> = changes.iter().map(|(id, _)| *id).collect();
+
+        if dry_run || changes.is_empty() {
+            tracing::info!("查找替换预览: 匹配 {} 个条目", ids.len());
+            return Ok(FindReplaceResult { ids, changed: 0, dry_run });
+        }
+
+        with_db_retry("查找替换", || apply_replacements_in_transaction(pool, &changes))
+            .await
+            .map_err(|e| {
+                let error_msg = format!("替换条目内容失败: {}", e);
+                tracing::error!("❌ 查找替换失败: {}", error_msg);
+                error_msg
+            })?;
+
+        if let Err(e) = app.emit(HISTORY_CHANGED_EVENT, HistoryChangedPayload { ids: ids.clone() }) {
+            tracing::warn!("⚠️ 发送 history-changed 事件失败: {}", e);
+        }
+        tracing::info!("✅ 查找替换完成: 修改了 {} 个条目", ids.len());
+        Ok(FindReplaceResult { changed: ids.len(), ids, dry_run })
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 查找替换失败: {}", error_msg);
+        Err(error_msg)
+    }
+}
+
+// ===== 文件剪贴板相关命令 =====
+
+/// 文件元信息结构
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct FileMetadata {
+    pub path: String,
+    pub name: String,
+    pub extension: String,
+    pub size: u64,
+    pub exists: bool,
+    pub is_directory: bool,
+    pub modified_time: String, // ISO 8601 string
+}
+
+// 剪贴板插件在 Linux 上按 text/uri-list 报告 file:// URI，转换为本地路径（解码 %XX 转义）；普通路径原样返回
+fn local_file_path(value: &str) -> PathBuf {
+    let Some(rest) = value.strip_prefix("file://") else {
+        return PathBuf::from(value);
+    };
+    // 跳过主机名（file://localhost/path）
+    let encoded = rest.find('/').map_or(rest, |index| &rest[index..]);
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| encoded.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    let path = String::from_utf8_lossy(&decoded).to_string();
+    // Windows 的 file:///C:/dir 去掉盘符前的斜杠
+    match path.as_bytes() {
+        [b'/', drive, b':', ..] if drive.is_ascii_alphabetic() => PathBuf::from(&path[1..]),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// 复制文件到剪贴板（Windows 写入 CF_HDROP，macOS/Linux 写入文件 URL 列表）
+#[tauri::command]
+pub async fn copy_files_to_clipboard(file_paths: Vec<String>) -> Result<(), String> {
+    let start = std::time::Instant::now();
+    tracing::info!("复制文件到剪贴板: {}", logging::preview(&file_paths.join(", ")));
+    let file_paths: Vec<String> = file_paths
+        .iter()
+        .map(|path| local_file_path(path).to_string_lossy().to_string())
+        .collect();
+    
+    if file_paths.is_empty() {
+        return Err("文件路径列表为空".to_string());
+    }
+    
+    // 验证所有文件是否存在
+    for path_str in &file_paths {
+        let path = PathBuf::from(path_str);
+        if !path.exists() {
+            return Err(format!("文件不存在: {}", path_str));
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        use clipboard_win::{formats, Clipboard, Setter};
+
+        let _clip = Clipboard::new_attempts(10)
+            .map_err(|e| format!("无法打开剪贴板: {}", e))?;
+
+        // 清空剪贴板，确保之前的内容被清除
+        clipboard_win::raw::empty()
+            .map_err(|e| format!("清空剪贴板失败: {}", e))?;
+
+        // 设置文件列表 (CF_HDROP)
+        formats::FileList.write_clipboard(&file_paths)
+            .map_err(|e| format!("设置剪贴板文件失败: {}", e))?;
+
+        tracing::info!("✅ 文件已写入剪贴板 (Windows CF_HDROP), 文件数: {}, 耗时: {:?}",
+            file_paths.len(), start.elapsed());
+        return Ok(());
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        // macOS 写入 NSPasteboard 文件 URL，Linux 写入 text/uri-list
+        let mut clipboard = arboard::Clipboard::new()
+            .map_err(|e| format!("无法打开剪贴板: {}", e))?;
+        clipboard.set().file_list(&file_paths)
+            .map_err(|e| format!("设置剪贴板文件失败: {}", e))?;
+
+        tracing::info!("✅ 文件已写入剪贴板, 文件数: {}, 耗时: {:?}",
+            file_paths.len(), start.elapsed());
+        Ok(())
+    }
+}
+
+/// 获取文件元信息
+#[tauri::command]
+pub async fn get_file_metadata(file_path: String) -> Result<FileMetadata, String> {
+    let path = local_file_path(&file_path);
+    
+    let name = path.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown")
+        .to_string();
+        
+    let extension = path.extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_string();
+    
+    let exists = path.exists();
+    let is_directory = path.is_dir();
+    
+    let size = if exists && !is_directory {
+        std::fs::metadata(&path)
+            .map(|m| m.len())
+            .unwrap_or(0)
+    } else {
+        0
+    };
+    
+    let modified_time = if exists {
+        std::fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .ok()
+            .map(|t| chrono::DateTime::<chrono::Local>::from(t).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| "".to_string())
+    } else {
+        "".to_string()
+    };
+    
+    Ok(FileMetadata {
+        path: file_path,
+        name,
+        extension,
+        size,
+        exists,
+        is_directory,
+        modified_time,
+    })
+}
+
+/// 批量获取文件元信息
+#[tauri::command]
+pub async fn get_files_metadata(file_paths: Vec<String>) -> Result<Vec<FileMetadata>, String> {
+    let mut results = Vec::with_capacity(file_paths.len());
+    
+    for file_path in file_paths {
+        let metadata = get_file_metadata(file_path).await?;
+        results.push(metadata);
+    }
+    
+    Ok(results)
+}
+
+/// 检查文件是否存在
+#[tauri::command]
+pub async fn check_files_exist(file_paths: Vec<String>) -> Result<Vec<bool>, String> {
+    let results: Vec<bool> = file_paths.iter()
+        .map(|p| local_file_path(p).exists())
+        .collect();
+    
+    Ok(results)
+}
+
+/// 获取文件图标 (Windows Shell API)
+#[tauri::command]
+pub async fn get_file_icon(file_path: String) -> Result<String, String> {
+    let path = PathBuf::from(&file_path);
+    
+    // 如果文件不存在，尝试根据扩展名获取图标
+    let extension = path.extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("");
+    
+    #[cfg(target_os = "windows")]
+    {
+        use std::ptr::null_mut;
+        use winapi::um::shellapi::{SHGetFileInfoW, SHFILEINFOW, SHGFI_ICON, SHGFI_SMALLICON, SHGFI_USEFILEATTRIBUTES};
+        use winapi::um::winuser::{DestroyIcon, GetIconInfo, ICONINFO};
+        use winapi::um::wingdi::{GetDIBits, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, DeleteObject, GetObjectW, BITMAP};
+        use winapi::shared::windef::HBITMAP;
+        use winapi::um::winuser::GetDC;
+        use winapi::um::wingdi::CreateCompatibleDC;
+        use winapi::um::wingdi::SelectObject;
+        use winapi::um::wingdi::DeleteDC;
+        use winapi::um::winuser::ReleaseDC;
+        use winapi::shared::minwindef::DWORD;
+        
+        // 将路径转换为宽字符
+        let wide_path: Vec<u16> = file_path.encode_utf16().chain(std::iter::once(0)).collect();
+        
+        let mut shfi: SHFILEINFOW = unsafe { std::mem::zeroed() };
+        
+        // 如果文件存在，直接获取图标；否则使用扩展名
+        let flags = if path.exists() {
+            SHGFI_ICON | SHGFI_SMALLICON
+        } else {
+            SHGFI_ICON | SHGFI_SMALLICON | SHGFI_USEFILEATTRIBUTES
+        };
+        
+        let result = unsafe {
+            SHGetFileInfoW(
+                wide_path.as_ptr(),
+                0x80, // FILE_ATTRIBUTE_NORMAL
+                &mut shfi,
+                std::mem::size_of::<SHFILEINFOW>() as u32,
+                flags,
+            )
+        };
+        
+        if result == 0 || shfi.hIcon.is_null() {
+            // 返回默认图标占位符
+            return Ok(get_default_file_icon(extension));
+        }
+        
+        // 获取图标信息
+        let mut icon_info: ICONINFO = unsafe { std::mem::zeroed() };
+        let got_info = unsafe { GetIconInfo(shfi.hIcon, &mut icon_info) };
+        
+        if got_info == 0 {
+            unsafe { DestroyIcon(shfi.hIcon) };
+            return Ok(get_default_file_icon(extension));
+        }
+        
+        // 获取位图信息
+        let hbm_color = icon_info.hbmColor;
+        if hbm_color.is_null() {
+            unsafe {
+                if !icon_info.hbmMask.is_null() { DeleteObject(icon_info.hbmMask as _); }
+                DestroyIcon(shfi.hIcon);
+            }
+            return Ok(get_default_file_icon(extension));
+        }
+        
+        // 获取位图尺寸
+        let mut bm: BITMAP = unsafe { std::mem::zeroed() };
+        unsafe {
+            GetObjectW(
+                hbm_color as _,
+                std::mem::size_of::<BITMAP>() as i32,
+                &mut bm as *mut _ as _,
+            );
+        }
+        
+        let width = bm.bmWidth as usize;
+        let height = bm.bmHeight as usize;
+        
+        if width == 0 || height == 0 {
+            unsafe {
+                if !icon_info.hbmMask.is_null() { DeleteObject(icon_info.hbmMask as _); }
+                DeleteObject(hbm_color as _);
+                DestroyIcon(shfi.hIcon);
+            }
+            return Ok(get_default_file_icon(extension));
+        }
+        
+        // 准备位图信息头
+        let mut bmi: BITMAPINFO = unsafe { std::mem::zeroed() };
+        bmi.bmiHeader.biSize = std::mem::size_of::<BITMAPINFOHEADER>() as u32;
+        bmi.bmiHeader.biWidth = width as i32;
+        bmi.bmiHeader.biHeight = -(height as i32); // 负数表示自上而下
+        bmi.bmiHeader.biPlanes = 1;
+        bmi.bmiHeader.biBitCount = 32;
+        bmi.bmiHeader.biCompression = BI_RGB;
+        
+        // 分配像素缓冲区
+        let mut pixels: Vec<u8> = vec![0u8; width * height * 4];
+        
+        // 获取设备上下文
+        let hdc_screen = unsafe { GetDC(null_mut()) };
+        let hdc_mem = unsafe { CreateCompatibleDC(hdc_screen) };
+        let old_bmp = unsafe { SelectObject(hdc_mem, hbm_color as _) };
+        
+        // 读取位图数据
+        unsafe {
+            GetDIBits(
+                hdc_mem,
+                hbm_color,
+                0,
+                height as u32,
+                pixels.as_mut_ptr() as _,
+                &mut bmi,
+                0, // DIB_RGB_COLORS
+            );
+        }
+        
+        // 清理资源
+        unsafe {
+            SelectObject(hdc_mem, old_bmp);
+            DeleteDC(hdc_mem);
+            ReleaseDC(null_mut(), hdc_screen);
+            if !icon_info.hbmMask.is_null() { DeleteObject(icon_info.hbmMask as _); }
+            DeleteObject(hbm_color as _);
+            DestroyIcon(shfi.hIcon);
+        }
+        
+        // BGRA -> RGBA 转换
+        for chunk in pixels.chunks_exact_mut(4) {
+            chunk.swap(0, 2); // B <-> R
+        }
+        
+        // 编码为 PNG
+        let png_data = encode_rgba_to_png(&pixels, width as u32, height as u32)?;
+        
+        // 转换为 base64 data URL
+        let b64 = base64::engine::general_purpose::STANDARD.encode(&png_data);
+        let data_url = format!("data:image/png;base64,{}", b64);
+        
+        return Ok(data_url);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        // 非 Windows 平台返回默认图标
+        Ok(get_default_file_icon(extension))
+    }
+}
+
+/// 根据扩展名返回默认图标（SVG data URL）
+fn get_default_file_icon(extension: &str) -> String {
+    // 使用简单的文件图标 SVG - 所有类型使用相同的基础图标，颜色不同
+    let (color, label) = match extension.to_lowercase().as_str() {
+        "pdf" => ("E53935", "PDF"),
+        "doc" | "docx" => ("1976D2", "DOC"),
+        "xls" | "xlsx" => ("388E3C", "XLS"),
+        "ppt" | "pptx" => ("D84315", "PPT"),
+        "zip" | "rar" | "7z" | "tar" | "gz" => ("FFA000", "ZIP"),
+        "mp3" | "wav" | "flac" | "aac" | "ogg" => ("7B1FA2", "♪"),
+        "mp4" | "avi" | "mkv" | "mov" | "wmv" => ("C62828", "▶"),
+        "exe" | "msi" => ("455A64", "EXE"),
+        "txt" | "md" | "log" => ("616161", "TXT"),
+        "js" | "ts" | "jsx" | "tsx" => ("F7DF1E", "JS"),
+        "py" => ("3776AB", "PY"),
+        "rs" => ("DEA584", "RS"),
+        "html" | "htm" => ("E34F26", "HTML"),
+        "css" | "scss" | "sass" => ("1572B6", "CSS"),
+        "json" => ("000000", "{ }"),
+        "xml" => ("F16529", "XML"),
+        "jpg" | "jpeg" | "png" | "gif" | "bmp" | "webp" => ("4CAF50", "IMG"),
+        _ => ("757575", "FILE"),
+    };
+    
+    // 构建简单的 SVG 文件图标
+    let svg = format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24"><path fill="#{}" d="M14 2H6c-1.1 0-2 .9-2 2v16c0 1.1.9 2 2 2h12c1.1 0 2-.9 2-2V8l-6-6zm4 18H6V4h7v5h5v11z"/><text x="12" y="16" text-anchor="middle" font-size="4" fill="#{}">{}</text></svg>"##,
+        color, color, label
+    );
+    
+    let encoded = base64::engine::general_purpose::STANDARD.encode(svg.as_bytes());
+    format!("data:image/svg+xml;base64,{}", encoded)
+}
+
+/// 将 RGBA 像素数据编码为 PNG
+fn encode_rgba_to_png(pixels: &[u8], width: u32, height: u32) -> Result<Vec<u8>, String> {
+    use image::{ImageBuffer, RgbaImage, ImageEncoder, ColorType};
+    
+    let img: RgbaImage = ImageBuffer::from_raw(width, height, pixels.to_vec())
+        .ok_or_else(|| "无法创建图片缓冲区".to_string())?;
+    
+    let mut png_data = Vec::new();
+    let encoder = image::codecs::png::PngEncoder::new(&mut png_data);
+    
+    encoder.write_image(
+        img.as_raw(),
+        width,
+        height,
+        ColorType::Rgba8,
+    ).map_err(|e| format!("PNG 编码失败: {}", e))?;
+    
+    Ok(png_data)
+}
+
+/// 打开文件所在文件夹并选中文件 (Windows Explorer)
+#[tauri::command]
+pub async fn open_file_location(file_path: String) -> Result<(), String> {
+    let path = local_file_path(&file_path);
+    
+    if !path.exists() {
+        return Err(format!("文件不存在: {}", file_path));
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        // Windows: 使用 explorer /select, 命令
+        std::process::Command::new("explorer")
+            .args(["/select,", &file_path])
+            .spawn()
+            .map_err(|e| format!("打开文件位置失败: {}", e))?;
+        
+        tracing::info!("✅ 已打开文件位置: {}", file_path);
+        return Ok(());
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        // macOS: 使用 open -R 命令
+        std::process::Command::new("open")
+            .args(["-R", &file_path])
+            .spawn()
+            .map_err(|e| format!("打开文件位置失败: {}", e))?;
+        
+        tracing::info!("✅ 已打开文件位置: {}", file_path);
+        return Ok(());
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        // Linux: 尝试使用 xdg-open 打开父目录
+        if let Some(parent) = path.parent() {
+            std::process::Command::new("xdg-open")
+                .arg(parent)
+                .spawn()
+                .map_err(|e| format!("打开文件位置失败: {}", e))?;
+            
+            tracing::info!("✅ 已打开文件位置: {}", file_path);
+            return Ok(());
+        }
+        return Err("无法获取文件父目录".to_string());
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    {
+        Err("不支持的操作系统".to_string())
+    }
+}
+
+/// 读取文本文件内容
+#[tauri::command]
+pub async fn read_text_file(file_path: String) -> Result<String, String> {
+    let start = std::time::Instant::now();
+    tracing::info!("读取文本文件: {}", logging::preview(&file_path));
+
+    // 验证文件存在
+    let path = std::path::PathBuf::from(&file_path);
+    if !path.exists() {
+        return Err(format!("文件不存在: {}", file_path));
+    }
+
+    // 验证是文件而不是目录
+    if path.is_dir() {
+        return Err("无法读取目录内容".to_string());
+    }
+
+    // 检查文件大小（限制为 10MB）
+    let metadata = std::fs::metadata(&path)
+        .map_err(|e| format!("无法获取文件元数据: {}", e))?;
+    
+    if metadata.len() > 10 * 1024 * 1024 {
+        return Err("文件过大（超过10MB），无法预览".to_string());
+    }
+
+    // 读取文件内容
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("读取文件失败: {}", e))?;
+
+    tracing::info!("✅ 文本文件读取成功，大小: {} 字节，耗时: {:?}",
+        content.len(), start.elapsed());
+
+    Ok(content)
+}
+
+// ==================== 数据导入导出 ====================
+
+// 导出为 JSON 的历史条目，图片条目内联图片文件的 data URL，使导出文件可独立使用
+#[derive(serde::Serialize, Debug)]
+struct HistoryExportItem {
+    #[serde(flatten)]
+    item: ClipboardItem,
+    // 图片文件缺失或读取失败时为 None
+    image_data: Option<String>,
+}
+
+fn history_export_item(item: ClipboardItem) -> HistoryExportItem {
+    let image_data = if item.item_type == "image" {
+        let image_path = item.image_path.clone().unwrap_or_else(|| item.content.clone());
+        match fs::read(resolve_image_path(&image_path)) {
+            Ok(bytes) => Some(image_bytes_to_data_url(&bytes)),
+            Err(e) => {
+                tracing::warn!("⚠️ 导出时读取图片失败 {}: {}", image_path, e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    HistoryExportItem { item, image_data }
+}
+
+// 导出文件内容：{ version, exported_at, items }。条目在序列化时才逐个读取图片，
+// 同一时刻内存中只有一张图片的 base64，而不是整个导出文件
+struct HistoryExport<'a> {
+    exported_at: String,
+    items: &'a [ClipboardItem],
+}
+
+struct HistoryExportItems<'a>(&'a [ClipboardItem]);
+
+impl serde::Serialize for HistoryExport<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut export = serializer.serialize_struct("HistoryExport", 3)?;
+        export.serialize_field("version", &1)?;
+        export.serialize_field("exported_at", &self.exported_at)?;
+        export.serialize_field("items", &HistoryExportItems(self.items))?;
+        export.end()
+    }
+}
+
+impl serde::Serialize for HistoryExportItems<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter().cloned().map(history_export_item))
+    }
+}
+
+// 流式写入导出文件：先写同目录下的 .tmp 文件再重命名，失败时不会留下写了一半的导出文件
+fn write_history_export(dest: &Path, items: &[ClipboardItem]) -> Result<(), String> {
+    let export = HistoryExport {
+        exported_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        items,
+    };
+    let temp_path = settings_sidecar_path(dest, ".tmp");
+    let result = (|| -> Result<(), String> {
+        let file = fs::File::create(&temp_path).map_err(|e| format!("创建导出文件失败: {}", e))?;
+        let mut writer = std::io::BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut writer, &export).map_err(|e| format!("序列化历史记录失败: {}", e))?;
+        let file = writer.into_inner().map_err(|e| format!("写入导出文件失败: {}", e))?;
+        file.sync_all().map_err(|e| format!("写入导出文件失败: {}", e))?;
+        fs::rename(&temp_path, dest).map_err(|e| format!("写入导出文件失败: {}", e))
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// 将历史记录（可只导出收藏）导出为 JSON 文件，图片内联为 base64，返回导出的条目数
+#[tauri::command]
+pub async fn export_history(app: AppHandle, dest_path: String, include_favorites_only: bool) -> Result<usize, String> {
+    tracing::info!("导出历史记录到: {}, 仅收藏={}", dest_path, include_favorites_only);
+
+    let items = if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        let filter = if include_favorites_only { " WHERE is_favorite = 1" } else { "" };
+        let query = format!(
+            "SELECT {} FROM clipboard_history{} ORDER BY timestamp DESC",
+            CLIPBOARD_ITEM_COLUMNS, filter
+        );
+        let rows = sqlx::query(&query)
+            .fetch_all(&db_guard.pool)
+            .await
+            .map_err(|e| {
+                let error_msg = format!("查询历史记录失败: {}", e);
+                tracing::error!("❌ 导出历史记录失败: {}", error_msg);
+                error_msg
+            })?;
+        rows.iter().map(row_to_clipboard_item).collect::<Vec<_>>()
+    } else {
+        let error_msg = "无法获取数据库状态".to_string();
+        tracing::error!("❌ 导出历史记录失败: {}", error_msg);
+        return Err(error_msg);
+    };
+
+    // 读取图片和写文件可能较慢，放到阻塞线程中执行，且不持有数据库锁
+    let count = items.len();
+    tokio::task::spawn_blocking(move || write_history_export(Path::new(&dest_path), &items))
+    .await
+    .map_err(|e| format!("导出历史记录任务失败: {}", e))??;
+
+    tracing::info!("✅ 已导出 {} 条历史记录", count);
+    Ok(count)
+}
+
+#[tauri::command]
+pub async fn export_data(app: AppHandle, export_path: String) -> Result<(), String> {
+    tracing::info!("开始导出数据到: {}", export_path);
+
+    let export_path = PathBuf::from(&export_path);
+    let file = fs::File::create(&export_path)
+        .map_err(|e| format!("无法创建导出文件: {}", e))?;
+
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+
+    // 1. 写入数据库文件
+    let db_path = active_profile_db_path(&app)?;
+
+    // WAL 模式下最近的写入可能仍在 -wal 文件中，先执行检查点合并回主数据库文件
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        if let Err(e) = sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)").execute(&db_guard.pool).await {
+            tracing::warn!("⚠️ 导出前执行 WAL 检查点失败: {}", e);
+        }
+    }
+
+    if db_path.exists() {
+        let db_bytes = fs::read(&db_path)
+            .map_err(|e| format!("无法读取数据库文件: {}", e))?;
+        zip.start_file("clipboard.db", options)
+            .map_err(|e| format!("写入数据库到zip失败: {}", e))?;
+        zip.write_all(&db_bytes)
+            .map_err(|e| format!("写入数据库数据失败: {}", e))?;
+        tracing::info!("已写入数据库文件 ({} 字节)", db_bytes.len());
+    } else {
+        return Err("数据库文件不存在".to_string());
+    }
+
+    // 2. 写入图片文件 - 从数据库查询所有图片路径
+    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
+        let db_guard = db_state.lock().await;
+        let pool = &db_guard.pool;
+
+        let image_rows = sqlx::query("SELECT DISTINCT image_path FROM clipboard_history WHERE image_path IS NOT NULL")
+            .fetch_all(pool)
+            .await
+            .unwrap_or_default();
+
+        let mut image_count = 0u64;
+        let mut image_size = 0u64;
+
+        for row in &image_rows {
+            if let Ok(Some(image_path)) = row.try_get::<Option<String>, &str>("image_path") {
+                let path = resolve_image_path(&image_path);
+                if path.exists() && path.is_file() {
+                    if let Some(filename) = path.file_name() {
+                        let zip_path = format!("images/{}", filename.to_string_lossy());
+                        match fs::read(&path) {
+                            Ok(file_bytes) => {
+                                if let Err(e) = zip.start_file(&zip_path, options) {
+                                    tracing::warn!("写入图片到zip失败: {}", e);
+                                    continue;
+                                }
+                                if let Err(e) = zip.write_all(&file_bytes) {
+                                    tracing::warn!("写入图片数据失败: {}", e);
+                                    continue;
+                                }
+                                image_count += 1;
+                                image_size += file_bytes.len() as u64;
+                            }
+                            Err(e) => {
+                                tracing::warn!("无法读取图片 {}: {}", image_path, e);
+                            }
+                        }
+                    }
+                } else {
+                    tracing::warn!("图片文件不存在，跳过: {}", image_path);
+                }
+            }
+        }
+        drop(db_guard);
+        tracing::info!("已写入 {} 个图片文件 ({} 字节)", image_count, image_size);
+    } else {
+        tracing::warn!("无法获取数据库状态，跳过图片导出");
+    }
+
+    zip.finish().map_err(|e| format!("完成zip写入失败: {}", e))?;
+
+    tracing::info!("✅ 数据导出完成");
+    Ok(())
+}
+
+/// 从 zip 中同步提取所有需要的数据（避免 ZipFile 跨 await 的 Send 问题）
+struct ExtractedZipData {
+    db_bytes: Vec<u8>,
+    images: Vec<(String, Vec<u8>)>, // (filename, bytes)
+}
+
+fn extract_zip_data(import_path: &PathBuf) -> Result<ExtractedZipData, String> {
+    let file = fs::File::open(import_path)
+        .map_err(|e| format!("无法打开导入文件: {}", e))?;
+    let mut archive = ZipArchive::new(file)
+        .map_err(|e| format!("无法读取zip文件: {}", e))?;
+
+    // 提取数据库文件
+    let mut db_file = archive.by_name("clipboard.db")
+        .map_err(|e| format!("zip中未找到数据库文件: {}", e))?;
+    let mut db_bytes = Vec::new();
+    std::io::Read::read_to_end(&mut db_file, &mut db_bytes)
+        .map_err(|e| format!("无法读取数据库数据: {}", e))?;
+    drop(db_file);
+
+    // 提取所有图片文件
+    let mut images = Vec::new();
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)
+            .map_err(|e| format!("读取zip条目失败: {}", e))?;
+        let entry_path = entry.name().to_string();
+
+        if entry_path.starts_with("images/") && !entry.is_dir() {
+            if let Some(filename) = std::path::Path::new(&entry_path).file_name() {
+                let mut buf = Vec::new();
+                std::io::Read::read_to_end(&mut entry, &mut buf)
+                    .map_err(|e| format!("无法读取图片 {}: {}", entry_path, e))?;
+                images.push((filename.to_string_lossy().to_string(), buf));
+            }
+        }
+    }
+
+    Ok(ExtractedZipData { db_bytes, images })
+}
+
+#[tauri::command]
+pub async fn import_data(app: AppHandle, import_path: String, mode: String) -> Result<(), String> {
+    tracing::info!("开始导入数据: {} (模式: {})", import_path, mode);
+
+    let import_path = PathBuf::from(&import_path);
+
+    // 第一步：同步提取所有 zip 数据（不跨 await）
+    let zip_data = tokio::task::spawn_blocking(move || extract_zip_data(&import_path))
+        .await
+        .map_err(|e| format!("提取zip数据失败: {}", e))??;
+
+    // 第二步：写入临时数据库文件
+    let temp_dir = tempfile::tempdir()
+        .map_err(|e| format!("无法创建临时目录: {}", e))?;
+    let temp_db_path = temp_dir.path().join("import.db");
+    fs::write(&temp_db_path, &zip_data.db_bytes)
+        .map_err(|e| format!("无法写入临时数据库文件: {}", e))?;
+
+    // 第三步：连接临时数据库并读取数据
+    // 导出的数据库可能处于 WAL 模式，只读连接无法创建 -shm/-wal 文件，因此这里不使用 read_only
+    let temp_options = sqlx::sqlite::SqliteConnectOptions::new()
+        .filename(&temp_db_path);
+    let temp_pool = sqlx::SqlitePool::connect_with(temp_options)
+        .await
+        .map_err(|e| format!("无法连接临时数据库: {}", e))?;
+
+    // 获取当前数据库连接
+    let db_state = app.try_state::<Mutex<DatabaseState>>()
+        .ok_or("无法访问数据库状态")?;
+    let db_guard = db_state.lock().await;
+    let pool = &db_guard.pool;
+
+    let new_images_dir = get_app_images_dir()?;
+
+    if mode == "replace" {
+        // 替换模式：清空现有数据
+        if new_images_dir.exists() {
+            if let Ok(entries) = fs::read_dir(&new_images_dir) {
+                for entry in entries.flatten() {
+                    let _ = fs::remove_file(entry.path());
+                }
+            }
+        }
+
+        sqlx::query("DELETE FROM clipboard_history").execute(pool).await
+            .map_err(|e| format!("清空历史记录失败: {}", e))?;
+        sqlx::query("DELETE FROM groups").execute(pool).await
+            .map_err(|e| format!("清空分组失败: {}", e))?;
+        sqlx::query("DELETE FROM sqlite_sequence WHERE name IN ('clipboard_history', 'groups')").execute(pool).await
+            .ok();
+
+        tracing::info!("替换模式：已清空现有数据");
+    }
+
+    // === 导入分组 ===
+    let old_groups: Vec<(i64, String, String, String)> = sqlx::query_as(
+        "SELECT id, name, color, created_at FROM groups ORDER BY id"
+    )
+    .fetch_all(&temp_pool)
+    .await
+    .map_err(|e| format!("读取导入分组失败: {}", e))?;
+
+    let mut group_id_map: std::collections::HashMap<i64, i64> = std::collections::HashMap::new();
+
+    for (old_id, name, color, created_at) in &old_groups {
+        let existing: Option<(i64,)> = sqlx::query_as(
+            "SELECT id FROM groups WHERE name = ?"
+        )
+        .bind(name)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("查询分组失败: {}", e))?;
+
+        if let Some((existing_id,)) = existing {
+            group_id_map.insert(*old_id, existing_id);
+        } else {
+            let result: (i64,) = sqlx::query_as(
+                "INSERT INTO groups (name, color, created_at, item_count) VALUES (?, ?, ?, 0) RETURNING id"
+            )
+            .bind(name)
+            .bind(color)
+            .bind(created_at)
+            .fetch_one(pool)
+            .await
+            .map_err(|e| format!("创建分组失败: {}", e))?;
+
+            group_id_map.insert(*old_id, result.0);
+        }
+    }
+    tracing::info!("分组映射完成: {} 个分组", group_id_map.len());
+
+    // === 导入剪贴板记录 ===
+    let old_records = sqlx::query(
+        "SELECT content, type, timestamp, is_favorite, is_pinned, image_path, source_app_name, source_app_icon, thumbnail_data, note, group_id, data_hash, metadata FROM clipboard_history ORDER BY id"
+    )
+    .fetch_all(&temp_pool)
+    .await
+    .map_err(|e| format!("读取导入记录失败: {}", e))?;
+
+    let mut imported_count = 0u64;
+    let mut skipped_count = 0u64;
+
+    for record in &old_records {
+        let content: String = record.try_get("content").unwrap_or_default();
+        let record_type: String = record.try_get("type").unwrap_or_default();
+        let timestamp: String = record.try_get("timestamp").unwrap_or_default();
+        let is_favorite: i64 = record.try_get("is_favorite").unwrap_or(0);
+        let is_pinned: i64 = record.try_get("is_pinned").unwrap_or(0);
+        let old_image_path: Option<String> = record.try_get("image_path").ok().flatten();
+        let source_app_name: Option<String> = record.try_get("source_app_name").ok().flatten();
+        let source_app_icon: Option<String> = record.try_get("source_app_icon").ok().flatten();
+        let thumbnail_data: Option<String> = record.try_get("thumbnail_data").ok().flatten();
+        let note: Option<String> = record.try_get("note").ok().flatten();
+        let old_group_id: Option<i64> = record.try_get("group_id").ok().flatten();
+        let data_hash: Option<String> = record.try_get("data_hash").ok().flatten();
+        let metadata: Option<String> = record.try_get("metadata").ok().flatten();
+
+        // 图片文件统一放在图片目录下，只保留文件名作为相对路径
+        let new_image_path = old_image_path.as_ref().and_then(|p| {
+            std::path::Path::new(p).file_name().map(|f| f.to_string_lossy().to_string())
+        });
+        // 图片条目的 content 存储的也是路径，同步改写
+        let content = match (&new_image_path, &old_image_path) {
+            (Some(new_path), Some(old_path)) if record_type == "image" && &content == old_path => new_path.clone(),
+            _ => content,
+        };
+
+        let new_group_id = old_group_id.and_then(|gid| group_id_map.get(&gid).copied());
+
+        // 去重检查
+        let is_duplicate = if let Some(ref hash) = data_hash {
+            if !hash.is_empty() {
+                let existing: Option<(i64,)> = sqlx::query_as(
+                    "SELECT id FROM clipboard_history WHERE data_hash = ?"
+                )
+                .bind(hash)
+                .fetch_optional(pool)
+                .await
+                .map_err(|e| format!("去重查询失败: {}", e))?;
+                existing.is_some()
+            } else {
+                false
+            }
+        } else {
+            let existing: Option<(i64,)> = sqlx::query_as(
+                "SELECT id FROM clipboard_history WHERE content = ? AND type = ?"
+            )
+            .bind(&content)
+            .bind(&record_type)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| format!("去重查询失败: {}", e))?;
+            existing.is_some()
+        };
+
+        if is_duplicate {
+            skipped_count += 1;
+            continue;
+        }
+
+        sqlx::query(
+            "INSERT INTO clipboard_history (content, type, timestamp, is_favorite, is_pinned, image_path, source_app_name, source_app_icon, thumbnail_data, note, group_id, data_hash, metadata)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&content)
+        .bind(&record_type)
+        .bind(&timestamp)
+        .bind(is_favorite)
+        .bind(is_pinned)
+        .bind(&new_image_path)
+        .bind(&source_app_name)
+        .bind(&source_app_icon)
+        .bind(&thumbnail_data)
+        .bind(&note)
+        .bind(new_group_id)
+        .bind(&data_hash)
+        .bind(&metadata)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("插入记录失败: {}", e))?;
+
+        imported_count += 1;
+    }
+
+    tracing::info!("记录导入完成: 导入 {} 条, 跳过 {} 条(重复)", imported_count, skipped_count);
+
+    // 更新分组的 item_count
+    for (_, new_gid) in &group_id_map {
+        let _ = sqlx::query("UPDATE groups SET item_count = (SELECT COUNT(*) FROM clipboard_history WHERE group_id = ?) WHERE id = ?")
+            .bind(new_gid)
+            .bind(new_gid)
+            .execute(pool)
+            .await;
+    }
+
+    // 关闭临时数据库连接
+    temp_pool.close().await;
+    drop(db_guard); // 释放数据库锁
+
+    // 第四步：写入图片文件（纯同步操作）
+    fs::create_dir_all(&new_images_dir)
+        .map_err(|e| format!("无法创建图片目录: {}", e))?;
+
+    let mut image_count = 0u64;
+    for (filename, data) in &zip_data.images {
+        let dest_path = new_images_dir.join(filename);
+        if !dest_path.exists() {
+            fs::write(&dest_path, data)
+                .map_err(|e| format!("无法写入图片文件 {}: {}", filename, e))?;
+            image_count += 1;
+        }
+    }
+
+    tracing::info!("图片文件提取完成: {} 个新文件", image_count);
+    tracing::info!("✅ 数据导入完成");
+
+    let _ = app.emit("data-imported", ());
+
+    Ok(())
 }
 
-/// 打开文件所在文件夹并选中文件 (Windows Explorer)
-#[tauri::command]
-pub async fn open_file_location(file_path: String) -> Result<(), String> {
-    let path = PathBuf::from(&file_path);
-    
-    if !path.exists() {
-        return Err(format!("文件不存在: {}", file_path));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{MAX_DB_MAX_CONNECTIONS, MAX_ICON_CACHE_TTL_HOURS};
+    use image::codecs::webp::WebPEncoder;
+    use image::{ColorType, ImageEncoder};
+
+    // 2x2 RGBA 测试图片编码为无损 WebP
+    fn webp_sample() -> Vec<u8> {
+        let pixels: Vec<u8> = vec![
+            255, 0, 0, 255, 0, 255, 0, 255,
+            0, 0, 255, 255, 255, 255, 255, 128,
+        ];
+        let mut bytes = Vec::new();
+        WebPEncoder::new_lossless(&mut bytes)
+            .write_image(&pixels, 2, 2, ColorType::Rgba8)
+            .unwrap();
+        bytes
     }
 
-    #[cfg(target_os = "windows")]
-    {
-        // Windows: 使用 explorer /select, 命令
-        std::process::Command::new("explorer")
-            .args(["/select,", &file_path])
-            .spawn()
-            .map_err(|e| format!("打开文件位置失败: {}", e))?;
-        
-        tracing::info!("✅ 已打开文件位置: {}", file_path);
-        return Ok(());
+    // 仅包含 ftyp 头的最小 AVIF 样本，足以被格式探测识别
+    const AVIF_SAMPLE: &[u8] = b"\x00\x00\x00\x1cftypavif\x00\x00\x00\x00avifmif1miaf";
+
+    #[test]
+    fn webp_images_are_converted_to_png() {
+        let webp = webp_sample();
+        assert_eq!(image::guess_format(&webp).unwrap(), ImageFormat::WebP);
+        assert!(image_bytes_to_data_url(&webp).starts_with("data:image/webp;base64,"));
+
+        let png = convert_image_to_storable(webp).unwrap();
+        assert_eq!(image::guess_format(&png).unwrap(), ImageFormat::Png);
+        let img = image::load_from_memory(&png).unwrap();
+        assert_eq!((img.width(), img.height()), (2, 2));
+        assert_eq!(img.to_rgba8().get_pixel(1, 1).0, [255, 255, 255, 128]);
     }
 
-    #[cfg(target_os = "macos")]
-    {
-        // macOS: 使用 open -R 命令
-        std::process::Command::new("open")
-            .args(["-R", &file_path])
-            .spawn()
-            .map_err(|e| format!("打开文件位置失败: {}", e))?;
-        
-        tracing::info!("✅ 已打开文件位置: {}", file_path);
-        return Ok(());
+    #[test]
+    fn avif_images_are_detected_and_rejected() {
+        assert_eq!(image::guess_format(AVIF_SAMPLE).unwrap(), ImageFormat::Avif);
+        assert!(image_bytes_to_data_url(AVIF_SAMPLE).starts_with("data:image/avif;base64,"));
+        assert_eq!(convert_image_to_storable(AVIF_SAMPLE.to_vec()).unwrap_err(), "AVIF 不受支持");
     }
 
-    #[cfg(target_os = "linux")]
-    {
-        // Linux: 尝试使用 xdg-open 打开父目录
-        if let Some(parent) = path.parent() {
-            std::process::Command::new("xdg-open")
-                .arg(parent)
-                .spawn()
-                .map_err(|e| format!("打开文件位置失败: {}", e))?;
-            
-            tracing::info!("✅ 已打开文件位置: {}", file_path);
-            return Ok(());
+    #[test]
+    fn thumbnails_are_downscaled_and_optionally_blurred() {
+        // 左半黑、右半白的图片，模糊后分界处会出现中间灰度
+        let mut sample = image::RgbaImage::new(512, 256);
+        for (x, _, pixel) in sample.enumerate_pixels_mut() {
+            let v = if x < 256 { 0 } else { 255 };
+            *pixel = image::Rgba([v, v, v, 255]);
         }
-        return Err("无法获取文件父目录".to_string());
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgba8(sample)
+            .write_to(&mut std::io::Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+
+        let sharp = image::load_from_memory(&generate_thumbnail(&png, false).unwrap()).unwrap().to_rgba8();
+        assert_eq!((sharp.width(), sharp.height()), (THUMBNAIL_MAX_SIZE, THUMBNAIL_MAX_SIZE / 2));
+        assert_eq!(sharp.get_pixel(THUMBNAIL_MAX_SIZE / 2 - 4, 64).0[0], 0);
+
+        let blurred = image::load_from_memory(&generate_thumbnail(&png, true).unwrap()).unwrap().to_rgba8();
+        assert_eq!(blurred.dimensions(), sharp.dimensions());
+        let edge = blurred.get_pixel(THUMBNAIL_MAX_SIZE / 2 - 4, 64).0[0];
+        assert!(edge > 0 && edge < 255);
+
+        assert!(generate_thumbnail(b"not an image", false).is_err());
     }
 
-    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
-    {
-        Err("不支持的操作系统".to_string())
+    #[test]
+    fn png_and_unknown_data_are_stored_as_is() {
+        let unknown = b"not an image".to_vec();
+        assert_eq!(convert_image_to_storable(unknown.clone()).unwrap(), unknown);
+
+        let png = convert_image_to_storable(webp_sample()).unwrap();
+        assert_eq!(convert_image_to_storable(png.clone()).unwrap(), png);
     }
-}
 
-/// 读取文本文件内容
-#[tauri::command]
-pub async fn read_text_file(file_path: String) -> Result<String, String> {
-    let start = std::time::Instant::now();
-    tracing::info!("读取文本文件: {}", file_path);
+    #[test]
+    fn images_are_reencoded_for_storage() {
+        // 带规律图案的截图式图片，默认 PNG 编码下仍有较大压缩空间
+        let mut sample = image::RgbaImage::new(200, 120);
+        for (x, y, pixel) in sample.enumerate_pixels_mut() {
+            *pixel = image::Rgba([(x % 7 * 30) as u8, (y * 2) as u8, 90, 255]);
+        }
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgba8(sample)
+            .write_to(&mut std::io::Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+
+        let (stored, format) = encode_image_for_storage(png.clone(), "png", 85);
+        assert_eq!((stored, format), (png.clone(), ImageFormat::Png));
+
+        let (stored, format) = encode_image_for_storage(png.clone(), "webp_lossless", 85);
+        assert_eq!(format, ImageFormat::WebP);
+        assert!(stored.len() < png.len());
+
+        // 结果不比原图小时保留原 PNG；无论哪种情况格式标记与内容一致且尺寸不变
+        for storage_format in ["png_optimized", "webp_lossless", "jpeg"] {
+            let (stored, format) = encode_image_for_storage(png.clone(), storage_format, 85);
+            assert_eq!(image::guess_format(&stored).unwrap(), format);
+            assert!(stored.len() <= png.len());
+            let img = image::load_from_memory(&stored).unwrap();
+            assert_eq!((img.width(), img.height()), (200, 120));
+        }
 
-    // 验证文件存在
-    let path = std::path::PathBuf::from(&file_path);
-    if !path.exists() {
-        return Err(format!("文件不存在: {}", file_path));
+        let unknown = b"not an image".to_vec();
+        assert_eq!(encode_image_for_storage(unknown.clone(), "jpeg", 85), (unknown, ImageFormat::Png));
     }
 
-    // 验证是文件而不是目录
-    if path.is_dir() {
-        return Err("无法读取目录内容".to_string());
+    // 内存数据库，表结构由启动时的迁移代码创建，与真实数据库保持一致
+    async fn memory_pool() -> sqlx::SqlitePool {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::migrate_schema(&pool, AppSettings::default().list_preview_chars).await.unwrap();
+        pool
     }
 
-    // 检查文件大小（限制为 10MB）
-    let metadata = std::fs::metadata(&path)
-        .map_err(|e| format!("无法获取文件元数据: {}", e))?;
-    
-    if metadata.len() > 10 * 1024 * 1024 {
-        return Err("文件过大（超过10MB），无法预览".to_string());
+    #[tokio::test]
+    async fn lan_revoke_only_deletes_items_from_the_original_sender() {
+        let pool = memory_pool().await;
+        sqlx::query(
+            "INSERT INTO clipboard_history (id, content, type, timestamp, is_favorite, metadata) VALUES
+             (1, 'secret', 'text', '2024-01-01T00:00:00.000Z', 0, '{\"lan_message_id\":\"m1\",\"lan_origin\":\"alice\"}'),
+             (2, 'secret', 'text', '2024-01-01T00:00:00.000Z', 1, '{\"lan_message_id\":\"m1\",\"lan_origin\":\"alice\"}'),
+             (3, 'other', 'text', '2024-01-01T00:00:00.000Z', 0, '{\"lan_message_id\":\"m2\",\"lan_origin\":\"bob\"}'),
+             (4, 'local', 'text', '2024-01-01T00:00:00.000Z', 0, 'not json')"
+        )
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        // 其他成员冒充不了原发送者
+        assert!(delete_lan_items_in_pool(&pool, "m2", "alice").await.unwrap().is_empty());
+        // 收藏的条目保留
+        assert_eq!(delete_lan_items_in_pool(&pool, "m1", "alice").await.unwrap(), vec![1]);
+
+        let remaining: Vec<i64> = sqlx::query_scalar("SELECT id FROM clipboard_history ORDER BY id")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(remaining, vec![2, 3, 4]);
     }
 
-    // 读取文件内容
-    let content = std::fs::read_to_string(&path)
-        .map_err(|e| format!("读取文件失败: {}", e))?;
+    #[tokio::test]
+    async fn old_image_data_hashes_are_cleared_once() {
+        let pool = memory_pool().await;
+        let dir = tempfile::tempdir().unwrap();
+        let image_file = dir.path().join("old.png");
+        std::fs::write(&image_file, b"image bytes").unwrap();
+        let image_path = image_file.to_string_lossy().to_string();
 
-    tracing::info!("✅ 文本文件读取成功，大小: {} 字节，耗时: {:?}",
-        content.len(), start.elapsed());
+        sqlx::query(
+            "INSERT INTO clipboard_history (id, content, type, timestamp, image_path, data_hash, content_hash) VALUES
+             (1, ?, 'image', '2024-01-01T00:00:00.000Z', ?, 'old-image-hash', NULL),
+             (2, 'missing.png', 'image', '2024-01-01T00:00:00.000Z', 'missing.png', 'old-image-hash-2', 'kept'),
+             (3, 'text', 'text', '2024-01-01T00:00:00.000Z', NULL, 'text-hash', NULL)"
+        )
+            .bind(&image_path)
+            .bind(&image_path)
+            .execute(&pool)
+            .await
+            .unwrap();
+        // 模拟迁移前的旧数据库
+        sqlx::query("PRAGMA user_version = 0").execute(&pool).await.unwrap();
 
-    Ok(content)
-}
+        assert_eq!(migrate_image_data_hashes(&pool).await.unwrap(), 2);
+        let rows: Vec<(i64, Option<String>, Option<String>)> = sqlx::query_as("SELECT id, data_hash, content_hash FROM clipboard_history ORDER BY id")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(rows, vec![
+            (1, None, Some(compute_bytes_hash(b"image bytes"))),
+            (2, None, Some("kept".to_string())),
+            (3, Some("text-hash".to_string()), None),
+        ]);
+
+        // 迁移后按新规则写入的图片哈希不再被清空
+        sqlx::query("UPDATE clipboard_history SET data_hash = 'new-hash' WHERE id = 1").execute(&pool).await.unwrap();
+        assert_eq!(migrate_image_data_hashes(&pool).await.unwrap(), 0);
+        let hash: Option<String> = sqlx::query_scalar("SELECT data_hash FROM clipboard_history WHERE id = 1").fetch_one(&pool).await.unwrap();
+        assert_eq!(hash.as_deref(), Some("new-hash"));
+    }
 
-// ==================== 数据导入导出 ====================
+    #[tokio::test]
+    async fn image_cleanup_estimate_uses_per_type_retention() {
+        let pool = memory_pool().await;
+        let dir = tempfile::tempdir().unwrap();
+        let write_image = |name: &str, len: usize| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, vec![0u8; len]).unwrap();
+            path.to_string_lossy().to_string()
+        };
+        let old_image = write_image("old.png", 10);
+        let new_image = write_image("new.png", 20);
+        let favorite_image = write_image("favorite.png", 40);
+        let ago = |hours: i64| (chrono::Utc::now() - chrono::Duration::hours(hours)).to_rfc3339();
+
+        for (id, item_type, timestamp, is_favorite, image_path) in [
+            (1, "image", ago(72), 0, Some(&old_image)),
+            (2, "text", ago(72), 0, None),
+            (3, "image", ago(1), 0, Some(&new_image)),
+            (4, "image", ago(24 * 40), 1, Some(&favorite_image)),
+        ] {
+            sqlx::query("INSERT INTO clipboard_history (id, content, type, timestamp, is_favorite, image_path) VALUES (?, 'x', ?, ?, ?, ?)")
+                .bind(id)
+                .bind(item_type)
+                .bind(timestamp)
+                .bind(is_favorite)
+                .bind(image_path)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
 
-#[tauri::command]
-pub async fn export_data(app: AppHandle, export_path: String) -> Result<(), String> {
-    tracing::info!("开始导出数据到: {}", export_path);
+        // 图片只保留 1 天，其他类型保留 30 天：3 天前的图片按时间清理，3 天前的文本保留
+        let settings = AppSettings {
+            max_history_items: 1,
+            max_history_time: 30,
+            retention_by_type: [("image".to_string(), 1)].into_iter().collect(),
+            ..AppSettings::default()
+        };
+        let estimate = estimate_image_cleanup_in_pool(&pool, &settings).await.unwrap();
+        assert_eq!((estimate.time_files, estimate.time_bytes), (1, 10));
+        // 按数量清理只在剩余的文本和新图片中删除最旧的一条（文本），不重复计入已过期的图片
+        assert_eq!((estimate.count_files, estimate.count_bytes), (0, 0));
+
+        // 与实际清理使用同一筛选条件
+        let cutoffs = retention_cutoffs(&settings);
+        let (image_type, image_days, image_cutoff) = &cutoffs[0];
+        assert_eq!((*image_type, *image_days), (Some("image"), 1));
+        let (deleted, images) = delete_items_older_than(&pool, image_cutoff, Some("image"), &[]).await.unwrap();
+        assert_eq!((deleted, images), (1, vec![old_image.clone()]));
+        assert_eq!(cutoffs[1].0, None);
+        assert_eq!(delete_items_older_than(&pool, &cutoffs[1].2, None, &["image"]).await.unwrap().0, 0);
+    }
 
-    let export_path = PathBuf::from(&export_path);
-    let file = fs::File::create(&export_path)
-        .map_err(|e| format!("无法创建导出文件: {}", e))?;
+    #[test]
+    fn idle_compaction_waits_for_configured_minutes() {
+        let minutes = |m: u64| std::time::Duration::from_secs(m * 60);
+        assert!(!idle_compaction_due(minutes(9), 10));
+        assert!(idle_compaction_due(minutes(10), 10));
+        assert!(idle_compaction_due(minutes(60), 10));
+        // 0 表示关闭空闲压缩
+        assert!(!idle_compaction_due(minutes(60), 0));
+
+        // 压缩后直到下次活动前不再重复执行
+        let activity = ActivityState::default();
+        activity.compacted.store(true, std::sync::atomic::Ordering::Relaxed);
+        activity.mark();
+        assert!(!activity.compacted.load(std::sync::atomic::Ordering::Relaxed));
+        assert!(activity.idle_for() < minutes(1));
+    }
 
-    let mut zip = ZipWriter::new(file);
-    let options = SimpleFileOptions::default()
-        .compression_method(zip::CompressionMethod::Deflated);
+    #[tokio::test]
+    async fn wal_checkpoint_truncates_wal_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("clipboard.db");
+        let options = sqlx::sqlite::SqliteConnectOptions::new()
+            .filename(&db_path)
+            .create_if_missing(true)
+            .journal_mode(sqlx::sqlite::SqliteJournalMode::Wal);
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .unwrap();
+        crate::migrate_schema(&pool, AppSettings::default().list_preview_chars).await.unwrap();
+        for i in 0..20 {
+            sqlx::query("INSERT INTO clipboard_history (content, type, timestamp) VALUES (?, 'text', '2024-01-01T00:00:00.000Z')")
+                .bind(format!("item {}", i))
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        let wal_path = dir.path().join("clipboard.db-wal");
+        assert!(fs::metadata(&wal_path).unwrap().len() > 0);
 
-    // 1. 写入数据库文件
-    let app_data_dir = app.path().app_data_dir()
-        .map_err(|e| format!("无法获取应用数据目录: {}", e))?;
-    let db_path = app_data_dir.join("clipboard.db");
+        checkpoint_wal(&pool).await.unwrap();
+        assert_eq!(fs::metadata(&wal_path).unwrap().len(), 0);
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM clipboard_history").fetch_one(&pool).await.unwrap();
+        assert_eq!(count, 20);
+    }
 
-    if db_path.exists() {
-        let db_bytes = fs::read(&db_path)
-            .map_err(|e| format!("无法读取数据库文件: {}", e))?;
-        zip.start_file("clipboard.db", options)
-            .map_err(|e| format!("写入数据库到zip失败: {}", e))?;
-        zip.write_all(&db_bytes)
-            .map_err(|e| format!("写入数据库数据失败: {}", e))?;
-        tracing::info!("已写入数据库文件 ({} 字节)", db_bytes.len());
-    } else {
-        return Err("数据库文件不存在".to_string());
+    #[tokio::test]
+    async fn schema_check_reports_missing_columns() {
+        let pool = memory_pool().await;
+        assert!(describe_table(&pool, "clipboard_history", HISTORY_SCHEMA_COLUMNS).await.unwrap().missing_columns.is_empty());
+        assert!(describe_table(&pool, "groups", GROUP_COLUMNS).await.unwrap().missing_columns.is_empty());
+
+        // 旧版本数据库缺少后来迁移添加的列
+        sqlx::query("CREATE TABLE legacy_history (id INTEGER PRIMARY KEY, content TEXT, type TEXT, timestamp TEXT)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let missing = describe_table(&pool, "legacy_history", HISTORY_SCHEMA_COLUMNS).await.unwrap().missing_columns;
+        assert!(missing.contains(&"preview".to_string()));
+        assert!(missing.contains(&"content_hash".to_string()));
+        assert!(!missing.contains(&"content".to_string()));
     }
 
-    // 2. 写入图片文件 - 从数据库查询所有图片路径
-    if let Some(db_state) = app.try_state::<Mutex<DatabaseState>>() {
-        let db_guard = db_state.lock().await;
-        let pool = &db_guard.pool;
+    #[tokio::test]
+    async fn group_counts_follow_item_changes() {
+        let pool = memory_pool().await;
+        for trigger in ["trg_group_count_insert", "trg_group_count_delete", "trg_group_count_update"] {
+            sqlx::query(&format!("DROP TRIGGER {}", trigger)).execute(&pool).await.unwrap();
+        }
+        sqlx::query("INSERT INTO groups (id, name, created_at) VALUES (1, 'a', '2024-01-01T00:00:00.000Z'), (2, 'b', '2024-01-01T00:00:00.000Z')")
+            .execute(&pool).await.unwrap();
+        // 创建触发器之前已有的关联会在初始化时计入
+        sqlx::query("INSERT INTO clipboard_history (id, content, type, timestamp, group_id) VALUES (1, 'a', 'text', '2024-01-01T00:00:00.000Z', 1)")
+            .execute(&pool).await.unwrap();
+
+        ensure_group_count_triggers(&pool).await.unwrap();
+        ensure_group_count_triggers(&pool).await.unwrap();
+
+        let counts = |pool: sqlx::SqlitePool| async move {
+            sqlx::query_scalar::<_, i64>("SELECT item_count FROM groups ORDER BY id")
+                .fetch_all(&pool).await.unwrap()
+        };
+        assert_eq!(counts(pool.clone()).await, vec![1, 0]);
 
-        let image_rows = sqlx::query("SELECT DISTINCT image_path FROM clipboard_history WHERE image_path IS NOT NULL")
-            .fetch_all(pool)
+        sqlx::query(
+            "INSERT INTO clipboard_history (id, content, type, timestamp, group_id) VALUES
+             (2, 'b', 'text', '2024-01-02T00:00:00.000Z', 1), (3, 'c', 'text', '2024-01-03T00:00:00.000Z', NULL)"
+        )
+            .execute(&pool).await.unwrap();
+        assert_eq!(counts(pool.clone()).await, vec![2, 0]);
+
+        sqlx::query("UPDATE clipboard_history SET group_id = 2 WHERE id = 1").execute(&pool).await.unwrap();
+        sqlx::query("UPDATE clipboard_history SET group_id = 2 WHERE id = 3").execute(&pool).await.unwrap();
+        assert_eq!(counts(pool.clone()).await, vec![1, 2]);
+
+        sqlx::query("DELETE FROM clipboard_history WHERE id IN (2, 3)").execute(&pool).await.unwrap();
+        assert_eq!(counts(pool.clone()).await, vec![0, 1]);
+
+        sqlx::query("UPDATE groups SET item_count = 42").execute(&pool).await.unwrap();
+        let fixed = sqlx::query(RECALCULATE_GROUP_COUNTS_SQL).execute(&pool).await.unwrap().rows_affected();
+        assert_eq!(fixed, 2);
+        assert_eq!(counts(pool.clone()).await, vec![0, 1]);
+    }
+
+    #[tokio::test]
+    async fn locked_database_writes_are_retried() {
+        use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+
+        let dir = tempfile::tempdir().unwrap();
+        // 关闭 busy timeout，让锁冲突立即返回，便于模拟并发争用
+        let options = SqliteConnectOptions::new()
+            .filename(dir.path().join("retry.db"))
+            .create_if_missing(true)
+            .busy_timeout(std::time::Duration::ZERO);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(2)
+            .connect_with(options)
             .await
-            .unwrap_or_default();
+            .unwrap();
+        sqlx::query("CREATE TABLE items (value INTEGER)").execute(&pool).await.unwrap();
 
-        let mut image_count = 0u64;
-        let mut image_size = 0u64;
+        // 另一个连接持有写锁
+        let mut locker = pool.acquire().await.unwrap();
+        sqlx::query("BEGIN IMMEDIATE").execute(&mut *locker).await.unwrap();
 
-        for row in &image_rows {
-            if let Ok(Some(image_path)) = row.try_get::<Option<String>, &str>("image_path") {
-                let path = PathBuf::from(&image_path);
-                if path.exists() && path.is_file() {
-                    if let Some(filename) = path.file_name() {
-                        let zip_path = format!("images/{}", filename.to_string_lossy());
-                        match fs::read(&path) {
-                            Ok(file_bytes) => {
-                                if let Err(e) = zip.start_file(&zip_path, options) {
-                                    tracing::warn!("写入图片到zip失败: {}", e);
-                                    continue;
-                                }
-                                if let Err(e) = zip.write_all(&file_bytes) {
-                                    tracing::warn!("写入图片数据失败: {}", e);
-                                    continue;
-                                }
-                                image_count += 1;
-                                image_size += file_bytes.len() as u64;
-                            }
-                            Err(e) => {
-                                tracing::warn!("无法读取图片 {}: {}", image_path, e);
-                            }
-                        }
-                    }
-                } else {
-                    tracing::warn!("图片文件不存在，跳过: {}", image_path);
-                }
-            }
-        }
-        drop(db_guard);
-        tracing::info!("已写入 {} 个图片文件 ({} 字节)", image_count, image_size);
-    } else {
-        tracing::warn!("无法获取数据库状态，跳过图片导出");
+        let err = sqlx::query("INSERT INTO items (value) VALUES (1)")
+            .execute(&pool)
+            .await
+            .unwrap_err();
+        assert!(is_database_locked(&err));
+
+        let release = tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(80)).await;
+            sqlx::query("COMMIT").execute(&mut *locker).await.unwrap();
+        });
+
+        let result = with_db_retry("测试写入", || {
+            sqlx::query("INSERT INTO items (value) VALUES (2)").execute(&pool)
+        })
+        .await;
+        release.await.unwrap();
+        assert!(result.is_ok());
+
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM items").fetch_one(&pool).await.unwrap();
+        assert_eq!(count, 1);
     }
 
-    zip.finish().map_err(|e| format!("完成zip写入失败: {}", e))?;
+    #[tokio::test]
+    async fn non_lock_errors_are_not_retried() {
+        assert!(!is_database_locked(&sqlx::Error::RowNotFound));
 
-    tracing::info!("✅ 数据导出完成");
-    Ok(())
-}
+        let mut calls = 0;
+        let result: Result<(), sqlx::Error> = with_db_retry("测试", || {
+            calls += 1;
+            async { Err(sqlx::Error::RowNotFound) }
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
 
-/// 从 zip 中同步提取所有需要的数据（避免 ZipFile 跨 await 的 Send 问题）
-struct ExtractedZipData {
-    db_bytes: Vec<u8>,
-    images: Vec<(String, Vec<u8>)>, // (filename, bytes)
-}
+    #[test]
+    fn capture_formats_are_normalized() {
+        let formats = vec![
+            "Text".to_string(),
+            "rtf".to_string(),
+            "files".to_string(),
+            " text ".to_string(),
+            "html".to_string(),
+        ];
+        assert_eq!(normalize_capture_formats(&formats), vec!["text", "files", "html"]);
+        assert!(normalize_capture_formats(&[]).is_empty());
+    }
 
-fn extract_zip_data(import_path: &PathBuf) -> Result<ExtractedZipData, String> {
-    let file = fs::File::open(import_path)
-        .map_err(|e| format!("无法打开导入文件: {}", e))?;
-    let mut archive = ZipArchive::new(file)
-        .map_err(|e| format!("无法读取zip文件: {}", e))?;
+    #[test]
+    fn ui_state_entries_are_merged_and_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(UI_STATE_FILE);
+        assert!(read_ui_state(&path).is_empty());
 
-    // 提取数据库文件
-    let mut db_file = archive.by_name("clipboard.db")
-        .map_err(|e| format!("zip中未找到数据库文件: {}", e))?;
-    let mut db_bytes = Vec::new();
-    std::io::Read::read_to_end(&mut db_file, &mut db_bytes)
-        .map_err(|e| format!("无法读取数据库数据: {}", e))?;
-    drop(db_file);
+        write_ui_state_entry(&path, "group", serde_json::json!(3)).unwrap();
+        write_ui_state_entry(&path, "filter", serde_json::json!({ "type": "text" })).unwrap();
+        let state = read_ui_state(&path);
+        assert_eq!(state.get("group"), Some(&serde_json::json!(3)));
+        assert_eq!(state.get("filter"), Some(&serde_json::json!({ "type": "text" })));
 
-    // 提取所有图片文件
-    let mut images = Vec::new();
-    for i in 0..archive.len() {
-        let mut entry = archive.by_index(i)
-            .map_err(|e| format!("读取zip条目失败: {}", e))?;
-        let entry_path = entry.name().to_string();
+        write_ui_state_entry(&path, "group", serde_json::Value::Null).unwrap();
+        assert!(!read_ui_state(&path).contains_key("group"));
 
-        if entry_path.starts_with("images/") && !entry.is_dir() {
-            if let Some(filename) = std::path::Path::new(&entry_path).file_name() {
-                let mut buf = Vec::new();
-                std::io::Read::read_to_end(&mut entry, &mut buf)
-                    .map_err(|e| format!("无法读取图片 {}: {}", entry_path, e))?;
-                images.push((filename.to_string_lossy().to_string(), buf));
-            }
-        }
+        fs::write(&path, "not json").unwrap();
+        assert!(read_ui_state(&path).is_empty());
+    }
+
+    #[tokio::test]
+    async fn oldest_favorites_beyond_cap_are_unfavorited() {
+        let pool = memory_pool().await;
+        sqlx::query(
+            "INSERT INTO clipboard_history (id, content, type, timestamp, is_favorite) VALUES
+             (1, 'a', 'text', '2024-01-01T00:00:00Z', 1), (2, 'b', 'text', '2024-01-02T00:00:00Z', 1),
+             (3, 'c', 'text', '2024-01-03T00:00:00Z', 0), (4, 'd', 'text', '2024-01-04T00:00:00Z', 1)",
+        )
+        .execute(&pool).await.unwrap();
+
+        assert_eq!(enforce_favorite_cap(&pool, 0, "unfavorite").await.unwrap(), 0);
+        assert_eq!(enforce_favorite_cap(&pool, 1, "keep").await.unwrap(), 0);
+        assert_eq!(enforce_favorite_cap(&pool, 2, "unfavorite").await.unwrap(), 1);
+
+        let favorites = sqlx::query_scalar::<_, i64>("SELECT id FROM clipboard_history WHERE is_favorite = 1 ORDER BY id")
+            .fetch_all(&pool).await.unwrap();
+        assert_eq!(favorites, vec![2, 4]);
+    }
+
+    #[test]
+    fn file_uris_are_converted_to_local_paths() {
+        assert_eq!(local_file_path("/home/me/a.txt"), PathBuf::from("/home/me/a.txt"));
+        assert_eq!(local_file_path("file:///home/me/My%20Notes/%E6%96%87.txt"), PathBuf::from("/home/me/My Notes/文.txt"));
+        assert_eq!(local_file_path("file://localhost/tmp/a.txt"), PathBuf::from("/tmp/a.txt"));
+        assert_eq!(local_file_path("file:///C:/Users/me/a.txt"), PathBuf::from("C:/Users/me/a.txt"));
+        // 不完整的转义保持原样
+        assert_eq!(local_file_path("file:///tmp/100%"), PathBuf::from("/tmp/100%"));
+    }
+
+    #[test]
+    fn html_title_survives_non_ascii_text_before_it() {
+        // İ 小写后字节长度会变化，不能用 to_lowercase 的偏移切原文
+        let html = "<!-- İİİİ --><HTML><head><TITLE> 发布 &amp; 部署\n 说明 </TITLE></head>";
+        assert_eq!(extract_html_title(html).as_deref(), Some("发布 & 部署 说明"));
+        assert_eq!(extract_html_title("<title>  </title>"), None);
+        assert_eq!(extract_html_title("<p>no title</p>"), None);
+    }
+
+    #[test]
+    fn content_hash_strips_data_url_prefix() {
+        // SHA-256("hello") 的小写十六进制
+        assert_eq!(compute_content_hash("hello"), "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824");
+        assert_eq!(compute_content_hash("data:image/png;base64,aGVsbG8="), compute_content_hash("aGVsbG8="));
+        // 只去掉 data URL 的 base64 前缀，普通文本中的 "base64," 保持原样
+        assert_ne!(compute_content_hash("see base64,aGVsbG8="), compute_content_hash("aGVsbG8="));
+        assert_ne!(compute_content_hash("data:text/plain,hello"), compute_content_hash("hello"));
+    }
+
+    #[tokio::test]
+    async fn replacements_update_content_hash() {
+        let pool = memory_pool().await;
+        sqlx::query("INSERT INTO clipboard_history (id, content, type, timestamp, data_hash) VALUES (1, 'old', 'text', '2024-01-01T00:00:00.000Z', ?)")
+            .bind(compute_content_hash("old"))
+            .execute(&pool).await.unwrap();
+
+        apply_replacements_in_transaction(&pool, &[(1, "new".to_string())]).await.unwrap();
+        let row: (String, String) = sqlx::query_as("SELECT content, data_hash FROM clipboard_history WHERE id = 1")
+            .fetch_one(&pool).await.unwrap();
+        assert_eq!(row, ("new".to_string(), compute_content_hash("new")));
+    }
+
+    #[test]
+    fn content_matcher_replaces_plain_and_regex() {
+        let plain = ContentMatcher::new("old.example.com", false).unwrap();
+        assert_eq!(
+            plain.replace("see https://old.example.com/a", "new.example.com").as_deref(),
+            Some("see https://new.example.com/a")
+        );
+        assert_eq!(plain.replace("unrelated", "new.example.com"), None);
+        // 正则模式下 "." 匹配任意字符，普通模式按字面匹配
+        assert_eq!(plain.replace("oldxexample.com", "new"), None);
+
+        let regex = ContentMatcher::new(r"v(\d+)\.(\d+)", true).unwrap();
+        assert_eq!(regex.replace("v1.2 and v3.4", "v$2.$1").as_deref(), Some("v2.1 and v4.3"));
+        // 替换结果与原文相同时不算修改
+        assert_eq!(regex.replace("v1.1", "v$1.$2"), None);
+
+        assert!(ContentMatcher::new("", false).is_err());
+        assert!(ContentMatcher::new("(", true).is_err());
+    }
+
+    #[test]
+    fn paste_metrics_are_summarized() {
+        let attempt = |target_app: Option<&str>, duration_ms: u64, error: Option<&str>| PasteAttempt {
+            command: "smart_paste_to_app".to_string(),
+            target_app: target_app.map(str::to_string),
+            duration_ms,
+            error: error.map(str::to_string),
+        };
+        let attempts = vec![
+            attempt(Some("Code"), 100, None),
+            attempt(Some("Code"), 300, Some("粘贴操作失败: timeout")),
+            attempt(Some("Slack"), 50, None),
+            attempt(None, 150, Some("粘贴操作失败: timeout")),
+        ];
+        let metrics = summarize_paste_attempts(&attempts);
+        assert_eq!((metrics.total, metrics.succeeded, metrics.failed), (4, 2, 2));
+        assert_eq!(metrics.success_rate, 0.5);
+        assert_eq!((metrics.avg_ms, metrics.p50_ms, metrics.p90_ms, metrics.max_ms), (150, 100, 300, 300));
+        assert_eq!(
+            metrics.by_app[0],
+            AppPasteMetrics { app_name: "Code".to_string(), count: 2, failed: 1, avg_ms: 200 }
+        );
+        assert_eq!(
+            metrics.failure_reasons,
+            vec![PasteFailureReason { reason: "粘贴操作失败: timeout".to_string(), count: 2 }]
+        );
+
+        let empty = summarize_paste_attempts(&[]);
+        assert_eq!((empty.total, empty.p99_ms, empty.success_rate), (0, 0, 0.0));
+    }
+
+    #[test]
+    fn auto_start_names_fall_back_to_defaults() {
+        let names: AutoStartNames = serde_json::from_str(r#"{ "display_name": "Clipboard Manager Dev", "registry_name": "  " }"#).unwrap();
+        let resolved = names.resolved();
+        assert_eq!(resolved.display_name, "Clipboard Manager Dev");
+        assert_eq!(resolved.registry_name, AutoStartNames::default().registry_name);
+        assert_eq!(resolved.bundle_id, AutoStartNames::default().bundle_id);
+
+        // 旧版本的设置文件没有该字段时使用默认名称
+        let settings: AppSettings = serde_json::from_value(serde_json::json!({
+            "max_history_items": 100,
+            "max_history_time": 30,
+            "hotkey": "Ctrl+Shift+V",
+            "auto_start": false
+        }))
+        .unwrap();
+        assert_eq!(settings.auto_start_names, AutoStartNames::default());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn linux_desktop_entry_quotes_exec() {
+        let entry = linux_autostart_desktop_entry("Clipboard Manager", std::path::Path::new("/opt/My Apps/clipboard-manager"));
+        assert!(entry.contains("Exec=\"/opt/My Apps/clipboard-manager\"\n"));
+        assert!(entry.contains("TryExec=/opt/My Apps/clipboard-manager\n"));
+        assert!(entry.contains("StartupWMClass=clipboard-manager\n"));
+        assert!(entry.contains("Categories=Utility;\n"));
+
+        assert_eq!(desktop_exec_arg("/usr/bin/clipboard-manager"), "/usr/bin/clipboard-manager");
+        assert_eq!(desktop_exec_arg("/tmp/100%/a$b"), "\"/tmp/100%%/a\\\\$b\"");
+    }
+
+    #[test]
+    fn text_diff_reports_line_changes() {
+        let diff = diff_text("a\nb\nc\n", "a\nB\nc\nd");
+        assert_eq!((diff.added, diff.removed, diff.unchanged), (2, 1, 2));
+        let kinds: Vec<_> = diff.lines.iter().map(|line| (line.kind.as_str(), line.content.as_str())).collect();
+        assert_eq!(
+            kinds,
+            vec![("unchanged", "a"), ("removed", "b"), ("added", "B"), ("unchanged", "c"), ("added", "d")]
+        );
+        assert_eq!((diff.lines[1].old_line, diff.lines[1].new_line), (Some(2), None));
+        assert_eq!((diff.lines[4].old_line, diff.lines[4].new_line), (None, Some(4)));
     }
 
-    Ok(ExtractedZipData { db_bytes, images })
-}
+    #[test]
+    fn external_export_maps_items_and_settings() {
+        let mut settings = AppSettings::default();
+        let export = parse_external_export(
+            &serde_json::json!({
+                "settings": { "history_limit": 250, "shortcut": "Alt+V", "theme": "neon", "sync_url": "x" },
+                "items": [
+                    "plain snippet",
+                    { "text": "with meta", "title": "greeting", "favorite": true, "created_at": 1700000000 },
+                    { "type": "image", "content": "a.png" },
+                    { "content": "" },
+                    42
+                ]
+            }),
+            &mut settings,
+        )
+        .unwrap();
+
+        assert_eq!(settings.max_history_items, 250);
+        assert_eq!(settings.hotkey, "Alt+V");
+        assert_eq!(settings.theme, AppSettings::default().theme);
+        assert_eq!(export.imported_settings, vec!["history_limit -> max_history_items", "shortcut -> hotkey"]);
+        assert_eq!(export.ignored_settings, vec!["sync_url", "theme"]);
+
+        assert_eq!(export.items.len(), 2);
+        assert_eq!(export.items[0].content, "plain snippet");
+        assert_eq!(export.items[1].note.as_deref(), Some("greeting"));
+        assert!(export.items[1].is_favorite);
+        assert_eq!(export.items[1].timestamp.as_deref(), Some("2023-11-14T22:13:20.000Z"));
+        assert_eq!(export.skipped_items.len(), 3);
+        assert!(export.skipped_items[0].starts_with("第 3 条"));
+
+        assert!(parse_external_export(&serde_json::json!("text"), &mut settings).is_err());
+        assert!(parse_external_export(&serde_json::json!([]), &mut settings).is_err());
+    }
 
-#[tauri::command]
-pub async fn import_data(app: AppHandle, import_path: String, mode: String) -> Result<(), String> {
-    tracing::info!("开始导入数据: {} (模式: {})", import_path, mode);
+    #[test]
+    fn trim_on_capture_modes() {
+        assert_eq!(trim_captured_text("  a b \n", "both"), "a b");
+        assert_eq!(trim_captured_text("  a b \n", "leading"), "a b \n");
+        assert_eq!(trim_captured_text("  a b \n", "trailing"), "  a b");
+        assert_eq!(trim_captured_text("  a b \n", "off"), "  a b \n");
+        // 只有空白的文本保持原样
+        assert_eq!(trim_captured_text(" \t\n", "both"), " \t\n");
+
+        let settings = AppSettings {
+            normalize_line_endings: "lf".to_string(),
+            trim_on_capture: "both".to_string(),
+            ..AppSettings::default()
+        };
+        assert_eq!(prepare_text_for_storage("\r\n a\r\nb \r\n", &settings), "a\nb");
+        assert_eq!(
+            compute_content_hash(&prepare_text_for_storage(" a\nb", &settings)),
+            compute_content_hash(&prepare_text_for_storage("a\nb\n", &settings))
+        );
+    }
 
-    let import_path = PathBuf::from(&import_path);
+    #[test]
+    fn log_bundle_redacts_quoted_content() {
+        let line = "INFO 更新条目备注: ID=3, note='secret note' path=/home/alice/app";
+        assert_eq!(
+            redact_log_line(line, 0, Some("/home/alice")),
+            "INFO 更新条目备注: ID=3, note='<已隐藏 11 字符>' path=~/app"
+        );
+        assert_eq!(
+            redact_log_line(line, 3, None),
+            "INFO 更新条目备注: ID=3, note='sec…(共 11 字符)' path=/home/alice/app"
+        );
+        // 未闭合的引号保持原样
+        assert_eq!(redact_log_line("it's fine", 0, None), "it's fine");
+    }
 
-    // 第一步：同步提取所有 zip 数据（不跨 await）
-    let zip_data = tokio::task::spawn_blocking(move || extract_zip_data(&import_path))
-        .await
-        .map_err(|e| format!("提取zip数据失败: {}", e))??;
+    #[test]
+    fn image_usage_groups_by_source_app() {
+        let rows = vec![
+            (Some("Snipaste".to_string()), "a.png".to_string()),
+            (Some("Snipaste".to_string()), "b.png".to_string()),
+            (Some("Snipaste".to_string()), "a.png".to_string()),
+            (Some("Chrome".to_string()), "c.png".to_string()),
+            (None, "missing.png".to_string()),
+            (Some(" ".to_string()), "d.png".to_string()),
+        ];
+        let sizes: std::collections::HashMap<&str, u64> = [("a.png", 300), ("b.png", 200), ("c.png", 900), ("d.png", 10)].into();
+        let usage = summarize_image_usage(rows, |path| sizes.get(path).copied());
+
+        let summary: Vec<_> = usage.iter().map(|entry| (entry.source_app_name.as_deref(), entry.items, entry.files, entry.bytes)).collect();
+        assert_eq!(
+            summary,
+            vec![(Some("Chrome"), 1, 1, 900), (Some("Snipaste"), 3, 2, 500), (None, 2, 1, 10)]
+        );
+    }
 
-    // 第二步：写入临时数据库文件
-    let temp_dir = tempfile::tempdir()
-        .map_err(|e| format!("无法创建临时目录: {}", e))?;
-    let temp_db_path = temp_dir.path().join("import.db");
-    fs::write(&temp_db_path, &zip_data.db_bytes)
-        .map_err(|e| format!("无法写入临时数据库文件: {}", e))?;
+    #[tokio::test]
+    async fn dangling_group_references_are_cleared() {
+        let pool = memory_pool().await;
+        sqlx::query("INSERT INTO groups (id, name, created_at) VALUES (1, 'a', '2024-01-01T00:00:00.000Z')")
+            .execute(&pool).await.unwrap();
+        sqlx::query(
+            "INSERT INTO clipboard_history (id, content, type, timestamp, group_id) VALUES
+             (1, 'a', 'text', '2024-01-01T00:00:00.000Z', 1), (2, 'b', 'text', '2024-01-02T00:00:00.000Z', 7),
+             (3, 'c', 'text', '2024-01-03T00:00:00.000Z', NULL), (4, 'd', 'text', '2024-01-04T00:00:00.000Z', 7)"
+        ).execute(&pool).await.unwrap();
+
+        assert_eq!(repair_group_references_in_transaction(&pool).await.unwrap(), vec![2, 4]);
+        let group_ids: Vec<Option<i64>> = sqlx::query_scalar("SELECT group_id FROM clipboard_history ORDER BY id")
+            .fetch_all(&pool).await.unwrap();
+        assert_eq!(group_ids, vec![Some(1), None, None, None]);
+        assert!(repair_group_references_in_transaction(&pool).await.unwrap().is_empty());
+    }
 
-    // 第三步：连接临时数据库并读取数据
-    let temp_options = sqlx::sqlite::SqliteConnectOptions::new()
-        .filename(&temp_db_path)
-        .read_only(true);
-    let temp_pool = sqlx::SqlitePool::connect_with(temp_options)
-        .await
-        .map_err(|e| format!("无法连接临时数据库: {}", e))?;
+    #[tokio::test]
+    async fn items_past_expiry_are_deleted() {
+        let pool = memory_pool().await;
+        sqlx::query(
+            "INSERT INTO clipboard_history (id, content, type, timestamp, is_favorite, image_path, expires_at) VALUES
+             (1, 'a.png', 'image', '2023-12-01T00:00:00.000Z', 0, 'a.png', '2024-01-01T00:00:00.000Z'),
+             (2, 'b', 'text', '2023-12-01T00:00:00.000Z', 1, NULL, '2024-01-01T00:00:00.000Z'),
+             (3, 'c', 'text', '2023-12-01T00:00:00.000Z', 0, NULL, '2024-06-01T00:00:00.000Z'),
+             (4, 'd', 'text', '2023-12-01T00:00:00.000Z', 0, NULL, NULL)"
+        ).execute(&pool).await.unwrap();
+
+        let now = "2024-03-01T00:00:00.000Z";
+        let (ids, images) = delete_items_past_expiry(&pool, now, true).await.unwrap();
+        assert_eq!(ids, vec![1]);
+        assert_eq!(images, vec!["a.png".to_string()]);
+
+        let (ids, images) = delete_items_past_expiry(&pool, now, false).await.unwrap();
+        assert_eq!(ids, vec![2]);
+        assert!(images.is_empty());
+
+        let remaining: Vec<i64> = sqlx::query_scalar("SELECT id FROM clipboard_history ORDER BY id")
+            .fetch_all(&pool).await.unwrap();
+        assert_eq!(remaining, vec![3, 4]);
+    }
 
-    // 获取当前数据库连接
-    let db_state = app.try_state::<Mutex<DatabaseState>>()
-        .ok_or("无法访问数据库状态")?;
-    let db_guard = db_state.lock().await;
-    let pool = &db_guard.pool;
+    #[test]
+    fn blacklist_matches_name_or_bundle_id_case_insensitively() {
+        let blacklist = vec!["1Password".to_string(), " com.bitwarden.desktop ".to_string(), "".to_string()];
+        assert!(app_matches_blacklist(&blacklist, "1password", None));
+        assert!(app_matches_blacklist(&blacklist, "Bitwarden", Some("COM.Bitwarden.Desktop")));
+        assert!(!app_matches_blacklist(&blacklist, "Notepad", Some("com.microsoft.notepad")));
+        // 空白的黑名单项不匹配名称为空的未知应用
+        assert!(!app_matches_blacklist(&blacklist, "", None));
+        assert!(!app_matches_blacklist(&[], "1Password", None));
+    }
 
-    let new_images_dir = get_app_images_dir()?;
-    let new_images_dir_str = new_images_dir.to_string_lossy().to_string();
+    #[test]
+    fn settings_validation_rejects_unusable_values() {
+        assert!(AppSettings::default().validate().is_ok());
+
+        let invalid = [
+            AppSettings { max_history_items: 0, ..AppSettings::default() },
+            AppSettings { max_history_time: 0, ..AppSettings::default() },
+            AppSettings { hotkey: "  ".to_string(), ..AppSettings::default() },
+            AppSettings {
+                retention_by_type: [("image".to_string(), 0)].into_iter().collect(),
+                ..AppSettings::default()
+            },
+            AppSettings {
+                retention_by_type: [("files".to_string(), 7)].into_iter().collect(),
+                ..AppSettings::default()
+            },
+            AppSettings { list_preview_chars: LIST_PREVIEW_MIN_CHARS - 1, ..AppSettings::default() },
+            AppSettings { list_preview_chars: LIST_PREVIEW_MAX_CHARS + 1, ..AppSettings::default() },
+            AppSettings { icon_cache_size: 0, ..AppSettings::default() },
+            AppSettings { icon_cache_ttl_hours: MAX_ICON_CACHE_TTL_HOURS + 1, ..AppSettings::default() },
+            AppSettings { db_max_connections: 0, ..AppSettings::default() },
+            AppSettings { db_max_connections: MAX_DB_MAX_CONNECTIONS + 1, ..AppSettings::default() },
+            AppSettings { image_storage_quality: 0, ..AppSettings::default() },
+            AppSettings { image_storage_quality: 101, ..AppSettings::default() },
+            AppSettings { lan_queue_port: 0, ..AppSettings::default() },
+            AppSettings { lan_queue_max_item_mb: 0, ..AppSettings::default() },
+        ];
+        for settings in invalid {
+            assert!(settings.validate().is_err());
+        }
+    }
 
-    if mode == "replace" {
-        // 替换模式：清空现有数据
-        if new_images_dir.exists() {
-            if let Ok(entries) = fs::read_dir(&new_images_dir) {
-                for entry in entries.flatten() {
-                    let _ = fs::remove_file(entry.path());
-                }
+    #[test]
+    fn auto_start_names_reject_hostile_values() {
+        assert!(AutoStartNames::default().validate().is_ok());
+        assert!(AutoStartNames { display_name: String::new(), ..AutoStartNames::default() }.validate().is_ok());
+
+        let hostile = ["../evil", "a/b", "..", "a\\b", "x\" & do shell script \"rm -rf ~", "name\nExec=sh"];
+        for name in hostile {
+            for names in [
+                AutoStartNames { display_name: name.to_string(), ..AutoStartNames::default() },
+                AutoStartNames { registry_name: name.to_string(), ..AutoStartNames::default() },
+                AutoStartNames { bundle_id: name.to_string(), ..AutoStartNames::default() },
+            ] {
+                assert!(names.validate().is_err(), "{:?}", names);
+                let settings = AppSettings { auto_start_names: names, ..AppSettings::default() };
+                assert!(settings.validate().is_err());
             }
         }
+    }
 
-        sqlx::query("DELETE FROM clipboard_history").execute(pool).await
-            .map_err(|e| format!("清空历史记录失败: {}", e))?;
-        sqlx::query("DELETE FROM groups").execute(pool).await
-            .map_err(|e| format!("清空分组失败: {}", e))?;
-        sqlx::query("DELETE FROM sqlite_sequence WHERE name IN ('clipboard_history', 'groups')").execute(pool).await
-            .ok();
+    #[test]
+    fn applescript_strings_are_escaped() {
+        assert_eq!(applescript_string("Clipboard Manager"), "Clipboard Manager");
+        assert_eq!(applescript_string(r#"x" & do shell script "id"#), r#"x\" & do shell script \"id"#);
+        assert_eq!(applescript_string(r"a\b"), r"a\\b");
+    }
 
-        tracing::info!("替换模式：已清空现有数据");
+    #[test]
+    fn old_settings_are_migrated_to_current_version() {
+        let (settings, migrated) = parse_settings(r#"{"max_history_items": 50, "max_history_time": 7, "hotkey": "Ctrl+Alt+V", "auto_start": false}"#).unwrap();
+        assert!(migrated);
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert_eq!(settings.max_history_items, 50);
+        assert!(settings.retention_by_type.is_empty());
+        assert!(settings.blacklisted_apps.is_empty());
+
+        let current = serde_json::to_string(&settings).unwrap();
+        assert!(!parse_settings(&current).unwrap().1);
+
+        // 更新版本写入的设置保持原样
+        let mut newer = serde_json::to_value(&settings).unwrap();
+        newer["version"] = (SETTINGS_VERSION + 1).into();
+        assert!(!migrate_settings(&mut newer));
+        assert_eq!(newer["version"], SETTINGS_VERSION + 1);
+        // 保存设置时不把更新版本的设置文件降级
+        let (newer, _) = parse_settings(&newer.to_string()).unwrap();
+        assert_eq!(settings_version_for_save(newer.version), SETTINGS_VERSION + 1);
+        assert_eq!(settings_version_for_save(0), SETTINGS_VERSION);
     }
 
-    // === 导入分组 ===
-    let old_groups: Vec<(i64, String, String, String)> = sqlx::query_as(
-        "SELECT id, name, color, created_at FROM groups ORDER BY id"
-    )
-    .fetch_all(&temp_pool)
-    .await
-    .map_err(|e| format!("读取导入分组失败: {}", e))?;
+    #[test]
+    fn always_on_top_stays_enabled_when_missing_from_settings() {
+        // 旧设置文件没有 always_on_top 时与窗口配置一致保持置顶
+        let (settings, _) = parse_settings(r#"{"version": 1, "max_history_items": 50, "max_history_time": 7, "hotkey": "Ctrl+Alt+V", "auto_start": false}"#).unwrap();
+        assert!(settings.always_on_top);
+        assert!(AppSettings::default().always_on_top);
 
-    let mut group_id_map: std::collections::HashMap<i64, i64> = std::collections::HashMap::new();
+        let (settings, _) = parse_settings(r#"{"version": 1, "max_history_items": 50, "max_history_time": 7, "hotkey": "Ctrl+Alt+V", "auto_start": false, "always_on_top": false}"#).unwrap();
+        assert!(!settings.always_on_top);
+    }
 
-    for (old_id, name, color, created_at) in &old_groups {
-        let existing: Option<(i64,)> = sqlx::query_as(
-            "SELECT id FROM groups WHERE name = ?"
-        )
-        .bind(name)
-        .fetch_optional(pool)
-        .await
-        .map_err(|e| format!("查询分组失败: {}", e))?;
+    #[test]
+    fn settings_are_written_atomically_with_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(SETTINGS_FILE);
+        let mut settings = AppSettings::default();
+
+        settings.hotkey = "Ctrl+Alt+1".to_string();
+        write_settings_file(&path, &serde_json::to_string(&settings).unwrap()).unwrap();
+        assert!(!settings_sidecar_path(&path, ".bak").exists());
+        settings.hotkey = "Ctrl+Alt+2".to_string();
+        write_settings_file(&path, &serde_json::to_string(&settings).unwrap()).unwrap();
+        assert!(!settings_sidecar_path(&path, ".tmp").exists());
+        assert_eq!(read_settings_file(&path).unwrap().0.hotkey, "Ctrl+Alt+2");
+
+        // 设置文件损坏时从上一份备份恢复，损坏的文件不会覆盖备份
+        fs::write(&path, "{\"max_history_items\": 1").unwrap();
+        assert_eq!(read_settings_file(&path).unwrap().0.hotkey, "Ctrl+Alt+1");
+        settings.hotkey = "Ctrl+Alt+3".to_string();
+        write_settings_file(&path, &serde_json::to_string(&settings).unwrap()).unwrap();
+        let backup = fs::read_to_string(settings_sidecar_path(&path, ".bak")).unwrap();
+        assert!(backup.contains("Ctrl+Alt+1"));
+
+        fs::remove_file(settings_sidecar_path(&path, ".bak")).unwrap();
+        fs::write(&path, "not json").unwrap();
+        assert!(read_settings_file(&path).is_err());
+    }
 
-        if let Some((existing_id,)) = existing {
-            group_id_map.insert(*old_id, existing_id);
-        } else {
-            let result: (i64,) = sqlx::query_as(
-                "INSERT INTO groups (name, color, created_at, item_count) VALUES (?, ?, ?, 0) RETURNING id"
-            )
-            .bind(name)
-            .bind(color)
-            .bind(created_at)
-            .fetch_one(pool)
-            .await
-            .map_err(|e| format!("创建分组失败: {}", e))?;
+    #[tokio::test]
+    async fn tags_follow_item_lifecycle() {
+        let pool = memory_pool().await;
+        sqlx::query(
+            "INSERT INTO clipboard_history (id, content, type, timestamp) VALUES
+             (1, 'a', 'text', '2024-01-01T00:00:00Z'),
+             (2, 'b', 'text', '2024-01-02T00:00:00Z')"
+        ).execute(&pool).await.unwrap();
+
+        assert!(add_item_tag(&pool, 1, "work").await.unwrap());
+        assert!(add_item_tag(&pool, 1, "work").await.unwrap());
+        assert!(add_item_tag(&pool, 1, "code").await.unwrap());
+        assert!(add_item_tag(&pool, 2, "work").await.unwrap());
+        assert!(!add_item_tag(&pool, 99, "work").await.unwrap());
+        assert_eq!(fetch_item_tags(&pool, 1).await.unwrap(), vec!["code", "work"]);
+
+        let ids: Vec<i64> = fetch_items_by_tag(&pool, "work").await.unwrap().iter().map(|item| item.id).collect();
+        assert_eq!(ids, vec![2, 1]);
+
+        sqlx::query("DELETE FROM clipboard_history WHERE id = 1").execute(&pool).await.unwrap();
+        let remaining: Vec<(i64, String)> = sqlx::query_as("SELECT item_id, tag FROM tags")
+            .fetch_all(&pool).await.unwrap();
+        assert_eq!(remaining, vec![(2, "work".to_string())]);
+        assert_eq!(normalize_tag("  "), Err("标签不能为空".to_string()));
+    }
 
-            group_id_map.insert(*old_id, result.0);
+    #[tokio::test]
+    async fn time_cleanup_respects_per_type_retention() {
+        let pool = memory_pool().await;
+        sqlx::query(
+            "INSERT INTO clipboard_history (id, content, type, timestamp, is_favorite, group_id, image_path) VALUES
+             (1, 'old.png', 'image', '2024-01-01T00:00:00Z', 0, NULL, 'old.png'),
+             (2, 'new.png', 'image', '2024-03-01T00:00:00Z', 0, NULL, 'new.png'),
+             (3, 'fav.png', 'image', '2024-01-01T00:00:00Z', 1, NULL, 'fav.png'),
+             (4, 'a', 'text', '2024-01-01T00:00:00Z', 0, NULL, NULL),
+             (5, 'b', 'text', '2023-01-01T00:00:00Z', 0, NULL, NULL),
+             (6, '/tmp/c.txt', 'file', '2023-01-01T00:00:00Z', 0, 2, NULL)"
+        ).execute(&pool).await.unwrap();
+
+        let (deleted, images) = delete_items_older_than(&pool, "2024-02-01T00:00:00Z", Some("image"), &[]).await.unwrap();
+        assert_eq!((deleted, images), (1, vec!["old.png".to_string()]));
+
+        // 全局清理跳过单独设置了保留天数的类型，收藏和分组的条目始终保留
+        let (deleted, images) = delete_items_older_than(&pool, "2024-06-01T00:00:00Z", None, &["image"]).await.unwrap();
+        assert_eq!((deleted, images), (2, Vec::new()));
+
+        let remaining: Vec<i64> = sqlx::query_scalar("SELECT id FROM clipboard_history ORDER BY id")
+            .fetch_all(&pool).await.unwrap();
+        assert_eq!(remaining, vec![2, 3, 6]);
+    }
+
+    #[tokio::test]
+    async fn transient_items_are_cleared_in_one_pass() {
+        let pool = memory_pool().await;
+        sqlx::query(
+            "INSERT INTO clipboard_history (id, content, type, timestamp, is_favorite, is_pinned, group_id, image_path) VALUES
+             (1, 'hello', 'text', '2024-01-01T00:00:00.000Z', 0, 0, NULL, NULL),
+             (2, '你好', 'text', '2024-01-01T00:00:00.000Z', 0, 0, NULL, NULL),
+             (3, 'a.png', 'image', '2024-01-01T00:00:00.000Z', 0, 0, NULL, 'a.png'),
+             (4, 'fav', 'text', '2024-01-01T00:00:00.000Z', 1, 0, NULL, NULL),
+             (5, 'pin', 'text', '2024-01-01T00:00:00.000Z', 0, 1, NULL, NULL),
+             (6, 'grouped', 'text', '2024-01-01T00:00:00.000Z', 0, 0, 7, NULL)"
+        ).execute(&pool).await.unwrap();
+
+        let (deleted, images, content_bytes) = delete_transient_items(&pool).await.unwrap();
+        assert_eq!(deleted, 3);
+        assert_eq!(images, vec!["a.png".to_string()]);
+        // 按 UTF-8 字节计算，图片条目的路径不计入
+        assert_eq!(content_bytes, 5 + 6);
+
+        let remaining: Vec<i64> = sqlx::query_scalar("SELECT id FROM clipboard_history ORDER BY id")
+            .fetch_all(&pool).await.unwrap();
+        assert_eq!(remaining, vec![4, 5, 6]);
+        assert_eq!(delete_transient_items(&pool).await.unwrap(), (0, Vec::new(), 0));
+    }
+
+    #[tokio::test]
+    async fn history_pages_continue_before_cursor() {
+        let pool = memory_pool().await;
+        for i in 1..=5 {
+            let item_type = if i % 2 == 0 { "image" } else { "text" };
+            sqlx::query("INSERT INTO clipboard_history (id, content, type, timestamp) VALUES (?, ?, ?, ?)")
+                .bind(i)
+                .bind(format!("item {}", i))
+                .bind(item_type)
+                .bind(format!("2024-01-0{}T00:00:00.000Z", i))
+                .execute(&pool).await.unwrap();
         }
+        sqlx::query("UPDATE clipboard_history SET preview = 'item', content_length = 6 WHERE id = 5")
+            .execute(&pool).await.unwrap();
+
+        let first = fetch_history_page(&pool, 2, None, None, None).await.unwrap();
+        assert_eq!(first.iter().map(|item| item.id).collect::<Vec<_>>(), vec![5, 4]);
+        assert_eq!(first[0].content, "item");
+        assert!(first[0].content_truncated);
+        assert!(!first[1].content_truncated);
+
+        let last = first.last().unwrap();
+        let cursor = Some((last.timestamp.as_str(), last.id));
+        let next = fetch_history_page(&pool, 2, cursor, None, None).await.unwrap();
+        assert_eq!(next.iter().map(|item| item.id).collect::<Vec<_>>(), vec![3, 2]);
+
+        let text_only = fetch_history_page(&pool, 10, cursor, Some("text"), None).await.unwrap();
+        assert_eq!(text_only.iter().map(|item| item.id).collect::<Vec<_>>(), vec![3, 1]);
     }
-    tracing::info!("分组映射完成: {} 个分组", group_id_map.len());
 
-    // === 导入剪贴板记录 ===
-    let old_records = sqlx::query(
-        "SELECT content, type, timestamp, is_favorite, is_pinned, image_path, source_app_name, source_app_icon, thumbnail_data, note, group_id, data_hash, metadata FROM clipboard_history ORDER BY id"
-    )
-    .fetch_all(&temp_pool)
-    .await
-    .map_err(|e| format!("读取导入记录失败: {}", e))?;
+    // 用于保留时间过滤的条目：图片保留 1 天，其他类型保留 30 天；返回对应的设置
+    async fn insert_retention_sample(pool: &sqlx::SqlitePool) -> AppSettings {
+        let ago = |days: i64| (chrono::Utc::now() - chrono::Duration::days(days)).to_rfc3339();
+        for (id, item_type, days, is_favorite, group_id) in [
+            (1, "text", 40, 0, None),
+            (2, "text", 41, 1, None),
+            (3, "image", 3, 0, None),
+            (4, "text", 3, 0, None),
+            (5, "text", 42, 0, Some(1)),
+        ] {
+            sqlx::query("INSERT INTO clipboard_history (id, content, type, timestamp, is_favorite, group_id) VALUES (?, ?, ?, ?, ?, ?)")
+                .bind(id)
+                .bind(format!("deploy note {}", id))
+                .bind(item_type)
+                .bind(ago(days))
+                .bind(is_favorite)
+                .bind(group_id)
+                .execute(pool)
+                .await
+                .unwrap();
+        }
+        AppSettings {
+            max_history_time: 30,
+            retention_by_type: [("image".to_string(), 1)].into_iter().collect(),
+            enforce_max_age_on_query: true,
+            ..AppSettings::default()
+        }
+    }
 
-    let mut imported_count = 0u64;
-    let mut skipped_count = 0u64;
+    #[tokio::test]
+    async fn history_pages_hide_items_past_retention() {
+        let pool = memory_pool().await;
+        let settings = insert_retention_sample(&pool).await;
+        let ids = |items: Vec<HistoryItem>| items.iter().map(|item| item.id).collect::<Vec<_>>();
 
-    for record in &old_records {
-        let content: String = record.try_get("content").unwrap_or_default();
-        let record_type: String = record.try_get("type").unwrap_or_default();
-        let timestamp: String = record.try_get("timestamp").unwrap_or_default();
-        let is_favorite: i64 = record.try_get("is_favorite").unwrap_or(0);
-        let is_pinned: i64 = record.try_get("is_pinned").unwrap_or(0);
-        let old_image_path: Option<String> = record.try_get("image_path").ok().flatten();
-        let source_app_name: Option<String> = record.try_get("source_app_name").ok().flatten();
-        let source_app_icon: Option<String> = record.try_get("source_app_icon").ok().flatten();
-        let thumbnail_data: Option<String> = record.try_get("thumbnail_data").ok().flatten();
-        let note: Option<String> = record.try_get("note").ok().flatten();
-        let old_group_id: Option<i64> = record.try_get("group_id").ok().flatten();
-        let data_hash: Option<String> = record.try_get("data_hash").ok().flatten();
-        let metadata: Option<String> = record.try_get("metadata").ok().flatten();
+        assert_eq!(ids(fetch_history_page(&pool, 10, None, None, None).await.unwrap()), vec![4, 3, 1, 2, 5]);
+        // 过期的文本和按类型保留天数过期的图片被隐藏，收藏和分组中的条目不受影响
+        assert_eq!(ids(fetch_history_page(&pool, 10, None, None, Some(&settings)).await.unwrap()), vec![4, 2, 5]);
+        assert!(fetch_history_page(&pool, 10, None, Some("image"), Some(&settings)).await.unwrap().is_empty());
+    }
 
-        let new_image_path = old_image_path.as_ref().and_then(|p| {
-            std::path::Path::new(p).file_name().map(|f| {
-                format!("{}/{}", new_images_dir_str, f.to_string_lossy())
-            })
-        });
+    #[tokio::test]
+    async fn search_hides_items_past_retention() {
+        let pool = memory_pool().await;
+        let settings = insert_retention_sample(&pool).await;
+        let ids = |items: Vec<HistoryItem>| {
+            let mut ids = items.iter().map(|item| item.id).collect::<Vec<_>>();
+            ids.sort();
+            ids
+        };
 
-        let new_group_id = old_group_id.and_then(|gid| group_id_map.get(&gid).copied());
+        // 全文索引和 LIKE 回退（短词）两条路径都过滤过期条目
+        assert_eq!(ids(search_history_in_pool(&pool, "deploy", 10, None).await.unwrap()), vec![1, 2, 3, 4, 5]);
+        assert_eq!(ids(search_history_in_pool(&pool, "deploy", 10, Some(&settings)).await.unwrap()), vec![2, 4, 5]);
+        assert_eq!(ids(search_history_in_pool(&pool, "de", 10, Some(&settings)).await.unwrap()), vec![2, 4, 5]);
+        // 过期条目不占用 limit 名额
+        assert_eq!(search_history_in_pool(&pool, "deploy", 3, Some(&settings)).await.unwrap().len(), 3);
+    }
 
-        // 去重检查
-        let is_duplicate = if let Some(ref hash) = data_hash {
-            if !hash.is_empty() {
-                let existing: Option<(i64,)> = sqlx::query_as(
-                    "SELECT id FROM clipboard_history WHERE data_hash = ?"
-                )
-                .bind(hash)
-                .fetch_optional(pool)
+    #[tokio::test]
+    async fn history_pages_split_items_with_equal_timestamps() {
+        let pool = memory_pool().await;
+        for i in 1..=5 {
+            sqlx::query("INSERT INTO clipboard_history (id, content, type, timestamp) VALUES (?, ?, 'text', ?)")
+                .bind(i)
+                .bind(format!("item {}", i))
+                .bind(if i == 1 { "2024-01-01T00:00:00.000Z" } else { "2024-01-02T00:00:00.000Z" })
+                .execute(&pool).await.unwrap();
+        }
+
+        // 页边界落在同一时间戳的条目之间时，下一页从同一时间戳中 id 更小的条目继续
+        let mut ids = Vec::new();
+        let mut cursor: Option<(String, i64)> = None;
+        loop {
+            let page = fetch_history_page(&pool, 2, cursor.as_ref().map(|(timestamp, id)| (timestamp.as_str(), *id)), None, None)
                 .await
-                .map_err(|e| format!("去重查询失败: {}", e))?;
-                existing.is_some()
-            } else {
-                false
-            }
-        } else {
-            let existing: Option<(i64,)> = sqlx::query_as(
-                "SELECT id FROM clipboard_history WHERE content = ? AND type = ?"
-            )
-            .bind(&content)
-            .bind(&record_type)
-            .fetch_optional(pool)
-            .await
-            .map_err(|e| format!("去重查询失败: {}", e))?;
-            existing.is_some()
+                .unwrap();
+            let Some(last) = page.last() else { break };
+            cursor = Some((last.timestamp.clone(), last.id));
+            ids.extend(page.iter().map(|item| item.id));
+        }
+        assert_eq!(ids, vec![5, 4, 3, 2, 1]);
+    }
+
+    #[tokio::test]
+    async fn search_uses_fts_ranking_and_like_fallback() {
+        let pool = memory_pool().await;
+        sqlx::query(
+            "INSERT INTO clipboard_history (id, content, type, timestamp) VALUES
+             (1, 'deploy script for staging', 'text', '2024-01-01T00:00:00.000Z'),
+             (2, 'deploy deploy deploy', 'text', '2024-01-02T00:00:00.000Z'),
+             (3, '今天的会议纪要', 'text', '2024-01-03T00:00:00.000Z')"
+        ).execute(&pool).await.unwrap();
+
+        // 重复创建索引不影响已有内容，新写入和修改由触发器同步
+        assert!(ensure_fts_index(&pool).await);
+        sqlx::query("INSERT INTO clipboard_history (id, content, type, timestamp) VALUES (4, 'staging 100% done', 'text', '2024-01-04T00:00:00.000Z')")
+            .execute(&pool).await.unwrap();
+
+        let ids = |items: Vec<HistoryItem>| items.iter().map(|item| item.id).collect::<Vec<_>>();
+        assert_eq!(ids(search_history_in_pool(&pool, "deploy", 10, None).await.unwrap()), vec![2, 1]);
+        assert_eq!(ids(search_history_in_pool(&pool, "deploy staging", 10, None).await.unwrap()), vec![1]);
+
+        sqlx::query("UPDATE clipboard_history SET content = 'nothing here' WHERE id = 1")
+            .execute(&pool).await.unwrap();
+        assert_eq!(ids(search_history_in_pool(&pool, "deploy", 10, None).await.unwrap()), vec![2]);
+        assert_eq!(ids(search_history_in_pool(&pool, "staging", 10, None).await.unwrap()), vec![4]);
+        assert_eq!(ids(search_history_in_pool(&pool, "会议纪", 10, None).await.unwrap()), vec![3]);
+        assert!(search_history_in_pool(&pool, "\"unbalanced", 10, None).await.unwrap().is_empty());
+
+        // 少于 3 个字符的词使用 LIKE 匹配，% 按字面处理
+        assert_eq!(ids(search_history_in_pool(&pool, "会议", 10, None).await.unwrap()), vec![3]);
+        assert_eq!(ids(search_history_in_pool(&pool, "0%", 10, None).await.unwrap()), vec![4]);
+
+        sqlx::query("DELETE FROM clipboard_history WHERE id = 2").execute(&pool).await.unwrap();
+        assert!(search_history_in_pool(&pool, "deploy", 10, None).await.unwrap().is_empty());
+        assert!(search_history_in_pool(&pool, "   ", 10, None).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn history_export_inlines_images() {
+        let dir = tempfile::tempdir().unwrap();
+        let image_file = dir.path().join("a.png");
+        std::fs::write(&image_file, webp_sample()).unwrap();
+
+        let item = |id: i64, item_type: &str, image_path: Option<String>| ClipboardItem {
+            id,
+            content: format!("item {}", id),
+            item_type: item_type.to_string(),
+            timestamp: "2024-01-01T00:00:00.000Z".to_string(),
+            is_favorite: false,
+            is_pinned: false,
+            image_path,
+            source_app_name: None,
+            source_app_icon: None,
+            thumbnail_data: None,
+            metadata: None,
+            note: Some("备注".to_string()),
+            group_id: Some(3),
+            data_hash: None,
+            expires_at: None,
         };
+        let dest = dir.path().join("export.json");
+        write_history_export(&dest, &[
+            item(1, "text", None),
+            item(2, "image", Some(image_file.to_string_lossy().to_string())),
+            item(3, "image", Some(dir.path().join("missing.png").to_string_lossy().to_string())),
+        ]).unwrap();
+        assert!(!settings_sidecar_path(&dest, ".tmp").exists());
+
+        let exported: serde_json::Value = serde_json::from_str(&fs::read_to_string(&dest).unwrap()).unwrap();
+        assert_eq!(exported["version"], 1);
+        let items = exported["items"].as_array().unwrap();
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0]["note"], "备注");
+        assert_eq!(items[0]["group_id"], 3);
+        assert_eq!(items[0]["type"], "text");
+        assert!(items[0]["image_data"].is_null());
+        assert!(items[1]["image_data"].as_str().unwrap().starts_with("data:image/webp;base64,"));
+        assert!(items[2]["image_data"].is_null());
+        // 目标目录不存在时返回错误
+        assert!(write_history_export(&dir.path().join("missing").join("export.json"), &[]).is_err());
+    }
 
-        if is_duplicate {
-            skipped_count += 1;
-            continue;
-        }
+    #[tokio::test]
+    async fn duplicate_items_are_promoted_instead_of_inserted() {
+        let pool = memory_pool().await;
+        // 没有 data_hash 的旧条目按内容和类型匹配
+        sqlx::query("INSERT INTO clipboard_history (id, content, type, timestamp) VALUES (1, 'hello', 'text', '2024-01-01T00:00:00.000Z')")
+            .execute(&pool).await.unwrap();
+
+        let item = |content: &str| NewClipboardItem {
+            content: content.to_string(),
+            item_type: "text".to_string(),
+            timestamp: None,
+            is_favorite: false,
+            is_pinned: false,
+            image_path: None,
+            source_app_name: None,
+            source_app_icon: None,
+            thumbnail_data: None,
+            metadata: None,
+            note: None,
+            group_id: None,
+            data_hash: None,
+            content_hash: None,
+        };
+        let settings = AppSettings::default();
+        let mut conn = pool.acquire().await.unwrap();
+
+        let promoted = upsert_clipboard_item(&mut conn, item("hello"), &settings).await.unwrap();
+        assert_eq!(promoted, UpsertResult { id: 1, inserted: false });
+        let timestamp: String = sqlx::query_scalar("SELECT timestamp FROM clipboard_history WHERE id = 1")
+            .fetch_one(&mut *conn).await.unwrap();
+        assert!(timestamp.as_str() > "2024-01-01T00:00:00.000Z");
+
+        let inserted = upsert_clipboard_item(&mut conn, item("world"), &settings).await.unwrap();
+        assert!(inserted.inserted);
+        let again = upsert_clipboard_item(&mut conn, item("world"), &settings).await.unwrap();
+        assert_eq!(again, UpsertResult { id: inserted.id, inserted: false });
+
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM clipboard_history")
+            .fetch_one(&mut *conn).await.unwrap();
+        assert_eq!(count, 2);
+    }
 
+    #[tokio::test]
+    async fn promoted_images_remove_the_new_image_file() {
+        let pool = memory_pool().await;
+        let dir = tempfile::tempdir().unwrap();
+        let existing_file = dir.path().join("existing.webp");
+        let new_file = dir.path().join("new.webp");
+        std::fs::write(&existing_file, b"same bytes").unwrap();
+        std::fs::write(&new_file, b"same bytes").unwrap();
+        let existing_path = existing_file.to_string_lossy().to_string();
+        sqlx::query("INSERT INTO clipboard_history (id, content, type, timestamp, image_path, content_hash) VALUES (1, ?1, 'image', '2024-01-01T00:00:00.000Z', ?1, ?2)")
+            .bind(&existing_path)
+            .bind(compute_bytes_hash(b"same bytes"))
+            .execute(&pool).await.unwrap();
+
+        let item = |path: &str| NewClipboardItem {
+            content: path.to_string(),
+            item_type: "image".to_string(),
+            timestamp: None,
+            is_favorite: false,
+            is_pinned: false,
+            image_path: Some(path.to_string()),
+            source_app_name: None,
+            source_app_icon: None,
+            thumbnail_data: None,
+            metadata: None,
+            note: None,
+            group_id: None,
+            data_hash: None,
+            content_hash: None,
+        };
+        let settings = AppSettings::default();
+        let mut conn = pool.acquire().await.unwrap();
+
+        // 图片字节相同，提升已有条目并删除新写入的文件
+        let promoted = upsert_clipboard_item(&mut conn, item(&new_file.to_string_lossy()), &settings).await.unwrap();
+        assert_eq!(promoted, UpsertResult { id: 1, inserted: false });
+        assert!(!new_file.exists());
+        assert!(existing_file.exists());
+
+        // 新条目就是已有条目引用的文件时保留
+        let promoted = upsert_clipboard_item(&mut conn, item(&existing_path), &settings).await.unwrap();
+        assert_eq!(promoted, UpsertResult { id: 1, inserted: false });
+        assert!(existing_file.exists());
+    }
+
+    #[tokio::test]
+    async fn duplicate_images_keep_protected_or_newest_copy() {
+        let pool = memory_pool().await;
+        let dir = tempfile::tempdir().unwrap();
+        let image_file = dir.path().join("a.png");
+        std::fs::write(&image_file, b"same bytes").unwrap();
+        let image_path = image_file.to_string_lossy().to_string();
+        let missing_path = dir.path().join("missing.png").to_string_lossy().to_string();
         sqlx::query(
-            "INSERT INTO clipboard_history (content, type, timestamp, is_favorite, is_pinned, image_path, source_app_name, source_app_icon, thumbnail_data, note, group_id, data_hash, metadata)
-             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+            "INSERT INTO clipboard_history (id, content, type, timestamp, is_favorite, image_path, content_hash) VALUES
+             (1, 'a', 'image', '2024-01-01T00:00:00.000Z', 0, ?1, NULL),
+             (2, 'b', 'image', '2024-01-02T00:00:00.000Z', 0, ?1, NULL),
+             (3, 'c', 'image', '2024-01-03T00:00:00.000Z', 0, ?2, NULL),
+             (4, 'd', 'image', '2024-01-04T00:00:00.000Z', 0, ?2, NULL),
+             (5, 'e', 'image', '2024-01-01T00:00:00.000Z', 1, NULL, 'h'),
+             (6, 'f', 'image', '2024-01-05T00:00:00.000Z', 0, NULL, 'h'),
+             (7, 'same bytes', 'text', '2024-01-01T00:00:00.000Z', 0, NULL, NULL)"
         )
-        .bind(&content)
-        .bind(&record_type)
-        .bind(&timestamp)
-        .bind(is_favorite)
-        .bind(is_pinned)
-        .bind(&new_image_path)
-        .bind(&source_app_name)
-        .bind(&source_app_icon)
-        .bind(&thumbnail_data)
-        .bind(&note)
-        .bind(new_group_id)
-        .bind(&data_hash)
-        .bind(&metadata)
-        .execute(pool)
-        .await
-        .map_err(|e| format!("插入记录失败: {}", e))?;
+            .bind(&image_path)
+            .bind(&missing_path)
+            .execute(&pool).await.unwrap();
+
+        assert_eq!(backfill_image_content_hashes(&pool, 100).await.unwrap(), 4);
+        let hash: String = sqlx::query_scalar("SELECT content_hash FROM clipboard_history WHERE id = 1")
+            .fetch_one(&pool).await.unwrap();
+        assert_eq!(hash, compute_bytes_hash(b"same bytes"));
+
+        // 1 被更新的 2 取代，6 与收藏的 5 重复；文件缺失的 3、4 不参与合并
+        assert_eq!(delete_duplicate_images(&pool).await.unwrap(), vec![1, 6]);
+        let remaining: Vec<i64> = sqlx::query_scalar("SELECT id FROM clipboard_history ORDER BY id")
+            .fetch_all(&pool).await.unwrap();
+        assert_eq!(remaining, vec![2, 3, 4, 5, 7]);
+        assert!(delete_duplicate_images(&pool).await.unwrap().is_empty());
+    }
 
-        imported_count += 1;
+    #[tokio::test]
+    async fn duplicate_images_keep_notes_and_tags() {
+        let pool = memory_pool().await;
+        sqlx::query(
+            "INSERT INTO clipboard_history (id, content, type, timestamp, note, content_hash) VALUES
+             (1, 'a', 'image', '2024-01-01T00:00:00.000Z', '发票', 'h'),
+             (2, 'b', 'image', '2024-01-02T00:00:00.000Z', NULL, 'h'),
+             (3, 'c', 'image', '2024-01-03T00:00:00.000Z', NULL, 'h'),
+             (4, 'd', 'image', '2024-01-01T00:00:00.000Z', '  ', 'g'),
+             (5, 'e', 'image', '2024-01-02T00:00:00.000Z', NULL, 'g')"
+        )
+            .execute(&pool).await.unwrap();
+        assert!(add_item_tag(&pool, 2, "work").await.unwrap());
+
+        // 有备注的 1 和有标签的 2 都保留，3 与它们重复而被删除；空白备注不算备注
+        assert_eq!(delete_duplicate_images(&pool).await.unwrap(), vec![3, 4]);
+        let remaining: Vec<i64> = sqlx::query_scalar("SELECT id FROM clipboard_history ORDER BY id")
+            .fetch_all(&pool).await.unwrap();
+        assert_eq!(remaining, vec![1, 2, 5]);
+        assert_eq!(fetch_item_tags(&pool, 2).await.unwrap(), vec!["work"]);
     }
 
-    tracing::info!("记录导入完成: 导入 {} 条, 跳过 {} 条(重复)", imported_count, skipped_count);
+    #[tokio::test]
+    async fn statistics_aggregate_history() {
+        let pool = memory_pool().await;
 
-    // 更新分组的 item_count
-    for (_, new_gid) in &group_id_map {
-        let _ = sqlx::query("UPDATE groups SET item_count = (SELECT COUNT(*) FROM clipboard_history WHERE group_id = ?) WHERE id = ?")
-            .bind(new_gid)
-            .bind(new_gid)
-            .execute(pool)
-            .await;
+        let empty = collect_statistics(&pool).await.unwrap();
+        assert_eq!(empty.total_items, 0);
+        assert!(empty.type_counts.is_empty());
+        assert_eq!(empty.oldest_timestamp, None);
+
+        sqlx::query(
+            "INSERT INTO clipboard_history (content, type, timestamp, is_favorite, group_id, source_app_name) VALUES
+             ('a', 'text', '2024-01-02T00:00:00.000Z', 1, NULL, 'Code'),
+             ('b', 'text', '2024-01-01T00:00:00.000Z', 0, 3, 'Code'),
+             ('c', 'image', '2024-01-03T00:00:00.000Z', 0, 3, 'Browser'),
+             ('d', 'file', '2024-01-04T00:00:00.000Z', 0, NULL, '  '),
+             ('e', 'text', '2024-01-05T00:00:00.000Z', 0, NULL, NULL)"
+        ).execute(&pool).await.unwrap();
+
+        let stats = collect_statistics(&pool).await.unwrap();
+        assert_eq!(stats.total_items, 5);
+        assert_eq!(stats.type_counts.get("text"), Some(&3));
+        assert_eq!(stats.type_counts.get("image"), Some(&1));
+        assert_eq!(stats.favorite_items, 1);
+        assert_eq!(stats.grouped_items, 2);
+        assert_eq!(stats.top_source_apps, vec![
+            AppItemCount { name: "Code".to_string(), item_count: 2 },
+            AppItemCount { name: "Browser".to_string(), item_count: 1 },
+        ]);
+        assert_eq!(stats.oldest_timestamp.as_deref(), Some("2024-01-01T00:00:00.000Z"));
+        assert_eq!(stats.newest_timestamp.as_deref(), Some("2024-01-05T00:00:00.000Z"));
     }
 
-    // 关闭临时数据库连接
-    temp_pool.close().await;
-    drop(db_guard); // 释放数据库锁
+    #[tokio::test]
+    async fn previews_follow_configured_length() {
+        let pool = memory_pool().await;
+        for trigger in ["trg_preview_insert", "trg_preview_update"] {
+            sqlx::query(&format!("DROP TRIGGER {}", trigger)).execute(&pool).await.unwrap();
+        }
+        let long_text = "x".repeat(250);
+        sqlx::query("INSERT INTO clipboard_history (id, content, type, timestamp) VALUES (1, ?, 'text', '2024-01-01T00:00:00.000Z')")
+            .bind(&long_text)
+            .execute(&pool).await.unwrap();
 
-    // 第四步：写入图片文件（纯同步操作）
-    fs::create_dir_all(&new_images_dir)
-        .map_err(|e| format!("无法创建图片目录: {}", e))?;
+        // 触发器创建前写入的条目由补全生成预览
+        assert_eq!(sync_content_previews(&pool, 200).await.unwrap(), 1);
+        sqlx::query(
+            "INSERT INTO clipboard_history (id, content, type, timestamp) VALUES
+             (2, 'short', 'text', '2024-01-02T00:00:00.000Z'), (3, 'a.png', 'image', '2024-01-03T00:00:00.000Z')"
+        )
+            .execute(&pool).await.unwrap();
+        let rows: Vec<(Option<String>, Option<i64>)> = sqlx::query_as("SELECT preview, content_length FROM clipboard_history ORDER BY id")
+            .fetch_all(&pool).await.unwrap();
+        assert_eq!(rows[0], (Some("x".repeat(200)), Some(250)));
+        assert_eq!(rows[1], (Some("short".to_string()), Some(5)));
+        assert_eq!(rows[2], (None, Some(5)));
+        assert_eq!(sync_content_previews(&pool, 200).await.unwrap(), 0);
+
+        // 修改内容时更新预览，修改预览长度时重建全部预览
+        sqlx::query("UPDATE clipboard_history SET content = 'edited' WHERE id = 1").execute(&pool).await.unwrap();
+        assert_eq!(sync_content_previews(&pool, 100).await.unwrap(), 3);
+        let previews: Vec<Option<String>> = sqlx::query_scalar("SELECT preview FROM clipboard_history ORDER BY id")
+            .fetch_all(&pool).await.unwrap();
+        assert_eq!(previews, vec![Some("edited".to_string()), Some("short".to_string()), None]);
+    }
 
-    let mut image_count = 0u64;
-    for (filename, data) in &zip_data.images {
-        let dest_path = new_images_dir.join(filename);
-        if !dest_path.exists() {
-            fs::write(&dest_path, data)
-                .map_err(|e| format!("无法写入图片文件 {}: {}", filename, e))?;
-            image_count += 1;
+    #[tokio::test]
+    async fn vacuum_reclaims_space_after_deletes() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("clipboard.db");
+        let options = sqlx::sqlite::SqliteConnectOptions::new()
+            .filename(&db_path)
+            .create_if_missing(true)
+            .journal_mode(sqlx::sqlite::SqliteJournalMode::Wal);
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .unwrap();
+        sqlx::query("CREATE TABLE clipboard_history (id INTEGER PRIMARY KEY, content TEXT NOT NULL)")
+            .execute(&pool).await.unwrap();
+        for _ in 0..200 {
+            sqlx::query("INSERT INTO clipboard_history (content) VALUES (?)")
+                .bind("x".repeat(4000))
+                .execute(&pool).await.unwrap();
         }
+        sqlx::query("DELETE FROM clipboard_history").execute(&pool).await.unwrap();
+        sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)").execute(&pool).await.unwrap();
+
+        let size_before = database_file_size(&db_path);
+        vacuum_pool(&pool).await.unwrap();
+        let size_after = database_file_size(&db_path);
+        assert!(size_after < size_before / 10, "{} -> {}", size_before, size_after);
     }
 
-    tracing::info!("图片文件提取完成: {} 个新文件", image_count);
-    tracing::info!("✅ 数据导入完成");
+    #[test]
+    fn database_paths_follow_custom_location() {
+        let app_data_dir = std::env::temp_dir().join("app-data");
+        let custom = std::env::temp_dir().join("synced").join("history.db");
+
+        assert_eq!(profile_db_path(&app_data_dir, None, DEFAULT_PROFILE), app_data_dir.join("clipboard.db"));
+        assert_eq!(profile_db_path(&app_data_dir, None, "work"), app_data_dir.join("clipboard-work.db"));
+        assert_eq!(profile_db_path(&app_data_dir, Some(&custom), DEFAULT_PROFILE), custom);
+        assert_eq!(
+            profile_db_path(&app_data_dir, Some(&custom), "work"),
+            std::env::temp_dir().join("synced").join("clipboard-work.db")
+        );
+        assert_eq!(sqlite_sidecar_path(&custom, "-wal"), std::env::temp_dir().join("synced").join("history.db-wal"));
 
-    let _ = app.emit("data-imported", ());
+        assert_eq!(validate_db_path(&custom.to_string_lossy()).unwrap(), custom);
+        assert!(validate_db_path("history.db").is_err());
+        assert!(validate_db_path(&std::env::temp_dir().join("history.sqlite").to_string_lossy()).is_err());
+        assert!(validate_db_path(&std::env::temp_dir().join("clipboard-work.db").to_string_lossy()).is_err());
 
-    Ok(())
+        let settings = AppSettings { db_path: Some("  ".to_string()), ..AppSettings::default() };
+        assert_eq!(configured_db_path(&settings), None);
+    }
+
+    #[test]
+    fn database_and_images_move_away_and_back() {
+        let root = tempfile::tempdir().unwrap();
+        let app_data_dir = root.path().join("app-data");
+        let exe_dir = root.path().join("program");
+        let custom = root.path().join("synced").join("history.db");
+        let profiles = vec![DEFAULT_PROFILE.to_string(), "work".to_string()];
+        fs::create_dir_all(&app_data_dir).unwrap();
+        fs::create_dir_all(exe_dir.join("images")).unwrap();
+        fs::write(app_data_dir.join("clipboard.db"), b"default db").unwrap();
+        fs::write(app_data_dir.join("clipboard-work.db"), b"work db").unwrap();
+        fs::write(exe_dir.join("images").join("a.png"), b"image").unwrap();
+
+        assert_eq!(profile_images_dir(&exe_dir, None, DEFAULT_PROFILE), exe_dir.join("images"));
+        assert_eq!(profile_images_dir(&exe_dir, Some(&custom), "work"), root.path().join("synced").join("images-work"));
+
+        let move_files = |old: Option<&Path>, new: Option<&Path>| {
+            if let Some(parent) = new.and_then(Path::parent) {
+                fs::create_dir_all(parent).unwrap();
+            }
+            let plan = plan_database_move(&app_data_dir, &exe_dir, old, new, &profiles).unwrap();
+            copy_database_files(&plan.db_files).unwrap();
+            copy_image_dirs(&plan.image_dirs).unwrap();
+            remove_moved_sources(&plan);
+        };
+
+        // 移到自定义位置：数据库和图片都移过去，原位置不再保留
+        move_files(None, Some(&custom));
+        assert_eq!(fs::read(&custom).unwrap(), b"default db");
+        assert_eq!(fs::read(root.path().join("synced").join("clipboard-work.db")).unwrap(), b"work db");
+        assert_eq!(fs::read(root.path().join("synced").join("images").join("a.png")).unwrap(), b"image");
+        assert!(!app_data_dir.join("clipboard.db").exists());
+        assert!(!exe_dir.join("images").exists());
+
+        // 再移回默认位置不会因为原位置残留的文件而失败
+        move_files(Some(&custom), None);
+        assert_eq!(fs::read(app_data_dir.join("clipboard.db")).unwrap(), b"default db");
+        assert_eq!(fs::read(app_data_dir.join("clipboard-work.db")).unwrap(), b"work db");
+        assert_eq!(fs::read(exe_dir.join("images").join("a.png")).unwrap(), b"image");
+        assert!(!custom.exists());
+
+        // 目标位置已有其他数据库时拒绝覆盖
+        fs::write(&custom, b"other").unwrap();
+        assert!(plan_database_move(&app_data_dir, &exe_dir, None, Some(&custom), &profiles).is_err());
+        assert!(plan_database_move(&app_data_dir, &exe_dir, None, None, &profiles).is_err());
+    }
+
+    #[test]
+    fn imported_settings_merge_with_defaults() {
+        let current = AppSettings {
+            lan_queue_password: "secret".to_string(),
+            active_profile: "work".to_string(),
+            ..AppSettings::default()
+        };
+        let result = merge_imported_settings(
+            r#"{"max_history_items": 42, "theme": "dark", "lan_queue_password": "", "active_profile": "home", "unknown_key": 1}"#,
+            &current,
+        ).unwrap();
+        assert_eq!(result.settings.max_history_items, 42);
+        assert_eq!(result.settings.theme, "dark");
+        assert_eq!(result.settings.hotkey, AppSettings::default().hotkey);
+        assert_eq!(result.settings.lan_queue_password, "secret");
+        assert_eq!(result.settings.active_profile, "work");
+        assert_eq!(result.settings.db_path, None);
+        assert_eq!(result.ignored_keys, vec!["unknown_key".to_string()]);
+
+        assert!(merge_imported_settings(r#"{"max_history_items": "many"}"#, &current).is_err());
+        assert!(merge_imported_settings(r#"{"foo": 1}"#, &current).is_err());
+        assert!(merge_imported_settings("[]", &current).is_err());
+        assert!(merge_imported_settings(r#"{"icon_cache_size": 0}"#, &current).is_err());
+    }
+
+    #[test]
+    fn profiles_map_to_separate_files() {
+        assert_eq!(profile_db_file_name("default"), "clipboard.db");
+        assert_eq!(profile_db_file_name("work"), "clipboard-work.db");
+        assert_eq!(profile_images_dir_name("work"), "images-work");
+        assert_eq!(validate_profile_name(" work ").unwrap(), "work");
+        assert!(validate_profile_name("../x").is_err());
+        assert!(validate_profile_name("").is_err());
+
+        let files = ["clipboard.db", "clipboard.db-wal", "clipboard-work.db", "clipboard-work.db-shm", "clipboard-a b.db", "clipboard-home.db", "settings.json"];
+        assert_eq!(
+            profile_names_from_files(files.iter().map(|f| f.to_string())),
+            vec!["default".to_string(), "home".to_string(), "work".to_string()]
+        );
+    }
+
+    #[test]
+    fn growth_projection_accounts_for_retention() {
+        // 每天 10 条、保留 30 天，稳定在 300 条，达不到 1000 条的上限
+        assert_eq!(project_days_until_cap(200, 1000, 10.0, 30), None);
+        // 每天 100 条、保留 30 天会超过上限，还剩 800 条空间
+        assert_eq!(project_days_until_cap(200, 1000, 100.0, 30), Some(8.0));
+        assert_eq!(project_days_until_cap(1200, 1000, 100.0, 30), Some(0.0));
+        assert_eq!(project_days_until_cap(200, 1000, 0.0, 30), None);
+    }
+
+    #[tokio::test]
+    async fn deleting_missing_item_reports_not_found() {
+        let pool = memory_pool().await;
+        sqlx::query("INSERT INTO clipboard_history (id, content, type, timestamp) VALUES (1, 'a', 'text', '2024-01-01T00:00:00.000Z')")
+            .execute(&pool).await.unwrap();
+
+        assert!(delete_single_item(&pool, 1).await.unwrap());
+        assert!(!delete_single_item(&pool, 1).await.unwrap());
+        let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM clipboard_history").fetch_one(&pool).await.unwrap();
+        assert_eq!(remaining, 0);
+    }
+
+    #[test]
+    fn timestamp_args_are_normalized_to_utc() {
+        assert_eq!(normalize_timestamp_arg("2024-03-01T10:00:00+08:00").unwrap(), "2024-03-01T02:00:00.000Z");
+        assert_eq!(normalize_timestamp_arg("2024-03-01T02:00:00.5Z").unwrap(), "2024-03-01T02:00:00.500Z");
+        assert!(normalize_timestamp_arg("yesterday").is_err());
+    }
+
+    #[test]
+    fn sync_snapshot_respects_budget() {
+        let item = |id: &str, payload: &str| crate::lan_queue::LanClipboardItem {
+            id: id.to_string(),
+            kind: "text".to_string(),
+            payload: payload.to_string(),
+            timestamp: "2024-01-01T00:00:00.000Z".to_string(),
+            origin: String::new(),
+            sender_name: None,
+        };
+        let small = item("a", "hello");
+        let small_size = serde_json::to_vec(&small).unwrap().len() + 1;
+        let budget = small_size * 2 + 10;
+
+        let mut used = 0;
+        assert!(fits_snapshot_budget(&small, &mut used, budget));
+        assert_eq!(used, small_size);
+        // 放不下的大条目被跳过，不占用预算
+        assert!(!fits_snapshot_budget(&item("b", &"x".repeat(budget)), &mut used, budget));
+        assert_eq!(used, small_size);
+        assert!(fits_snapshot_budget(&small, &mut used, budget));
+        assert!(!fits_snapshot_budget(&small, &mut used, budget));
+    }
+
+    #[test]
+    fn sync_items_over_the_payload_limit_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let image_file = dir.path().join("a.webp");
+        std::fs::write(&image_file, [7u8; 30]).unwrap();
+        let item = |item_type: &str, content: String| ClipboardItem {
+            id: 1,
+            content,
+            item_type: item_type.to_string(),
+            timestamp: "2024-01-01T00:00:00.000Z".to_string(),
+            is_favorite: false,
+            is_pinned: false,
+            image_path: None,
+            source_app_name: None,
+            source_app_icon: None,
+            thumbnail_data: None,
+            metadata: None,
+            note: None,
+            group_id: None,
+            data_hash: None,
+            expires_at: None,
+        };
+        let image = item("image", image_file.to_string_lossy().to_string());
+
+        // 30 字节的图片 base64 编码后为 40 字节
+        let lan_item = history_item_to_lan(&image, 40).unwrap();
+        assert_eq!(lan_item.payload, general_purpose::STANDARD.encode([7u8; 30]));
+        assert!(history_item_to_lan(&image, 39).is_none());
+        assert!(history_item_to_lan(&item("text", "hello".to_string()), 5).is_some());
+        assert!(history_item_to_lan(&item("text", "hello".to_string()), 4).is_none());
+        assert!(history_item_to_lan(&item("image", dir.path().join("missing.webp").to_string_lossy().to_string()), 100).is_none());
+    }
 }
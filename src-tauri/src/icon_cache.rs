@@ -1,15 +1,45 @@
 use std::collections::{HashMap, BTreeMap};
+use std::path::Path;
 use std::sync::{Arc, RwLock};
+use serde::{Deserialize, Serialize};
+use crate::types::{DEFAULT_ICON_CACHE_SIZE, DEFAULT_ICON_CACHE_TTL_HOURS};
 
 pub struct IconCacheEntry {
     pub icon: Option<String>,
     pub access_time: std::time::Instant,
+    // 图标提取的时间，用于按 TTL 过期（access_time 每次命中都会刷新，不能反映图标的新旧）
+    pub extracted_at: std::time::Instant,
+}
+
+// 保存到磁盘的图标；modified 为缓存键对应文件（Windows 下为 exe 路径）的修改时间，
+// 键不是文件路径（如 macOS 的 bundle ID）时为 None；extracted_at 为提取时间（Unix 秒）
+#[derive(Serialize, Deserialize)]
+struct PersistedIcon {
+    icon: String,
+    modified: Option<u64>,
+    #[serde(default)]
+    extracted_at: Option<u64>,
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// 缓存键对应文件的修改时间（秒），用于判断应用更新后缓存的图标是否过期
+fn source_modified(key: &str) -> Option<u64> {
+    let modified = std::fs::metadata(key).ok()?.modified().ok()?;
+    modified.duration_since(std::time::UNIX_EPOCH).ok().map(|d| d.as_secs())
 }
 
 pub struct IconCache {
     pub cache: HashMap<String, IconCacheEntry>,
     pub access_order: BTreeMap<std::time::Instant, String>,
     pub max_size: usize,
+    // 图标提取后的有效期，过期后 get 返回 None 以重新提取（应用更新后图标会变化）；None 表示不过期
+    pub ttl: Option<std::time::Duration>,
 }
 
 impl IconCache {
@@ -18,14 +48,40 @@ impl IconCache {
             cache: HashMap::new(),
             access_order: BTreeMap::new(),
             max_size,
+            ttl: Some(std::time::Duration::from_secs(DEFAULT_ICON_CACHE_TTL_HOURS * 3600)),
         }
     }
 
+    fn is_expired(&self, age: std::time::Duration) -> bool {
+        self.ttl.is_some_and(|ttl| age > ttl)
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.cache.remove(key) {
+            self.access_order.remove(&entry.access_time);
+        }
+    }
+
+    // 当前时间作为访问时间；与已有条目的时间相同时顺延，避免 access_order 中的键互相覆盖
+    fn next_access_time(&self) -> std::time::Instant {
+        let mut now = std::time::Instant::now();
+        while self.access_order.contains_key(&now) {
+            now += std::time::Duration::from_nanos(1);
+        }
+        now
+    }
+
     pub fn get(&mut self, key: &str) -> Option<Option<String>> {
+        let expired = self.cache.get(key).is_some_and(|entry| self.is_expired(entry.extracted_at.elapsed()));
+        if expired {
+            self.remove(key);
+            return None;
+        }
+        let now = self.next_access_time();
         if let Some(entry) = self.cache.get_mut(key) {
             // 更新访问时间
             self.access_order.remove(&entry.access_time);
-            entry.access_time = std::time::Instant::now();
+            entry.access_time = now;
             self.access_order.insert(entry.access_time, key.to_string());
             Some(entry.icon.clone())
         } else {
@@ -34,7 +90,14 @@ impl IconCache {
     }
 
     pub fn insert(&mut self, key: String, icon: Option<String>) {
-        let now = std::time::Instant::now();
+        self.insert_extracted(key, icon, std::time::Duration::ZERO);
+    }
+
+    // 插入 age 之前提取的图标（从磁盘加载时保留原来的提取时间）
+    fn insert_extracted(&mut self, key: String, icon: Option<String>, age: std::time::Duration) {
+        // 替换已有条目时先移除其访问记录
+        self.remove(&key);
+        let now = self.next_access_time();
         
         // 如果缓存已满，移除最旧的条目
         while self.cache.len() >= self.max_size {
@@ -48,12 +111,25 @@ impl IconCache {
         let entry = IconCacheEntry {
             icon,
             access_time: now,
+            extracted_at: now.checked_sub(age).unwrap_or(now),
         };
 
         self.cache.insert(key.clone(), entry);
         self.access_order.insert(now, key);
     }
 
+    // 调整缓存容量（至少为 1），超出新容量时移除最久未访问的条目
+    pub fn resize(&mut self, max_size: usize) {
+        self.max_size = max_size.max(1);
+        while self.cache.len() > self.max_size {
+            if let Some((_, oldest_key)) = self.access_order.pop_first() {
+                self.cache.remove(&oldest_key);
+            } else {
+                break;
+            }
+        }
+    }
+
     pub fn clear(&mut self) {
         self.cache.clear();
         self.access_order.clear();
@@ -62,30 +138,205 @@ impl IconCache {
     pub fn len(&self) -> usize {
         self.cache.len()
     }
+
+    // 把成功提取的图标（键 -> base64）写入 JSON 文件，提取失败的条目不保存，下次运行重新提取
+    pub fn save_to_disk(&self, path: &Path) -> Result<usize, String> {
+        let persisted: HashMap<&str, PersistedIcon> = self.cache
+            .iter()
+            .filter_map(|(key, entry)| {
+                let icon = entry.icon.clone()?;
+                let extracted_at = unix_now().saturating_sub(entry.extracted_at.elapsed().as_secs());
+                Some((key.as_str(), PersistedIcon { icon, modified: source_modified(key), extracted_at: Some(extracted_at) }))
+            })
+            .collect();
+        let json = serde_json::to_string(&persisted).map_err(|e| format!("序列化图标缓存失败: {}", e))?;
+        // 先写临时文件再重命名覆盖，退出时写入中断不会留下损坏的缓存文件
+        let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+        tmp_name.push(".tmp");
+        let tmp_path = path.with_file_name(tmp_name);
+        std::fs::write(&tmp_path, json).map_err(|e| format!("写入图标缓存文件失败: {}", e))?;
+        if let Err(e) = std::fs::rename(&tmp_path, path) {
+            let _ = std::fs::remove_file(&tmp_path);
+            return Err(format!("替换图标缓存文件失败: {}", e));
+        }
+        Ok(persisted.len())
+    }
+
+    // 从 JSON 文件加载图标，跳过对应文件已被修改或删除（应用更新后需要重新提取图标）以及已超过 TTL 的条目
+    pub fn load_from_disk(&mut self, path: &Path) -> Result<usize, String> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(format!("读取图标缓存文件失败: {}", e)),
+        };
+        let persisted: HashMap<String, PersistedIcon> = serde_json::from_str(&content)
+            .map_err(|e| format!("解析图标缓存文件失败: {}", e))?;
+        let mut loaded = 0;
+        for (key, entry) in persisted {
+            if entry.modified.is_some() && source_modified(&key) != entry.modified {
+                continue;
+            }
+            let age = std::time::Duration::from_secs(
+                entry.extracted_at.map_or(0, |extracted_at| unix_now().saturating_sub(extracted_at))
+            );
+            if self.is_expired(age) {
+                continue;
+            }
+            if loaded >= self.max_size {
+                break;
+            }
+            self.insert_extracted(key, Some(entry.icon), age);
+            loaded += 1;
+        }
+        Ok(loaded)
+    }
 }
 
 // 使用改进的图标缓存
 static ICON_CACHE: std::sync::OnceLock<Arc<RwLock<IconCache>>> = std::sync::OnceLock::new();
 
 pub fn get_icon_cache() -> &'static Arc<RwLock<IconCache>> {
-    ICON_CACHE.get_or_init(|| Arc::new(RwLock::new(IconCache::new(10)))) // 减少到10个条目
+    ICON_CACHE.get_or_init(|| Arc::new(RwLock::new(IconCache::new(DEFAULT_ICON_CACHE_SIZE))))
 }
 
-// 更严格的图标缓存清理
+// 按设置调整图标缓存容量；设置加载前获取的图标已在默认容量的缓存中，超出部分会被移除
+pub fn set_icon_cache_size(max_size: usize) {
+    if let Ok(mut cache_guard) = get_icon_cache().write() {
+        if cache_guard.max_size != max_size {
+            cache_guard.resize(max_size);
+            tracing::info!("图标缓存容量已设置为 {}，当前 {} 项", cache_guard.max_size, cache_guard.len());
+        }
+    }
+}
+
+// 按设置调整图标的有效期（小时），0 表示不过期
+pub fn set_icon_cache_ttl(hours: u64) {
+    let ttl = (hours > 0).then(|| std::time::Duration::from_secs(hours.saturating_mul(3600)));
+    if let Ok(mut cache_guard) = get_icon_cache().write() {
+        if cache_guard.ttl != ttl {
+            cache_guard.ttl = ttl;
+            tracing::info!("图标缓存有效期已设置为 {:?}", ttl);
+        }
+    }
+}
+
+// 启动时加载上次运行保存的图标缓存
+pub fn load_icon_cache_from_disk(path: &Path) {
+    if let Ok(mut cache_guard) = get_icon_cache().write() {
+        match cache_guard.load_from_disk(path) {
+            Ok(loaded) => tracing::info!("已从磁盘加载 {} 个应用图标", loaded),
+            Err(e) => tracing::warn!("⚠️ {}", e),
+        }
+    }
+}
+
+// 退出时把图标缓存保存到磁盘，下次启动无需重新提取
+pub fn save_icon_cache_to_disk(path: &Path) {
+    if let Ok(cache_guard) = get_icon_cache().read() {
+        match cache_guard.save_to_disk(path) {
+            Ok(saved) => tracing::info!("已保存 {} 个应用图标到磁盘", saved),
+            Err(e) => tracing::warn!("⚠️ {}", e),
+        }
+    }
+}
+
+// 图标缓存超出容量时移除最久未访问的条目，容量以内的图标全部保留
 pub fn cleanup_icon_cache() {
     let cache = get_icon_cache();
     if let Ok(mut cache_guard) = cache.write() {
-        if cache_guard.len() > 5 {  // 只保留5个最新的
-            // 清空一半缓存
-            let to_clear = cache_guard.len() / 2;
-            for _ in 0..to_clear {
-                if let Some((_, oldest_key)) = cache_guard.access_order.pop_first() {
-                    cache_guard.cache.remove(&oldest_key);
-                } else {
-                    break;
-                }
-            }
+        if cache_guard.len() > cache_guard.max_size {
+            let max_size = cache_guard.max_size;
+            cache_guard.resize(max_size);
             tracing::info!("清理图标缓存，保留 {} 项", cache_guard.len());
         }
     }
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn persisted_icons_skip_modified_sources() {
+        let dir = tempfile::tempdir().unwrap();
+        let exe_path = dir.path().join("app.exe");
+        std::fs::write(&exe_path, b"v1").unwrap();
+        let exe_key = exe_path.to_string_lossy().to_string();
+        let cache_path = dir.path().join("icon_cache.json");
+
+        let mut cache = IconCache::new(10);
+        cache.insert(exe_key.clone(), Some("exe-icon".to_string()));
+        cache.insert("macos:com.example.app".to_string(), Some("bundle-icon".to_string()));
+        cache.insert("failed".to_string(), None);
+        assert_eq!(cache.save_to_disk(&cache_path).unwrap(), 2);
+        assert!(!dir.path().join("icon_cache.json.tmp").exists());
+
+        let mut restored = IconCache::new(10);
+        assert_eq!(restored.load_from_disk(&cache_path).unwrap(), 2);
+        assert_eq!(restored.get(&exe_key), Some(Some("exe-icon".to_string())));
+        assert_eq!(restored.get("failed"), None);
+
+        // 应用更新后（文件修改时间变化）缓存的图标不再加载
+        let file = std::fs::File::options().write(true).open(&exe_path).unwrap();
+        file.set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(60)).unwrap();
+        let mut reloaded = IconCache::new(10);
+        assert_eq!(reloaded.load_from_disk(&cache_path).unwrap(), 1);
+        assert_eq!(reloaded.get(&exe_key), None);
+        assert_eq!(reloaded.get("macos:com.example.app"), Some(Some("bundle-icon".to_string())));
+
+        assert_eq!(IconCache::new(10).load_from_disk(&dir.path().join("missing.json")).unwrap(), 0);
+    }
+
+    #[test]
+    fn resize_evicts_least_recently_used_icons() {
+        let mut cache = IconCache::new(4);
+        for key in ["a", "b", "c", "d"] {
+            cache.insert(key.to_string(), Some(key.to_string()));
+        }
+        // 访问 a 后它成为最近使用的条目，缩容时先移除 b、c
+        assert!(cache.get("a").is_some());
+        cache.resize(2);
+        assert_eq!(cache.max_size, 2);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.access_order.len(), 2);
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("c").is_none());
+        assert_eq!(cache.get("a"), Some(Some("a".to_string())));
+        assert_eq!(cache.get("d"), Some(Some("d".to_string())));
+
+        // 容量至少为 1，保留最近访问的 d
+        cache.resize(0);
+        assert_eq!(cache.max_size, 1);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get("d"), Some(Some("d".to_string())));
+
+        // 扩容不移除条目
+        cache.resize(8);
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn icons_expire_after_ttl_even_when_accessed() {
+        let mut cache = IconCache::new(10);
+        cache.ttl = Some(std::time::Duration::from_secs(3600));
+        cache.insert("fresh".to_string(), Some("a".to_string()));
+        cache.insert_extracted("stale".to_string(), Some("b".to_string()), std::time::Duration::from_secs(7200));
+
+        assert_eq!(cache.get("fresh"), Some(Some("a".to_string())));
+        assert_eq!(cache.get("stale"), None);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.access_order.len(), 1);
+
+        // 保存时记录提取时间，加载时跳过已过期的图标
+        cache.insert_extracted("stale".to_string(), Some("b".to_string()), std::time::Duration::from_secs(7200));
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = dir.path().join("icon_cache.json");
+        cache.save_to_disk(&cache_path).unwrap();
+        let mut restored = IconCache::new(10);
+        restored.ttl = Some(std::time::Duration::from_secs(3600));
+        assert_eq!(restored.load_from_disk(&cache_path).unwrap(), 1);
+
+        cache.ttl = None;
+        assert_eq!(cache.get("stale"), Some(Some("b".to_string())));
+    }
+}
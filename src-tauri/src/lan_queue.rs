@@ -11,7 +11,13 @@ use uuid::Uuid;
 use sha2::{Digest, Sha256};
 
 const DEDUP_CAPACITY: usize = 512;
-const FRAME_MAX_SIZE: usize = 6 * 1024 * 1024; // 6MB safety cap (images are limited to 5MB)
+const FRAME_MAX_SIZE: usize = 6 * 1024 * 1024; // 6MB hard cap; item payloads are limited by lan_queue_max_item_mb
+const ENVELOPE_HEADROOM: usize = 64 * 1024; // room for the envelope and item fields around a payload
+/// Budget for the items of a history snapshot, leaving room for the envelope within one frame.
+pub(crate) const SNAPSHOT_MAX_BYTES: usize = FRAME_MAX_SIZE - ENVELOPE_HEADROOM;
+const FRAME_READ_TIMEOUT_SECS: u64 = 15; // max time to finish a frame once its first byte arrived
+const CONNECT_TIMEOUT_SECS: u64 = 3; // default timeout for connect/auth when joining a host
+const PEER_IDLE_SECS: i64 = 120; // peers with no frame for this long are reported as idle
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -29,6 +35,26 @@ pub struct LanQueueStatus {
     pub port: Option<u16>,
     pub self_id: String,
     pub self_name: Option<String>,
+    #[serde(default)]
+    pub host_last_seen: Option<String>,
+    #[serde(default)]
+    pub unread_count: usize,
+}
+
+/// Emitted with every received item so the UI can badge/highlight it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LanQueueUnread {
+    pub unread_count: usize,
+    pub item_id: String,
+    pub kind: String,
+    pub sender_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LanQueuePing {
+    pub host: String,
+    pub port: u16,
+    pub rtt_ms: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -37,6 +63,18 @@ pub struct LanQueueMember {
     pub name: Option<String>,
     pub addr: Option<String>,
     pub is_self: bool,
+    #[serde(default)]
+    pub last_seen: Option<String>,
+}
+
+/// A remote peer as tracked locally, with the time its last frame arrived here.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LanQueuePeer {
+    pub id: String,
+    pub name: Option<String>,
+    pub addr: Option<String>,
+    pub last_seen: String,
+    pub active: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -67,6 +105,20 @@ enum LanQueueEnvelope {
     MemberUpdate {
         members: Vec<LanQueueMember>,
     },
+    Revoke {
+        revoke: LanClipboardRevoke,
+    },
+}
+
+/// Asks peers to drop a previously shared item from their local history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LanClipboardRevoke {
+    pub item_id: String,
+    pub origin: String,
+    pub sender_name: Option<String>,
+    /// Local history rows removed on the receiving side (filled in before emitting).
+    #[serde(default)]
+    pub removed_ids: Vec<i64>,
 }
 
 #[derive(Debug)]
@@ -108,6 +160,7 @@ struct PeerHandle {
     sender: mpsc::UnboundedSender<Vec<u8>>,
     name: Option<String>,
     addr: Option<String>,
+    last_seen: String,
 }
 
 #[derive(Debug)]
@@ -124,7 +177,11 @@ pub struct LanQueueState {
     client_write_task: Option<tokio::task::JoinHandle<()>>,
     client_sender: Option<mpsc::UnboundedSender<Vec<u8>>>,
     peers: HashMap<String, PeerHandle>,
+    host_last_seen: Option<String>,
+    host_member: Option<LanQueueMember>,
+    frame_timeout: Duration,
     dedup: DedupCache,
+    unread_count: usize,
 }
 
 impl Default for LanQueueState {
@@ -142,8 +199,62 @@ impl Default for LanQueueState {
             client_write_task: None,
             client_sender: None,
             peers: HashMap::new(),
+            host_last_seen: None,
+            host_member: None,
+            frame_timeout: Duration::from_secs(FRAME_READ_TIMEOUT_SECS),
             dedup: DedupCache::new(DEDUP_CAPACITY),
+            unread_count: 0,
+        }
+    }
+}
+
+/// Dedup key for an item's content, hashed like the history `data_hash` column so a data URL
+/// and its bare base64 payload map to the same key.
+fn content_dedup_key(item: &LanClipboardItem) -> String {
+    format!("content:{}", crate::commands::compute_content_hash(&item.payload))
+}
+
+fn resolve_dedup_capacity(capacity: Option<usize>) -> usize {
+    match capacity {
+        Some(value) if value > 0 => value,
+        _ => DEDUP_CAPACITY,
+    }
+}
+
+fn resolve_frame_timeout(secs: Option<u64>) -> Duration {
+    match secs {
+        Some(value) if value > 0 => Duration::from_secs(value),
+        _ => Duration::from_secs(FRAME_READ_TIMEOUT_SECS),
+    }
+}
+
+fn resolve_connect_timeout(secs: Option<u64>) -> u64 {
+    match secs {
+        Some(value) if value > 0 => value,
+        _ => CONNECT_TIMEOUT_SECS,
+    }
+}
+
+/// Suggests a next step for the socket errors users most often hit when setting up the queue.
+fn io_error_hint(kind: std::io::ErrorKind) -> Option<&'static str> {
+    use std::io::ErrorKind;
+    match kind {
+        ErrorKind::AddrInUse => Some("the port is already in use; try another port or close the program using it"),
+        ErrorKind::AddrNotAvailable => Some("the address is not available on this machine; check the host address"),
+        ErrorKind::PermissionDenied => Some("permission denied; use a port above 1024 or allow the app through the firewall"),
+        ErrorKind::ConnectionRefused => Some("the host refused the connection; make sure the host is running and the port matches"),
+        ErrorKind::NetworkUnreachable | ErrorKind::HostUnreachable => {
+            Some("the host is unreachable; check both devices are on the same network and the firewall allows the port")
         }
+        ErrorKind::TimedOut => Some("the connection timed out; check the host address and firewall settings"),
+        _ => None,
+    }
+}
+
+fn describe_io_error(context: &str, error: &std::io::Error) -> String {
+    match io_error_hint(error.kind()) {
+        Some(hint) => format!("{}: {} ({})", context, error, hint),
+        None => format!("{}: {}", context, error),
     }
 }
 
@@ -177,9 +288,37 @@ fn current_status(state: &LanQueueState) -> LanQueueStatus {
         port: state.port,
         self_id: state.self_id.clone(),
         self_name: state.self_name.clone(),
+        host_last_seen: state.host_last_seen.clone(),
+        unread_count: state.unread_count,
     }
 }
 
+// Counts a received item as unread; the returned event tells the UI who sent it.
+fn count_unread(state: &mut LanQueueState, item: &LanClipboardItem) -> LanQueueUnread {
+    state.unread_count += 1;
+    LanQueueUnread {
+        unread_count: state.unread_count,
+        item_id: item.id.clone(),
+        kind: item.kind.clone(),
+        sender_name: item.sender_name.clone(),
+    }
+}
+
+fn record_unread(app: &AppHandle, state: &mut LanQueueState, item: &LanClipboardItem) {
+    let unread = count_unread(state, item);
+    let _ = app.emit("lan-queue-unread", unread);
+}
+
+// Resets the unread count once the user has looked at the queue.
+fn clear_unread(state: &mut LanQueueState) -> LanQueueStatus {
+    state.unread_count = 0;
+    current_status(state)
+}
+
+fn now_timestamp() -> String {
+    chrono::Utc::now().to_rfc3339()
+}
+
 fn make_members(state: &LanQueueState) -> Vec<LanQueueMember> {
     let mut members = Vec::new();
     members.push(LanQueueMember {
@@ -187,6 +326,7 @@ fn make_members(state: &LanQueueState) -> Vec<LanQueueMember> {
         name: state.self_name.clone(),
         addr: None,
         is_self: true,
+        last_seen: Some(now_timestamp()),
     });
     for (id, peer) in &state.peers {
         members.push(LanQueueMember {
@@ -194,11 +334,48 @@ fn make_members(state: &LanQueueState) -> Vec<LanQueueMember> {
             name: peer.name.clone(),
             addr: peer.addr.clone(),
             is_self: false,
+            last_seen: Some(peer.last_seen.clone()),
         });
     }
     members
 }
 
+/// Remote peers with the time their last frame arrived; peers quiet for longer than
+/// `PEER_IDLE_SECS` are reported as inactive. A client only tracks the host.
+fn tracked_peers(state: &LanQueueState, now: chrono::DateTime<chrono::Utc>) -> Vec<LanQueuePeer> {
+    let is_active = |last_seen: &str| {
+        chrono::DateTime::parse_from_rfc3339(last_seen)
+            .map(|seen| (now - seen.with_timezone(&chrono::Utc)).num_seconds() < PEER_IDLE_SECS)
+            .unwrap_or(false)
+    };
+    let mut peers: Vec<LanQueuePeer> = match state.role {
+        LanQueueRole::Host => state
+            .peers
+            .iter()
+            .map(|(id, peer)| LanQueuePeer {
+                id: id.clone(),
+                name: peer.name.clone(),
+                addr: peer.addr.clone(),
+                last_seen: peer.last_seen.clone(),
+                active: is_active(&peer.last_seen),
+            })
+            .collect(),
+        LanQueueRole::Client => match (&state.host_member, &state.host_last_seen) {
+            (Some(host), Some(last_seen)) => vec![LanQueuePeer {
+                id: host.id.clone(),
+                name: host.name.clone(),
+                addr: state.host.as_ref().zip(state.port).map(|(host, port)| format!("{}:{}", host, port)),
+                last_seen: last_seen.clone(),
+                active: is_active(last_seen),
+            }],
+            _ => Vec::new(),
+        },
+        LanQueueRole::Off => Vec::new(),
+    };
+    peers.sort_by(|a, b| b.last_seen.cmp(&a.last_seen));
+    peers
+}
+
 async fn emit_members(app: &AppHandle, state: &LanQueueState) {
     let members = make_members(state);
     let _ = app.emit("lan-queue-members", members);
@@ -223,21 +400,47 @@ fn build_frame(payload: &[u8]) -> Vec<u8> {
     buf
 }
 
-async fn read_frame<R>(stream: &mut R) -> Result<Vec<u8>, String>
+async fn read_frame<R>(stream: &mut R, read_timeout: Duration) -> Result<Vec<u8>, String>
 where
     R: AsyncReadExt + Unpin,
 {
+    // Idle connections may wait indefinitely for the next frame, but once a frame
+    // has started the rest of it must arrive within `read_timeout`.
     let mut len_buf = [0u8; 4];
-    stream.read_exact(&mut len_buf).await.map_err(|e| e.to_string())?;
+    stream.read_exact(&mut len_buf[..1]).await.map_err(|e| e.to_string())?;
+    timeout(read_timeout, stream.read_exact(&mut len_buf[1..]))
+        .await
+        .map_err(|_| "Frame read timeout".to_string())?
+        .map_err(|e| e.to_string())?;
     let len = u32::from_be_bytes(len_buf) as usize;
     if len == 0 || len > FRAME_MAX_SIZE {
         return Err("Invalid frame size".to_string());
     }
     let mut payload = vec![0u8; len];
-    stream.read_exact(&mut payload).await.map_err(|e| e.to_string())?;
+    timeout(read_timeout, stream.read_exact(&mut payload))
+        .await
+        .map_err(|_| "Frame read timeout".to_string())?
+        .map_err(|e| e.to_string())?;
     Ok(payload)
 }
 
+async fn apply_revoke(app: &AppHandle, mut revoke: LanClipboardRevoke) {
+    revoke.removed_ids = match crate::commands::delete_lan_items(app, &revoke.item_id, &revoke.origin).await {
+        Ok(ids) => ids,
+        Err(e) => {
+            tracing::error!("❌ 撤回 LAN 条目失败 {}: {}", revoke.item_id, e);
+            Vec::new()
+        }
+    };
+    tracing::info!("LAN 条目已撤回: {}，删除 {} 条本地记录", revoke.item_id, revoke.removed_ids.len());
+    let _ = app.emit("lan-clipboard-revoke", revoke);
+}
+
+/// A peer may only revoke items it shared itself, so the revoke must come from its claimed origin.
+fn revoke_matches_sender(revoke: &LanClipboardRevoke, sender_id: &str) -> bool {
+    revoke.origin == sender_id
+}
+
 async fn write_frames(mut stream: OwnedWriteHalf, mut rx: mpsc::UnboundedReceiver<Vec<u8>>) {
     while let Some(frame) = rx.recv().await {
         if stream.write_all(&frame).await.is_err() {
@@ -253,9 +456,11 @@ async fn handle_host_connection(
     mut shutdown_rx: broadcast::Receiver<()>,
 ) {
     let peer_addr = stream.peer_addr().ok().map(|addr| addr.to_string());
-    let auth_payload = match read_frame(&mut stream).await {
-        Ok(payload) => payload,
-        Err(_) => return,
+    let frame_timeout = state.lock().await.frame_timeout;
+    // The auth frame must arrive promptly; otherwise drop the connection.
+    let auth_payload = match timeout(frame_timeout, read_frame(&mut stream, frame_timeout)).await {
+        Ok(Ok(payload)) => payload,
+        _ => return,
     };
     let envelope: LanQueueEnvelope = match serde_json::from_slice(&auth_payload) {
         Ok(value) => value,
@@ -303,6 +508,7 @@ async fn handle_host_connection(
                 sender: tx,
                 name: client_name.clone(),
                 addr: peer_addr.clone(),
+                last_seen: now_timestamp(),
             },
         );
         broadcast_members_to_peers(&mut state_guard).await;
@@ -312,7 +518,7 @@ async fn handle_host_connection(
     let mut read_half = read_half;
     loop {
         let payload = tokio::select! {
-            result = read_frame(&mut read_half) => {
+            result = read_frame(&mut read_half, frame_timeout) => {
                 match result {
                     Ok(payload) => payload,
                     Err(_) => break,
@@ -322,6 +528,12 @@ async fn handle_host_connection(
                 break;
             }
         };
+        {
+            let mut state_guard = state.lock().await;
+            if let Some(peer) = state_guard.peers.get_mut(&client_id) {
+                peer.last_seen = now_timestamp();
+            }
+        }
         let envelope: LanQueueEnvelope = match serde_json::from_slice(&payload) {
             Ok(value) => value,
             Err(_) => continue,
@@ -333,7 +545,9 @@ async fn handle_host_connection(
                     continue;
                 }
                 state_guard.dedup.insert(item.id.clone());
+                state_guard.dedup.insert(content_dedup_key(&item));
                 let _ = app.emit("lan-clipboard-item", item.clone());
+                record_unread(&app, &mut state_guard, &item);
                 for (peer_id, peer) in &state_guard.peers {
                     if peer_id == &client_id {
                         continue;
@@ -344,6 +558,27 @@ async fn handle_host_connection(
                     }
                 }
             }
+            LanQueueEnvelope::Revoke { mut revoke } => {
+                if !revoke_matches_sender(&revoke, &client_id) {
+                    tracing::warn!("⚠️ 忽略来源不匹配的 LAN 撤回: {} (声称 {}，实际 {})", revoke.item_id, revoke.origin, client_id);
+                    continue;
+                }
+                {
+                    let mut state_guard = state.lock().await;
+                    // A late copy of the revoked item must not be re-added.
+                    state_guard.dedup.insert(revoke.item_id.clone());
+                    revoke.removed_ids.clear();
+                    if let Ok(payload) = serde_json::to_vec(&LanQueueEnvelope::Revoke { revoke: revoke.clone() }) {
+                        let frame = build_frame(&payload);
+                        for (peer_id, peer) in &state_guard.peers {
+                            if peer_id != &client_id {
+                                let _ = peer.sender.send(frame.clone());
+                            }
+                        }
+                    }
+                }
+                apply_revoke(&app, revoke).await;
+            }
             _ => {}
         }
     }
@@ -361,11 +596,14 @@ async fn handle_client_stream(
     state: Arc<Mutex<LanQueueState>>,
     mut read_half: OwnedReadHalf,
 ) {
+    let frame_timeout = state.lock().await.frame_timeout;
     loop {
-        let payload = match read_frame(&mut read_half).await {
+        let payload = match read_frame(&mut read_half, frame_timeout).await {
             Ok(payload) => payload,
             Err(_) => break,
         };
+        let host_last_seen = now_timestamp();
+        state.lock().await.host_last_seen = Some(host_last_seen.clone());
         let envelope: LanQueueEnvelope = match serde_json::from_slice(&payload) {
             Ok(value) => value,
             Err(_) => continue,
@@ -377,11 +615,22 @@ async fn handle_client_stream(
                     continue;
                 }
                 state_guard.dedup.insert(item.id.clone());
-                let _ = app.emit("lan-clipboard-item", item);
+                state_guard.dedup.insert(content_dedup_key(&item));
+                let _ = app.emit("lan-clipboard-item", item.clone());
+                record_unread(&app, &mut state_guard, &item);
             }
-            LanQueueEnvelope::MemberUpdate { members } => {
+            LanQueueEnvelope::MemberUpdate { mut members } => {
+                // 成员列表由 host 生成，其中 is_self 的条目即 host 本身，用本地观测到的时间覆盖
+                for member in members.iter_mut().filter(|member| member.is_self) {
+                    member.last_seen = Some(host_last_seen.clone());
+                }
+                state.lock().await.host_member = members.iter().find(|member| member.is_self).cloned();
                 let _ = app.emit("lan-queue-members", members);
             }
+            LanQueueEnvelope::Revoke { revoke } => {
+                state.lock().await.dedup.insert(revoke.item_id.clone());
+                apply_revoke(&app, revoke).await;
+            }
             _ => {}
         }
     }
@@ -389,6 +638,8 @@ async fn handle_client_stream(
     let mut state_guard = state.lock().await;
     state_guard.client_sender = None;
     state_guard.client_write_task = None;
+    state_guard.host_last_seen = None;
+    state_guard.host_member = None;
     state_guard.role = LanQueueRole::Off;
     let _ = app.emit("lan-queue-status", current_status(&state_guard));
     let _ = app.emit("lan-queue-members", Vec::<LanQueueMember>::new());
@@ -401,6 +652,8 @@ pub async fn lan_queue_start_host(
     password: String,
     queue_name: Option<String>,
     member_name: Option<String>,
+    dedup_capacity: Option<usize>,
+    frame_timeout_secs: Option<u64>,
 ) -> Result<LanQueueStatus, String> {
     let state = app.state::<Arc<Mutex<LanQueueState>>>();
     let mut state_guard = state.inner().lock().await;
@@ -418,16 +671,21 @@ pub async fn lan_queue_start_host(
         handle.abort();
     }
     state_guard.client_sender = None;
+    state_guard.host_last_seen = None;
+    state_guard.host_member = None;
+    state_guard.unread_count = 0;
     state_guard.peers.clear();
     state_guard.role = LanQueueRole::Host;
     state_guard.host = Some("0.0.0.0".to_string());
     state_guard.port = Some(port);
     state_guard.self_name = normalize_name(member_name.clone().or(queue_name));
     state_guard.password_hash = Some(hash_password(&password));
+    state_guard.dedup = DedupCache::new(resolve_dedup_capacity(dedup_capacity));
+    state_guard.frame_timeout = resolve_frame_timeout(frame_timeout_secs);
 
     let listener = TcpListener::bind(("0.0.0.0", port))
         .await
-        .map_err(|e| format!("Failed to bind host port: {}", e))?;
+        .map_err(|e| describe_io_error(&format!("Failed to bind host port {}", port), &e))?;
 
     let app_handle = app.clone();
     let state_arc = state.inner().clone();
@@ -461,6 +719,9 @@ pub async fn lan_queue_join(
     port: u16,
     password: String,
     member_name: Option<String>,
+    dedup_capacity: Option<usize>,
+    frame_timeout_secs: Option<u64>,
+    connect_timeout_secs: Option<u64>,
 ) -> Result<LanQueueStatus, String> {
     let state = app.state::<Arc<Mutex<LanQueueState>>>();
     let mut state_guard = state.inner().lock().await;
@@ -478,17 +739,26 @@ pub async fn lan_queue_join(
         handle.abort();
     }
     state_guard.client_sender = None;
+    state_guard.host_last_seen = None;
+    state_guard.host_member = None;
+    state_guard.unread_count = 0;
     state_guard.peers.clear();
     state_guard.role = LanQueueRole::Client;
     state_guard.host = Some(host.clone());
     state_guard.port = Some(port);
     state_guard.self_name = normalize_name(member_name);
     state_guard.password_hash = None;
+    state_guard.dedup = DedupCache::new(resolve_dedup_capacity(dedup_capacity));
+    state_guard.frame_timeout = resolve_frame_timeout(frame_timeout_secs);
 
-    let mut stream = match timeout(Duration::from_secs(3), TcpStream::connect((host.as_str(), port))).await {
+    let connect_timeout_secs = resolve_connect_timeout(connect_timeout_secs);
+    let connect_timeout = Duration::from_secs(connect_timeout_secs);
+    let timeout_error = || format!("Connection timeout ({}s)", connect_timeout_secs);
+
+    let mut stream = match timeout(connect_timeout, TcpStream::connect((host.as_str(), port))).await {
         Ok(Ok(stream)) => stream,
-        Ok(Err(e)) => return Err(format!("Failed to connect: {}", e)),
-        Err(_) => return Err("Connection timeout (3s)".to_string()),
+        Ok(Err(e)) => return Err(describe_io_error(&format!("Failed to connect to {}:{}", host, port), &e)),
+        Err(_) => return Err(timeout_error()),
     };
 
     let auth = LanQueueEnvelope::AuthRequest {
@@ -497,14 +767,14 @@ pub async fn lan_queue_join(
         client_name: state_guard.self_name.clone(),
     };
     let auth_payload = serde_json::to_vec(&auth).map_err(|e| e.to_string())?;
-    timeout(Duration::from_secs(3), stream.write_all(&build_frame(&auth_payload)))
+    timeout(connect_timeout, stream.write_all(&build_frame(&auth_payload)))
         .await
-        .map_err(|_| "Connection timeout (3s)".to_string())?
+        .map_err(|_| timeout_error())?
         .map_err(|e| e.to_string())?;
 
-    let response_payload = timeout(Duration::from_secs(3), read_frame(&mut stream))
+    let response_payload = timeout(connect_timeout, read_frame(&mut stream, connect_timeout))
         .await
-        .map_err(|_| "Connection timeout (3s)".to_string())??;
+        .map_err(|_| timeout_error())??;
     let response: LanQueueEnvelope = serde_json::from_slice(&response_payload).map_err(|e| e.to_string())?;
     match response {
         LanQueueEnvelope::AuthResponse { ok, reason } => {
@@ -531,6 +801,26 @@ pub async fn lan_queue_join(
     Ok(status)
 }
 
+/// Checks that a host is reachable with a plain TCP connect; does not touch `LanQueueState`.
+#[tauri::command]
+pub async fn lan_queue_ping(
+    host: String,
+    port: u16,
+    timeout_secs: Option<u64>,
+) -> Result<LanQueuePing, String> {
+    let timeout_secs = resolve_connect_timeout(timeout_secs);
+    let started = std::time::Instant::now();
+    match timeout(Duration::from_secs(timeout_secs), TcpStream::connect((host.as_str(), port))).await {
+        Ok(Ok(stream)) => {
+            let rtt_ms = started.elapsed().as_millis() as u64;
+            drop(stream);
+            Ok(LanQueuePing { host, port, rtt_ms })
+        }
+        Ok(Err(e)) => Err(describe_io_error(&format!("Failed to connect to {}:{}", host, port), &e)),
+        Err(_) => Err(format!("Connection timeout ({}s)", timeout_secs)),
+    }
+}
+
 #[tauri::command]
 pub async fn lan_queue_leave(app: AppHandle) -> Result<(), String> {
     let state = app.state::<Arc<Mutex<LanQueueState>>>();
@@ -549,6 +839,9 @@ pub async fn lan_queue_leave(app: AppHandle) -> Result<(), String> {
         handle.abort();
     }
     state_guard.client_sender = None;
+    state_guard.host_last_seen = None;
+    state_guard.host_member = None;
+    state_guard.unread_count = 0;
     state_guard.peers.clear();
     state_guard.role = LanQueueRole::Off;
     state_guard.host = None;
@@ -562,10 +855,32 @@ pub async fn lan_queue_leave(app: AppHandle) -> Result<(), String> {
 }
 
 #[tauri::command]
-pub async fn lan_queue_send(
-    app: AppHandle,
-    mut item: LanClipboardItem,
-) -> Result<(), String> {
+pub async fn lan_queue_send(app: AppHandle, item: LanClipboardItem) -> Result<(), String> {
+    let result = send_item(&app, item).await;
+    crate::commands::report_command_error(&app, "lan_queue_send", result)
+}
+
+/// Payload limit for a single item: the configured size, never above what fits in one frame.
+fn item_payload_limit(max_item_mb: u64) -> usize {
+    let configured = usize::try_from(max_item_mb.max(1).saturating_mul(1024 * 1024)).unwrap_or(usize::MAX);
+    configured.min(FRAME_MAX_SIZE - ENVELOPE_HEADROOM)
+}
+
+fn check_item_payload_size(item: &LanClipboardItem, limit: usize) -> Result<(), String> {
+    if item.payload.len() > limit {
+        return Err(format!(
+            "Item is too large to send: {:.1} MB (limit {:.1} MB)",
+            item.payload.len() as f64 / (1024.0 * 1024.0),
+            limit as f64 / (1024.0 * 1024.0)
+        ));
+    }
+    Ok(())
+}
+
+async fn send_item(app: &AppHandle, mut item: LanClipboardItem) -> Result<(), String> {
+    let settings = crate::commands::load_settings(app.clone()).await.unwrap_or_default();
+    check_item_payload_size(&item, item_payload_limit(settings.lan_queue_max_item_mb))?;
+
     let state = app.state::<Arc<Mutex<LanQueueState>>>();
     let mut state_guard = state.inner().lock().await;
 
@@ -582,10 +897,19 @@ pub async fn lan_queue_send(
     if state_guard.dedup.contains(&item.id) {
         return Ok(());
     }
-    state_guard.dedup.insert(item.id.clone());
+    // Content that just arrived from the LAN and was captured again locally is not echoed back.
+    if state_guard.dedup.contains(&content_dedup_key(&item)) {
+        return Ok(());
+    }
 
+    let item_id = item.id.clone();
     let envelope = LanQueueEnvelope::ClipboardItem { item };
     let payload = serde_json::to_vec(&envelope).map_err(|e| e.to_string())?;
+    // Peers drop frames above the cap, so never send one
+    if payload.len() > FRAME_MAX_SIZE {
+        return Err("Item exceeds the maximum frame size".to_string());
+    }
+    state_guard.dedup.insert(item_id);
     let frame = build_frame(&payload);
 
     match state_guard.role {
@@ -607,6 +931,90 @@ pub async fn lan_queue_send(
     Ok(())
 }
 
+#[tauri::command]
+pub async fn lan_queue_revoke(app: AppHandle, item_id: String) -> Result<(), String> {
+    let result = revoke_item(&app, item_id).await;
+    crate::commands::report_command_error(&app, "lan_queue_revoke", result)
+}
+
+async fn revoke_item(app: &AppHandle, item_id: String) -> Result<(), String> {
+    let state = app.state::<Arc<Mutex<LanQueueState>>>();
+    let mut state_guard = state.inner().lock().await;
+
+    if item_id.trim().is_empty() {
+        return Err("Item id is required".to_string());
+    }
+    state_guard.dedup.insert(item_id.clone());
+
+    let envelope = LanQueueEnvelope::Revoke {
+        revoke: LanClipboardRevoke {
+            item_id,
+            origin: state_guard.self_id.clone(),
+            sender_name: state_guard.self_name.clone(),
+            removed_ids: Vec::new(),
+        },
+    };
+    let payload = serde_json::to_vec(&envelope).map_err(|e| e.to_string())?;
+    let frame = build_frame(&payload);
+
+    match state_guard.role {
+        LanQueueRole::Host => {
+            for peer in state_guard.peers.values() {
+                let _ = peer.sender.send(frame.clone());
+            }
+        }
+        LanQueueRole::Client => match &state_guard.client_sender {
+            Some(sender) => {
+                let _ = sender.send(frame);
+            }
+            None => return Err("Not connected".to_string()),
+        },
+        LanQueueRole::Off => return Err("LAN queue is not active".to_string()),
+    }
+    Ok(())
+}
+
+/// Clears the count of items received since the user last looked at the queue.
+#[tauri::command]
+pub async fn mark_lan_items_seen(app: AppHandle) -> Result<LanQueueStatus, String> {
+    let state = app.state::<Arc<Mutex<LanQueueState>>>();
+    let mut state_guard = state.inner().lock().await;
+    let status = clear_unread(&mut state_guard);
+    let _ = app.emit("lan-queue-status", status.clone());
+    Ok(status)
+}
+
+/// Members a manual refresh can rebuild locally; a client only knows what the host last sent.
+fn members_for_refresh(state: &LanQueueState) -> Result<Vec<LanQueueMember>, String> {
+    match state.role {
+        LanQueueRole::Host => Ok(make_members(state)),
+        LanQueueRole::Client => Err("Only the host can refresh the member list".to_string()),
+        LanQueueRole::Off => Ok(Vec::new()),
+    }
+}
+
+/// Re-emits `lan-queue-members` from the current state to recover from a missed update.
+/// With `broadcast`, the host also pushes the list to every peer.
+#[tauri::command]
+pub async fn lan_queue_refresh_members(app: AppHandle, broadcast: Option<bool>) -> Result<Vec<LanQueueMember>, String> {
+    let state = app.state::<Arc<Mutex<LanQueueState>>>();
+    let mut state_guard = state.inner().lock().await;
+    let members = members_for_refresh(&state_guard)?;
+    let _ = app.emit("lan-queue-members", members.clone());
+    if broadcast.unwrap_or(false) && matches!(state_guard.role, LanQueueRole::Host) {
+        broadcast_members_to_peers(&mut state_guard).await;
+    }
+    Ok(members)
+}
+
+/// Lists the remote peers this side has heard from, with their real last-seen timestamps.
+#[tauri::command]
+pub async fn lan_queue_peers(app: AppHandle) -> Result<Vec<LanQueuePeer>, String> {
+    let state = app.state::<Arc<Mutex<LanQueueState>>>();
+    let state_guard = state.inner().lock().await;
+    Ok(tracked_peers(&state_guard, chrono::Utc::now()))
+}
+
 #[tauri::command]
 pub async fn lan_queue_status(app: AppHandle) -> Result<LanQueueStatus, String> {
     let state = app.state::<Arc<Mutex<LanQueueState>>>();
@@ -629,6 +1037,73 @@ mod tests {
         assert_eq!(decoded, payload);
     }
 
+    #[test]
+    fn refresh_members_depends_on_role() {
+        let mut state = LanQueueState {
+            self_name: Some("me".to_string()),
+            ..LanQueueState::default()
+        };
+        assert!(members_for_refresh(&state).unwrap().is_empty());
+
+        state.role = LanQueueRole::Host;
+        let members = members_for_refresh(&state).unwrap();
+        assert_eq!(members.len(), 1);
+        assert!(members[0].is_self);
+        assert_eq!(members[0].name.as_deref(), Some("me"));
+
+        state.role = LanQueueRole::Client;
+        assert!(members_for_refresh(&state).is_err());
+    }
+
+    #[test]
+    fn stale_peers_are_reported_idle() {
+        let now = chrono::Utc::now();
+        let seen = |secs_ago: i64| (now - chrono::Duration::seconds(secs_ago)).to_rfc3339();
+        let peer = |name: &str, last_seen: String| PeerHandle {
+            sender: mpsc::unbounded_channel().0,
+            name: Some(name.to_string()),
+            addr: Some("192.168.1.2:50000".to_string()),
+            last_seen,
+        };
+        let mut state = LanQueueState {
+            role: LanQueueRole::Host,
+            ..LanQueueState::default()
+        };
+        state.peers.insert("fresh".to_string(), peer("alice", seen(5)));
+        state.peers.insert("stale".to_string(), peer("bob", seen(PEER_IDLE_SECS + 1)));
+
+        let peers = tracked_peers(&state, now);
+        assert_eq!(peers.len(), 2);
+        assert_eq!(peers[0].id, "fresh");
+        assert!(peers[0].active);
+        assert_eq!(peers[0].last_seen, seen(5));
+        assert_eq!(peers[1].id, "stale");
+        assert!(!peers[1].active);
+        // The local member is not a tracked peer.
+        assert!(peers.iter().all(|peer| peer.id != state.self_id));
+
+        // A client only tracks the host, using the time the host was last heard from.
+        let mut state = LanQueueState {
+            role: LanQueueRole::Client,
+            host: Some("192.168.1.1".to_string()),
+            port: Some(21991),
+            ..LanQueueState::default()
+        };
+        assert!(tracked_peers(&state, now).is_empty());
+        state.host_last_seen = Some(seen(PEER_IDLE_SECS + 30));
+        state.host_member = Some(LanQueueMember {
+            id: "host".to_string(),
+            name: Some("host".to_string()),
+            addr: None,
+            is_self: true,
+            last_seen: None,
+        });
+        let peers = tracked_peers(&state, now);
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].addr.as_deref(), Some("192.168.1.1:21991"));
+        assert!(!peers[0].active);
+    }
+
     #[test]
     fn dedup_cache_evicts_oldest() {
         let mut cache = DedupCache::new(3);
@@ -641,5 +1116,132 @@ mod tests {
         assert!(cache.contains("b"));
         assert!(cache.contains("c"));
         assert!(cache.contains("d"));
+
+        let mut cache = DedupCache::new(resolve_dedup_capacity(Some(5)));
+        for id in ["a", "b", "c", "d", "e"] {
+            cache.insert(id.to_string());
+        }
+        assert!(cache.contains("a"));
+        cache.insert("f".to_string());
+        assert!(!cache.contains("a"));
+        assert!(cache.contains("b"));
+        assert!(cache.contains("f"));
+
+        assert_eq!(resolve_dedup_capacity(None), DEDUP_CAPACITY);
+        assert_eq!(resolve_dedup_capacity(Some(0)), DEDUP_CAPACITY);
+    }
+
+    #[test]
+    fn content_dedup_key_ignores_data_url_prefix() {
+        let item = |id: &str, payload: &str| LanClipboardItem {
+            id: id.to_string(),
+            kind: "image".to_string(),
+            payload: payload.to_string(),
+            timestamp: "2024-01-01T00:00:00.000Z".to_string(),
+            origin: String::new(),
+            sender_name: None,
+        };
+        let received = item("a", "data:image/png;base64,aGVsbG8=");
+        assert_eq!(content_dedup_key(&received), content_dedup_key(&item("b", "aGVsbG8=")));
+        assert_ne!(content_dedup_key(&received), content_dedup_key(&item("a", "aGVsbG9=")));
+        assert_ne!(content_dedup_key(&received), received.id);
+    }
+
+    #[test]
+    fn unread_count_tracks_received_items_until_seen() {
+        let mut state = LanQueueState {
+            role: LanQueueRole::Client,
+            ..LanQueueState::default()
+        };
+        let item = |id: &str, sender: &str| LanClipboardItem {
+            id: id.to_string(),
+            kind: "text".to_string(),
+            payload: "hello".to_string(),
+            timestamp: "2024-01-01T00:00:00.000Z".to_string(),
+            origin: "peer-1".to_string(),
+            sender_name: Some(sender.to_string()),
+        };
+
+        let first = count_unread(&mut state, &item("a", "alice"));
+        assert_eq!(first.unread_count, 1);
+        let second = count_unread(&mut state, &item("b", "bob"));
+        assert_eq!(second.unread_count, 2);
+        assert_eq!(second.item_id, "b");
+        assert_eq!(second.sender_name.as_deref(), Some("bob"));
+        assert_eq!(current_status(&state).unread_count, 2);
+
+        assert_eq!(clear_unread(&mut state).unread_count, 0);
+        assert_eq!(count_unread(&mut state, &item("c", "alice")).unread_count, 1);
+    }
+
+    #[test]
+    fn revoke_envelope_roundtrip() {
+        let envelope = LanQueueEnvelope::Revoke {
+            revoke: LanClipboardRevoke {
+                item_id: "item-1".to_string(),
+                origin: "peer-1".to_string(),
+                sender_name: None,
+                removed_ids: Vec::new(),
+            },
+        };
+        let payload = serde_json::to_vec(&envelope).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&payload).unwrap();
+        assert_eq!(json["type"], "revoke");
+        match serde_json::from_slice::<LanQueueEnvelope>(&payload).unwrap() {
+            LanQueueEnvelope::Revoke { revoke } => {
+                assert_eq!(revoke.item_id, "item-1");
+                assert_eq!(revoke.origin, "peer-1");
+            }
+            other => panic!("unexpected envelope: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn revoke_must_come_from_its_origin() {
+        let revoke = LanClipboardRevoke {
+            item_id: "item-1".to_string(),
+            origin: "peer-1".to_string(),
+            sender_name: None,
+            removed_ids: Vec::new(),
+        };
+        assert!(revoke_matches_sender(&revoke, "peer-1"));
+        assert!(!revoke_matches_sender(&revoke, "peer-2"));
+    }
+
+    #[test]
+    fn oversized_items_are_rejected_before_sending() {
+        let item = LanClipboardItem {
+            id: "item-1".to_string(),
+            kind: "text".to_string(),
+            payload: "x".repeat(2 * 1024 * 1024),
+            timestamp: String::new(),
+            origin: String::new(),
+            sender_name: None,
+        };
+        assert!(check_item_payload_size(&item, item_payload_limit(5)).is_ok());
+        let err = check_item_payload_size(&item, item_payload_limit(1)).unwrap_err();
+        assert_eq!(err, "Item is too large to send: 2.0 MB (limit 1.0 MB)");
+
+        // The configured limit is capped by the frame size, and 0 is treated as 1MB
+        assert_eq!(item_payload_limit(100), FRAME_MAX_SIZE - ENVELOPE_HEADROOM);
+        assert_eq!(item_payload_limit(0), 1024 * 1024);
+    }
+
+    #[test]
+    fn io_errors_include_hints() {
+        let in_use = std::io::Error::from(std::io::ErrorKind::AddrInUse);
+        let message = describe_io_error("Failed to bind host port 21991", &in_use);
+        assert!(message.starts_with("Failed to bind host port 21991: "));
+        assert!(message.contains("try another port"));
+
+        let other = std::io::Error::other("boom");
+        assert_eq!(describe_io_error("Failed to connect", &other), "Failed to connect: boom");
+    }
+
+    #[test]
+    fn connect_timeout_defaults_when_unset() {
+        assert_eq!(resolve_connect_timeout(None), CONNECT_TIMEOUT_SECS);
+        assert_eq!(resolve_connect_timeout(Some(0)), CONNECT_TIMEOUT_SECS);
+        assert_eq!(resolve_connect_timeout(Some(10)), 10);
     }
 }
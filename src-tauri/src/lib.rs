@@ -31,27 +31,79 @@ use tauri_plugin_global_shortcut::{ShortcutState};
 use tauri::tray::{TrayIconBuilder, TrayIconEvent};
 use tauri::menu::{Menu, MenuItem};
 use tokio::sync::Mutex;
-use sqlx::{SqlitePool, sqlite::SqliteConnectOptions};
+use sqlx::{SqlitePool, sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous}};
 
-// 初始化数据库连接
+// SQLite 遇到锁时的等待时间（秒）
+const DB_BUSY_TIMEOUT_SECS: u64 = 10;
+
+// 连接池大小：未配置时使用默认值，配置值限制在 1..=MAX_DB_MAX_CONNECTIONS
+fn resolve_db_max_connections(configured: Option<u32>) -> u32 {
+    configured
+        .unwrap_or(DEFAULT_DB_MAX_CONNECTIONS)
+        .clamp(1, MAX_DB_MAX_CONNECTIONS)
+}
+
+// 初始化数据库连接（使用设置中保存的历史配置）
 async fn init_database(app: &tauri::AppHandle) -> Result<SqlitePool, String> {
-    let app_data_dir = app.path().app_data_dir().map_err(|e| format!("无法获取应用数据目录: {}", e))?;
-    
+    if let Ok(settings) = commands::load_settings(app.clone()).await {
+        commands::set_active_profile_name(&settings.active_profile);
+        commands::set_db_path_override(commands::configured_db_path(&settings));
+    }
+    let db_path = commands::active_profile_db_path(app)?;
+    open_database(app, &db_path).await
+}
+
+// 打开（必要时创建）数据库文件并完成建表、迁移和索引
+pub(crate) async fn open_database(app: &tauri::AppHandle, db_path: &std::path::Path) -> Result<SqlitePool, String> {
     // 确保目录存在
-    if let Err(e) = std::fs::create_dir_all(&app_data_dir) {
-        return Err(format!("无法创建应用数据目录: {}", e));
+    if let Some(parent) = db_path.parent() {
+        if let Err(e) = std::fs::create_dir_all(parent) {
+            return Err(format!("无法创建应用数据目录: {}", e));
+        }
     }
     
-    let db_path = app_data_dir.join("clipboard.db");
-    
+    // 并发访问（清理 + 插入 + 查询）时等待锁释放，而不是立即返回 "database is locked"
+    // WAL 模式下读写互不阻塞，适合后台清理定时器与频繁插入并存的场景。
+    // synchronous=NORMAL 在 WAL 模式下不会损坏数据库，但系统崩溃或断电时可能丢失最近提交的少量事务，
+    // 对剪贴板历史而言可以接受，换取明显更快的写入速度。
     let options = SqliteConnectOptions::new()
-        .filename(&db_path)
-        .create_if_missing(true);
+        .filename(db_path)
+        .create_if_missing(true)
+        .busy_timeout(std::time::Duration::from_secs(DB_BUSY_TIMEOUT_SECS))
+        .journal_mode(SqliteJournalMode::Wal)
+        .synchronous(SqliteSynchronous::Normal)
+        .pragma("cache_size", "-8000") // 约 8MB 页缓存
+        .pragma("mmap_size", "67108864"); // 64MB 内存映射
     
-    let pool = SqlitePool::connect_with(options)
+    let configured_connections = commands::load_settings(app.clone())
+        .await
+        .map(|settings| settings.db_max_connections)
+        .ok();
+    let max_connections = resolve_db_max_connections(configured_connections);
+    tracing::info!("数据库连接池大小: {}", max_connections);
+
+    let pool = SqlitePoolOptions::new()
+        .max_connections(max_connections)
+        .connect_with(options)
         .await
         .map_err(|e| format!("无法连接到数据库: {}", e))?;
-    
+
+    let list_preview_chars = commands::load_settings(app.clone())
+        .await
+        .unwrap_or_default()
+        .list_preview_chars;
+    migrate_schema(&pool, list_preview_chars).await?;
+
+    // 将旧的绝对图片路径迁移为相对于图片目录的路径
+    commands::migrate_image_paths_to_relative(&pool).await;
+
+    tracing::info!("数据库初始化完成");
+    tracing::info!("已创建数据库索引: type, timestamp, is_favorite, is_pinned, 以及复合索引");
+    Ok(pool)
+}
+
+// 建表、迁移字段并创建索引和触发器（open_database 与测试共用，保证测试使用与实际相同的表结构）
+pub(crate) async fn migrate_schema(pool: &SqlitePool, list_preview_chars: usize) -> Result<(), String> {
     // 直接创建包含所有字段的完整表结构
     sqlx::query(
         "CREATE TABLE IF NOT EXISTS clipboard_history (
@@ -67,38 +119,56 @@ async fn init_database(app: &tauri::AppHandle) -> Result<SqlitePool, String> {
             metadata TEXT
         )"
     )
-    .execute(&pool)
+    .execute(pool)
     .await
     .map_err(|e| format!("无法创建数据库表: {}", e))?;
     
     // 进行数据库迁移 - 添加缩略图字段（如果不存在）
     let _ = sqlx::query("ALTER TABLE clipboard_history ADD COLUMN thumbnail_data TEXT")
-        .execute(&pool)
+        .execute(pool)
         .await; // 忽略错误，因为字段可能已存在
 
     // 进行数据库迁移 - 添加备注字段（如果不存在）
     let _ = sqlx::query("ALTER TABLE clipboard_history ADD COLUMN note TEXT")
-        .execute(&pool)
+        .execute(pool)
         .await; // 忽略错误，因为字段可能已存在
 
     // 添加分组字段（如果不存在）
     let _ = sqlx::query("ALTER TABLE clipboard_history ADD COLUMN group_id INTEGER")
-        .execute(&pool)
+        .execute(pool)
         .await; // 忽略错误，因为字段可能已存在
 
     // 添加数据哈希字段（如果不存在）- 用于去重检测
     let _ = sqlx::query("ALTER TABLE clipboard_history ADD COLUMN data_hash TEXT")
-        .execute(&pool)
+        .execute(pool)
         .await; // 忽略错误，因为字段可能已存在
 
     // 添加元数据字段（如果不存在）- 用于存储图片大小、分辨率等
     let _ = sqlx::query("ALTER TABLE clipboard_history ADD COLUMN metadata TEXT")
-        .execute(&pool)
+        .execute(pool)
         .await; // 忽略错误，因为字段可能已存在
 
     // 添加置顶字段（如果不存在）
     let _ = sqlx::query("ALTER TABLE clipboard_history ADD COLUMN is_pinned INTEGER NOT NULL DEFAULT 0")
-        .execute(&pool)
+        .execute(pool)
+        .await; // 忽略错误，因为字段可能已存在
+
+    // 添加列表预览和内容长度字段（如果不存在），由触发器在写入时维护
+    let _ = sqlx::query("ALTER TABLE clipboard_history ADD COLUMN preview TEXT")
+        .execute(pool)
+        .await; // 忽略错误，因为字段可能已存在
+    let _ = sqlx::query("ALTER TABLE clipboard_history ADD COLUMN content_length INTEGER")
+        .execute(pool)
+        .await; // 忽略错误，因为字段可能已存在
+
+    // 添加图片文件哈希字段（如果不存在）- 用于识别剪贴板编码不同但文件相同的重复图片
+    let _ = sqlx::query("ALTER TABLE clipboard_history ADD COLUMN content_hash TEXT")
+        .execute(pool)
+        .await; // 忽略错误，因为字段可能已存在
+
+    // 添加单条到期时间字段（如果不存在）
+    let _ = sqlx::query("ALTER TABLE clipboard_history ADD COLUMN expires_at TEXT")
+        .execute(pool)
         .await; // 忽略错误，因为字段可能已存在
 
     // 创建分组表
@@ -111,67 +181,177 @@ async fn init_database(app: &tauri::AppHandle) -> Result<SqlitePool, String> {
             item_count INTEGER NOT NULL DEFAULT 0
         )"
     )
-    .execute(&pool)
+    .execute(pool)
     .await
     .map_err(|e| format!("无法创建分组表: {}", e))?;
+
+    // 用触发器维护分组条目数，避免 get_groups 每次都做关联计数
+    commands::ensure_group_count_triggers(pool).await?;
+
+    // 创建标签表（条目删除时由触发器清理标签）
+    commands::ensure_tags_table(pool).await?;
     
     // 创建索引
     sqlx::query("CREATE INDEX IF NOT EXISTS idx_clipboard_content ON clipboard_history(content)")
-        .execute(&pool)
+        .execute(pool)
         .await
         .map_err(|e| format!("无法创建 content 索引: {}", e))?;
     
     // 为 type 字段创建索引以提高查询性能
     sqlx::query("CREATE INDEX IF NOT EXISTS idx_clipboard_type ON clipboard_history(type)")
-        .execute(&pool)
+        .execute(pool)
         .await
         .map_err(|e| format!("无法创建 type 索引: {}", e))?;
     
     // 为 timestamp 字段创建索引以提高排序性能
     sqlx::query("CREATE INDEX IF NOT EXISTS idx_clipboard_timestamp ON clipboard_history(timestamp DESC)")
-        .execute(&pool)
+        .execute(pool)
         .await
         .map_err(|e| format!("无法创建 timestamp 索引: {}", e))?;
     
     // 为 is_favorite 字段创建索引以提高收藏查询性能
     sqlx::query("CREATE INDEX IF NOT EXISTS idx_clipboard_favorite ON clipboard_history(is_favorite)")
-        .execute(&pool)
+        .execute(pool)
         .await
         .map_err(|e| format!("无法创建 is_favorite 索引: {}", e))?;
 
     // 为 data_hash 字段创建索引以提高去重查询性能
     sqlx::query("CREATE INDEX IF NOT EXISTS idx_clipboard_hash ON clipboard_history(data_hash)")
-        .execute(&pool)
+        .execute(pool)
         .await
         .map_err(|e| format!("无法创建 data_hash 索引: {}", e))?;
     
     // 创建复合索引以优化常用查询组合
     sqlx::query("CREATE INDEX IF NOT EXISTS idx_clipboard_type_timestamp ON clipboard_history(type, timestamp DESC)")
-        .execute(&pool)
+        .execute(pool)
         .await
         .map_err(|e| format!("无法创建复合索引: {}", e))?;
     
     // 为收藏查询创建复合索引
     sqlx::query("CREATE INDEX IF NOT EXISTS idx_clipboard_favorite_timestamp ON clipboard_history(is_favorite, timestamp DESC)")
-        .execute(&pool)
+        .execute(pool)
         .await
         .map_err(|e| format!("无法创建收藏复合索引: {}", e))?;
 
     // 为置顶字段创建索引
     sqlx::query("CREATE INDEX IF NOT EXISTS idx_clipboard_pinned ON clipboard_history(is_pinned)")
-        .execute(&pool)
+        .execute(pool)
         .await
         .map_err(|e| format!("无法创建 is_pinned 索引: {}", e))?;
 
     // 为置顶查询创建复合索引
     sqlx::query("CREATE INDEX IF NOT EXISTS idx_clipboard_pinned_timestamp ON clipboard_history(is_pinned DESC, timestamp DESC)")
-        .execute(&pool)
+        .execute(pool)
         .await
         .map_err(|e| format!("无法创建置顶复合索引: {}", e))?;
 
-    tracing::info!("数据库初始化完成");
-    tracing::info!("已创建数据库索引: type, timestamp, is_favorite, is_pinned, 以及复合索引");
-    Ok(pool)
+    // 为图片文件哈希创建部分索引，文本条目没有 content_hash
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_clipboard_content_hash ON clipboard_history(content_hash) WHERE content_hash IS NOT NULL")
+        .execute(pool)
+        .await
+        .map_err(|e| format!("无法创建 content_hash 索引: {}", e))?;
+
+    // 为分组字段创建索引，按分组查询、删除分组和清理时过滤未分组条目都会用到
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_clipboard_group_id ON clipboard_history(group_id)")
+        .execute(pool)
+        .await
+        .map_err(|e| format!("无法创建 group_id 索引: {}", e))?;
+
+    // 为单条到期时间创建部分索引，定时清理只扫描设置了到期时间的条目
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_clipboard_expires_at ON clipboard_history(expires_at) WHERE expires_at IS NOT NULL")
+        .execute(pool)
+        .await
+        .map_err(|e| format!("无法创建 expires_at 索引: {}", e))?;
+
+    // 缺少预览的条目的部分索引，补全完成后为空，启动时检查无需扫描全表
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_clipboard_missing_preview ON clipboard_history(id) WHERE content_length IS NULL")
+        .execute(pool)
+        .await
+        .map_err(|e| format!("无法创建预览索引: {}", e))?;
+
+    // 创建预览触发器并为已有条目生成预览（一次性迁移）
+    commands::sync_content_previews(pool, list_preview_chars).await?;
+
+    // 清空按旧规则计算的图片 data_hash（一次性迁移）
+    commands::migrate_image_data_hashes(pool).await?;
+
+    // 创建全文索引（SQLite 不支持 FTS5 时搜索回退到 LIKE）
+    commands::ensure_fts_index(pool).await;
+    Ok(())
+}
+
+// 空闲检测的检查间隔
+const IDLE_CHECK_INTERVAL_SECS: u64 = 60;
+
+// 单条到期检查的间隔
+const EXPIRY_CHECK_INTERVAL_SECS: u64 = 60;
+
+// 定时删除已到单条到期时间（expires_at）的条目，不受全局保留策略和空闲状态影响
+fn start_expiry_sweeper(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(std::time::Duration::from_secs(EXPIRY_CHECK_INTERVAL_SECS)).await;
+            let settings = commands::load_settings(app.clone()).await.unwrap_or_default();
+            if let Err(e) = commands::purge_expired_items(&app, settings.expiry_protects_favorites).await {
+                tracing::warn!("⚠️ 清理到期条目失败: {}", e);
+            }
+        }
+    });
+}
+
+// 空闲压缩：超过 idle_compaction_minutes 没有捕获、粘贴或打开窗口时执行一次 WAL 检查点（可选清理过期数据），
+// 之后直到下次活动前不再重复执行
+fn start_idle_compaction(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(std::time::Duration::from_secs(IDLE_CHECK_INTERVAL_SECS)).await;
+            let Some(activity) = app.try_state::<ActivityState>() else {
+                continue;
+            };
+            if activity.compacted.load(Ordering::Relaxed) {
+                continue;
+            }
+            let settings = commands::load_settings(app.clone()).await.unwrap_or_default();
+            let idle = activity.idle_for();
+            if !commands::idle_compaction_due(idle, settings.idle_compaction_minutes) {
+                continue;
+            }
+
+            tracing::info!("已空闲 {} 分钟，开始压缩数据库（清理过期数据: {}）", idle.as_secs() / 60, settings.idle_compaction_cleanup);
+            if let Err(e) = commands::compact_when_idle(&app, &settings).await {
+                tracing::warn!("⚠️ 空闲压缩失败: {}", e);
+            }
+            activity.compacted.store(true, Ordering::Relaxed);
+        }
+    });
+}
+
+// 失去焦点后稍作等待再确认，避免快速切换窗口时闪烁
+const BLUR_HIDE_DELAY_MS: u64 = 100;
+
+// 主窗口失去焦点时按 hide_on_blur 设置自动隐藏，并通知前端重置界面
+fn hide_main_window_on_blur(app: tauri::AppHandle) {
+    let should_hide = |app: &tauri::AppHandle| {
+        app.try_state::<BlurHideState>().map(|state| state.should_hide()).unwrap_or(false)
+    };
+    if !should_hide(&app) {
+        return;
+    }
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(std::time::Duration::from_millis(BLUR_HIDE_DELAY_MS)).await;
+        // 等待期间可能重新获得焦点，或前端打开了原生对话框
+        if !should_hide(&app) {
+            return;
+        }
+        if let Some(window) = app.get_webview_window("main") {
+            if window.is_focused().unwrap_or(false) || !window.is_visible().unwrap_or(false) {
+                return;
+            }
+            let _ = window.hide();
+            let _ = app.emit(WINDOW_HIDDEN_ON_BLUR_EVENT, ());
+            tracing::debug!("主窗口失去焦点，已自动隐藏");
+        }
+    });
 }
 
 // 简化的剪贴板监听器 - 使用事件驱动而不是轮询
@@ -259,8 +439,16 @@ pub fn run() {
     
     builder
         .plugin(tauri_plugin_global_shortcut::Builder::new()
-            .with_handler(move |app, _shortcut, event| {
+            .with_handler(move |app, shortcut, event| {
                 if event.state() == ShortcutState::Pressed {
+                    // 手动捕获快捷键：记录当前剪贴板，不切换窗口
+                    if commands::is_manual_capture_shortcut(app, shortcut) {
+                        let app_handle = app.clone();
+                        tauri::async_runtime::spawn(async move {
+                            let _ = commands::capture_current_clipboard(app_handle).await;
+                        });
+                        return;
+                    }
                     if let Some(window) = app.get_webview_window("main") {
                         if window.is_visible().unwrap_or(false) {
                             let _ = window.hide();
@@ -279,8 +467,23 @@ pub fn run() {
             
             // 将剪贴板监听器的停止控制保存到应用状态
             app.manage(ClipboardWatcherState { should_stop: should_stop.clone() });
+            app.manage(LastPastedState::default());
+            app.manage(CaptureGroupState::default());
+            app.manage(BlurHideState::new(true));
+            app.manage(CaptureControlState::default());
+            app.manage(PasteMetricsState::default());
+            app.manage(ActivityState::default());
             app.manage(Arc::new(Mutex::new(lan_queue::LanQueueState::default())));
 
+            // 加载上次运行保存的应用图标，避免启动后重新提取。
+            // 先应用设置中的缓存容量和 TTL，加载时才能按设置淘汰多余和过期的图标
+            let settings = tauri::async_runtime::block_on(commands::load_settings(app_handle.clone())).unwrap_or_default();
+            icon_cache::set_icon_cache_size(settings.icon_cache_size);
+            icon_cache::set_icon_cache_ttl(settings.icon_cache_ttl_hours);
+            if let Ok(app_data_dir) = app.path().app_data_dir() {
+                icon_cache::load_icon_cache_from_disk(&app_data_dir.join(ICON_CACHE_FILE));
+            }
+
             // macOS 专用：初始化 NSPanel 以支持全屏弹窗
             #[cfg(target_os = "macos")]
             {
@@ -307,6 +510,8 @@ pub fn run() {
                         // 将数据库连接池注册为应用状态
                         app_handle_for_delayed.manage(Mutex::new(DatabaseState { pool }));
                         tracing::info!("数据库状态已注册");
+                        start_idle_compaction(app_handle_for_delayed.clone());
+                        start_expiry_sweeper(app_handle_for_delayed.clone());
                         
                         // 加载设置并注册默认快捷键
                         match commands::load_settings(app_handle_for_delayed.clone()).await {
@@ -314,8 +519,15 @@ pub fn run() {
                                 let _ = commands::register_shortcut(app_handle_for_delayed.clone(), settings.hotkey.clone()).await;
                                 // 应用自启动设置
                                 let _ = commands::set_auto_start(app_handle_for_delayed.clone(), settings.auto_start).await;
-                                // 启动时清理过期数据
-                                let _ = commands::cleanup_history(app_handle_for_delayed.clone()).await;
+                                // 应用窗口置顶设置
+                                let _ = commands::set_always_on_top(app_handle_for_delayed.clone(), settings.always_on_top).await;
+                                // 启动时清理过期数据（可在设置中关闭以加快大数据库的启动）
+                                if settings.cleanup_on_startup {
+                                    tracing::info!("执行启动清理");
+                                    let _ = commands::cleanup_history(app_handle_for_delayed.clone()).await;
+                                } else {
+                                    tracing::info!("已跳过启动清理（cleanup_on_startup 已关闭）");
+                                }
                             }
                             Err(_) => {
                                 // 如果没有保存的设置，使用默认快捷键
@@ -333,7 +545,6 @@ pub fn run() {
 
             // 创建系统托盘菜单
             let show_hide_item = MenuItem::with_id(app, "toggle", "显示/隐藏", true, None::<&str>)?;
-            let is_monitoring_paused = Arc::new(AtomicBool::new(false));
             let stop_monitor_item = MenuItem::with_id(app, "stop-monitor", "⏸ 停止监听", true, None::<&str>)?;
             let quit_item = MenuItem::with_id(app, "quit", "退出", true, None::<&str>)?;
             let menu = Menu::with_items(app, &[&show_hide_item, &stop_monitor_item, &quit_item])?;
@@ -364,7 +575,6 @@ pub fn run() {
                 })
                 .on_menu_event({
                     let should_stop_clone = should_stop.clone();
-                    let is_paused_clone = is_monitoring_paused.clone();
                     let stop_item_ref = stop_monitor_item.clone();
                     move |app, event| {
                         let event_id = event.id().as_ref();
@@ -373,9 +583,9 @@ pub fn run() {
                                 toggle_window_visibility(app);
                             }
                             "stop-monitor" => {
-                                let was_paused = is_paused_clone.load(Ordering::Relaxed);
-                                let new_state = !was_paused;
-                                is_paused_clone.store(new_state, Ordering::Relaxed);
+                                // 暂停状态与手动捕获共享，暂停期间手动捕获快捷键也不记录
+                                let Some(capture_control) = app.try_state::<CaptureControlState>() else { return };
+                                let new_state = !capture_control.paused.fetch_xor(true, Ordering::Relaxed);
 
                                 // 直接通过引用更新托盘菜单文字
                                 let _ = if new_state {
@@ -394,6 +604,9 @@ pub fn run() {
                                     let _ = lan_queue::lan_queue_leave(app_handle).await;
                                 });
                                 should_stop_clone.store(true, Ordering::Relaxed);
+                                if let Ok(app_data_dir) = app.path().app_data_dir() {
+                                    icon_cache::save_icon_cache_to_disk(&app_data_dir.join(ICON_CACHE_FILE));
+                                }
                                 tracing::info!("正在停止剪贴板监听器...");
                                 std::thread::sleep(std::time::Duration::from_millis(100));
                                 tracing::info!("应用程序正常退出");
@@ -408,45 +621,119 @@ pub fn run() {
             Ok(())
         })
         .on_window_event(|window, event| {
-            if let tauri::WindowEvent::CloseRequested { .. } = event {
-                let app_handle = window.app_handle().clone();
-                tauri::async_runtime::spawn(async move {
-                    let _ = lan_queue::lan_queue_leave(app_handle).await;
-                });
+            match event {
+                tauri::WindowEvent::CloseRequested { .. } => {
+                    let app_handle = window.app_handle().clone();
+                    tauri::async_runtime::spawn(async move {
+                        let _ = lan_queue::lan_queue_leave(app_handle).await;
+                    });
+                }
+                tauri::WindowEvent::Focused(false) if window.label() == "main" => {
+                    hide_main_window_on_blur(window.app_handle().clone());
+                }
+                tauri::WindowEvent::Focused(true) if window.label() == "main" => {
+                    commands::mark_activity(window.app_handle());
+                }
+                _ => {}
             }
         })
         .invoke_handler(tauri::generate_handler![
             commands::greet,
+            commands::get_capabilities,
             commands::save_settings,
             commands::auto_paste,
             commands::smart_paste_to_app,
+            commands::paste_keep_open,
+            commands::get_last_pasted,
+            commands::repeat_last_paste,
             commands::reset_database,
+            commands::compact_database,
+            commands::describe_schema,
+            commands::rebase_image_paths,
+            commands::get_image_file_path,
             commands::load_image_file,
+            commands::load_image_thumbnail,
+            commands::get_item_image,
             commands::save_clipboard_image,
             commands::get_image_metadata,
             commands::copy_image_to_clipboard,
             commands::cleanup_history,
+            commands::delete_before,
+            commands::clear_transient_history,
+            commands::count_since,
+            commands::get_growth_stats,
+            commands::get_statistics,
+            commands::set_item_expiry,
+            commands::backfill_content_previews,
+            commands::get_profiles,
+            commands::set_active_profile,
+            commands::move_database,
+            commands::prepare_captured_text,
+            commands::content_hash,
+            commands::estimate_image_cleanup,
+            commands::get_image_usage_by_app,
             commands::load_settings,
+            commands::export_settings,
+            commands::import_settings,
             commands::set_auto_start,
+            commands::set_always_on_top,
+            commands::set_blur_hide_suppressed,
+            commands::set_manual_capture_mode,
+            commands::capture_current_clipboard,
+            commands::is_app_blacklisted,
+            commands::resize_icon_cache,
             commands::get_auto_start_status,
             commands::register_shortcut,
+            commands::reapply_shortcuts,
+            commands::probe_hotkey,
             window_info::get_active_window_info,
             window_info::get_active_window_info_with_icon,
             window_info::get_active_window_info_for_clipboard,
             // 日志相关命令
             commands::open_log_folder,
+            commands::export_logs_bundle,
             commands::delete_all_logs,
             commands::write_frontend_log,
             // 备注管理命令
             commands::update_item_note,
+            commands::set_item_favorite,
             commands::get_item_note,
+            // 条目查询命令
+            commands::get_item,
+            commands::get_source_apps,
+            commands::diff_items,
+            commands::find_and_replace,
+            commands::get_paste_metrics,
+            commands::paste_image_item,
+            commands::import_external_settings,
+            commands::get_ui_state,
+            commands::set_ui_state,
+            commands::refresh_item_icon,
+            commands::find_by_content,
+            commands::get_items_by_ids,
+            commands::notify_item_added,
+            commands::get_history,
+            commands::search_history,
+            commands::get_recent_for_sync,
+            commands::batch_insert,
+            commands::upsert_history_item,
             // 分组管理命令
             commands::create_group,
             commands::get_groups,
             commands::update_group,
             commands::delete_group,
+            commands::recalculate_group_counts,
+            commands::repair_group_references,
+            commands::merge_groups,
             commands::add_item_to_group,
+            commands::set_capture_group,
+            commands::get_capture_group,
             commands::delete_item,
+            commands::delete_history_item,
+            commands::add_tag,
+            commands::remove_tag,
+            commands::get_tags,
+            commands::get_items_by_tag,
             // 文件剪贴板相关命令
             commands::copy_files_to_clipboard,
             commands::get_file_metadata,
@@ -457,12 +744,20 @@ pub fn run() {
             commands::read_text_file,
             lan_queue::lan_queue_start_host,
             lan_queue::lan_queue_join,
+            lan_queue::lan_queue_ping,
             lan_queue::lan_queue_leave,
             lan_queue::lan_queue_send,
+            lan_queue::lan_queue_revoke,
+            lan_queue::mark_lan_items_seen,
             lan_queue::lan_queue_status,
+            lan_queue::lan_queue_peers,
+            lan_queue::lan_queue_refresh_members,
             // 数据导入导出命令
             commands::export_data,
-            commands::import_data
+            commands::export_history,
+            commands::import_data,
+            commands::export_item,
+            commands::copy_as_markdown_link
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -538,3 +833,16 @@ fn show_window_with_context(app: &tauri::AppHandle) {
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn db_pool_size_defaults_and_clamps() {
+        assert_eq!(resolve_db_max_connections(None), DEFAULT_DB_MAX_CONNECTIONS);
+        assert_eq!(resolve_db_max_connections(Some(2)), 2);
+        assert_eq!(resolve_db_max_connections(Some(0)), 1);
+        assert_eq!(resolve_db_max_connections(Some(1000)), MAX_DB_MAX_CONNECTIONS);
+    }
+}
@@ -1,5 +1,6 @@
 use std::fs;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use tracing_appender::{non_blocking, rolling};
 use tracing_subscriber::{
     fmt::time::LocalTime,
@@ -10,6 +11,42 @@ use tracing_subscriber::{
 };
 use backtrace::Backtrace;
 
+/// 生产环境 panic 时默认解析的堆栈帧数
+const PRODUCTION_BACKTRACE_FRAMES: usize = 16;
+
+/// panic 时堆栈跟踪的捕获方式（panic 信息和位置始终会记录）
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BacktraceMode {
+    /// 捕获并解析完整堆栈
+    Full,
+    /// 只解析 panic 位置起的前若干帧，减少日志体积和 panic 时的开销
+    Limited(usize),
+    /// 不捕获堆栈
+    Off,
+}
+
+impl BacktraceMode {
+    /// 解析 CLIPBOARD_MANAGER_BACKTRACE 环境变量："full"、"off"/"0"，或正整数表示帧数
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "full" => Some(Self::Full),
+            "off" | "0" => Some(Self::Off),
+            frames => frames.parse().ok().map(Self::Limited),
+        }
+    }
+
+    /// 开发环境保留完整堆栈，生产环境只解析 panic 位置附近的帧；env_value 为环境变量的值，可覆盖默认方式
+    fn resolve(env_value: Option<&str>, is_production: bool) -> Self {
+        env_value
+            .and_then(Self::parse)
+            .unwrap_or(if is_production {
+                Self::Limited(PRODUCTION_BACKTRACE_FRAMES)
+            } else {
+                Self::Full
+            })
+    }
+}
+
 /// 日志配置结构
 #[derive(Clone)]
 pub struct LogConfig {
@@ -18,20 +55,57 @@ pub struct LogConfig {
     pub max_log_files: usize,
     pub is_production: bool,
     pub console_enabled: bool,
+    pub backtrace: BacktraceMode,
 }
 
 impl Default for LogConfig {
     fn default() -> Self {
+        let is_production = !cfg!(debug_assertions);
         Self {
             app_name: "clipboard-manager".to_string(),
             log_dir: get_app_log_dir(),
             max_log_files: 30, // 保留30天的日志
-            is_production: !cfg!(debug_assertions),
+            is_production,
             console_enabled: true, // 总是启用控制台输出以便调试
+            backtrace: BacktraceMode::resolve(
+                std::env::var("CLIPBOARD_MANAGER_BACKTRACE").ok().as_deref(),
+                is_production,
+            ),
         }
     }
 }
 
+/// 日志中剪贴板内容/备注预览的最大字符数，0 表示日志中不记录任何内容
+static CONTENT_PREVIEW_LEN: AtomicUsize = AtomicUsize::new(32);
+
+/// 设置日志内容预览长度（由设置加载/保存时调用）
+pub fn set_content_preview_len(len: usize) {
+    CONTENT_PREVIEW_LEN.store(len, Ordering::Relaxed);
+}
+
+/// 获取当前的日志内容预览长度
+pub fn content_preview_len() -> usize {
+    CONTENT_PREVIEW_LEN.load(Ordering::Relaxed)
+}
+
+/// 生成用于日志输出的内容预览：超出长度的部分截断，长度为 0 时完全隐藏
+pub fn preview(content: &str) -> String {
+    preview_with_len(content, content_preview_len())
+}
+
+/// 按指定长度生成内容预览，规则同 preview
+pub fn preview_with_len(content: &str, max_len: usize) -> String {
+    let char_count = content.chars().count();
+    if max_len == 0 {
+        format!("<已隐藏 {} 字符>", char_count)
+    } else if char_count > max_len {
+        let truncated: String = content.chars().take(max_len).collect();
+        format!("{}…(共 {} 字符)", truncated, char_count)
+    } else {
+        content.to_string()
+    }
+}
+
 /// 获取应用程序日志目录（位于程序安装目录）
 fn get_app_log_dir() -> PathBuf {
     // 尝试获取程序执行路径
@@ -45,17 +119,107 @@ fn get_app_log_dir() -> PathBuf {
     PathBuf::from(".").join("logs")
 }
 
+// panic 处理流程本身的帧（符号名可能带有 "std[hash]::" 形式的修饰，因此只匹配路径片段）
+fn is_panic_machinery_frame(name: &str) -> bool {
+    [
+        "panicking::",
+        "PanicHookInfo",
+        "rust_begin_unwind",
+        "__rust_end_short_backtrace",
+    ]
+    .iter()
+    .any(|pattern| name.contains(pattern))
+}
+
+// 逐帧格式化有限长度的堆栈：跳过捕获堆栈、panic hook 和 panic 处理流程的帧，从触发 panic 的代码开始记录
+struct LimitedBacktrace {
+    max_frames: usize,
+    lines: Vec<String>,
+    frame_count: usize,
+    seen_panic_machinery: bool,
+    truncated: bool,
+}
+
+impl LimitedBacktrace {
+    fn new(max_frames: usize) -> Self {
+        Self { max_frames, lines: Vec::new(), frame_count: 0, seen_panic_machinery: false, truncated: false }
+    }
+
+    // 处理一帧的符号 (名称, 位置)，返回 false 表示已收集到足够的帧、无需继续遍历
+    fn push_frame(&mut self, symbols: Vec<(String, Option<String>)>, ip: *mut std::ffi::c_void) -> bool {
+        if self.frame_count == 0 {
+            if symbols.iter().any(|(name, _)| is_panic_machinery_frame(name)) {
+                self.seen_panic_machinery = true;
+                return true;
+            }
+            // 没有符号信息时无法识别 panic 处理帧，直接从当前帧开始记录
+            if !self.seen_panic_machinery && !symbols.is_empty() {
+                return true;
+            }
+        }
+
+        if self.frame_count >= self.max_frames {
+            self.truncated = true;
+            return false;
+        }
+        if symbols.is_empty() {
+            self.lines.push(format!("{:>4}: {:?}", self.frame_count, ip));
+        }
+        for (name, location) in symbols {
+            self.lines.push(format!("{:>4}: {}", self.frame_count, name));
+            if let Some(location) = location {
+                self.lines.push(format!("             at {}", location));
+            }
+        }
+        self.frame_count += 1;
+        true
+    }
+
+    fn finish(mut self) -> String {
+        if self.truncated {
+            self.lines.push(format!("      ...（仅显示前 {} 帧）", self.max_frames));
+        }
+        self.lines.join("\n")
+    }
+}
+
+// 按设置捕获堆栈跟踪文本，Off 时返回 None
+fn capture_backtrace(mode: BacktraceMode) -> Option<String> {
+    let max_frames = match mode {
+        BacktraceMode::Off => return None,
+        BacktraceMode::Full => return Some(format!("{:?}", Backtrace::new())),
+        BacktraceMode::Limited(max_frames) => max_frames,
+    };
+
+    // 逐帧解析符号，收集到足够的帧后停止，避免解析整个堆栈
+    let mut limited = LimitedBacktrace::new(max_frames);
+    backtrace::trace(|frame| {
+        let mut symbols = Vec::new();
+        backtrace::resolve_frame(frame, |symbol| {
+            let name = symbol.name().map(|name| name.to_string()).unwrap_or_else(|| "<unknown>".to_string());
+            let location = match (symbol.filename(), symbol.lineno()) {
+                (Some(file), Some(line)) => Some(format!("{}:{}", file.display(), line)),
+                _ => None,
+            };
+            symbols.push((name, location));
+        });
+        limited.push_frame(symbols, frame.ip())
+    });
+    Some(limited.finish())
+}
+
 /// 初始化日志系统
 pub fn init_logging(config: LogConfig) -> Result<(), Box<dyn std::error::Error>> {
-    // 设置 RUST_BACKTRACE 环境变量以启用堆栈跟踪
-    if std::env::var("RUST_BACKTRACE").is_err() {
+    // 完整堆栈模式下设置 RUST_BACKTRACE 环境变量以启用堆栈跟踪
+    if config.backtrace == BacktraceMode::Full && std::env::var("RUST_BACKTRACE").is_err() {
         unsafe {
             std::env::set_var("RUST_BACKTRACE", "1");
         }
     }
     
     // 设置 panic hook 来捕获并记录 panic 异常
-    std::panic::set_hook(Box::new(|panic_info| {
+    let backtrace_mode = config.backtrace;
+    std::panic::set_hook(Box::new(move |panic_info| {
         let panic_message = if let Some(s) = panic_info.payload().downcast_ref::<&str>() {
             s.to_string()
         } else if let Some(s) = panic_info.payload().downcast_ref::<String>() {
@@ -70,16 +234,16 @@ pub fn init_logging(config: LogConfig) -> Result<(), Box<dyn std::error::Error>>
             " at unknown location".to_string()
         };
         
-        // 获取堆栈跟踪
-        let bt = Backtrace::new();
-        
-        // 使用 eprintln! 确保在日志系统失败时也能输出
+        // 使用 eprintln! 确保在日志系统失败时也能输出；panic 信息先于堆栈记录，避免捕获堆栈出错时丢失
         eprintln!("🔥 PANIC: {}{}", panic_message, location);
-        eprintln!("🔥 BACKTRACE:\n{:?}", bt);
-        
         // 如果 tracing 已初始化，也记录到日志文件
         tracing::error!("🔥 PANIC: {}{}", panic_message, location);
-        tracing::error!("🔥 BACKTRACE:\n{:?}", bt);
+
+        // 获取堆栈跟踪
+        if let Some(bt) = capture_backtrace(backtrace_mode) {
+            eprintln!("🔥 BACKTRACE:\n{}", bt);
+            tracing::error!("🔥 BACKTRACE:\n{}", bt);
+        }
     }));
     
     // 确保日志目录存在
@@ -146,6 +310,7 @@ pub fn init_logging(config: LogConfig) -> Result<(), Box<dyn std::error::Error>>
         app_name = %config.app_name,
         log_dir = %config.log_dir.display(),
         is_production = config.is_production,
+        backtrace = ?config.backtrace,
         "日志系统初始化完成"
     );
 
@@ -251,4 +416,61 @@ pub fn redirect_stdio_to_log() -> Result<(), Box<dyn std::error::Error>> {
 pub fn redirect_stdio_to_log() -> Result<(), Box<dyn std::error::Error>> {
     // 在非Windows平台上，stdio重定向由系统处理
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backtrace_mode_defaults_and_env_override() {
+        assert_eq!(BacktraceMode::resolve(None, false), BacktraceMode::Full);
+        assert_eq!(BacktraceMode::resolve(None, true), BacktraceMode::Limited(PRODUCTION_BACKTRACE_FRAMES));
+        assert_eq!(BacktraceMode::resolve(Some("full"), true), BacktraceMode::Full);
+        assert_eq!(BacktraceMode::resolve(Some(" OFF "), false), BacktraceMode::Off);
+        assert_eq!(BacktraceMode::resolve(Some("0"), false), BacktraceMode::Off);
+        assert_eq!(BacktraceMode::resolve(Some("8"), false), BacktraceMode::Limited(8));
+        // 无法识别的值回退到默认方式
+        assert_eq!(BacktraceMode::resolve(Some("lots"), true), BacktraceMode::Limited(PRODUCTION_BACKTRACE_FRAMES));
+    }
+
+    // 不安装全局 panic hook：用构造的帧驱动格式化，避免影响并行运行的其他测试
+    #[test]
+    fn limited_backtrace_starts_at_panic_location() {
+        assert_eq!(capture_backtrace(BacktraceMode::Off), None);
+
+        let frame = |name: &str| vec![(name.to_string(), Some(format!("src/{}.rs:1", name)))];
+        let frames = [
+            frame("backtrace::backtrace::trace"),
+            frame("clipboard_manager::logging::capture_backtrace"),
+            frame("std::panicking::rust_panic_with_hook"),
+            frame("std::panicking::begin_panic_handler"),
+            frame("clipboard_manager::commands::copy_item"),
+            Vec::new(),
+            frame("tokio::runtime::task::poll"),
+        ];
+        let mut limited = LimitedBacktrace::new(2);
+        let mut visited = 0;
+        for symbols in frames {
+            visited += 1;
+            if !limited.push_frame(symbols, std::ptr::null_mut()) {
+                break;
+            }
+        }
+        let backtrace = limited.finish();
+
+        // 跳过 panic hook 和 panic 处理流程的帧，第一帧是触发 panic 的代码；收集够帧数后停止遍历
+        let frame_lines: Vec<&str> = backtrace.lines().filter(|line| !line.starts_with("      ")).collect();
+        assert_eq!(frame_lines.len(), 2, "{}", backtrace);
+        assert!(frame_lines[0].starts_with("   0: clipboard_manager::commands::copy_item"), "{}", backtrace);
+        assert!(frame_lines[1].starts_with("   1: 0x0"), "{}", backtrace);
+        assert_eq!(visited, 7);
+        assert!(backtrace.ends_with("（仅显示前 2 帧）"));
+
+        // 没有符号信息时无法识别 panic 处理帧，从第一个无符号的帧开始记录
+        let mut unsymbolized = LimitedBacktrace::new(1);
+        assert!(unsymbolized.push_frame(frame("clipboard_manager::main"), std::ptr::null_mut()));
+        assert!(unsymbolized.push_frame(Vec::new(), std::ptr::null_mut()));
+        assert_eq!(unsymbolized.finish(), "   0: 0x0");
+    }
+}
@@ -1,17 +1,48 @@
 use serde::{Deserialize, Serialize};
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::collections::HashMap;
 use std::sync::Arc;
 use sqlx::SqlitePool;
 
 // 常量定义
 pub const SETTINGS_FILE: &str = "clipboard_settings.json";
+// UI 视图状态文件（位于应用数据目录），与设置分开存储
+pub const UI_STATE_FILE: &str = "ui_state.json";
+// 来源应用图标缓存文件（位于应用数据目录），退出时保存、启动时加载
+pub const ICON_CACHE_FILE: &str = "icon_cache.json";
+// 支持的剪贴板捕获格式（默认优先级顺序）
+pub const CAPTURE_FORMATS: [&str; 4] = ["files", "image", "text", "html"];
+// 历史记录中的条目类型
+pub const CLIPBOARD_ITEM_TYPES: [&str; 3] = ["text", "image", "file"];
+// SQLite 连接池的默认/最大连接数：WAL 模式下只允许一个写者，少量连接即可满足并发读
+pub const DEFAULT_DB_MAX_CONNECTIONS: u32 = 4;
+pub const MAX_DB_MAX_CONNECTIONS: u32 = 16;
+// 来源应用图标缓存的默认条目数
+pub const DEFAULT_ICON_CACHE_SIZE: usize = 10;
+// 来源应用图标的默认有效期（小时），过期后重新提取，应用更新后图标随之更新
+pub const DEFAULT_ICON_CACHE_TTL_HOURS: u64 = 24;
+pub const MAX_ICON_CACHE_TTL_HOURS: u64 = 24 * 365;
+// 列表预览字符数的范围
+pub const LIST_PREVIEW_MIN_CHARS: usize = 100;
+pub const LIST_PREVIEW_MAX_CHARS: usize = 100_000;
+// 设置文件的结构版本，结构变化需要迁移旧文件时递增（见 commands::migrate_settings）
+pub const SETTINGS_VERSION: u32 = 1;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AppSettings {
+    // 设置文件的结构版本，没有该字段的旧文件视为版本 0
+    #[serde(default)]
+    pub version: u32,
     pub max_history_items: usize,
     pub max_history_time: u64,
+    // 按条目类型（text/image/file）单独设置的保留天数，未设置的类型使用 max_history_time
+    #[serde(default)]
+    pub retention_by_type: HashMap<String, u64>,
     pub hotkey: String,
     pub auto_start: bool,
+    // 自启动项使用的名称，改名构建或多实例时可修改以避免互相覆盖
+    #[serde(default)]
+    pub auto_start_names: AutoStartNames,
     #[serde(default = "default_lan_queue_role")]
     pub lan_queue_role: String,
     #[serde(default)]
@@ -24,14 +55,304 @@ pub struct AppSettings {
     pub lan_queue_name: String,
     #[serde(default)]
     pub lan_queue_member_name: String,
+    // LAN 队列单个条目的最大载荷（MB），超出时发送前直接报错
+    #[serde(default = "default_lan_queue_max_item_mb")]
+    pub lan_queue_max_item_mb: u64,
     #[serde(default = "default_theme")]
     pub theme: String,
+    // 复制为 Markdown 链接时是否联网获取网页标题
+    #[serde(default)]
+    pub fetch_link_titles: bool,
+    // 日志中内容/备注预览的最大字符数，0 表示日志中不记录内容
+    #[serde(default = "default_log_content_preview_len")]
+    pub log_content_preview_len: usize,
+    // 列表中文本条目预览的最大字符数（写入时生成 preview 列，列表查询不读取完整内容）
+    #[serde(default = "default_list_preview_chars")]
+    pub list_preview_chars: usize,
+    // 是否记录剪贴板中的图片（关闭后只记录文本）
+    #[serde(default = "default_true")]
+    pub capture_images: bool,
+    // 列表中的图片缩略图是否模糊显示（屏幕共享时保护隐私，选中条目后仍显示原图）
+    #[serde(default)]
+    pub blur_thumbnails: bool,
+    // 粘贴后保持窗口打开，便于连续粘贴多个条目
+    #[serde(default)]
+    pub paste_keep_open: bool,
+    // 主窗口是否置顶显示（macOS 上主窗口是浮动 NSPanel，始终位于普通窗口之上）；
+    // 默认开启，与 tauri.conf.json 中窗口的 alwaysOnTop 一致，旧设置文件没有该项时保持置顶
+    #[serde(default = "default_true")]
+    pub always_on_top: bool,
+    // 主窗口失去焦点时是否自动隐藏
+    #[serde(default = "default_true")]
+    pub hide_on_blur: bool,
+    // 收藏条目的数量上限，0 表示不限制
+    #[serde(default)]
+    pub max_favorites: usize,
+    // 收藏超出上限时的处理方式："unfavorite"（自动取消最旧收藏）、"keep"（不处理）
+    #[serde(default = "default_favorite_overflow_policy")]
+    pub favorite_overflow_policy: String,
+    // 空闲多少分钟后执行 WAL 检查点压缩数据库，0 表示关闭
+    #[serde(default = "default_idle_compaction_minutes")]
+    pub idle_compaction_minutes: u64,
+    // 空闲压缩时是否同时清理过期数据
+    #[serde(default)]
+    pub idle_compaction_cleanup: bool,
+    // 启动时是否立即清理过期数据（关闭后由定时清理或手动清理处理）
+    #[serde(default = "default_true")]
+    pub cleanup_on_startup: bool,
+    // 条目单独设置的到期时间（expires_at）到期后是否保留收藏的条目
+    #[serde(default)]
+    pub expiry_protects_favorites: bool,
+    // 当前使用的历史配置（每个配置使用独立的数据库文件和图片目录）
+    #[serde(default = "default_profile_name")]
+    pub active_profile: String,
+    // 自定义的数据库文件位置（默认配置的数据库文件，其他配置的数据库放在同一目录），None 表示使用应用数据目录
+    #[serde(default)]
+    pub db_path: Option<String>,
+    // 捕获文本时的换行符规范化方式："as_is"（保持原样）、"lf"、"crlf"
+    #[serde(default = "default_normalize_line_endings")]
+    pub normalize_line_endings: String,
+    // 捕获文本时去除首尾空白："off"（保持原样）、"both"、"leading"、"trailing"；
+    // 在哈希和去重之前处理，只有空白的文本保持原样
+    #[serde(default = "default_trim_on_capture")]
+    pub trim_on_capture: String,
+    // 手动捕获模式：不自动记录剪贴板，只在按下 manual_capture_hotkey 时记录当前剪贴板内容
+    #[serde(default)]
+    pub manual_capture_mode: bool,
+    #[serde(default = "default_manual_capture_hotkey")]
+    pub manual_capture_hotkey: String,
+    // 启用的剪贴板格式（"files"、"image"、"text"、"html"），按优先级排序：
+    // 剪贴板同时包含多种格式时只记录优先级最高的一种；"html" 表示为文本条目额外保留富文本
+    #[serde(default = "default_capture_formats")]
+    pub capture_formats: Vec<String>,
+    // 不记录的来源应用（应用名称或 bundle id，不区分大小写），例如密码管理器
+    #[serde(default)]
+    pub blacklisted_apps: Vec<String>,
+    // 来源应用图标缓存的最大条目数，来源应用很多时可以调大，内存紧张时调小
+    #[serde(default = "default_icon_cache_size")]
+    pub icon_cache_size: usize,
+    // 缓存的应用图标在提取多少小时后重新提取，0 表示不过期
+    #[serde(default = "default_icon_cache_ttl_hours")]
+    pub icon_cache_ttl_hours: u64,
+    // 查询历史时直接过滤超过 max_history_time 天的条目（收藏/置顶/分组除外），无需等待定时清理
+    #[serde(default)]
+    pub enforce_max_age_on_query: bool,
+    // 数据库连接池的最大连接数（重启后生效）
+    #[serde(default = "default_db_max_connections")]
+    pub db_max_connections: u32,
+    // 图片的存储格式："png"（原样保存）、"png_optimized"、"webp_lossless"、"jpeg"
+    #[serde(default = "default_image_storage_format")]
+    pub image_storage_format: String,
+    // 有损格式（jpeg）的压缩质量，1-100
+    #[serde(default = "default_image_storage_quality")]
+    pub image_storage_quality: u8,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            max_history_items: 100,
+            max_history_time: 30,
+            retention_by_type: HashMap::new(),
+            hotkey: "Ctrl+Shift+V".to_string(),
+            auto_start: false,
+            auto_start_names: AutoStartNames::default(),
+            lan_queue_role: default_lan_queue_role(),
+            lan_queue_host: String::new(),
+            lan_queue_port: default_lan_queue_port(),
+            lan_queue_password: String::new(),
+            lan_queue_name: default_lan_queue_name(),
+            lan_queue_member_name: String::new(),
+            lan_queue_max_item_mb: default_lan_queue_max_item_mb(),
+            theme: default_theme(),
+            fetch_link_titles: false,
+            log_content_preview_len: default_log_content_preview_len(),
+            list_preview_chars: default_list_preview_chars(),
+            capture_images: true,
+            blur_thumbnails: false,
+            paste_keep_open: false,
+            always_on_top: true,
+            hide_on_blur: true,
+            max_favorites: 0,
+            favorite_overflow_policy: default_favorite_overflow_policy(),
+            idle_compaction_minutes: default_idle_compaction_minutes(),
+            idle_compaction_cleanup: false,
+            cleanup_on_startup: true,
+            expiry_protects_favorites: false,
+            active_profile: default_profile_name(),
+            db_path: None,
+            normalize_line_endings: default_normalize_line_endings(),
+            trim_on_capture: default_trim_on_capture(),
+            manual_capture_mode: false,
+            manual_capture_hotkey: default_manual_capture_hotkey(),
+            capture_formats: default_capture_formats(),
+            blacklisted_apps: Vec::new(),
+            icon_cache_size: DEFAULT_ICON_CACHE_SIZE,
+            icon_cache_ttl_hours: DEFAULT_ICON_CACHE_TTL_HOURS,
+            enforce_max_age_on_query: false,
+            db_max_connections: default_db_max_connections(),
+            image_storage_format: default_image_storage_format(),
+            image_storage_quality: default_image_storage_quality(),
+        }
+    }
+}
+
+impl AppSettings {
+    // 保存前检查设置取值，返回可以直接展示给用户的错误信息
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_history_items < 1 {
+            return Err("最大历史条目数必须至少为 1".to_string());
+        }
+        if self.max_history_time < 1 {
+            return Err("历史保留天数必须至少为 1 天".to_string());
+        }
+        if self.hotkey.trim().is_empty() {
+            return Err("快捷键不能为空".to_string());
+        }
+        if let Some(item_type) = self.retention_by_type.keys().find(|item_type| !CLIPBOARD_ITEM_TYPES.contains(&item_type.as_str())) {
+            return Err(format!("不支持按类型设置保留天数: {}", item_type));
+        }
+        if let Some((item_type, _)) = self.retention_by_type.iter().find(|(_, days)| **days < 1) {
+            return Err(format!("{} 类型的保留天数必须至少为 1 天", item_type));
+        }
+        if !(LIST_PREVIEW_MIN_CHARS..=LIST_PREVIEW_MAX_CHARS).contains(&self.list_preview_chars) {
+            return Err(format!("列表预览字符数必须在 {} 到 {} 之间", LIST_PREVIEW_MIN_CHARS, LIST_PREVIEW_MAX_CHARS));
+        }
+        if self.icon_cache_size < 1 {
+            return Err("图标缓存容量必须至少为 1".to_string());
+        }
+        if self.icon_cache_ttl_hours > MAX_ICON_CACHE_TTL_HOURS {
+            return Err(format!("图标有效期不能超过 {} 小时", MAX_ICON_CACHE_TTL_HOURS));
+        }
+        if !(1..=MAX_DB_MAX_CONNECTIONS).contains(&self.db_max_connections) {
+            return Err(format!("数据库最大连接数必须在 1 到 {} 之间", MAX_DB_MAX_CONNECTIONS));
+        }
+        if !(1..=100).contains(&self.image_storage_quality) {
+            return Err("图片压缩质量必须在 1 到 100 之间".to_string());
+        }
+        if self.lan_queue_port == 0 {
+            return Err("LAN 队列端口不能为 0".to_string());
+        }
+        if self.lan_queue_max_item_mb < 1 {
+            return Err("LAN 队列单个条目的大小上限必须至少为 1 MB".to_string());
+        }
+        self.auto_start_names.validate()?;
+        Ok(())
+    }
+}
+
+// 自启动项的标识：display_name 用于 macOS 登录项和 Linux .desktop 文件（文件名由其生成），
+// registry_name 为 Windows 注册表 Run 键下的值名，bundle_id 用于 macOS LaunchAgent 的 plist 文件名
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct AutoStartNames {
+    pub display_name: String,
+    pub registry_name: String,
+    pub bundle_id: String,
+}
+
+impl Default for AutoStartNames {
+    fn default() -> Self {
+        Self {
+            display_name: "Clipboard Manager".to_string(),
+            registry_name: "ClipboardManager".to_string(),
+            bundle_id: "com.clipboardmanager.app".to_string(),
+        }
+    }
+}
+
+impl AutoStartNames {
+    // 空白的名称回退到默认值
+    pub fn resolved(&self) -> Self {
+        let defaults = Self::default();
+        let pick = |value: &str, default: String| {
+            let value = value.trim();
+            if value.is_empty() { default } else { value.to_string() }
+        };
+        Self {
+            display_name: pick(&self.display_name, defaults.display_name),
+            registry_name: pick(&self.registry_name, defaults.registry_name),
+            bundle_id: pick(&self.bundle_id, defaults.bundle_id),
+        }
+    }
+
+    // 名称会被拼进文件名和 AppleScript，只允许字母、数字、空格、点、下划线和短横线，且不能包含 ".."
+    pub fn validate(&self) -> Result<(), String> {
+        for name in [&self.display_name, &self.registry_name, &self.bundle_id] {
+            let allowed = name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | ' ' | '-'));
+            if !allowed || name.contains("..") {
+                return Err(format!("自启动名称只能包含字母、数字、空格、点、下划线和短横线，且不能包含 \"..\": {}", name));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn default_icon_cache_size() -> usize {
+    DEFAULT_ICON_CACHE_SIZE
+}
+
+fn default_icon_cache_ttl_hours() -> u64 {
+    DEFAULT_ICON_CACHE_TTL_HOURS
 }
 
 fn default_theme() -> String {
     "light".to_string()
 }
 
+fn default_normalize_line_endings() -> String {
+    "as_is".to_string()
+}
+
+fn default_trim_on_capture() -> String {
+    "off".to_string()
+}
+
+fn default_profile_name() -> String {
+    DEFAULT_PROFILE.to_string()
+}
+
+fn default_manual_capture_hotkey() -> String {
+    "Ctrl+Shift+Alt+C".to_string()
+}
+
+pub fn default_capture_formats() -> Vec<String> {
+    CAPTURE_FORMATS.iter().map(|format| format.to_string()).collect()
+}
+
+fn default_db_max_connections() -> u32 {
+    DEFAULT_DB_MAX_CONNECTIONS
+}
+
+fn default_image_storage_format() -> String {
+    "png".to_string()
+}
+
+fn default_image_storage_quality() -> u8 {
+    85
+}
+
+fn default_favorite_overflow_policy() -> String {
+    "unfavorite".to_string()
+}
+
+fn default_idle_compaction_minutes() -> u64 {
+    10
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_log_content_preview_len() -> usize {
+    32
+}
+
+fn default_list_preview_chars() -> usize {
+    1000
+}
+
 fn default_lan_queue_role() -> String {
     "off".to_string()
 }
@@ -40,11 +361,80 @@ fn default_lan_queue_port() -> u16 {
     21991
 }
 
+fn default_lan_queue_max_item_mb() -> u64 {
+    5
+}
+
 fn default_lan_queue_name() -> String {
     "LAN Queue".to_string()
 }
 
+// 剪贴板条目（对应 clipboard_history 表中的一行）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ClipboardItem {
+    pub id: i64,
+    pub content: String,
+    #[serde(rename = "type")]
+    pub item_type: String,
+    pub timestamp: String,
+    pub is_favorite: bool,
+    pub is_pinned: bool,
+    pub image_path: Option<String>,
+    pub source_app_name: Option<String>,
+    pub source_app_icon: Option<String>,
+    pub thumbnail_data: Option<String>,
+    pub metadata: Option<String>,
+    pub note: Option<String>,
+    pub group_id: Option<i64>,
+    pub data_hash: Option<String>,
+    pub expires_at: Option<String>,
+}
+
+// 分页加载历史记录时返回的列表条目，文本只包含列表预览
+#[derive(Debug, Serialize, Clone)]
+pub struct HistoryItem {
+    pub id: i64,
+    pub content: String,
+    // content 只是预览、完整内容更长时为 true，需要时用 get_item 读取完整内容
+    pub content_truncated: bool,
+    #[serde(rename = "type")]
+    pub item_type: String,
+    pub timestamp: String,
+    pub is_favorite: bool,
+    pub image_path: Option<String>,
+    pub source_app_name: Option<String>,
+    pub note: Option<String>,
+}
+
+// 新条目写入数据库后发送给前端的事件
+pub const ITEM_ADDED_EVENT: &str = "item-added";
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ItemAddedPayload {
+    #[serde(flatten)]
+    pub item: ClipboardItem,
+    // 截断后的内容预览，便于前端直接展示
+    pub preview: String,
+}
+
+// 命令失败时发送给前端的事件，用于统一的错误提示
+pub const COMMAND_ERROR_EVENT: &str = "command-error";
+
 #[derive(Debug, Serialize, Clone)]
+pub struct CommandErrorPayload {
+    pub command: String,
+    pub message: String,
+}
+
+// 后端修改了已有条目（如刷新来源应用图标）后发送给前端的事件，前端按 id 重新加载这些条目
+pub const HISTORY_CHANGED_EVENT: &str = "history-changed";
+
+#[derive(Debug, Serialize, Clone)]
+pub struct HistoryChangedPayload {
+    pub ids: Vec<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SourceAppInfo {
     pub name: String,
     pub icon: Option<String>, // base64 encoded icon
@@ -56,6 +446,120 @@ pub struct DatabaseState {
     pub pool: SqlitePool,
 }
 
+// 最近一次粘贴的条目（仅在本次运行期间有效，不持久化）
+#[derive(Default)]
+pub struct LastPastedState {
+    pub item_id: std::sync::Mutex<Option<i64>>,
+}
+
+// 一次粘贴尝试的耗时与结果
+#[derive(Debug, Clone)]
+pub struct PasteAttempt {
+    pub command: String,
+    pub target_app: Option<String>,
+    pub duration_ms: u64,
+    pub error: Option<String>,
+}
+
+// 本次运行期间的粘贴记录（仅保留最近的若干次，不持久化）
+#[derive(Default)]
+pub struct PasteMetricsState {
+    pub attempts: std::sync::Mutex<std::collections::VecDeque<PasteAttempt>>,
+}
+
+// 最近一次用户活动（捕获、粘贴、打开窗口）的时间，用于判断是否空闲以执行数据库压缩
+pub struct ActivityState {
+    last_activity: std::sync::Mutex<std::time::Instant>,
+    // 自上次活动以来是否已执行过空闲压缩，避免长时间空闲时重复执行
+    pub compacted: AtomicBool,
+}
+
+impl Default for ActivityState {
+    fn default() -> Self {
+        Self {
+            last_activity: std::sync::Mutex::new(std::time::Instant::now()),
+            compacted: AtomicBool::new(false),
+        }
+    }
+}
+
+impl ActivityState {
+    pub fn mark(&self) {
+        if let Ok(mut last_activity) = self.last_activity.lock() {
+            *last_activity = std::time::Instant::now();
+        }
+        self.compacted.store(false, Ordering::Relaxed);
+    }
+
+    pub fn idle_for(&self) -> std::time::Duration {
+        self.last_activity
+            .lock()
+            .map(|last_activity| last_activity.elapsed())
+            .unwrap_or_default()
+    }
+}
+
+// 当前的捕获分组：设置后新捕获的条目自动归入该分组（仅在本次运行期间有效，不持久化）
+#[derive(Default)]
+pub struct CaptureGroupState {
+    pub group_id: std::sync::Mutex<Option<i64>>,
+}
+
+// 失去焦点自动隐藏：enabled 来自设置；suppressed 为暂停计数，
+// 钉住窗口和打开原生对话框可能同时发生，计数归零后才恢复自动隐藏
+pub struct BlurHideState {
+    pub enabled: AtomicBool,
+    pub suppressed: AtomicUsize,
+}
+
+impl BlurHideState {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled: AtomicBool::new(enabled),
+            suppressed: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn set_suppressed(&self, suppressed: bool) {
+        let _ = self.suppressed.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |count| {
+            Some(if suppressed { count + 1 } else { count.saturating_sub(1) })
+        });
+    }
+
+    pub fn should_hide(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed) && self.suppressed.load(Ordering::Relaxed) == 0
+    }
+}
+
+// 主窗口因失去焦点被隐藏后通知前端重置界面状态
+pub const WINDOW_HIDDEN_ON_BLUR_EVENT: &str = "window-hidden-on-blur";
+
+// 捕获控制：paused 由托盘菜单的“停止监听”切换，暂停期间手动捕获也不记录；
+// manual 为手动捕获模式，manual_shortcut 为当前已注册的手动捕获快捷键，供全局快捷键处理函数区分
+#[derive(Default)]
+pub struct CaptureControlState {
+    pub paused: AtomicBool,
+    pub manual: AtomicBool,
+    pub manual_shortcut: std::sync::Mutex<Option<tauri_plugin_global_shortcut::Shortcut>>,
+}
+
+// 手动捕获模式切换后通知前端启用/停止自动记录
+pub const MANUAL_CAPTURE_MODE_EVENT: &str = "manual-capture-mode";
+
+// 默认配置沿用原有的 clipboard.db 和 images 目录
+pub const DEFAULT_PROFILE: &str = "default";
+
+// 切换历史配置后发送给前端的事件，前端据此重新连接数据库并刷新列表
+pub const PROFILE_CHANGED_EVENT: &str = "profile-changed";
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ProfileList {
+    pub active: String,
+    pub profiles: Vec<String>,
+    // 当前配置的数据库文件名（相对于应用数据目录）
+    pub db_file: String,
+}
+
 // 剪贴板监听器控制
 pub struct ClipboardWatcherState {
     pub should_stop: Arc<AtomicBool>,
@@ -1,7 +1,7 @@
 use std::time::Duration;
 use std::sync::{Arc, RwLock};
 use crate::types::SourceAppInfo;
-#[cfg(target_os = "windows")]
+#[cfg(any(target_os = "windows", target_os = "macos"))]
 use crate::icon_cache::get_icon_cache;
 #[cfg(target_os = "windows")]
 use crate::resource_manager::WindowsResourceManager;
@@ -535,6 +535,75 @@ pub fn convert_bgra_to_png_base64(bgra_data: &[u8], width: u32, height: u32) ->
     }
 }
 
+// macOS 获取前台应用的 AppleScript 超时时间
+#[cfg(target_os = "macos")]
+const MACOS_SCRIPT_TIMEOUT: Duration = Duration::from_secs(3);
+
+// macOS 图标提取相关子进程（mdfind/sips/osascript/qlmanage 等）的超时时间
+#[cfg(target_os = "macos")]
+const MACOS_ICON_COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+
+// 为子进程增加超时：超时后终止进程并返回 TimedOut 错误，避免个别应用导致工具卡死
+#[cfg(target_os = "macos")]
+trait CommandTimeoutExt {
+    fn output_with_timeout(&mut self, timeout: Duration) -> std::io::Result<std::process::Output>;
+}
+
+#[cfg(target_os = "macos")]
+impl CommandTimeoutExt for std::process::Command {
+    fn output_with_timeout(&mut self, timeout: Duration) -> std::io::Result<std::process::Output> {
+        use std::io::Read;
+        use std::process::Stdio;
+
+        let mut child = self
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        // 在后台线程读取输出，避免管道写满导致子进程阻塞
+        let mut stdout_pipe = child.stdout.take();
+        let mut stderr_pipe = child.stderr.take();
+        let stdout_reader = std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            if let Some(pipe) = stdout_pipe.as_mut() {
+                let _ = pipe.read_to_end(&mut buf);
+            }
+            buf
+        });
+        let stderr_reader = std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            if let Some(pipe) = stderr_pipe.as_mut() {
+                let _ = pipe.read_to_end(&mut buf);
+            }
+            buf
+        });
+
+        let started = std::time::Instant::now();
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if started.elapsed() >= timeout {
+                let _ = child.kill();
+                let _ = child.wait();
+                tracing::warn!("⚠️ macOS: 子进程执行超时（{:?}），已终止: {:?}", timeout, self.get_program());
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    format!("命令执行超时: {:?}", self.get_program()),
+                ));
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        };
+
+        Ok(std::process::Output {
+            status,
+            stdout: stdout_reader.join().unwrap_or_default(),
+            stderr: stderr_reader.join().unwrap_or_default(),
+        })
+    }
+}
+
 #[cfg(target_os = "macos")]
 #[tauri::command]
 pub async fn get_active_window_info() -> Result<SourceAppInfo, String> {
@@ -572,7 +641,7 @@ end tell
     let output = Command::new("osascript")
         .arg("-e")
         .arg(script)
-        .output()
+        .output_with_timeout(MACOS_SCRIPT_TIMEOUT)
         .map_err(|e| format!("执行 AppleScript 失败: {}", e))?;
     
     if output.status.success() {
@@ -639,7 +708,7 @@ end tell
     let output = Command::new("osascript")
         .arg("-e")
         .arg(script)
-        .output()
+        .output_with_timeout(MACOS_SCRIPT_TIMEOUT)
         .map_err(|e| format!("执行 AppleScript 失败: {}", e))?;
     
     if output.status.success() {
@@ -652,8 +721,8 @@ end tell
             
             tracing::info!("✅ 获取到活动应用: {} ({})", app_name, bundle_id);
             
-            // 获取应用图标
-            let app_icon = get_app_icon_base64_macos(&bundle_id);
+            // 获取应用图标（带缓存与并发限制）
+            let app_icon = get_app_icon_cached_macos(&bundle_id).await;
             if app_icon.is_some() {
                 tracing::info!("✅ 成功获取应用图标");
             } else {
@@ -696,6 +765,75 @@ pub async fn get_active_window_info_with_icon() -> Result<SourceAppInfo, String>
     Ok(new_info)
 }
 
+// 按应用名称（可执行文件名，不含扩展名）在正在运行的进程中查找可执行文件路径，返回以 0 结尾的宽字符串
+#[cfg(target_os = "windows")]
+fn find_running_exe_by_name(app_name: &str) -> Option<Vec<u16>> {
+    use winapi::um::psapi::EnumProcesses;
+
+    let mut process_ids = [0u32; 1024];
+    let mut bytes_returned = 0u32;
+    unsafe {
+        if EnumProcesses(process_ids.as_mut_ptr(), std::mem::size_of_val(&process_ids) as u32, &mut bytes_returned) == 0 {
+            tracing::error!("❌ 枚举进程失败");
+            return None;
+        }
+        let count = bytes_returned as usize / std::mem::size_of::<u32>();
+        for &process_id in &process_ids[..count] {
+            let process_handle = OpenProcess(PROCESS_QUERY_INFORMATION, 0, process_id);
+            if process_handle.is_null() {
+                continue;
+            }
+            let mut exe_path = [0u16; 256];
+            let path_len = GetModuleFileNameExW(process_handle, ptr::null_mut(), exe_path.as_mut_ptr(), exe_path.len() as u32);
+            CloseHandle(process_handle);
+            if path_len == 0 {
+                continue;
+            }
+            let path = &exe_path[..path_len as usize];
+            let path_os = OsString::from_wide(path);
+            let matches = std::path::Path::new(&path_os)
+                .file_stem()
+                .is_some_and(|stem| stem.to_string_lossy().eq_ignore_ascii_case(app_name));
+            if matches {
+                let mut exe_path = path.to_vec();
+                exe_path.push(0);
+                return Some(exe_path);
+            }
+        }
+    }
+    None
+}
+
+// 按应用名称重新提取图标（跳过缓存中的旧结果，提取后更新缓存）；应用需正在运行
+#[cfg(target_os = "windows")]
+pub async fn get_app_icon_by_name(app_name: &str) -> Option<String> {
+    let owned_name = app_name.to_string();
+    let result = tokio::task::spawn_blocking(move || {
+        let exe_path = find_running_exe_by_name(&owned_name)?;
+        let exe_path_str = String::from_utf16_lossy(&exe_path[..exe_path.len() - 1]);
+        let icon = get_app_icon_base64(&exe_path);
+        if let Ok(mut cache) = get_icon_cache().write() {
+            cache.insert(exe_path_str, icon.clone());
+        }
+        icon
+    })
+    .await;
+    match result {
+        Ok(icon) => icon,
+        Err(e) => {
+            tracing::error!("❌ 图标提取任务失败: {}", e);
+            None
+        }
+    }
+}
+
+// Linux 暂不支持提取应用图标
+#[cfg(target_os = "linux")]
+pub async fn get_app_icon_by_name(app_name: &str) -> Option<String> {
+    tracing::debug!("Linux 暂不支持提取应用图标: {}", app_name);
+    None
+}
+
 // 专门用于剪贴板监听的窗口信息获取函数（不使用缓存）
 #[cfg(target_os = "linux")]
 #[tauri::command]
@@ -743,6 +881,157 @@ pub async fn get_active_window_info_for_clipboard() -> Result<SourceAppInfo, Str
     })
 }
 
+// macOS 图标提取最多同时进行的数量（回退路径会启动 mdfind/sips/osascript 等多个子进程）
+#[cfg(target_os = "macos")]
+const MAX_CONCURRENT_ICON_EXTRACTIONS: usize = 2;
+
+#[cfg(target_os = "macos")]
+static ICON_EXTRACTION_SEMAPHORE: std::sync::OnceLock<tokio::sync::Semaphore> = std::sync::OnceLock::new();
+
+#[cfg(target_os = "macos")]
+fn get_icon_extraction_semaphore() -> &'static tokio::sync::Semaphore {
+    ICON_EXTRACTION_SEMAPHORE.get_or_init(|| tokio::sync::Semaphore::new(MAX_CONCURRENT_ICON_EXTRACTIONS))
+}
+
+// macOS 专用：按 bundle ID 缓存图标，并通过全局信号量限制并发提取，快速切换应用时其余请求排队等待
+#[cfg(target_os = "macos")]
+async fn get_app_icon_cached_macos(bundle_id: &str) -> Option<String> {
+    let cache_key = format!("macos:{}", bundle_id);
+
+    if let Ok(mut cache) = get_icon_cache().write() {
+        if let Some(icon) = cache.get(&cache_key) {
+            tracing::debug!("📋 macOS: 使用缓存的应用图标: {}", bundle_id);
+            return icon;
+        }
+    }
+
+    let _permit = match get_icon_extraction_semaphore().acquire().await {
+        Ok(permit) => permit,
+        Err(e) => {
+            tracing::error!("❌ macOS: 获取图标提取许可失败: {}", e);
+            return None;
+        }
+    };
+
+    // 排队期间可能已有相同 bundle ID 的提取完成
+    if let Ok(mut cache) = get_icon_cache().write() {
+        if let Some(icon) = cache.get(&cache_key) {
+            tracing::debug!("📋 macOS: 排队后命中图标缓存: {}", bundle_id);
+            return icon;
+        }
+    }
+
+    let owned_bundle_id = bundle_id.to_string();
+    let icon = match tokio::task::spawn_blocking(move || get_app_icon_base64_macos(&owned_bundle_id)).await {
+        Ok(icon) => icon,
+        Err(e) => {
+            tracing::error!("❌ macOS: 图标提取任务失败: {}", e);
+            None
+        }
+    };
+
+    if let Ok(mut cache) = get_icon_cache().write() {
+        cache.insert(cache_key, icon.clone());
+    }
+
+    icon
+}
+
+// macOS 原生图标尺寸（point）
+#[cfg(target_os = "macos")]
+const NATIVE_ICON_SIZE: f64 = 64.0;
+
+// macOS 专用：通过 NSRunningApplication.icon / NSWorkspace iconForFile: 获取图标，并在进程内渲染为 PNG
+#[cfg(target_os = "macos")]
+fn get_app_icon_native_macos(bundle_id: &str) -> Option<String> {
+    use base64::{engine::general_purpose, Engine as _};
+    use cocoa::base::{id, nil};
+    use cocoa::foundation::{NSAutoreleasePool, NSPoint, NSRect, NSSize, NSString};
+    use objc::runtime::Class;
+
+    // NSBitmapImageFileTypePNG
+    const NS_BITMAP_IMAGE_FILE_TYPE_PNG: u64 = 4;
+
+    let start = std::time::Instant::now();
+
+    let png_bytes = unsafe {
+        let pool = NSAutoreleasePool::new(nil);
+
+        let result = (|| -> Option<Vec<u8>> {
+            let workspace_class = Class::get("NSWorkspace")?;
+            let running_app_class = Class::get("NSRunningApplication")?;
+            let bitmap_class = Class::get("NSBitmapImageRep")?;
+            let dictionary_class = Class::get("NSDictionary")?;
+
+            let bundle_string: id = NSString::alloc(nil).init_str(bundle_id);
+            let _: id = msg_send![bundle_string, autorelease];
+
+            // 方法1：正在运行的应用直接读取 icon
+            let mut icon: id = nil;
+            let apps: id = msg_send![running_app_class, runningApplicationsWithBundleIdentifier:bundle_string];
+            if apps != nil {
+                let count: usize = msg_send![apps, count];
+                if count > 0 {
+                    let app: id = msg_send![apps, objectAtIndex:0usize];
+                    icon = msg_send![app, icon];
+                }
+            }
+
+            // 方法2：根据 bundle ID 找到应用路径，再通过 iconForFile: 获取
+            if icon == nil {
+                let workspace: id = msg_send![workspace_class, sharedWorkspace];
+                let app_url: id = msg_send![workspace, URLForApplicationWithBundleIdentifier:bundle_string];
+                if app_url == nil {
+                    return None;
+                }
+                let app_path: id = msg_send![app_url, path];
+                if app_path == nil {
+                    return None;
+                }
+                icon = msg_send![workspace, iconForFile:app_path];
+            }
+
+            if icon == nil {
+                return None;
+            }
+
+            // 将 NSImage 渲染为 CGImage，再编码为 PNG
+            let mut rect = NSRect::new(NSPoint::new(0.0, 0.0), NSSize::new(NATIVE_ICON_SIZE, NATIVE_ICON_SIZE));
+            let cg_image: id = msg_send![icon, CGImageForProposedRect:(&mut rect as *mut NSRect) context:nil hints:nil];
+            if cg_image == nil {
+                return None;
+            }
+
+            let bitmap: id = msg_send![bitmap_class, alloc];
+            let bitmap: id = msg_send![bitmap, initWithCGImage:cg_image];
+            if bitmap == nil {
+                return None;
+            }
+            let _: id = msg_send![bitmap, autorelease];
+
+            let properties: id = msg_send![dictionary_class, dictionary];
+            let png_data: id = msg_send![bitmap, representationUsingType:NS_BITMAP_IMAGE_FILE_TYPE_PNG properties:properties];
+            if png_data == nil {
+                return None;
+            }
+
+            let length: usize = msg_send![png_data, length];
+            let bytes: *const u8 = msg_send![png_data, bytes];
+            if bytes.is_null() || length == 0 {
+                return None;
+            }
+
+            Some(std::slice::from_raw_parts(bytes, length).to_vec())
+        })();
+
+        pool.drain();
+        result
+    }?;
+
+    tracing::debug!("✅ macOS: 原生 API 获取图标成功，大小: {} bytes，耗时: {:?}", png_bytes.len(), start.elapsed());
+    Some(format!("data:image/png;base64,{}", general_purpose::STANDARD.encode(&png_bytes)))
+}
+
 // macOS 专用：根据 bundle ID 获取应用图标
 #[cfg(target_os = "macos")]
 fn get_app_icon_base64_macos(bundle_id: &str) -> Option<String> {
@@ -750,10 +1039,16 @@ fn get_app_icon_base64_macos(bundle_id: &str) -> Option<String> {
     
     tracing::debug!("🎨 macOS: 开始获取应用图标，bundle_id: {}", bundle_id);
     
+    // 优先使用原生 NSWorkspace/NSRunningApplication API，无需启动子进程
+    if let Some(icon) = get_app_icon_native_macos(bundle_id) {
+        return Some(icon);
+    }
+    tracing::debug!("🔄 macOS: 原生方法未获取到图标，回退到命令行方法...");
+    
     // 方法1：使用 mdfind 查找应用路径
     let find_output = Command::new("mdfind")
         .arg(format!("kMDItemCFBundleIdentifier=={}", bundle_id))
-        .output();
+        .output_with_timeout(MACOS_ICON_COMMAND_TIMEOUT);
     
     if let Ok(result) = find_output {
         if result.status.success() {
@@ -772,6 +1067,65 @@ fn get_app_icon_base64_macos(bundle_id: &str) -> Option<String> {
     get_app_icon_simple_macos(bundle_id)
 }
 
+// macOS 专用：按应用名称（进程名）查找 bundle ID，用于为历史条目补全图标
+#[cfg(target_os = "macos")]
+fn find_bundle_id_by_name_macos(app_name: &str) -> Option<String> {
+    use std::process::Command;
+
+    let escaped = app_name.replace('\\', "\\\\").replace('"', "\\\"");
+    let query = format!(
+        "kMDItemContentType == \"com.apple.application-bundle\" && (kMDItemDisplayName == \"{0}\" || kMDItemDisplayName == \"{0}.app\")",
+        escaped
+    );
+    let output = Command::new("mdfind")
+        .arg(query)
+        .output_with_timeout(MACOS_ICON_COMMAND_TIMEOUT)
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let app_path = String::from_utf8_lossy(&output.stdout).lines().next()?.to_string();
+    tracing::debug!("📁 macOS: 按名称找到应用路径: {} -> {}", app_name, app_path);
+
+    let output = Command::new("mdls")
+        .args(["-name", "kMDItemCFBundleIdentifier", "-raw", &app_path])
+        .output_with_timeout(MACOS_ICON_COMMAND_TIMEOUT)
+        .ok()?;
+    let bundle_id = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() || bundle_id.is_empty() || bundle_id == "(null)" {
+        return None;
+    }
+    Some(bundle_id)
+}
+
+// 按应用名称重新提取图标（跳过缓存中的旧结果，提取后更新缓存）
+#[cfg(target_os = "macos")]
+pub async fn get_app_icon_by_name(app_name: &str) -> Option<String> {
+    let owned_name = app_name.to_string();
+    let bundle_id = match tokio::task::spawn_blocking(move || find_bundle_id_by_name_macos(&owned_name)).await {
+        Ok(Some(bundle_id)) => bundle_id,
+        Ok(None) => {
+            tracing::warn!("⚠️ macOS: 未找到应用 '{}' 的 bundle ID", app_name);
+            return None;
+        }
+        Err(e) => {
+            tracing::error!("❌ macOS: 查找 bundle ID 任务失败: {}", e);
+            return None;
+        }
+    };
+
+    let _permit = get_icon_extraction_semaphore().acquire().await.ok()?;
+    let owned_bundle_id = bundle_id.clone();
+    let icon = tokio::task::spawn_blocking(move || get_app_icon_base64_macos(&owned_bundle_id))
+        .await
+        .ok()
+        .flatten();
+    if let Ok(mut cache) = get_icon_cache().write() {
+        cache.insert(format!("macos:{}", bundle_id), icon.clone());
+    }
+    icon
+}
+
 // 从应用路径提取图标
 #[cfg(target_os = "macos")]
 fn get_icon_from_app_path(app_path: &str) -> Option<String> {
@@ -822,30 +1176,72 @@ fn get_icon_from_app_path(app_path: &str) -> Option<String> {
     None
 }
 
+// 临时文件序号，与 PID 组合保证并发提取时临时路径互不冲突
+#[cfg(target_os = "macos")]
+static TEMP_ICON_COUNTER: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+// 图标提取用的临时路径，离开作用域时自动删除（包括各种错误路径）
+#[cfg(target_os = "macos")]
+struct TempIconPath {
+    path: String,
+}
+
+#[cfg(target_os = "macos")]
+impl TempIconPath {
+    fn new(prefix: &str, extension: &str) -> Self {
+        let seq = TEMP_ICON_COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let file_name = format!("{}_{}_{}{}", prefix, std::process::id(), seq, extension);
+        let path = std::env::temp_dir().join(file_name).to_string_lossy().to_string();
+        Self { path }
+    }
+
+    fn as_str(&self) -> &str {
+        &self.path
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl Drop for TempIconPath {
+    fn drop(&mut self) {
+        let path = std::path::Path::new(&self.path);
+        let result = if path.is_dir() {
+            std::fs::remove_dir_all(path)
+        } else if path.exists() {
+            std::fs::remove_file(path)
+        } else {
+            Ok(())
+        };
+        if let Err(e) = result {
+            tracing::warn!("⚠️ macOS: 清理临时图标文件失败 {}: {}", self.path, e);
+        }
+    }
+}
+
 // 使用 sips 提取图标（macOS 原生方法）
 #[cfg(target_os = "macos")]
 fn extract_icon_with_sips(icon_path: &str) -> Option<String> {
     use std::process::Command;
     
-    let tmp_png = format!("/tmp/clipboard_icon_{}.png", std::process::id());
+    let tmp_file = TempIconPath::new("clipboard_icon", ".png");
+    let tmp_png = tmp_file.as_str();
     
     tracing::debug!("🔧 macOS: 使用 sips 转换图标: {} -> {}", icon_path, tmp_png);
     
     // 直接使用 sips 命令，不依赖 timeout
     let sips_output = Command::new("sips")
-        .args(&["-s", "format", "png", "-Z", "64", icon_path, "--out", &tmp_png])
-        .output();
+        .args(&["-s", "format", "png", "-Z", "64", icon_path, "--out", tmp_png])
+        .output_with_timeout(MACOS_ICON_COMMAND_TIMEOUT);
     
     match sips_output {
         Ok(result) if result.status.success() => {
             tracing::info!("✅ macOS: sips 转换成功");
             
             // 检查输出文件是否存在
-            if std::path::Path::new(&tmp_png).exists() {
+            if std::path::Path::new(tmp_png).exists() {
                 // 转换为 base64
                 let b64_output = Command::new("base64")
-                    .args(&["-i", &tmp_png])
-                    .output();
+                    .args(&["-i", tmp_png])
+                    .output_with_timeout(MACOS_ICON_COMMAND_TIMEOUT);
                 
                 let base64_result = match b64_output {
                     Ok(b64_result) if b64_result.status.success() => {
@@ -869,9 +1265,6 @@ fn extract_icon_with_sips(icon_path: &str) -> Option<String> {
                     }
                 };
                 
-                // 清理临时文件
-                let _ = Command::new("rm").arg(&tmp_png).output();
-                
                 if base64_result.is_some() {
                     tracing::info!("✅ macOS: 成功从 icns 提取图标");
                 }
@@ -886,8 +1279,6 @@ fn extract_icon_with_sips(icon_path: &str) -> Option<String> {
             tracing::warn!("⚠️ macOS: sips 转换失败，返回码: {}", result.status);
             tracing::warn!("⚠️ macOS: sips stderr: {}", String::from_utf8_lossy(&result.stderr));
             tracing::warn!("⚠️ macOS: sips stdout: {}", String::from_utf8_lossy(&result.stdout));
-            // 清理可能的临时文件
-            let _ = Command::new("rm").arg(&tmp_png).output();
             None
         }
         Err(e) => {
@@ -908,7 +1299,8 @@ fn extract_icon_with_iconutil(app_path: &str) -> Option<String> {
     if std::path::Path::new(&iconset_path).exists() {
         tracing::debug!("📁 macOS: 找到 iconset: {}", iconset_path);
         
-        let tmp_png = format!("/tmp/clipboard_iconset_{}.png", std::process::id());
+        let tmp_file = TempIconPath::new("clipboard_iconset", ".png");
+        let tmp_png = tmp_file.as_str();
         
         // 直接复制一个合适大小的图标文件
         let icon_files = vec![
@@ -924,15 +1316,15 @@ fn extract_icon_with_iconutil(app_path: &str) -> Option<String> {
                 
                 // 直接复制文件
                 let cp_output = Command::new("cp")
-                    .args(&[icon_file, &tmp_png])
-                    .output();
+                    .args(&[icon_file.as_str(), tmp_png])
+                    .output_with_timeout(MACOS_ICON_COMMAND_TIMEOUT);
                 
                 if let Ok(result) = cp_output {
                     if result.status.success() {
                         // 转换为 base64
                         let b64_output = Command::new("base64")
-                            .args(&["-i", &tmp_png])
-                            .output();
+                            .args(&["-i", tmp_png])
+                            .output_with_timeout(MACOS_ICON_COMMAND_TIMEOUT);
                         
                         if let Ok(b64_result) = b64_output {
                             if b64_result.status.success() {
@@ -940,9 +1332,6 @@ fn extract_icon_with_iconutil(app_path: &str) -> Option<String> {
                                     .trim()
                                     .replace("\n", "");
                                 
-                                // 清理临时文件
-                                let _ = Command::new("rm").arg(&tmp_png).output();
-                                
                                 if !base64_data.is_empty() {
                                     tracing::info!("✅ macOS: 成功从 iconset 提取图标");
                                     return Some(format!("data:image/png;base64,{}", base64_data));
@@ -952,8 +1341,6 @@ fn extract_icon_with_iconutil(app_path: &str) -> Option<String> {
                     }
                 }
                 
-                // 清理临时文件
-                let _ = Command::new("rm").arg(&tmp_png).output();
                 break;
             }
         }
@@ -993,7 +1380,7 @@ fn extract_icon_with_mdls(app_path: &str) -> Option<String> {
     // 获取应用的 CFBundleIconFile
     let mdls_output = Command::new("mdls")
         .args(&["-name", "kMDItemCFBundleIdentifier", "-name", "kMDItemDisplayName", app_path])
-        .output();
+        .output_with_timeout(MACOS_ICON_COMMAND_TIMEOUT);
     
     if let Ok(result) = mdls_output {
         if result.status.success() {
@@ -1003,19 +1390,27 @@ fn extract_icon_with_mdls(app_path: &str) -> Option<String> {
     }
     
     // 尝试最直接的方法：直接使用 Finder 复制图标
-    let tmp_png = format!("/tmp/clipboard_mdls_icon_{}.png", std::process::id());
+    let tmp_file = TempIconPath::new("clipboard_mdls_icon", ".png");
+    let tmp_png = tmp_file.as_str();
+    // qlmanage 输出文件名取决于应用名，使用独立目录避免并发时互相覆盖
+    let ql_dir = TempIconPath::new("clipboard_ql", "");
+    if let Err(e) = std::fs::create_dir_all(ql_dir.as_str()) {
+        tracing::warn!("⚠️ macOS: 创建 QuickLook 临时目录失败: {}", e);
+        return None;
+    }
     
     let script = format!(r#"
 set appPath to "{}"
 set outputPath to "{}"
+set qlDir to "{}"
 
 try
     -- 使用 QuickLook 生成缩略图
-    do shell script "qlmanage -t -s 64 -o /tmp " & quoted form of appPath
+    do shell script "qlmanage -t -s 64 -o " & quoted form of qlDir & " " & quoted form of appPath
     
     -- 查找生成的文件
     set appName to do shell script "basename " & quoted form of appPath & " .app"
-    set qlPath to "/tmp/" & appName & ".png"
+    set qlPath to qlDir & "/" & appName & ".png"
     
     -- 如果文件存在，复制到目标位置
     do shell script "if [ -f " & quoted form of qlPath & " ]; then cp " & quoted form of qlPath & " " & quoted form of outputPath & " && echo SUCCESS; else echo NOTFOUND; fi"
@@ -1023,29 +1418,24 @@ try
 on error errMsg
     return "ERROR: " & errMsg
 end try
-    "#, app_path, tmp_png);
+    "#, app_path, tmp_png, ql_dir.as_str());
     
     let output = Command::new("osascript")
         .args(&["-e", &script])
-        .output();
+        .output_with_timeout(MACOS_ICON_COMMAND_TIMEOUT);
     
     match output {
         Ok(result) if result.status.success() => {
             let response = String::from_utf8_lossy(&result.stdout).trim().to_string();
             tracing::debug!("📋 macOS: osascript 返回: {}", response);
             
-            if response.contains("SUCCESS") && std::path::Path::new(&tmp_png).exists() {
+            if response.contains("SUCCESS") && std::path::Path::new(tmp_png).exists() {
                 // 转换为 base64
-                if let Some(base64_data) = convert_png_to_base64(&tmp_png) {
-                    // 清理临时文件
-                    let _ = Command::new("rm").arg(&tmp_png).output();
+                if let Some(base64_data) = convert_png_to_base64(tmp_png) {
                     tracing::info!("✅ macOS: mdls 方法成功");
                     return Some(base64_data);
                 }
             }
-            
-            // 清理临时文件
-            let _ = Command::new("rm").arg(&tmp_png).output();
         }
         Ok(result) => {
             tracing::warn!("⚠️ macOS: osascript 失败: {}", String::from_utf8_lossy(&result.stderr));
@@ -1065,7 +1455,8 @@ fn extract_icon_with_shell(app_path: &str) -> Option<String> {
     
     tracing::debug!("🐚 macOS: 使用纯 shell 方法获取图标");
     
-    let tmp_png = format!("/tmp/clipboard_shell_icon_{}.png", std::process::id());
+    let tmp_file = TempIconPath::new("clipboard_shell_icon", ".png");
+    let tmp_png = tmp_file.as_str();
     
     // 使用 shell 脚本组合多种方法
     let script = format!(r#"
@@ -1102,7 +1493,7 @@ echo "FAILED"
     
     let output = Command::new("sh")
         .args(&["-c", &script])
-        .output();
+        .output_with_timeout(MACOS_ICON_COMMAND_TIMEOUT);
     
     match output {
         Ok(result) if result.status.success() => {
@@ -1110,22 +1501,16 @@ echo "FAILED"
             tracing::debug!("📋 macOS: shell 脚本输出: {}", response);
             
             if (response.contains("SUCCESS_SIPS") || response.contains("SUCCESS_ICONSET")) 
-                && std::path::Path::new(&tmp_png).exists() {
+                && std::path::Path::new(tmp_png).exists() {
                 
-                if let Some(base64_data) = convert_png_to_base64(&tmp_png) {
-                    // 清理临时文件
-                    let _ = Command::new("rm").arg(&tmp_png).output();
+                if let Some(base64_data) = convert_png_to_base64(tmp_png) {
                     tracing::info!("✅ macOS: shell 方法成功");
                     return Some(base64_data);
                 }
             }
-            
-            // 清理临时文件
-            let _ = Command::new("rm").arg(&tmp_png).output();
         }
         Ok(result) => {
             tracing::warn!("⚠️ macOS: shell 脚本失败: {}", String::from_utf8_lossy(&result.stderr));
-            let _ = Command::new("rm").arg(&tmp_png).output();
         }
         Err(e) => {
             tracing::error!("❌ macOS: shell 命令失败: {}", e);
@@ -1142,7 +1527,7 @@ fn convert_png_to_base64(png_path: &str) -> Option<String> {
     
     let b64_output = Command::new("base64")
         .args(&["-i", png_path])
-        .output();
+        .output_with_timeout(MACOS_ICON_COMMAND_TIMEOUT);
     
     match b64_output {
         Ok(result) if result.status.success() => {
@@ -1180,7 +1565,7 @@ end try
     let output = Command::new("osascript")
         .arg("-e")
         .arg(&script)
-        .output();
+        .output_with_timeout(MACOS_ICON_COMMAND_TIMEOUT);
     
     if let Ok(result) = output {
         if result.status.success() {
@@ -1190,16 +1575,18 @@ end try
                 tracing::debug!("📁 macOS: 获取到应用路径: {}", app_path);
                 
                 // 使用 sips 命令提取图标
+                let tmp_file = TempIconPath::new("clipboard_app_icon_simple", ".png");
+                let tmp_png = tmp_file.as_str();
                 let icon_output = Command::new("sips")
-                    .args(&["-s", "format", "png", "--resampleHeight", "64", &app_path, "--out", "/tmp/clipboard_app_icon_simple.png"])
-                    .output();
+                    .args(&["-s", "format", "png", "--resampleHeight", "64", &app_path, "--out", tmp_png])
+                    .output_with_timeout(MACOS_ICON_COMMAND_TIMEOUT);
                 
                 if let Ok(sips_result) = icon_output {
                     if sips_result.status.success() {
                         // 读取生成的图标文件并转换为 base64
                         let base64_output = Command::new("base64")
-                            .args(&["-i", "/tmp/clipboard_app_icon_simple.png"])
-                            .output();
+                            .args(&["-i", tmp_png])
+                            .output_with_timeout(MACOS_ICON_COMMAND_TIMEOUT);
                         
                         if let Ok(b64_result) = base64_output {
                             if b64_result.status.success() {
@@ -1207,11 +1594,6 @@ end try
                                     .trim()
                                     .replace("\n", "");
                                 
-                                // 清理临时文件
-                                let _ = Command::new("rm")
-                                    .arg("/tmp/clipboard_app_icon_simple.png")
-                                    .output();
-                                
                                 if !base64_data.is_empty() {
                                     tracing::info!("✅ macOS: 备用方法成功获取图标");
                                     return Some(format!("data:image/png;base64,{}", base64_data));
@@ -1249,7 +1631,7 @@ end tell
     let output = Command::new("osascript")
         .arg("-e")
         .arg(script)
-        .output()
+        .output_with_timeout(MACOS_SCRIPT_TIMEOUT)
         .map_err(|e| format!("执行 AppleScript 失败: {}", e))?;
     
     if output.status.success() {
@@ -1262,8 +1644,8 @@ end tell
             
             tracing::info!("✅ 剪贴板专用：获取到活动应用: {} ({})", app_name, bundle_id);
             
-            // 获取应用图标
-            let app_icon = get_app_icon_base64_macos(&bundle_id);
+            // 获取应用图标（带缓存与并发限制）
+            let app_icon = get_app_icon_cached_macos(&bundle_id).await;
             if app_icon.is_some() {
                 tracing::info!("✅ 成功获取应用图标");
             } else {